@@ -3,6 +3,7 @@
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
+use std::io::Write;
 use std::process;
 use std::fs::File;
 
@@ -11,14 +12,14 @@ use encoding_rs as enc;
 use marked::{
     chain_filters,
     filter,
-    html::parse_buffered,
+    html::{a, t, parse_buffered},
     logger::setup_logger,
-    EncodingHint,
+    Document, EncodingHint, NodeData, NodeRef,
 };
 
 use clap::{
     crate_version,
-    Arg, App, AppSettings, SubCommand,
+    Arg, ArgMatches, App, AppSettings, SubCommand,
 };
 
 use log::{debug, error};
@@ -57,18 +58,8 @@ fn run() -> Result<(), Flaw> {
         .about("HTML processing")
         .after_help(
             "Parses input, applies filters, and serializes to output.")
+        .args(&io_args())
         .args(&[
-            Arg::with_name("output")
-                .short("o")
-                .long("output")
-                .number_of_values(1)
-                .help("Output to specified file (default: STDOUT)"),
-            Arg::with_name("encoding")
-                .short("e")
-                .long("encoding")
-                .number_of_values(1)
-                .multiple(true)
-                .help("Hint at input encoding label (default: UTF-8)"),
             Arg::with_name("filter-banned")
                 .short("f")
                 .long("filter-banned")
@@ -77,12 +68,58 @@ fn run() -> Result<(), Flaw> {
                 .short("t")
                 .long("text-normalize")
                 .help("Aggressively normalize document text"),
-            Arg::with_name("file")
-                .required(false)
-                .value_name("INPUT-FILE")
-                .help("File path to read (default: STDIN)")
         ]);
 
+    let clean = SubCommand::with_name("clean")
+        .setting(AppSettings::DeriveDisplayOrder)
+        .about("HTML cleaning with filter presets")
+        .after_help(
+            "Parses input, applies filter presets in the order given, and \
+             serializes to output.")
+        .args(&io_args())
+        .arg(Arg::with_name("preset")
+             .short("p")
+             .long("preset")
+             .number_of_values(1)
+             .multiple(true)
+             .possible_values(filter::PRESETS)
+             .help("Filter preset to apply (default: clean)"));
+
+    let extract = SubCommand::with_name("extract")
+        .setting(AppSettings::DeriveDisplayOrder)
+        .about("HTML metadata and main text extraction")
+        .after_help(
+            "Parses input and outputs the page title, description and \
+             canonical URL, if found, followed by the cleaned text of the \
+             main content: the first <article>, <main> or role=\"main\" \
+             element, or otherwise <body>.")
+        .args(&io_args());
+
+    let links = SubCommand::with_name("links")
+        .setting(AppSettings::DeriveDisplayOrder)
+        .about("HTML link listing")
+        .after_help(
+            "Parses input and outputs the href and normalized text of each \
+             <a> element, tab separated, one per line.")
+        .args(&io_args());
+
+    let text = SubCommand::with_name("text")
+        .setting(AppSettings::DeriveDisplayOrder)
+        .about("HTML text extraction")
+        .after_help(
+            "Parses input, applies the clean filter preset, and outputs the \
+             text of the <body>.")
+        .args(&io_args());
+
+    let tojson = SubCommand::with_name("tojson")
+        .setting(AppSettings::DeriveDisplayOrder)
+        .about("HTML to JSON tree conversion")
+        .after_help(
+            "Parses input and outputs the document tree as JSON, with an \
+             object per node, of a \"type\", element \"name\" and \"attrs\" \
+             (an array of [name, value] pairs), \"text\" and \"children\".")
+        .args(&io_args());
+
     let app = App::new("marked")
         .version(crate_version!())
         .about("Tool for *ML I/O filtering")
@@ -95,17 +132,115 @@ fn run() -> Result<(), Flaw> {
              .multiple(true)
              .help("Enable more logging, and up to `-dddd`")
              .global(true))
-        .subcommand(html);
+        .subcommands(vec![html, clean, extract, links, text, tojson]);
 
     let mtch = app.get_matches();
     setup_logger(mtch.occurrences_of("debug") as u32)?;
 
     let scname = mtch.subcommand_name().unwrap(); // required
-    if scname != "html" {
-        quit!("only html (command) processing is supported")
-    }
     let mtch = mtch.subcommand_matches(scname).unwrap();
+    let mut doc = read_input(mtch)?;
+
+    // FIXME: report non-fatal errors?
+
+    let mut output = open_output(mtch)?;
+    match scname {
+        "html" => {
+            if mtch.is_present("filter-banned") {
+                doc.filter_breadth(chain_filters!(
+                    filter::detach_banned_elements,
+                    filter::detach_comments,
+                    filter::detach_pis,
+                    filter::retain_basic_attributes,
+                    filter::xmp_to_pre,
+                ));
+            }
+
+            if mtch.is_present("text-normalize") {
+                doc.filter(filter::fold_empty_inline);
+                doc.filter(filter::text_normalize); // Always use new pass.
+            }
+            doc.serialize(&mut output)?;
+        }
+        "clean" => {
+            let presets = match mtch.values_of("preset") {
+                Some(vals) => vals.collect(),
+                None => vec!["clean"],
+            };
+            for p in presets {
+                debug!("applying filter preset {}", p);
+                doc.filter_preset(p);
+            }
+            doc.serialize(&mut output)?;
+        }
+        "extract" => {
+            let meta = doc.page_meta();
+            if let Some(v) = &meta.title {
+                writeln!(output, "title: {}", v)?;
+            }
+            if let Some(v) = &meta.description {
+                writeln!(output, "description: {}", v)?;
+            }
+            if let Some(v) = &meta.canonical {
+                writeln!(output, "canonical: {}", v)?;
+            }
+            doc.filter_preset("clean");
+            let main = find_main(&doc);
+            writeln!(output)?;
+            writeln!(output, "{}", node_text(main))?;
+        }
+        "links" => {
+            doc.filter(filter::text_normalize);
+            let root = doc.document_node_ref();
+            for link in root.descendants().filter(|n| n.is_elem(t::A)) {
+                if let Some(href) = link.attr(a::HREF) {
+                    writeln!(
+                        output, "{}\t{}",
+                        href.trim(),
+                        node_text(link).trim())?;
+                }
+            }
+        }
+        "text" => {
+            doc.filter_preset("clean");
+            let root = doc.document_node_ref();
+            let body = root.find(|n| n.is_elem(t::BODY)).unwrap_or(root);
+            writeln!(output, "{}", node_text(body).trim())?;
+        }
+        "tojson" => {
+            write_json(&mut output, doc.document_node_ref())?;
+            writeln!(output)?;
+        }
+        _ => quit!("unsupported command: {}", scname)
+    }
+    output.flush()?;
+
+    Ok(())
+}
+
+// Return the arguments for input, output and input encoding, common to all
+// subcommands.
+fn io_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .number_of_values(1)
+            .help("Output to specified file (default: STDOUT)"),
+        Arg::with_name("encoding")
+            .short("e")
+            .long("encoding")
+            .number_of_values(1)
+            .multiple(true)
+            .help("Hint at input encoding label (default: UTF-8)"),
+        Arg::with_name("file")
+            .required(false)
+            .value_name("INPUT-FILE")
+            .help("File path to read (default: STDIN)")
+    ]
+}
 
+fn read_input(mtch: &ArgMatches<'_>) -> Result<Document, Flaw> {
     let eh = EncodingHint::shared_default(enc::UTF_8);
 
     if let Some(vals) = mtch.values_of("encoding") {
@@ -122,29 +257,15 @@ fn run() -> Result<(), Flaw> {
         Box::new(io::stdin())
     };
 
-    let mut doc = parse_buffered(eh, &mut input)?;
-
-    // FIXME: report non-fatal errors?
-
-    if mtch.is_present("filter-banned") {
-        doc.filter_breadth(chain_filters!(
-            filter::detach_banned_elements,
-            filter::detach_comments,
-            filter::detach_pis,
-            filter::retain_basic_attributes,
-            filter::xmp_to_pre,
-        ));
-    }
-
-    if mtch.is_present("text-normalize") {
-        doc.filter(filter::fold_empty_inline);
-        doc.filter(filter::text_normalize); // Always use new pass.
-    }
+    Ok(parse_buffered(eh, &mut input)?)
+}
 
+fn open_output(mtch: &ArgMatches<'_>) -> Result<Box<dyn io::Write>, Flaw> {
+    let fin = mtch.value_of("file");
     let fout = mtch.value_of("output");
-    let mut output: Box<dyn io::Write> = if let Some(fout) = fout {
+    let output: Box<dyn io::Write> = if let Some(fout) = fout {
         if Some(fout) != fin {
-            Box::new(File::create(fout)?)
+            Box::new(io::BufWriter::new(File::create(fout)?))
         } else {
             quit!(
                 "input {} same as output {} not supported",
@@ -153,8 +274,118 @@ fn run() -> Result<(), Flaw> {
     } else {
         Box::new(io::stdout())
     };
+    Ok(output)
+}
+
+// Return the main content element, or the body or document node.
+fn find_main(doc: &Document) -> NodeRef<'_> {
+    let root = doc.document_node_ref();
+    root.find(|n| n.is_elem(t::ARTICLE))
+        .or_else(|| root.find(|n| n.is_elem(t::MAIN)))
+        .or_else(|| root.find(|n| n.matches("[role=main]")))
+        .or_else(|| root.find(|n| n.is_elem(t::BODY)))
+        .unwrap_or(root)
+}
+
+fn node_text(node: NodeRef<'_>) -> String {
+    node.text().map_or_else(String::new, |t| t.to_string())
+}
+
+// A step of `write_json`.
+enum JsonStep<'a> {
+    // Write the node, preceded by a comma if true.
+    Node(NodeRef<'a>, bool),
+
+    // Close the children of an element or document.
+    End,
+}
 
-    doc.serialize(&mut output)?;
+// Write the node and its descendants as JSON. An explicit stack is used, as
+// the depth of parsed HTML is unbounded.
+fn write_json(out: &mut dyn io::Write, node: NodeRef<'_>) -> io::Result<()> {
+    let mut stack = vec![JsonStep::Node(node, false)];
+    while let Some(step) = stack.pop() {
+        let (node, comma) = match step {
+            JsonStep::Node(node, comma) => (node, comma),
+            JsonStep::End => {
+                write!(out, "]}}")?;
+                continue;
+            }
+        };
+        if comma {
+            write!(out, ",")?;
+        }
+        write_json_fields(out, node)?;
+        if let NodeData::Document | NodeData::Elem(_) = &**node {
+            write!(out, ",\"children\":[")?;
+            stack.push(JsonStep::End);
+            for child in node.children().rev() {
+                let comma = child.prev_sibling().is_some();
+                stack.push(JsonStep::Node(child, comma));
+            }
+        } else {
+            write!(out, "}}")?;
+        }
+    }
+    Ok(())
+}
 
+// Write the opening and fields of the node's JSON object. Attributes are
+// written as an array of name, value pairs, retaining any duplicates.
+fn write_json_fields(out: &mut dyn io::Write, node: NodeRef<'_>)
+    -> io::Result<()>
+{
+    match &**node {
+        NodeData::Document => write!(out, "{{\"type\":\"document\"")?,
+        NodeData::DocType(dt) => {
+            write!(out, "{{\"type\":\"doctype\",\"name\":")?;
+            write_json_str(out, &dt.name)?;
+        }
+        NodeData::Text(t) => {
+            write!(out, "{{\"type\":\"text\",\"text\":")?;
+            write_json_str(out, t)?;
+        }
+        NodeData::Comment(t) => {
+            write!(out, "{{\"type\":\"comment\",\"text\":")?;
+            write_json_str(out, t)?;
+        }
+        NodeData::Pi(pi) => {
+            write!(out, "{{\"type\":\"pi\",\"text\":")?;
+            write_json_str(out, &pi.data)?;
+        }
+        NodeData::Elem(elm) => {
+            write!(out, "{{\"type\":\"element\",\"name\":")?;
+            write_json_str(out, &elm.name.local)?;
+            write!(out, ",\"attrs\":[")?;
+            for (i, attr) in elm.attrs.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+                write!(out, "[")?;
+                write_json_str(out, &attr.name.local)?;
+                write!(out, ",")?;
+                write_json_str(out, &attr.value)?;
+                write!(out, "]")?;
+            }
+            write!(out, "]")?;
+        }
+        NodeData::Hole => write!(out, "{{\"type\":\"hole\"")?,
+    }
     Ok(())
 }
+
+fn write_json_str(out: &mut dyn io::Write, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
@@ -51,6 +51,20 @@ fn b01_round_trip_marked(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn b02_serialize_marked(b: &mut Bencher) {
+    let mut fin = sample_file("github-dekellum.html")
+        .expect("sample_file");
+    let eh = EncodingHint::shared_default(enc::UTF_8);
+    let doc = parse_buffered(eh, &mut fin).expect("parse");
+
+    b.iter(|| {
+        let mut out = Vec::with_capacity(273108);
+        doc.serialize(&mut out).expect("serialization");
+        assert_eq!(out.len(), 273108);
+    });
+}
+
 #[bench]
 fn b11_decode_eucjp_parse_marked(b: &mut Bencher) {
     b.iter(|| {
@@ -139,6 +153,33 @@ fn b31_text_normalize_content_identity(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn b32_text_normalize_content_timed(b: &mut Bencher) {
+    let mut fin = sample_file("github-dekellum.html")
+        .expect("sample_file");
+    let eh = EncodingHint::shared_default(enc::UTF_8);
+    let doc = parse_buffered(eh, &mut fin).expect("parse");
+    let banned = filter::timed(filter::detach_banned_elements);
+    let basic = filter::timed(filter::retain_basic_attributes);
+    let normalize = filter::timed(filter::text_normalize);
+    b.iter(|| {
+        let mut doc = doc.deep_clone(doc.root_element().unwrap());
+        doc.filter_breadth(chain_filters!(
+            |p, d| banned.filter(p, d),
+            filter::detach_comments,
+            filter::detach_pis,
+            |p, d| basic.filter(p, d),
+            filter::xmp_to_pre,
+        ));
+        doc.filter(filter::fold_empty_inline);
+        doc.filter(|p, d| normalize.filter(p, d));
+        let out = doc.document_node_ref().text().unwrap();
+        assert_eq!(out.len32(), 3257, "txt: {}", out.as_ref());
+    });
+    assert!(banned.calls() > 0);
+    assert!(normalize.elapsed() > std::time::Duration::default());
+}
+
 // In b5*_ benches below, compare with in-place `compact()`, so need to parse
 // and filter each time to produce a new "sparse" document.
 
@@ -228,6 +269,20 @@ fn b70_count(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn b71_stats(b: &mut Bencher) {
+    let mut fin = sample_file("github-dekellum.html")
+        .expect("sample_file");
+    let eh = EncodingHint::shared_default(enc::UTF_8);
+    let doc = parse_buffered(eh, &mut fin).expect("parse");
+
+    b.iter(|| {
+        let stats = doc.stats();
+        assert_eq!(5499, stats.reachable());
+        assert_eq!(0, stats.garbage);
+    });
+}
+
 fn sample_file(fname: &str) -> Result<File, io::Error> {
     let root = env!("CARGO_MANIFEST_DIR");
     let fpath = format!("{}/samples/{}", root, fname);
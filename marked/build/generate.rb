@@ -25,6 +25,7 @@ class Generator
     'I' => 'inline',
     'M' => 'meta',
     'B' => 'banned',
+    'R' => 'raw_text',
     'U' => 'undefined'
   }
 
@@ -36,14 +37,15 @@ class Generator
         case line
         when /^\s*#/, /^\s*$/
           # ignore comment, empty lines
-        when /^\s*[^\s,]+\s*,[^,]*,[^,]*$/
+        when /^\s*[^\s,]+\s*,[^,]*,[^,]*(,[^,]*)?$/
           r = line.split(',').map { |c| c.strip }
-          r = r.compact.reject { |c| c.empty? }
           flags = r[1].split(' ').map { |f| FLAGS[f] }.compact
+          parents = (r[3] || '').split(' ').compact.reject { |t| t.empty? }
           @tags << OpenStruct.new(
             :name => r[0],
             :flags => flags,
-            :desc => r[2]
+            :desc => r[2],
+            :parents => parents.sort
           )
         else
           raise "Parse ERROR: line [#{line}]"
@@ -129,6 +131,12 @@ class Generator
     end
   end
 
+  def tag_const(name)
+    tag = @tags.find { |t| t.name == name }
+    raise "Unknown parent tag [#{name}]" unless tag
+    clone_if(tag, 't::' + const(name.upcase))
+  end
+
   def tags_with(flag)
     @tags
       .select {|t| t.flags.include?(flag) }
@@ -0,0 +1,40 @@
+/* C declarations for the marked crate _capi_ feature. See src/capi.rs. */
+
+#ifndef MARKED_H
+#define MARKED_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct MarkedDocument MarkedDocument;
+typedef struct MarkedSelection MarkedSelection;
+
+MarkedDocument *marked_parse(const uint8_t *bytes, size_t len);
+MarkedDocument *marked_parse_fragment(const uint8_t *bytes, size_t len);
+void marked_document_free(MarkedDocument *doc);
+
+int marked_filter_preset(MarkedDocument *doc, const char *name);
+char *marked_serialize(const MarkedDocument *doc);
+
+MarkedSelection *marked_select(const MarkedDocument *doc,
+                               const char *selector);
+size_t marked_selection_len(const MarkedSelection *sel);
+char *marked_selection_html(const MarkedDocument *doc,
+                            const MarkedSelection *sel,
+                            size_t index);
+char *marked_selection_text(const MarkedDocument *doc,
+                            const MarkedSelection *sel,
+                            size_t index);
+void marked_selection_free(MarkedSelection *sel);
+
+void marked_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MARKED_H */
@@ -0,0 +1,124 @@
+//! Support for reading HTML from web archive formats: WARC and MHTML.
+//!
+//! HTML found in records or parts is parsed via
+//! [`html::parse_buffered`](crate::html::parse_buffered), with any charset
+//! of the record's HTTP or MIME `Content-Type` header as an encoding hint.
+
+use std::io;
+
+use encoding_rs as enc;
+
+use crate::{Document, EncodingHint, HTTP_CTYPE_CONF};
+use crate::html::parse_buffered;
+
+mod mhtml;
+mod warc;
+
+pub use mhtml::{Mhtml, MimePart};
+pub use warc::{HttpResponse, WarcReader, WarcRecord};
+
+/// An ordered list of header name and value pairs, as found in WARC, HTTP and
+/// MIME messages.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    /// Return the value of the first header with the given name, compared
+    /// ASCII case-insensitively.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Return an iterator over all header name and value pairs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.0.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Return the number of headers.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return true if there are no headers.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Add a header line, or continuation of the prior header if it starts
+    // with whitespace.
+    fn push_line(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        if line.starts_with(|c: char| c == ' ' || c == '\t') {
+            if let Some((_, v)) = self.0.last_mut() {
+                v.push(' ');
+                v.push_str(line.trim());
+            }
+        } else if let Some(i) = line.find(':') {
+            self.0.push((
+                line[..i].trim().to_owned(),
+                line[i+1..].trim().to_owned()
+            ));
+        }
+    }
+}
+
+// Split headers from the start of buf, up to and including an empty line,
+// returning the headers and the remaining body. Lines may be terminated by
+// CRLF or LF.
+fn split_headers(buf: &[u8]) -> (Headers, &[u8]) {
+    let mut headers = Headers::default();
+    let mut rest = buf;
+    while !rest.is_empty() {
+        let (line, next) = split_line(rest);
+        rest = next;
+        if line.is_empty() {
+            break;
+        }
+        headers.push_line(line);
+    }
+    (headers, rest)
+}
+
+// Split the first line from buf, without its CRLF or LF terminator.
+fn split_line(buf: &[u8]) -> (&[u8], &[u8]) {
+    let (line, rest) = match buf.iter().position(|&b| b == b'\n') {
+        Some(i) => (&buf[..i], &buf[i+1..]),
+        None => (buf, &buf[buf.len()..]),
+    };
+    (trim_cr(line), rest)
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    if line.last() == Some(&b'\r') {
+        &line[..line.len()-1]
+    } else {
+        line
+    }
+}
+
+// Return true if the Content-Type value is HTML or XHTML.
+fn is_html_type(ctype: Option<&str>) -> bool {
+    ctype
+        .and_then(|ct| ct.parse::<mime::Mime>().ok())
+        .map_or(false, |m| {
+            (m.type_() == mime::TEXT && m.subtype() == mime::HTML) ||
+                (m.type_() == mime::APPLICATION &&
+                 m.subtype().as_str().eq_ignore_ascii_case("xhtml"))
+        })
+}
+
+// Parse HTML from body bytes, with any charset of the Content-Type value as
+// an encoding hint, and otherwise UTF-8 by default.
+fn parse_body(ctype: Option<&str>, body: &[u8]) -> io::Result<Document> {
+    let hint = EncodingHint::shared_default(enc::UTF_8);
+    let charset = ctype
+        .and_then(|ct| ct.parse::<mime::Mime>().ok())
+        .and_then(|m| m.get_param(mime::CHARSET).map(|cs| cs.to_string()));
+    if let Some(cs) = charset {
+        hint.borrow_mut().add_label_hint(cs.trim(), HTTP_CTYPE_CONF);
+        hint.borrow_mut().clear_changed();
+    }
+    parse_buffered(hint, &mut &body[..])
+}
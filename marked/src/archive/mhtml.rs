@@ -0,0 +1,259 @@
+//! MHTML (MIME HTML, `.mht`) archive reading.
+
+use std::io::{self, Read};
+
+use crate::Document;
+use super::{is_html_type, parse_body, split_headers, split_line, Headers};
+
+/// An MHTML archive: a MIME `multipart/related` message containing an HTML
+/// document and its inline resources (images, style sheets, etc.).
+#[derive(Clone, Debug)]
+pub struct Mhtml {
+    headers: Headers,
+    parts: Vec<MimePart>,
+}
+
+/// A part of an MHTML archive, with its headers and decoded body.
+#[derive(Clone, Debug)]
+pub struct MimePart {
+    headers: Headers,
+    body: Vec<u8>,
+}
+
+impl Mhtml {
+    /// Read an MHTML archive from the given stream, until end.
+    pub fn read<R>(r: &mut R) -> io::Result<Mhtml>
+        where R: Read + ?Sized
+    {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)?;
+        Mhtml::parse(&buf)
+    }
+
+    /// Parse an MHTML archive from the given bytes.
+    ///
+    /// Part bodies with `base64` or `quoted-printable`
+    /// `Content-Transfer-Encoding` are decoded.
+    pub fn parse(bytes: &[u8]) -> io::Result<Mhtml> {
+        let (headers, body) = split_headers(bytes);
+        let boundary = headers.get("Content-Type")
+            .and_then(|ct| ct.parse::<mime::Mime>().ok())
+            .filter(|m| m.type_() == mime::MULTIPART)
+            .and_then(|m| m.get_param(mime::BOUNDARY).map(|b| b.to_string()))
+            .ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an MHTML (multipart) message"))?;
+        let delim = format!("--{}", boundary);
+
+        let mut parts = Vec::new();
+        let mut current: Option<Vec<u8>> = None;
+        let mut rest = body;
+        while !rest.is_empty() {
+            let start = rest;
+            let (line, next) = split_line(rest);
+            rest = next;
+            if line.starts_with(delim.as_bytes()) {
+                if let Some(part) = current.take() {
+                    parts.push(MimePart::parse(trim_eol(&part)));
+                }
+                if line[delim.len()..].starts_with(b"--") {
+                    break;
+                }
+                current = Some(Vec::new());
+            } else if let Some(part) = current.as_mut() {
+                // Retain the original line terminator
+                part.extend_from_slice(&start[..start.len() - rest.len()]);
+            }
+        }
+        if let Some(part) = current {
+            parts.push(MimePart::parse(trim_eol(&part)));
+        }
+        Ok(Mhtml { headers, parts })
+    }
+
+    /// Return the top-level message headers.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Return all parts, in order.
+    pub fn parts(&self) -> &[MimePart] {
+        &self.parts
+    }
+
+    /// Return the root HTML part: that referenced by the `start` parameter
+    /// of the message content type, if any, or otherwise the first HTML
+    /// part.
+    pub fn root(&self) -> Option<&MimePart> {
+        let start = self.headers.get("Content-Type")
+            .and_then(|ct| ct.parse::<mime::Mime>().ok())
+            .and_then(|m| m.get_param("start").map(|s| s.to_string()));
+        if let Some(start) = start {
+            let start = start.trim_start_matches('<').trim_end_matches('>');
+            let found = self.parts.iter().find(|p| {
+                p.content_id() == Some(start)
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+        self.parts.iter().find(|p| p.is_html())
+    }
+
+    /// Return the part for the given resource URL, as referenced from the
+    /// HTML, by `Content-Location`, or by `Content-ID` for `cid:` URLs.
+    pub fn resource(&self, url: &str) -> Option<&MimePart> {
+        let is_cid = url.len() > 4 &&
+            url.as_bytes()[..4].eq_ignore_ascii_case(b"cid:");
+        let cid = if is_cid {
+            Some(&url[4..])
+        } else {
+            None
+        };
+        self.parts.iter().find(|p| match cid {
+            Some(cid) => p.content_id() == Some(cid),
+            None => p.content_location() == Some(url),
+        })
+    }
+
+    /// Parse the root HTML part as a `Document`. See [`Mhtml::root`].
+    pub fn parse_html(&self) -> io::Result<Document> {
+        match self.root() {
+            Some(part) => part.parse_html(),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no HTML part found in MHTML message")),
+        }
+    }
+}
+
+impl MimePart {
+    fn parse(bytes: &[u8]) -> MimePart {
+        let (headers, body) = split_headers(bytes);
+        let encoding = headers.get("Content-Transfer-Encoding")
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let body = match encoding.as_str() {
+            "base64" => decode_base64(body),
+            "quoted-printable" => decode_quoted_printable(body),
+            _ => body.to_vec(),
+        };
+        MimePart { headers, body }
+    }
+
+    /// Return the part headers.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Return the decoded body.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Return the `Content-Type` header value.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("Content-Type")
+    }
+
+    /// Return the `Content-Location` header value, typically the original
+    /// URL of the resource.
+    pub fn content_location(&self) -> Option<&str> {
+        self.headers.get("Content-Location")
+    }
+
+    /// Return the `Content-ID` header value, without enclosing angle
+    /// brackets.
+    pub fn content_id(&self) -> Option<&str> {
+        self.headers.get("Content-ID")
+            .map(|id| id.trim_start_matches('<').trim_end_matches('>'))
+    }
+
+    /// Return true if this part is HTML (or XHTML) per its content type.
+    pub fn is_html(&self) -> bool {
+        is_html_type(self.content_type())
+    }
+
+    /// Parse the body as an HTML `Document`, with any `Content-Type` charset
+    /// as an encoding hint.
+    pub fn parse_html(&self) -> io::Result<Document> {
+        parse_body(self.content_type(), &self.body)
+    }
+}
+
+// Remove one trailing CRLF or LF, which precedes a boundary delimiter and is
+// considered part of it.
+fn trim_eol(part: &[u8]) -> &[u8] {
+    let part = if part.last() == Some(&b'\n') {
+        &part[..part.len()-1]
+    } else {
+        part
+    };
+    if part.last() == Some(&b'\r') {
+        &part[..part.len()-1]
+    } else {
+        part
+    }
+}
+
+fn decode_base64(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for &b in input {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => continue, // whitespace, line breaks
+        };
+        acc = (acc << 6) | u32::from(v);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    out
+}
+
+fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let b = input[i];
+        if b == b'=' {
+            let rest = &input[i+1..];
+            if rest.starts_with(b"\r\n") {
+                i += 3; // soft line break
+                continue;
+            }
+            if rest.starts_with(b"\n") {
+                i += 2;
+                continue;
+            }
+            if rest.len() >= 2 {
+                if let (Some(h), Some(l)) = (hex(rest[0]), hex(rest[1])) {
+                    out.push(h << 4 | l);
+                    i += 3;
+                    continue;
+                }
+            }
+        }
+        out.push(b);
+        i += 1;
+    }
+    out
+}
+
+fn hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        _ => None,
+    }
+}
@@ -0,0 +1,258 @@
+//! WARC (Web ARChive) record reading.
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Read};
+
+use crate::Document;
+use super::{is_html_type, parse_body, split_headers, split_line};
+use super::Headers;
+
+/// An iterator over the records of a WARC file.
+///
+/// The reader must provide uncompressed WARC content. For `.warc.gz` files,
+/// wrap the file in a multi-member gzip decoder (e.g. `MultiGzDecoder` of
+/// the _flate2_ crate) and a `BufReader`.
+pub struct WarcReader<R> {
+    r: R,
+}
+
+/// A single WARC record, with its headers and content block.
+#[derive(Clone, Debug)]
+pub struct WarcRecord {
+    version: String,
+    headers: Headers,
+    block: Vec<u8>,
+    http: Option<HttpHead>,
+}
+
+/// An HTTP response, as found in the block of a WARC `response` record.
+#[derive(Clone, Debug)]
+pub struct HttpResponse<'a> {
+    /// Response status code, e.g. 200.
+    pub status: u16,
+
+    /// The HTTP response headers.
+    pub headers: &'a Headers,
+
+    /// The response body, as is, including any transfer encoding.
+    pub body: &'a [u8],
+}
+
+// Parsed HTTP response status and headers, with the offset of the body in
+// the WARC block.
+#[derive(Clone, Debug)]
+struct HttpHead {
+    status: u16,
+    headers: Headers,
+    body_start: usize,
+}
+
+impl<R> WarcReader<R>
+    where R: BufRead
+{
+    /// Construct a new reader.
+    pub fn new(r: R) -> WarcReader<R> {
+        WarcReader { r }
+    }
+
+    /// Return an iterator over only those records with an HTML payload, and
+    /// any errors. See [`WarcRecord::is_html`].
+    pub fn html_records(self)
+        -> impl Iterator<Item = io::Result<WarcRecord>>
+    {
+        self.filter(|r| r.as_ref().map_or(true, |r| r.is_html()))
+    }
+
+    fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<bool> {
+        line.clear();
+        if self.r.read_until(b'\n', line)? == 0 {
+            return Ok(false);
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(true)
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<WarcRecord>> {
+        let mut line = Vec::new();
+
+        // Skip the empty lines terminating any prior record
+        loop {
+            if !self.read_line(&mut line)? {
+                return Ok(None);
+            }
+            if !line.is_empty() {
+                break;
+            }
+        }
+        let version = String::from_utf8_lossy(&line).into_owned();
+        if !version.starts_with("WARC/") {
+            return Err(invalid(format!("not a WARC record: {}", version)));
+        }
+
+        let mut headers = Headers::default();
+        loop {
+            if !self.read_line(&mut line)? {
+                return Err(invalid("unexpected end of WARC headers"));
+            }
+            if line.is_empty() {
+                break;
+            }
+            headers.push_line(&line);
+        }
+
+        let len = headers.get("Content-Length")
+            .and_then(|l| l.parse::<u64>().ok())
+            .ok_or_else(|| invalid("missing WARC Content-Length"))?;
+        let mut block = Vec::with_capacity(len.min(1 << 24) as usize);
+        (&mut self.r).take(len).read_to_end(&mut block)?;
+        if (block.len() as u64) < len {
+            return Err(invalid("unexpected end of WARC block"));
+        }
+
+        let http = http_head(&headers, &block);
+        Ok(Some(WarcRecord { version, headers, block, http }))
+    }
+}
+
+impl<R> Iterator for WarcReader<R>
+    where R: BufRead
+{
+    type Item = io::Result<WarcRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+impl WarcRecord {
+    /// Return the WARC version, e.g. "WARC/1.0".
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Return the WARC record headers.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Return the record content block, as is.
+    pub fn block(&self) -> &[u8] {
+        &self.block
+    }
+
+    /// Return the `WARC-Type` header value, e.g. "response".
+    pub fn record_type(&self) -> Option<&str> {
+        self.headers.get("WARC-Type")
+    }
+
+    /// Return the `WARC-Target-URI` header value.
+    pub fn target_uri(&self) -> Option<&str> {
+        self.headers.get("WARC-Target-URI")
+    }
+
+    /// Return the HTTP response of the block, if this is a `response` record
+    /// of type `application/http`.
+    pub fn http_response(&self) -> Option<HttpResponse<'_>> {
+        self.http.as_ref().map(|h| HttpResponse {
+            status: h.status,
+            headers: &h.headers,
+            body: &self.block[h.body_start..],
+        })
+    }
+
+    /// Return the `Content-Type` of the payload: that of the HTTP response if
+    /// available, otherwise that of the record.
+    pub fn content_type(&self) -> Option<&str> {
+        match &self.http {
+            Some(h) => h.headers.get("Content-Type"),
+            None => self.headers.get("Content-Type"),
+        }
+    }
+
+    /// Return the payload: the HTTP response body, with any chunked
+    /// transfer encoding decoded, if available, otherwise the block.
+    ///
+    /// Note that any `Content-Encoding` (e.g. gzip) is not decoded.
+    pub fn payload(&self) -> Cow<'_, [u8]> {
+        match self.http_response() {
+            Some(resp) => {
+                let chunked = resp.headers.get("Transfer-Encoding")
+                    .map_or(false, |te| te.eq_ignore_ascii_case("chunked"));
+                if chunked {
+                    Cow::Owned(dechunk(resp.body))
+                } else {
+                    Cow::Borrowed(resp.body)
+                }
+            }
+            None => Cow::Borrowed(&self.block),
+        }
+    }
+
+    /// Return true if the payload is HTML (or XHTML) per its content type,
+    /// and for HTTP responses, the status is successful (2xx).
+    pub fn is_html(&self) -> bool {
+        if let Some(h) = &self.http {
+            if h.status < 200 || h.status >= 300 {
+                return false;
+            }
+        }
+        is_html_type(self.content_type())
+    }
+
+    /// Parse the payload as an HTML `Document`, with any `Content-Type`
+    /// charset as an encoding hint.
+    pub fn parse_html(&self) -> io::Result<Document> {
+        parse_body(self.content_type(), &self.payload())
+    }
+}
+
+// Parse the HTTP status line and headers of a response record block.
+fn http_head(headers: &Headers, block: &[u8]) -> Option<HttpHead> {
+    if headers.get("WARC-Type") != Some("response") {
+        return None;
+    }
+    let is_http = headers.get("Content-Type")
+        .map_or(false, |ct| ct.starts_with("application/http"));
+    if !is_http {
+        return None;
+    }
+    let (status_line, rest) = split_line(block);
+    let status_line = String::from_utf8_lossy(status_line);
+    let mut parts = status_line.split_whitespace();
+    if !parts.next().map_or(false, |p| p.starts_with("HTTP/")) {
+        return None;
+    }
+    let status = parts.next().and_then(|s| s.parse().ok())?;
+    let (headers, body) = split_headers(rest);
+    let body_start = block.len() - body.len();
+    Some(HttpHead { status, headers, body_start })
+}
+
+// Decode an HTTP chunked transfer encoded body, ignoring any trailers. On
+// malformed input, the chunks decoded so far are returned.
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    loop {
+        let (line, rest) = split_line(body);
+        let line = String::from_utf8_lossy(line);
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = match usize::from_str_radix(size, 16) {
+            Ok(s) if s > 0 && s <= rest.len() => s,
+            _ => break,
+        };
+        out.extend_from_slice(&rest[..size]);
+        body = split_line(&rest[size..]).1;
+    }
+    out
+}
+
+fn invalid<E>(msg: E) -> io::Error
+    where E: Into<Box<dyn std::error::Error + Send + Sync>>
+{
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,283 @@
+//! C ABI bindings (_capi_ feature).
+//!
+//! These expose parsing, selecting, named filter presets and serializing via
+//! opaque handles, for use from C or other languages with a C foreign
+//! function interface (e.g. Python `ctypes` or `cffi`, Ruby `ffi`). See
+//! `include/marked.h` for the corresponding declarations. A shared library
+//! may be built with, for example:
+//!
+//! ```sh
+//! cargo rustc --release --features capi --crate-type cdylib
+//! ```
+//!
+//! All returned handles and strings are owned by the caller and must be
+//! released with the corresponding `marked_*_free` function. A
+//! `MarkedSelection` refers to nodes of the `MarkedDocument` it was selected
+//! from, which must outlive it. Null pointer arguments are tolerated, with
+//! the same result as for invalid input. Any panic is caught at the
+//! boundary, with the same result.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::{html, Document, NodeId, NodeRef};
+
+/// Opaque handle to a parsed `Document`.
+pub struct MarkedDocument {
+    doc: Document,
+}
+
+/// Opaque handle to a list of selected elements of a `MarkedDocument`.
+pub struct MarkedSelection {
+    ids: Vec<NodeId>,
+}
+
+/// Parse an HTML document from `len` UTF-8 bytes, returning a new document
+/// handle, or null on failure.
+///
+/// # Safety
+///
+/// `bytes` must be valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn marked_parse(bytes: *const u8, len: usize)
+    -> *mut MarkedDocument
+{
+    let input = match input_slice(bytes, len) {
+        Some(input) => input,
+        None => return ptr::null_mut(),
+    };
+    guard(ptr::null_mut(), || into_handle(html::parse_utf8(input)))
+}
+
+/// Parse an HTML fragment from `len` UTF-8 bytes, as per
+/// [`html::parse_utf8_fragment`], returning a new document handle, or null
+/// on failure.
+///
+/// # Safety
+///
+/// `bytes` must be valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn marked_parse_fragment(bytes: *const u8, len: usize)
+    -> *mut MarkedDocument
+{
+    let input = match input_slice(bytes, len) {
+        Some(input) => input,
+        None => return ptr::null_mut(),
+    };
+    guard(ptr::null_mut(), || into_handle(html::parse_utf8_fragment(input)))
+}
+
+/// Release a document handle.
+///
+/// # Safety
+///
+/// `doc` must be null or a handle returned by this library, not already
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn marked_document_free(doc: *mut MarkedDocument) {
+    if !doc.is_null() {
+        drop(Box::from_raw(doc));
+    }
+}
+
+/// Apply the named filter preset, as per [`Document::filter_preset`],
+/// returning 0 on success or -1 if the name is unknown.
+///
+/// # Safety
+///
+/// `doc` must be a valid document handle and `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn marked_filter_preset(
+    doc: *mut MarkedDocument,
+    name: *const c_char)
+    -> c_int
+{
+    let (doc, name) = match (doc.as_mut(), input_str(name)) {
+        (Some(doc), Some(name)) => (doc, name),
+        _ => return -1,
+    };
+    guard(-1, || if doc.doc.filter_preset(name) { 0 } else { -1 })
+}
+
+/// Serialize the document as HTML, returning a new NUL-terminated string, or
+/// null on failure.
+///
+/// # Safety
+///
+/// `doc` must be a valid document handle.
+#[no_mangle]
+pub unsafe extern "C" fn marked_serialize(doc: *const MarkedDocument)
+    -> *mut c_char
+{
+    match doc.as_ref() {
+        Some(doc) => guard(ptr::null_mut(), || into_c_string(
+            doc.doc.to_string())),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Select all elements matching the selector, in the subset of CSS syntax
+/// supported by [`NodeRef::matches`], returning a new selection handle (which
+/// may be empty), or null on failure.
+///
+/// # Safety
+///
+/// `doc` must be a valid document handle and `selector` a NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn marked_select(
+    doc: *const MarkedDocument,
+    selector: *const c_char)
+    -> *mut MarkedSelection
+{
+    let (doc, selector) = match (doc.as_ref(), input_str(selector)) {
+        (Some(doc), Some(selector)) => (doc, selector),
+        _ => return ptr::null_mut(),
+    };
+    guard(ptr::null_mut(), || {
+        let ids = doc.doc.document_node_ref()
+            .descendants()
+            .filter(|n| n.matches(selector))
+            .map(|n| n.id())
+            .collect();
+        Box::into_raw(Box::new(MarkedSelection { ids }))
+    })
+}
+
+/// Return the number of elements in the selection.
+///
+/// # Safety
+///
+/// `sel` must be null or a valid selection handle.
+#[no_mangle]
+pub unsafe extern "C" fn marked_selection_len(sel: *const MarkedSelection)
+    -> usize
+{
+    sel.as_ref().map_or(0, |sel| sel.ids.len())
+}
+
+/// Serialize the selected element at `index` as HTML, returning a new
+/// NUL-terminated string, or null if out of range.
+///
+/// # Safety
+///
+/// `doc` and `sel` must be valid handles, with the selection made from the
+/// same document, and without any intervening filtering.
+#[no_mangle]
+pub unsafe extern "C" fn marked_selection_html(
+    doc: *const MarkedDocument,
+    sel: *const MarkedSelection,
+    index: usize)
+    -> *mut c_char
+{
+    with_selected(doc, sel, index, |n| n.to_string())
+}
+
+/// Return the text content of the selected element at `index`, as a new
+/// NUL-terminated string, or null if out of range.
+///
+/// # Safety
+///
+/// As per [`marked_selection_html`].
+#[no_mangle]
+pub unsafe extern "C" fn marked_selection_text(
+    doc: *const MarkedDocument,
+    sel: *const MarkedSelection,
+    index: usize)
+    -> *mut c_char
+{
+    with_selected(doc, sel, index, |n| {
+        n.text().map_or_else(String::new, |t| t.to_string())
+    })
+}
+
+/// Release a selection handle.
+///
+/// # Safety
+///
+/// `sel` must be null or a handle returned by this library, not already
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn marked_selection_free(sel: *mut MarkedSelection) {
+    if !sel.is_null() {
+        drop(Box::from_raw(sel));
+    }
+}
+
+/// Release a string returned by this library.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library, not already
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn marked_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn with_selected<F>(
+    doc: *const MarkedDocument,
+    sel: *const MarkedSelection,
+    index: usize,
+    f: F)
+    -> *mut c_char
+    where F: FnOnce(NodeRef<'_>) -> String
+{
+    let (doc, sel) = match (doc.as_ref(), sel.as_ref()) {
+        (Some(doc), Some(sel)) => (doc, sel),
+        _ => return ptr::null_mut(),
+    };
+    let id = match sel.ids.get(index) {
+        Some(&id) => id,
+        None => return ptr::null_mut(),
+    };
+    match doc.doc.try_node_ref(id) {
+        Ok(node) => guard(ptr::null_mut(), || into_c_string(f(node))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+// Run f, returning the default value if it panics, rather than unwinding
+// across the C ABI.
+fn guard<T, F>(default: T, f: F) -> T
+    where F: FnOnce() -> T
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+fn into_handle(doc: Document) -> *mut MarkedDocument {
+    Box::into_raw(Box::new(MarkedDocument { doc }))
+}
+
+// Convert to a C string, removing any (unlikely) interior NUL chars.
+fn into_c_string(s: String) -> *mut c_char {
+    let cs = CString::new(s).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).expect("NUL removed")
+    });
+    cs.into_raw()
+}
+
+unsafe fn input_slice<'a>(bytes: *const u8, len: usize)
+    -> Option<&'a [u8]>
+{
+    if bytes.is_null() {
+        if len == 0 { Some(&[]) } else { None }
+    } else {
+        Some(slice::from_raw_parts(bytes, len))
+    }
+}
+
+unsafe fn input_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
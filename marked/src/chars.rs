@@ -14,6 +14,24 @@ pub(crate) fn replace_chars(
     ctrl: bool,
     trim_start: bool,
     trim_end: bool)
+{
+    replace_chars_ext(st, ws, ctrl, false, false, trim_start, trim_end)
+}
+
+/// Extended variant of [`replace_chars`].
+///
+/// If `keep_nbsp` is true, U+00A0 NO-BREAK SPACE is not considered
+/// whitespace, and is retained. If `keep_newlines` is true, a replaced
+/// whitespace sequence containing a line feed (or other line separator) is
+/// replaced with a single U+000A LINE FEED instead of SPACE.
+pub(crate) fn replace_chars_ext(
+    st: &mut StrTendril,
+    ws: bool,
+    ctrl: bool,
+    keep_nbsp: bool,
+    keep_newlines: bool,
+    trim_start: bool,
+    trim_end: bool)
 {
     let mut last = 0;
     let mut ost = None; // output lazy allocated
@@ -21,7 +39,11 @@ pub(crate) fn replace_chars(
 
     let ins = st.as_ref();
     for (i, ch) in ins.char_indices() {
-        let rmask = replace_mask(ch, ws, ctrl);
+        let rmask = if keep_nbsp && ch == '\u{00A0}' {
+            0
+        } else {
+            replace_mask(ch, ws, ctrl)
+        };
         if rmask > 0 {
             if replacing == 0 {
                 if ost.is_none() {
@@ -34,7 +56,8 @@ pub(crate) fn replace_chars(
             if  replacing >= 2 &&
                 (ost.as_ref().unwrap().len32() > 0 || !trim_start)
             {
-                ost.as_mut().unwrap().push_char(' ');
+                ost.as_mut().unwrap()
+                    .push_char(ws_replacement(replacing, keep_newlines));
             }
             last = i;
             replacing = 0;
@@ -42,7 +65,8 @@ pub(crate) fn replace_chars(
     }
     if replacing > 0 {
         if replacing >= 2 && !trim_end {
-            ost.as_mut().unwrap().push_char(' ');
+            ost.as_mut().unwrap()
+                .push_char(ws_replacement(replacing, keep_newlines));
         }
     } else if ost.is_some() {
         ost.as_mut().unwrap().push_slice(&ins[last..]);
@@ -53,16 +77,28 @@ pub(crate) fn replace_chars(
 }
 
 // Compare CharClass to flags and return bit-1 (control or zero-width) or bit-2
-// (whitespace).
+// (whitespace), with bit-3 also set for line separating whitespace.
 fn replace_mask(c: char, ws: bool, ctrl: bool) -> u8 {
     use CharClass::*;
     match char_class(c) {
         ZeroSpace | Control if ctrl => 1,
-        WhiteSpace if ws => 2,
+        WhiteSpace if ws => {
+            if is_line_sep(c) { 2 | 4 } else { 2 }
+        }
         _ => 0,
     }
 }
 
+fn is_line_sep(c: char) -> bool {
+    c == '\n' || c == '\u{2028}' || c == '\u{2029}'
+}
+
+// Return the replacement character for a whitespace sequence with the given
+// mask.
+fn ws_replacement(replacing: u8, keep_newlines: bool) -> char {
+    if keep_newlines && (replacing & 4) != 0 { '\n' } else { ' ' }
+}
+
 // Character classes of internal interest (not the same as Unicode classes).
 #[derive(Debug, Eq, PartialEq)]
 enum CharClass {
@@ -229,6 +265,25 @@ mod tests {
         assert_clean_trim_r("", "\t \r\n");
     }
 
+    #[test]
+    fn replace_keep_nbsp() {
+        assert_clean_ext("a\u{00A0}b c", "a\u{00A0}b \t c", true, false);
+        assert_clean_ext("a b c", "a\u{00A0}b \t c", false, false);
+    }
+
+    #[test]
+    fn replace_keep_newlines() {
+        assert_clean_ext("a\nb c", "a \n\n b \t c", false, true);
+        assert_clean_ext("a\nb", "\n a \r\n b \n", false, true);
+        assert_clean_ext("a b", "a \n\n b", false, false);
+    }
+
+    fn assert_clean_ext(exp: &str, src: &str, nbsp: bool, newlines: bool) {
+        let mut st = src.to_tendril();
+        replace_chars_ext(&mut st, true, true, nbsp, newlines, true, true);
+        assert_eq!(exp, st.as_ref());
+    }
+
     fn assert_clean_trim(exp: &str, src: &str) {
         let mut st = src.to_tendril();
         replace_chars(&mut st, true, true, true, true);
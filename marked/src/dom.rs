@@ -9,6 +9,7 @@
 
 //! An efficient and simple DOM-like container and associated tools.
 
+use std::collections::HashSet;
 use std::convert::TryInto;
 use std::fmt;
 use std::iter;
@@ -24,18 +25,77 @@ pub use tendril::StrTendril;
 
 // custom ordering of these effects rustdoc for Document, etc.
 
+mod attr_tokens;
+mod compare;
+mod debug_tree;
+mod doc_stats;
+mod fragment;
+mod interop;
+mod memory;
+mod node_id;
+mod node_mut;
 mod node_ref;
+mod picture;
+mod send;
 mod serializer;
+mod text_stats;
+mod user_data;
+mod visibility;
+pub mod audit;
+pub mod boilerplate;
+pub mod css;
+pub mod dedup;
 #[macro_use] pub mod filter;
+pub mod diff;
+pub mod extract;
+pub mod highlight;
 pub mod html;
+pub mod lang;
+pub mod layout;
+pub mod media;
+pub mod meta;
+pub mod microdata;
+pub mod outline;
+pub mod paginate;
+pub mod provenance;
+pub mod query;
+pub mod render;
+pub mod robots;
+pub mod table;
+pub mod template;
+pub mod traverse;
+pub mod xpath;
 
 #[cfg(feature = "xml")]
 pub mod xml;
 
+#[cfg(feature = "xml")]
+pub mod feed;
+
+#[cfg(feature = "xml")]
+pub mod sitemap;
+
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+
+#[cfg(feature = "jsonld")]
+pub mod jsonld;
+
 #[cfg(test)]
 mod tests;
 
-pub use node_ref::{NodeRef, Descender, Selector};
+pub use attr_tokens::{SrcsetCandidate, SrcsetDescriptor};
+pub use compare::EqOptions;
+pub use doc_stats::DocStats;
+pub use fragment::Fragment;
+pub use memory::{CompactPolicy, MemoryUsage};
+pub use node_id::NodeIdError;
+pub use node_mut::NodeMut;
+pub use serializer::{Escaping, SerializeOptions};
+pub use text_stats::TextStats;
+pub use node_ref::{NodeRef, Descender, Selector, Siblings};
+pub use send::SendDocument;
+use user_data::UserData;
 
 /// A DOM-like container for a tree of markup elements and text.
 ///
@@ -48,15 +108,87 @@ pub use node_ref::{NodeRef, Descender, Selector};
 /// constructed, contain a synthetic document node at the fixed
 /// [`Document::DOCUMENT_NODE_ID`] that serves as a container for N top level
 /// nodes, including the [`Document::root_element()`], if present.
+///
+/// ### Document order
+///
+/// Node order is defined exclusively by the parent, child and sibling links
+/// of each node, never by `NodeId` values or position in the underlying
+/// vector, which are arbitrary after mutations. All of [`Document::children`],
+/// [`Document::descendants`] (pre-order), [`NodeRef::select`], the
+/// [`Document::filter`] family (which visit nodes in post-order or
+/// pre-order of this same sequence), and serialization use this same
+/// document order. This order is preserved by [`Document::compact`],
+/// [`Document::deep_clone`] and [`Document::bulk_clone`]. See also
+/// [`Document::assert_consistent`].
+///
+/// ### Thread safety
+///
+/// A `Document` is neither `Send` nor `Sync`, as its `StrTendril`s are not.
+/// Use [`Document::into_send`] or [`Document::to_send`] to move a document,
+/// or a copy of it, to another thread.
 pub struct Document {
     nodes: Vec<Node>,
+    user_data: UserData,
+    compact_policy: Option<CompactPolicy>,
+    #[cfg(debug_assertions)]
+    tag: u32,
 }
 
 /// A `Node` identifier as a u32 index into a `Document`s `Node` vector.
 ///
 /// Should only be used with the `Document` it was obtained from.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct NodeId(NonZeroU32);
+///
+/// ### Stability
+///
+/// A `NodeId` remains stable, referencing the same `Node`, across all
+/// mutations of its `Document`, including all filtering, with the single
+/// exception of [`Document::compact`], which returns a [`NodeIdMap`] for
+/// translating prior identifiers. Node's that are removed (e.g. via
+/// [`Document::unlink`], [`Document::fold`] or filter `Action`s) retain their
+/// `NodeId`, but their data is replaced with `NodeData::Hole` and they are no
+/// longer reachable from the document node. Nodes moved to another `Document`
+/// (e.g. via [`Document::detach`] or [`Document::attach_child`]) or cloned
+/// are assigned new identifiers in that `Document`. Use
+/// [`Document::deep_clone_mapped`] to obtain a mapping for clones. The
+/// ordering of `NodeId`s has no relation to document order.
+///
+/// ### Debug builds
+///
+/// In debug builds, each `NodeId` is additionally tagged with an identifier
+/// of the `Document` it was obtained from, such that indexing another
+/// `Document` with it (or indexing the same `Document` after
+/// [`Document::compact`]) panics. [`Document::check_id`] and related
+/// methods return a `Result` instead. The [`Document::DOCUMENT_NODE_ID`] and
+/// prior ids of [`NodeIdMap::iter`] are untagged and accepted by any
+/// `Document`. Equality, ordering and hashing of `NodeId`s only consider the
+/// index.
+#[derive(Copy, Clone)]
+pub struct NodeId(NonZeroU32, #[cfg(debug_assertions)] u32);
+
+/// A mapping of prior (or original) to new `NodeId`s, as returned by
+/// [`Document::compact`], [`Document::deep_clone_mapped`] and
+/// [`Document::adopt`].
+#[derive(Clone, Debug)]
+pub struct NodeIdMap(Vec<Option<NodeId>>);
+
+impl NodeIdMap {
+    /// Return the new `NodeId` for the given prior `NodeId`, or `None` if that
+    /// node was removed.
+    pub fn get(&self, prior: NodeId) -> Option<NodeId> {
+        self.0.get(prior.0.get() as usize).and_then(|n| *n)
+    }
+
+    /// Return an iterator over all (prior, new) `NodeId` pairs, for retained
+    /// nodes, in order of prior `NodeId`.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        self.0.iter().enumerate().filter_map(|(i, n)| {
+            n.map(|n| {
+                let prior = NonZeroU32::new(i as u32).unwrap();
+                (NodeId::from_parts(prior, 0), n)
+            })
+        })
+    }
+}
 
 /// A typed node (e.g. text, element, etc.) within a `Document` including
 /// identifiers to parent, siblings and children.
@@ -118,11 +250,36 @@ pub struct Element {
     _priv: ()
 }
 
+/// A policy for same named (duplicate) attributes of an element, as per
+/// [`Element::dedup_attrs`].
+///
+/// Note that the HTML parser (as per the HTML specification) always retains
+/// only the first of any duplicate attributes, reporting a parse error, and
+/// the XML parser rejects these. Duplicates may still be introduced via
+/// manual mutation of [`Element::attrs`] or conversion from other DOMs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateAttrs {
+    /// Retain only the first instance, as per HTML parsing.
+    FirstWins,
+
+    /// Retain only the last instance.
+    LastWins,
+
+    /// Retain all instances.
+    KeepAll,
+}
+
+impl Default for DuplicateAttrs {
+    fn default() -> DuplicateAttrs {
+        DuplicateAttrs::FirstWins
+    }
+}
+
 /// Core implementation.
 impl Document {
     /// The constant `NodeId` for the document node of all `Document`s.
-    pub const DOCUMENT_NODE_ID: NodeId = NodeId(
-        unsafe { NonZeroU32::new_unchecked(1) }
+    pub const DOCUMENT_NODE_ID: NodeId = NodeId::from_parts(
+        unsafe { NonZeroU32::new_unchecked(1) }, 0
     );
 
     // An accepted amount of excess Vec<Node> capacity
@@ -139,7 +296,13 @@ impl Document {
         let mut nodes = Vec::with_capacity(count as usize);
         nodes.push(Node::new(NodeData::Hole));     // Index 0: Padding
         nodes.push(Node::new(NodeData::Document)); // Index 1: DOCUMENT_NODE_ID
-        Document { nodes }
+        Document {
+            nodes,
+            user_data: UserData::default(),
+            compact_policy: None,
+            #[cfg(debug_assertions)]
+            tag: Document::next_tag(),
+        }
     }
 
     /// Return total number of `Node`s.
@@ -223,7 +386,10 @@ impl Document {
             .expect("Document (u32) node index overflow");
         debug_assert!(next_index > 1);
         self.nodes.push(node);
-        NodeId(unsafe { NonZeroU32::new_unchecked(next_index) })
+        NodeId::from_parts(
+            unsafe { NonZeroU32::new_unchecked(next_index) },
+            self.tag()
+        )
     }
 
     /// Detach the specified node ID and return it and its children moved into
@@ -297,6 +463,77 @@ impl Document {
         }
     }
 
+    /// Move all nodes of an other `Document` to self, appending its top-level
+    /// nodes as children of the given parent node, and return a [`NodeIdMap`]
+    /// of the other's `NodeId`s to the new `NodeId`s in self.
+    ///
+    /// Unlike [`Document::attach_child`], this moves the nodes in bulk, in
+    /// their existing order and without a tree walk, by offsetting all
+    /// identifiers. Any user data of the moved nodes is also retained. Note
+    /// that unreachable nodes of the other `Document` (e.g. those removed by
+    /// filtering) are also moved, so it may be worthwhile to
+    /// [`Document::compact`] it first. The other's document node (mapped to
+    /// `None`) and its user data are dropped.
+    pub fn adopt(&mut self, mut other: Document, at: NodeId) -> NodeIdMap {
+        self[at].assert_suitable_parent();
+        let count = other.nodes.len();
+        assert!(
+            self.nodes.len() + count - 2 <= u32::max_value() as usize,
+            "Document (u32) node index overflow");
+        // Offset for other's nodes following its DOCUMENT_NODE_ID
+        let offset = self.nodes.len() as u32 - 2;
+
+        let tag = self.tag();
+        let remap = |id: NodeId| {
+            if id == Document::DOCUMENT_NODE_ID {
+                at
+            } else {
+                NodeId::from_parts(
+                    NonZeroU32::new(id.0.get() + offset).unwrap(),
+                    tag)
+            }
+        };
+        let remap_opt = |id: Option<NodeId>| id.map(remap);
+
+        let first = remap_opt(other[Document::DOCUMENT_NODE_ID].first_child);
+        let last = remap_opt(other[Document::DOCUMENT_NODE_ID].last_child);
+
+        self.nodes.reserve(other.nodes.len() - 2);
+        for onode in other.nodes.drain(2..) {
+            self.nodes.push(Node {
+                data: onode.data,
+                parent: remap_opt(onode.parent),
+                prev_sibling: remap_opt(onode.prev_sibling),
+                next_sibling: remap_opt(onode.next_sibling),
+                first_child: remap_opt(onode.first_child),
+                last_child: remap_opt(onode.last_child),
+            });
+        }
+
+        if let (Some(first), Some(last)) = (first, last) {
+            if let Some(prior) = self[at].last_child {
+                self[prior].next_sibling = Some(first);
+                self[first].prev_sibling = Some(prior);
+            } else {
+                self[at].first_child = Some(first);
+            }
+            self[at].last_child = Some(last);
+        }
+
+        let mut map = vec![None; 2];
+        map.extend((2..count as u32).map(|i| {
+            Some(NodeId::from_parts(
+                NonZeroU32::new(i + offset).unwrap(),
+                tag))
+        }));
+
+        other.user_data.take_node(Document::DOCUMENT_NODE_ID);
+        for (oid, data) in other.user_data.drain() {
+            self.user_data.insert_node(remap(oid), data);
+        }
+        NodeIdMap(map)
+    }
+
     /// Move node oid in odoc and all its descendants, appending to id in
     /// self.
     fn append_move(&mut self, id: NodeId, odoc: &mut Document, oid: NodeId) {
@@ -477,18 +714,39 @@ impl Document {
 
     /// Compact in place, by removing `Node`s that are no longer referenced
     /// from the document node.
-    pub fn compact(&mut self) {
+    ///
+    /// This is the only `Document` mutation which changes the `NodeId`s of
+    /// retained nodes. The returned [`NodeIdMap`] may be used to translate any
+    /// prior `NodeId`s held, or otherwise ignored. See also
+    /// [`Document::set_compact_policy`] for automatic compaction.
+    pub fn compact(&mut self) -> NodeIdMap {
         let mut ndoc = Document::with_capacity(self.len() + 1);
         let mut ns = NodeStack2::new();
         ns.push_if(
             self[Document::DOCUMENT_NODE_ID].first_child,
             Document::DOCUMENT_NODE_ID);
 
+        let mut map = vec![None; self.nodes.len()];
+        map[Document::DOCUMENT_NODE_ID.0.get() as usize] =
+            Some(Document::DOCUMENT_NODE_ID);
+
+        // Move user data of retained nodes, by new id
+        let mut udata = mem::replace(&mut self.user_data, UserData::default());
+        if let Some(data) = udata.take_node(Document::DOCUMENT_NODE_ID) {
+            ndoc.user_data.insert_node(Document::DOCUMENT_NODE_ID, data);
+        }
+
         while let Some((id, nid)) = ns.pop() {
             let nnode = Node::new(self[id].take_data());
             let ncid = ndoc.append_child(nid, nnode);
+            map[id.0.get() as usize] = Some(ncid);
             ns.push_if(self[id].next_sibling, nid);
             ns.push_if(self[id].first_child, ncid);
+            if !udata.is_empty() {
+                if let Some(data) = udata.take_node(id) {
+                    ndoc.user_data.insert_node(ncid, data);
+                }
+            }
         }
 
         // If guess cap was higher then allowance, shrink it
@@ -499,6 +757,12 @@ impl Document {
         }
 
         self.nodes = ndoc.nodes;
+        self.user_data = ndoc.user_data;
+        #[cfg(debug_assertions)]
+        {
+            self.tag = ndoc.tag;
+        }
+        NodeIdMap(map)
     }
 
     /// Create a new `Document` from the ordered sub-tree rooted in the node
@@ -521,6 +785,33 @@ impl Document {
         ndoc
     }
 
+    /// Create a new `Document` from the ordered sub-tree rooted in the node
+    /// referenced by ID, as per [`Document::deep_clone`], and also return a
+    /// [`NodeIdMap`] of original to cloned `NodeId`s.
+    ///
+    /// This allows identifiers tracked for the original (e.g. in a
+    /// [`Layout`](crate::layout::Layout) or other sidecar) to be translated
+    /// for use with the clone. Nodes outside the sub-tree are unmapped. Any
+    /// user data is not cloned.
+    pub fn deep_clone_mapped(&self, id: NodeId) -> (Document, NodeIdMap) {
+        let guess_cap = std::cmp::max(8, (self.len() - id.0.get() + 2) / 8);
+        let mut ndoc = Document::with_capacity(guess_cap);
+        let mut map = vec![None; self.nodes.len()];
+
+        if id == Document::DOCUMENT_NODE_ID {
+            map[id.0.get() as usize] = Some(Document::DOCUMENT_NODE_ID);
+            for child in self.children(id) {
+                ndoc.append_deep_clone_mapped(
+                    Document::DOCUMENT_NODE_ID, self, child, &mut map);
+            }
+        } else {
+            ndoc.append_deep_clone_mapped(
+                Document::DOCUMENT_NODE_ID, self, id, &mut map);
+        }
+
+        (ndoc, NodeIdMap(map))
+    }
+
     /// Clone node oid in odoc and all its descendants, appending to id in
     /// self.
     pub fn append_deep_clone(
@@ -535,6 +826,20 @@ impl Document {
         }
     }
 
+    fn append_deep_clone_mapped(
+        &mut self,
+        id: NodeId,
+        odoc: &Document,
+        oid: NodeId,
+        map: &mut Vec<Option<NodeId>>)
+    {
+        let id = self.append_child(id, Node::new(odoc[oid].data.clone()));
+        map[oid.0.get() as usize] = Some(id);
+        for child in odoc.children(oid) {
+            self.append_deep_clone_mapped(id, odoc, child, map);
+        }
+    }
+
     /// Return a clone of self by bulk clone of all `Node`s.
     ///
     /// This clone is performed without regard for what nodes are reachable
@@ -542,7 +847,91 @@ impl Document {
     /// same as the original. As compared with `deep_clone(DOCUMENT_NODE_ID)`
     /// this is faster but potentially much less memory efficient.
     pub fn bulk_clone(&self) -> Document {
-        Document { nodes: self.nodes.clone() }
+        Document {
+            nodes: self.nodes.clone(),
+            user_data: UserData::default(),
+            compact_policy: self.compact_policy,
+            #[cfg(debug_assertions)]
+            tag: self.tag,
+        }
+    }
+
+    /// Validate the parent, child and sibling link invariants of all nodes
+    /// reachable from the document node, panicking with a description of
+    /// the first violation found.
+    ///
+    /// This is intended as a safety net for testing code that mutates the
+    /// `Document`, and is only checked if debug assertions are enabled
+    /// (e.g. debug builds). Otherwise it is a no-op.
+    pub fn assert_consistent(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(e) = self.check_links() {
+                panic!("Document inconsistent: {}", e);
+            }
+        }
+    }
+
+    fn check_links(&self) -> Result<(), String> {
+        let dnode = &self[Document::DOCUMENT_NODE_ID];
+        match dnode.data {
+            NodeData::Document => {}
+            _ => return Err(format!("not document node: {:?}", dnode.data)),
+        }
+        if  dnode.parent.is_some() ||
+            dnode.prev_sibling.is_some() ||
+            dnode.next_sibling.is_some()
+        {
+            return Err("document node with parent or siblings".to_owned());
+        }
+
+        let mut visited = 0;
+        let mut ns = NodeStack1::new();
+        ns.push_if(Some(Document::DOCUMENT_NODE_ID));
+        while let Some(pid) = ns.pop() {
+            visited += 1;
+            if visited > self.nodes.len() {
+                return Err("cycle in node links".to_owned());
+            }
+            let parent = &self[pid];
+            match parent.data {
+                NodeData::Document | NodeData::Elem(_) => {}
+                _ if parent.first_child.is_none() => {}
+                _ => {
+                    return Err(format!(
+                        "{:?} is not a suitable parent: {:?}",
+                        pid, parent.data));
+                }
+            }
+            let mut prev = None;
+            let mut next = parent.first_child;
+            while let Some(id) = next {
+                let node = &self[id];
+                if let NodeData::Document | NodeData::Hole = node.data {
+                    return Err(format!(
+                        "{:?} invalid child of {:?}: {:?}",
+                        id, pid, node.data));
+                }
+                if node.parent != Some(pid) {
+                    return Err(format!(
+                        "{:?} parent {:?}, expected {:?}",
+                        id, node.parent, pid));
+                }
+                if node.prev_sibling != prev {
+                    return Err(format!(
+                        "{:?} prev_sibling {:?}, expected {:?}",
+                        id, node.prev_sibling, prev));
+                }
+                ns.push_if(Some(id));
+                prev = Some(id);
+                next = node.next_sibling;
+            }
+            if parent.last_child != prev {
+                return Err(format!(
+                    "{:?} last_child {:?}, expected {:?}",
+                    pid, parent.last_child, prev));
+            }
+        }
+        Ok(())
     }
 
     /// Replace the specified node ID with its children, and return the
@@ -598,6 +987,7 @@ impl std::ops::Index<NodeId> for Document {
 
     #[inline]
     fn index(&self, id: NodeId) -> &Node {
+        self.assert_tag(id);
         &self.nodes[id.0.get() as usize]
     }
 }
@@ -605,6 +995,7 @@ impl std::ops::Index<NodeId> for Document {
 impl std::ops::IndexMut<NodeId> for Document {
     #[inline]
     fn index_mut(&mut self, id: NodeId) -> &mut Node {
+        self.assert_tag(id);
         &mut self.nodes[id.0.get() as usize]
     }
 }
@@ -628,11 +1019,29 @@ impl Element {
         self.name.local == lname.into()
     }
 
+    /// Return true if this element has the given local name, compared ASCII
+    /// case-insensitively and regardless of namespace.
+    ///
+    /// This will also match names which differ in case from the lower-case
+    /// `html::t` constants, as may be found in XML input or foreign content,
+    /// e.g. `is_elem_local("div")` matches `<DIV>` and
+    /// `is_elem_local("clippath")` matches SVG `<clipPath>`.
+    pub fn is_elem_local(&self, lname: &str) -> bool {
+        self.name.local.eq_ignore_ascii_case(lname)
+    }
+
+    /// Return true if this element has any of the given local names, as per
+    /// [`Element::is_elem_local`].
+    pub fn is_any_elem_local(&self, lnames: &[&str]) -> bool {
+        lnames.iter().any(|ln| self.is_elem_local(ln))
+    }
+
     /// Return [`html::TagMeta`] for this element, if the tag is a known part
-    /// of the HTML `Namespace`.
+    /// of the HTML `Namespace` or a registered custom element. See
+    /// [`html::register_custom_element`].
     pub fn html_tag_meta(&self) -> Option<&'static html::TagMeta> {
         if self.name.ns == html::ns::HTML {
-            html::TAG_META.get(&self.name.local)
+            html::tag_meta(&self.name.local)
         } else {
             None
         }
@@ -649,6 +1058,52 @@ impl Element {
             .map(|attr| &attr.value)
     }
 
+    /// Return attribute value by local name, compared ASCII
+    /// case-insensitively and regardless of namespace, if present.
+    pub fn attr_local(&self, lname: &str) -> Option<&StrTendril> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.name.local.eq_ignore_ascii_case(lname))
+            .map(|attr| &attr.value)
+    }
+
+    /// Return an iterator over the values of all attributes with the given
+    /// local name, in order.
+    ///
+    /// Unlike [`Element::attr`], which returns only the first, this allows
+    /// observing any duplicates, for example, to avoid a security filter
+    /// being bypassed by a second instance of an attribute.
+    pub fn attrs_all<LN>(&self, lname: LN)
+        -> impl Iterator<Item = &StrTendril> + '_
+        where LN: Into<LocalName>
+    {
+        let lname = lname.into();
+        self.attrs
+            .iter()
+            .filter(move |attr| attr.name.local == lname)
+            .map(|attr| &attr.value)
+    }
+
+    /// Remove duplicate attributes (by local name) according to the given
+    /// policy, returning the number removed.
+    pub fn dedup_attrs(&mut self, policy: DuplicateAttrs) -> usize {
+        let len = self.attrs.len();
+        match policy {
+            DuplicateAttrs::KeepAll => {}
+            DuplicateAttrs::FirstWins => {
+                let mut seen = HashSet::new();
+                self.attrs.retain(|a| seen.insert(a.name.local.clone()));
+            }
+            DuplicateAttrs::LastWins => {
+                let mut seen = HashSet::new();
+                self.attrs.reverse();
+                self.attrs.retain(|a| seen.insert(a.name.local.clone()));
+                self.attrs.reverse();
+            }
+        }
+        len - self.attrs.len()
+    }
+
     /// Remove attribute by local name, returning any value found.
     ///
     /// This removes _all_ instances of attributes with the given local name
@@ -822,6 +1277,17 @@ impl NodeData {
         }
     }
 
+    /// Return true if this Node is an element with the given local name,
+    /// compared ASCII case-insensitively and regardless of namespace. See
+    /// [`Element::is_elem_local`].
+    pub fn is_elem_local(&self, lname: &str) -> bool {
+        if let Some(edata) = self.as_element() {
+            edata.is_elem_local(lname)
+        } else {
+            false
+        }
+    }
+
     #[inline]
     fn assert_suitable_parent(&self) {
         debug_assert!(
@@ -850,6 +1316,11 @@ impl NodeStack1 {
     fn pop(&mut self) -> Option<NodeId> {
         self.0.pop()
     }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 struct NodeStack2(Vec<(NodeId, NodeId)>);
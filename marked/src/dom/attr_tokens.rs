@@ -0,0 +1,212 @@
+//! Tokenization of structured attribute values.
+
+use crate::dom::{html::a, Element};
+
+/// A width or pixel density descriptor of a [`SrcsetCandidate`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SrcsetDescriptor {
+    /// Width in CSS pixels, e.g. `480w`.
+    Width(u32),
+
+    /// Pixel density, e.g. `1.5x`.
+    Density(f32),
+}
+
+/// An image candidate of a `srcset` attribute, as returned by
+/// [`Element::srcset`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SrcsetCandidate<'a> {
+    /// The (unresolved) image URL.
+    pub url: &'a str,
+
+    /// Any width or density descriptor. If `None`, the candidate is
+    /// equivalent to a density of `1x`.
+    pub descriptor: Option<SrcsetDescriptor>,
+}
+
+/// Attribute value tokenization methods.
+impl Element {
+    /// Return an iterator over the distinct whitespace separated names of the
+    /// `class` attribute, which is empty if there is no such attribute.
+    pub fn class_list(&self) -> impl Iterator<Item = &str> + '_ {
+        self.attr(a::CLASS)
+            .map_or("", |v| &**v)
+            .split_ascii_whitespace()
+    }
+
+    /// Return true if the given name is found in the `class` attribute.
+    pub fn has_class(&self, name: &str) -> bool {
+        self.class_list().any(|c| c == name)
+    }
+
+    /// Return the image candidates of the `srcset` attribute, in order.
+    ///
+    /// Candidates with invalid descriptors are skipped, per the HTML
+    /// parsing rules.
+    pub fn srcset(&self) -> Vec<SrcsetCandidate<'_>> {
+        self.attr(local_name!("srcset"))
+            .map_or_else(Vec::new, |v| parse_srcset(v))
+    }
+
+    /// Return the `(property, value)` declarations of the `style` attribute,
+    /// in order, with surrounding whitespace trimmed.
+    ///
+    /// Declarations are separated by `;` outside of quotes and parentheses.
+    /// Declarations lacking a `:` or with an empty property or value are
+    /// skipped. Property names are not case normalized, and values are not
+    /// further interpreted, e.g. `!important` is retained.
+    pub fn style(&self) -> Vec<(&str, &str)> {
+        self.attr(a::STYLE).map_or_else(Vec::new, |v| parse_style(v))
+    }
+}
+
+/// Parse a `srcset` attribute value into image candidates.
+pub(crate) fn parse_srcset(value: &str) -> Vec<SrcsetCandidate<'_>> {
+    let mut out = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| {
+            c.is_ascii_whitespace() || c == ','
+        });
+        if rest.is_empty() {
+            break;
+        }
+        let end = rest.find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or_else(|| rest.len());
+        let (url, r) = rest.split_at(end);
+        rest = r;
+
+        let descriptor = if url.ends_with(',') {
+            Ok(None)
+        } else {
+            let end = split_point(rest, ',');
+            let desc = &rest[..end];
+            rest = &rest[end..];
+            parse_descriptors(desc)
+        };
+        let url = url.trim_end_matches(',');
+        if let Ok(descriptor) = descriptor {
+            if !url.is_empty() {
+                out.push(SrcsetCandidate { url, descriptor });
+            }
+        }
+    }
+    out
+}
+
+// Parse the descriptors of a single candidate, returning `Err` if invalid.
+fn parse_descriptors(desc: &str) -> Result<Option<SrcsetDescriptor>, ()> {
+    let mut found = None;
+    for d in desc.split_ascii_whitespace() {
+        // When the last char is ASCII, this is a valid char boundary.
+        let num = || &d[..d.len() - 1];
+        let parsed = if d.ends_with('w') {
+            num().parse::<u32>().ok()
+                .filter(|&w| w > 0)
+                .map(SrcsetDescriptor::Width)
+        } else if d.ends_with('x') {
+            num().parse::<f32>().ok()
+                .filter(|x| x.is_finite() && *x >= 0.0)
+                .map(SrcsetDescriptor::Density)
+        } else if d.ends_with('h') {
+            // Height descriptors are only meaningful with width, and are
+            // ignored here.
+            continue;
+        } else {
+            None
+        };
+        if parsed.is_none() || found.is_some() {
+            return Err(());
+        }
+        found = parsed;
+    }
+    Ok(found)
+}
+
+/// Parse a `style` attribute value into (property, value) declarations.
+pub(crate) fn parse_style(value: &str) -> Vec<(&str, &str)> {
+    let mut out = Vec::new();
+    let mut rest = value;
+    while !rest.is_empty() {
+        let end = split_point(rest, ';');
+        let decl = &rest[..end];
+        rest = if end < rest.len() { &rest[end + 1..] } else { "" };
+
+        if let Some(colon) = decl.find(':') {
+            let prop = decl[..colon].trim();
+            let val = decl[colon + 1..].trim();
+            if !prop.is_empty() && !val.is_empty() {
+                out.push((prop, val));
+            }
+        }
+    }
+    out
+}
+
+// Return the byte index of the first `sep` outside of quotes and
+// parentheses, or the length of s if not found.
+fn split_point(s: &str, sep: char) -> usize {
+    let mut depth = 0u32;
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' {
+            depth = depth.saturating_sub(1);
+        } else if c == sep && depth == 0 {
+            return i;
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srcset() {
+        let c = parse_srcset(
+            " a.jpg, b.jpg 2x,c.jpg 480w , d.jpg 1.5x 100h,,e.jpg bad, \
+             f(1,2).jpg 3x, g.jpg,h.jpg"
+        );
+        let expected = [
+            ("a.jpg", None),
+            ("b.jpg", Some(SrcsetDescriptor::Density(2.0))),
+            ("c.jpg", Some(SrcsetDescriptor::Width(480))),
+            ("d.jpg", Some(SrcsetDescriptor::Density(1.5))),
+            ("f(1,2).jpg", Some(SrcsetDescriptor::Density(3.0))),
+            ("g.jpg,h.jpg", None),
+        ];
+        assert_eq!(expected.len(), c.len(), "{:?}", c);
+        for (c, &(url, descriptor)) in c.iter().zip(expected.iter()) {
+            assert_eq!(url, c.url);
+            assert_eq!(descriptor, c.descriptor);
+        }
+        assert!(parse_srcset("a.jpg 1x 2x").is_empty());
+        assert!(parse_srcset("a.jpg 0w").is_empty());
+        assert!(parse_srcset(" , ").is_empty());
+    }
+
+    #[test]
+    fn style() {
+        assert_eq!(
+            vec![
+                ("color", "red"),
+                ("background", "url(\"a;b.png\") no-repeat"),
+                ("font-family", "'x;y', serif !important"),
+            ],
+            parse_style(
+                "color: red; ;background:url(\"a;b.png\") no-repeat;\
+                 junk; font-family : 'x;y', serif !important;empty:"
+            )
+        );
+        assert!(parse_style("").is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+//! Auditing of documents, with issues reported as [`Diagnostic`]s.
+//!
+//! See the [`a11y`] and [`lint`] modules.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::dom::{html::SourceSpan, Document, NodeId};
+
+pub mod a11y;
+pub mod lint;
+
+/// The severity of a [`Diagnostic`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// A likely problem, worth review.
+    Warning,
+
+    /// A definite problem.
+    Error,
+}
+
+/// An issue found by an audit, at a node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    node: NodeId,
+    code: &'static str,
+    severity: Severity,
+    message: Cow<'static, str>,
+    span: Option<SourceSpan>,
+}
+
+impl Diagnostic {
+    /// Construct a new diagnostic.
+    pub fn new<M>(
+        node: NodeId,
+        code: &'static str,
+        severity: Severity,
+        message: M)
+        -> Diagnostic
+        where M: Into<Cow<'static, str>>
+    {
+        Diagnostic {
+            node,
+            code,
+            severity,
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Set the source span of the node, for reporting.
+    pub fn with_span(mut self, span: Option<SourceSpan>) -> Diagnostic {
+        self.span = span;
+        self
+    }
+
+    /// Return the ID of the node at issue.
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// Return the short, stable code of the check which found the issue,
+    /// e.g. `img-alt`.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Return the severity.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Return a descriptive message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Return the source span of the node, if known. Audits set this when
+    /// the `Document` was parsed with
+    /// [`parse_utf8_with_spans`](crate::html::parse_utf8_with_spans).
+    pub fn span(&self) -> Option<SourceSpan> {
+        self.span
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sev = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if let Some(span) = self.span {
+            write!(f, "line {}: ", span.start_line)?;
+        }
+        write!(f, "{} [{}]: {}", sev, self.code, self.message)
+    }
+}
+
+// Set spans of all diagnostics from the document, if recorded.
+fn set_spans(doc: &Document, diags: &mut [Diagnostic]) {
+    for d in diags {
+        d.span = doc.source_span(d.node);
+    }
+}
@@ -0,0 +1,154 @@
+//! Accessibility audit.
+//!
+//! [`check`] walks a `Document`, reporting common accessibility issues which
+//! can be found from markup alone, for example in CI checks of generated
+//! HTML. This is not a substitute for a complete audit.
+
+use std::collections::HashSet;
+
+use crate::dom::{
+    audit::{set_spans, Diagnostic, Severity},
+    html::{a, t},
+    Document, NodeRef,
+};
+
+/// Code for an `<img>` or `<area>` without an `alt` attribute.
+pub const IMG_ALT: &str = "img-alt";
+
+/// Code for a form control without an associated label.
+pub const INPUT_LABEL: &str = "input-label";
+
+/// Code for a heading more than one level below the prior heading.
+pub const HEADING_SKIP: &str = "heading-skip";
+
+/// Code for a link without any text or text alternative.
+pub const LINK_EMPTY: &str = "link-empty";
+
+/// Audit the document, returning diagnostics in document order, with source
+/// spans if recorded.
+///
+/// Elements which are probably hidden, per
+/// [`NodeRef::is_probably_hidden`], and their descendants, are skipped.
+/// Checks, by code:
+///
+/// [`IMG_ALT`]
+/// : An `<img>` or `<area>` without an `alt` attribute. An empty `alt`,
+///   marking a decorative image, is accepted. (Error)
+///
+/// [`INPUT_LABEL`]
+/// : An `<input>` (other than of hidden, submit, reset, button or image
+///   type), `<select>` or `<textarea>` which is not within a `<label>`, not
+///   referenced by the `for` attribute of a `<label>`, and lacks a
+///   non-empty `aria-label`, `aria-labelledby` or `title`. (Error)
+///
+/// [`HEADING_SKIP`]
+/// : A heading more than one level below the prior heading, e.g. an `<h4>`
+///   following an `<h2>`. (Warning)
+///
+/// [`LINK_EMPTY`]
+/// : An `<a href>` without text, an image with non-empty `alt`, or a
+///   non-empty `aria-label` or `title`. (Error)
+pub fn check(doc: &Document) -> Vec<Diagnostic> {
+    let root = doc.document_node_ref();
+    let label_for: HashSet<&str> = root.descendants()
+        .filter(|n| n.is_elem(t::LABEL))
+        .filter_map(|n| n.attr("for"))
+        .map(|v| v.trim())
+        .collect();
+
+    let mut diags = Vec::new();
+    let mut last_heading = None;
+    let mut hidden = None;
+    for node in root.descendants() {
+        let elm = match node.as_element() {
+            Some(elm) => elm,
+            None => continue,
+        };
+        if let Some(h) = hidden {
+            if node.ancestors().any(|n| n.id() == h) {
+                continue;
+            }
+            hidden = None;
+        }
+        if node.is_probably_hidden() {
+            hidden = Some(node.id());
+            continue;
+        }
+        let name = &elm.name.local;
+
+        if (*name == t::IMG || *name == t::AREA) && elm.attr(a::ALT).is_none()
+        {
+            diags.push(Diagnostic::new(
+                node.id(), IMG_ALT, Severity::Error,
+                format!("<{}> without alt attribute", name)));
+        } else if is_labelable(node) &&
+            !has_label(node, &label_for) &&
+            !has_aria_label(node)
+        {
+            diags.push(Diagnostic::new(
+                node.id(), INPUT_LABEL, Severity::Error,
+                format!("<{}> without label", name)));
+        } else if let Some(level) = heading_level(node) {
+            if let Some(last) = last_heading {
+                if level > last + 1 {
+                    diags.push(Diagnostic::new(
+                        node.id(), HEADING_SKIP, Severity::Warning,
+                        format!("<h{}> following <h{}>", level, last)));
+                }
+            }
+            last_heading = Some(level);
+        } else if *name == t::A && elm.attr(a::HREF).is_some() &&
+            !has_link_text(node) &&
+            !has_aria_label(node)
+        {
+            diags.push(Diagnostic::new(
+                node.id(), LINK_EMPTY, Severity::Error,
+                "link without text"));
+        }
+    }
+    set_spans(doc, &mut diags);
+    diags
+}
+
+fn is_labelable(node: NodeRef<'_>) -> bool {
+    if node.is_elem(t::SELECT) || node.is_elem(t::TEXTAREA) {
+        return true;
+    }
+    if !node.is_elem(t::INPUT) {
+        return false;
+    }
+    let kind = node.attr(a::TYPE).map_or("", |v| v.trim());
+    !["hidden", "submit", "reset", "button", "image"]
+        .iter()
+        .any(|k| kind.eq_ignore_ascii_case(k))
+}
+
+fn has_label(node: NodeRef<'_>, label_for: &HashSet<&str>) -> bool {
+    node.ancestors().any(|n| n.is_elem(t::LABEL)) ||
+        node.attr(a::ID).map_or(false, |id| label_for.contains(id.trim()))
+}
+
+fn has_aria_label(node: NodeRef<'_>) -> bool {
+    ["aria-label", "aria-labelledby", "title"]
+        .iter()
+        .any(|&n| node.attr(n).map_or(false, |v| !v.trim().is_empty()))
+}
+
+fn has_link_text(node: NodeRef<'_>) -> bool {
+    node.descendants().any(|n| {
+        if let Some(text) = n.as_text() {
+            !text.trim().is_empty()
+        } else if n.is_elem(t::IMG) {
+            n.attr(a::ALT).map_or(false, |v| !v.trim().is_empty())
+        } else {
+            false
+        }
+    })
+}
+
+fn heading_level(node: NodeRef<'_>) -> Option<u8> {
+    [t::H1, t::H2, t::H3, t::H4, t::H5, t::H6]
+        .iter()
+        .position(|h| node.is_elem(h.clone()))
+        .map(|i| i as u8 + 1)
+}
@@ -0,0 +1,235 @@
+//! HTML validity linting, with pluggable rules.
+//!
+//! A [`Linter`] walks a `Document` once, calling each registered
+//! [`LintRule`] for every node. The default linter includes all rules of
+//! this module; custom rules may be added by implementing `LintRule`.
+
+use std::collections::HashSet;
+
+use crate::dom::{
+    audit::{set_spans, Diagnostic, Severity},
+    html::{a, t},
+    Document, NodeData, NodeRef,
+};
+
+/// A rule for use with a [`Linter`].
+pub trait LintRule {
+    /// Return the code of diagnostics reported by this rule, e.g.
+    /// `duplicate-id`.
+    fn code(&self) -> &'static str;
+
+    /// Called before checking each `Document`, to reset any state. The
+    /// default implementation does nothing.
+    fn begin(&mut self, _doc: &Document) {}
+
+    /// Check the node, pushing any diagnostics to `out`. Called for every
+    /// node of the `Document`, in document order.
+    fn check(&mut self, node: NodeRef<'_>, out: &mut Vec<Diagnostic>);
+}
+
+/// A registry of [`LintRule`]s, applied in a single pass.
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Linter {
+    /// Construct a new linter, without any rules.
+    pub fn new() -> Linter {
+        Linter { rules: Vec::new() }
+    }
+
+    /// Add a rule, returning self.
+    pub fn rule<R>(mut self, rule: R) -> Self
+        where R: LintRule + 'static
+    {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Return the codes of all registered rules.
+    pub fn codes(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|r| r.code()).collect()
+    }
+
+    /// Check the document, returning diagnostics in document order (and
+    /// then rule order), with source spans if recorded.
+    pub fn check(&mut self, doc: &Document) -> Vec<Diagnostic> {
+        for rule in &mut self.rules {
+            rule.begin(doc);
+        }
+        let mut diags = Vec::new();
+        for node in doc.document_node_ref().descendants() {
+            for rule in &mut self.rules {
+                rule.check(node, &mut diags);
+            }
+        }
+        set_spans(doc, &mut diags);
+        diags
+    }
+}
+
+/// The default linter includes, in order: [`ObsoleteTags`],
+/// [`DuplicateIds`], [`InvalidNesting`] and [`InvalidChars`].
+impl Default for Linter {
+    fn default() -> Linter {
+        Linter::new()
+            .rule(ObsoleteTags)
+            .rule(DuplicateIds::default())
+            .rule(InvalidNesting)
+            .rule(InvalidChars)
+    }
+}
+
+/// Reports elements which are deprecated (obsolete) in HTML 5, per
+/// [`TagMeta::is_deprecated`](crate::html::TagMeta::is_deprecated), e.g.
+/// `<font>` or `<center>`, as warnings.
+#[derive(Clone, Debug, Default)]
+pub struct ObsoleteTags;
+
+impl LintRule for ObsoleteTags {
+    fn code(&self) -> &'static str {
+        "obsolete-tag"
+    }
+
+    fn check(&mut self, node: NodeRef<'_>, out: &mut Vec<Diagnostic>) {
+        if let Some(elm) = node.as_element() {
+            if elm.html_tag_meta().map_or(false, |m| m.is_deprecated()) {
+                out.push(Diagnostic::new(
+                    node.id(), self.code(), Severity::Warning,
+                    format!("obsolete element <{}>", elm.name.local)));
+            }
+        }
+    }
+}
+
+/// Reports each element with the same `id` attribute value as a prior
+/// element, as errors.
+#[derive(Clone, Debug, Default)]
+pub struct DuplicateIds {
+    seen: HashSet<String>,
+}
+
+impl LintRule for DuplicateIds {
+    fn code(&self) -> &'static str {
+        "duplicate-id"
+    }
+
+    fn begin(&mut self, _doc: &Document) {
+        self.seen.clear();
+    }
+
+    fn check(&mut self, node: NodeRef<'_>, out: &mut Vec<Diagnostic>) {
+        let id = match node.attr(a::ID) {
+            Some(id) if !id.is_empty() => id,
+            _ => return,
+        };
+        if self.seen.contains(&**id) {
+            out.push(Diagnostic::new(
+                node.id(), self.code(), Severity::Error,
+                format!("duplicate id \"{}\"", id)));
+        } else {
+            self.seen.insert(id.to_string());
+        }
+    }
+}
+
+/// Reports elements not allowed by their parent, as errors: block elements
+/// within inline (phrasing) elements, e.g. `<p>` inside `<span>`, any
+/// element within a `<p>` which would close it on parsing, and elements with
+/// a disallowed parent per
+/// [`TagMeta::allowed_parents`](crate::html::TagMeta::allowed_parents), e.g.
+/// `<li>` outside a list.
+///
+/// Block nesting of this kind can't result from HTML parsing, but may from
+/// XML parsing or mutation of the `Document`. The `<a>`, `<ins>`, `<del>`,
+/// `<object>`, `<video>`, `<audio>`, `<canvas>`, `<map>` and `<noscript>`
+/// elements are transparent to their parent's content model, so are allowed
+/// to contain blocks.
+#[derive(Clone, Debug, Default)]
+pub struct InvalidNesting;
+
+impl LintRule for InvalidNesting {
+    fn code(&self) -> &'static str {
+        "invalid-nesting"
+    }
+
+    fn check(&mut self, node: NodeRef<'_>, out: &mut Vec<Diagnostic>) {
+        let (elm, meta) = match node.as_element()
+            .and_then(|e| e.html_tag_meta().map(|m| (e, m)))
+        {
+            Some(em) => em,
+            None => return,
+        };
+        let parent = match node.parent().and_then(|p| p.as_element()) {
+            Some(p) => p,
+            None => return,
+        };
+        let name = &elm.name.local;
+        let msg = if !meta.allows_parent(&parent.name.local) {
+            format!("<{}> not allowed in <{}>", name, parent.name.local)
+        } else if meta.is_block() && !meta.is_empty() {
+            let container = node.ancestors()
+                .filter_map(|n| n.as_element())
+                .find(|e| !is_transparent(&e.name.local));
+            match container {
+                Some(c) if c.name.local == t::P ||
+                    c.html_tag_meta().map_or(false, |m| m.is_inline()) =>
+                {
+                    format!("block <{}> in <{}>", name, c.name.local)
+                }
+                _ => return,
+            }
+        } else {
+            return;
+        };
+        out.push(Diagnostic::new(
+            node.id(), self.code(), Severity::Error, msg));
+    }
+}
+
+/// Reports text and attribute values containing characters which are
+/// invalid in HTML however escaped, as errors: control characters other
+/// than whitespace, non-characters, and U+FFFD REPLACEMENT CHARACTER, which
+/// typically results from encoding errors.
+///
+/// Note that the `Document` does not retain how characters were escaped in
+/// the source. Unescaped markup characters such as a bare `<` or `&` are
+/// reported by the parser, via
+/// [`parse_utf8_checked`](crate::html::parse_utf8_checked).
+#[derive(Clone, Debug, Default)]
+pub struct InvalidChars;
+
+impl LintRule for InvalidChars {
+    fn code(&self) -> &'static str {
+        "invalid-char"
+    }
+
+    fn check(&mut self, node: NodeRef<'_>, out: &mut Vec<Diagnostic>) {
+        let found = match &node.data {
+            NodeData::Text(text) => text.chars().find(|&c| is_invalid(c)),
+            NodeData::Elem(elm) => elm.attrs.iter()
+                .flat_map(|attr| attr.value.chars())
+                .find(|&c| is_invalid(c)),
+            _ => None,
+        };
+        if let Some(c) = found {
+            out.push(Diagnostic::new(
+                node.id(), self.code(), Severity::Error,
+                format!("invalid character U+{:04X}", c as u32)));
+        }
+    }
+}
+
+fn is_invalid(c: char) -> bool {
+    let u = c as u32;
+    (c.is_control() && !c.is_whitespace()) ||
+        c == '\u{FFFD}' ||
+        (0xFDD0..=0xFDEF).contains(&u) ||
+        (u & 0xFFFE) == 0xFFFE
+}
+
+fn is_transparent(name: &str) -> bool {
+    ["a", "ins", "del", "object", "video", "audio", "canvas", "map",
+     "noscript"]
+        .contains(&name)
+}
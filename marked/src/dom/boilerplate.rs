@@ -0,0 +1,183 @@
+//! Detection of boilerplate sub-trees repeated across a corpus of documents.
+//!
+//! Navigation menus, headers, footers and the like are typically repeated
+//! verbatim across many pages of a site. A [`Fingerprint`] is computed for
+//! each block-level element sub-tree from its tag path and normalized text.
+//! Fingerprints of a sample of pages are accumulated in [`CorpusStats`], then
+//! [`Document::mark_boilerplate`] marks those sub-trees of a page which are
+//! frequent across the corpus. The [`filter::detach_boilerplate`] filter may
+//! then be used to remove them.
+//!
+//! [`filter::detach_boilerplate`]: crate::filter::detach_boilerplate
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dom::{dedup::Fnv, html::t, Document, NodeId, NodeRef};
+
+/// A 64-bit fingerprint of an element sub-tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub u64);
+
+/// User data marker for boilerplate sub-trees, as set by
+/// [`Document::mark_boilerplate`].
+///
+/// See [`Document::user_data`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Boilerplate {
+    /// The fraction of corpus documents containing the sub-tree.
+    pub frequency: f32,
+}
+
+/// Fingerprint occurrence counts across a corpus of documents.
+#[derive(Clone, Debug, Default)]
+pub struct CorpusStats {
+    documents: u32,
+    counts: HashMap<Fingerprint, u32>,
+}
+
+impl CorpusStats {
+    /// Construct a new empty `CorpusStats`.
+    pub fn new() -> CorpusStats {
+        CorpusStats::default()
+    }
+
+    /// Add the fingerprints of a document to the corpus. Each distinct
+    /// fingerprint is counted at most once per document.
+    pub fn add(&mut self, doc: &Document) {
+        self.documents += 1;
+        let distinct: HashSet<Fingerprint> = fingerprints(doc)
+            .into_iter()
+            .map(|(_, fp)| fp)
+            .collect();
+        for fp in distinct {
+            *self.counts.entry(fp).or_insert(0) += 1;
+        }
+    }
+
+    /// Return the number of documents added.
+    pub fn documents(&self) -> u32 {
+        self.documents
+    }
+
+    /// Return the number of documents containing the fingerprint.
+    pub fn count(&self, fp: Fingerprint) -> u32 {
+        self.counts.get(&fp).copied().unwrap_or(0)
+    }
+
+    /// Return the fraction (0.0 to 1.0) of documents containing the
+    /// fingerprint, or 0.0 if no documents have been added.
+    pub fn frequency(&self, fp: Fingerprint) -> f32 {
+        if self.documents == 0 {
+            0.0
+        } else {
+            self.count(fp) as f32 / self.documents as f32
+        }
+    }
+}
+
+/// Return the fingerprints of all candidate sub-trees of the document, in
+/// document order.
+///
+/// Candidates are block-level elements under `<body>` (or otherwise the root
+/// element) with non-empty text. The fingerprint is a hash of the element's
+/// tag path (e.g. `html/body/div/footer`) and its text, normalized by
+/// collapsing whitespace, lower casing, and replacing all ASCII digits with
+/// `0` (so that dates, counters and the like don't prevent matching).
+pub fn fingerprints(doc: &Document) -> Vec<(NodeId, Fingerprint)> {
+    let mut out = Vec::new();
+    if let Some(root) = doc.root_element_ref() {
+        let mut path = String::new();
+        collect(root, &mut path, &mut out);
+    }
+    out
+}
+
+fn collect(
+    node: NodeRef<'_>,
+    path: &mut String,
+    out: &mut Vec<(NodeId, Fingerprint)>)
+{
+    let elm = match node.as_element() {
+        Some(elm) => elm,
+        None => return,
+    };
+    if elm.is_elem(t::HEAD) {
+        return;
+    }
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('/');
+    }
+    path.push_str(&elm.name.local);
+
+    let candidate = !elm.is_elem(t::HTML) && !elm.is_elem(t::BODY) &&
+        elm.html_tag_meta().map_or(true, |tm| !tm.is_inline());
+    if candidate {
+        if let Some(fp) = fingerprint(node, path) {
+            out.push((node.id(), fp));
+        }
+    }
+    for child in node.children() {
+        collect(child, path, out);
+    }
+    path.truncate(len);
+}
+
+fn fingerprint(node: NodeRef<'_>, path: &str) -> Option<Fingerprint> {
+    let text = node.text()?;
+    let mut hash = Fnv::new();
+    hash.write(path.as_bytes());
+    hash.write(&[0]);
+    let mut empty = true;
+    let mut space = false;
+    for w in text.split_whitespace() {
+        if space {
+            hash.write(b" ");
+        }
+        for c in w.chars() {
+            if c.is_ascii_digit() {
+                hash.write(b"0");
+            } else {
+                for lc in c.to_lowercase() {
+                    let mut buf = [0u8; 4];
+                    hash.write(lc.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        space = true;
+        empty = false;
+    }
+    if empty { None } else { Some(Fingerprint(hash.0)) }
+}
+
+/// Boilerplate detection methods.
+impl Document {
+    /// Mark sub-trees with a fingerprint found in at least `min_frequency`
+    /// (0.0 to 1.0) of the documents of the corpus, by setting
+    /// [`Boilerplate`] user data, and return the number marked.
+    ///
+    /// Only the outermost of any nested boilerplate sub-trees are marked.
+    /// Nothing is marked if the corpus contains fewer than two documents.
+    pub fn mark_boilerplate(&mut self, stats: &CorpusStats, min_frequency: f32)
+        -> usize
+    {
+        if stats.documents() < 2 {
+            return 0;
+        }
+        let mut marked: HashSet<NodeId> = HashSet::new();
+        for (id, fp) in fingerprints(self) {
+            let frequency = stats.frequency(fp);
+            if stats.count(fp) < 2 || frequency < min_frequency {
+                continue;
+            }
+            let nested = self.node_and_ancestors(id)
+                .skip(1)
+                .any(|a| marked.contains(&a));
+            if !nested {
+                marked.insert(id);
+                self.set_user_data(id, Boilerplate { frequency });
+            }
+        }
+        marked.len()
+    }
+}
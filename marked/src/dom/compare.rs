@@ -0,0 +1,238 @@
+//! Structural comparison of documents, optionally ignoring differences.
+
+use std::fmt::Write;
+
+use crate::chars::is_all_ctrl_ws;
+use crate::dom::{Attribute, Document, NodeData, NodeId, NodeRef};
+
+/// Options for [`Document::eq_ignoring`].
+///
+/// The default options require exact structural equality.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EqOptions {
+    ignore_attr_order: bool,
+    ignore_whitespace: bool,
+    ignore_comments: bool,
+}
+
+impl EqOptions {
+    /// Construct new default options.
+    pub fn new() -> EqOptions {
+        EqOptions::default()
+    }
+
+    /// If true, compare element attributes as sets, ignoring their order.
+    /// Default: false.
+    pub fn ignore_attr_order(mut self, ignore: bool) -> Self {
+        self.ignore_attr_order = ignore;
+        self
+    }
+
+    /// If true, ignore text nodes containing only whitespace (or control
+    /// characters). Default: false.
+    pub fn ignore_whitespace(mut self, ignore: bool) -> Self {
+        self.ignore_whitespace = ignore;
+        self
+    }
+
+    /// If true, ignore comment nodes. Default: false.
+    pub fn ignore_comments(mut self, ignore: bool) -> Self {
+        self.ignore_comments = ignore;
+        self
+    }
+
+    fn is_ignored(&self, data: &NodeData) -> bool {
+        match data {
+            NodeData::Comment(_) => self.ignore_comments,
+            NodeData::Text(t) => self.ignore_whitespace && is_all_ctrl_ws(t),
+            _ => false,
+        }
+    }
+}
+
+/// Comparison methods.
+impl Document {
+    /// Return true if this and the other `Document` are structurally equal,
+    /// as modified by the given options.
+    ///
+    /// Nodes are compared by type, element name and attributes, and text (or
+    /// other character data), in tree order. `NodeId`s, unreachable nodes
+    /// and user data are not compared.
+    pub fn eq_ignoring(&self, other: &Document, options: EqOptions) -> bool {
+        self.difference(other, options).is_none()
+    }
+
+    /// Return a description of the first structural difference between this
+    /// and the other `Document`, including the path to the differing node,
+    /// or `None` if equal as per [`Document::eq_ignoring`].
+    ///
+    /// This is used by the [`assert_doc_eq!`](crate::assert_doc_eq) macro.
+    pub fn difference(&self, other: &Document, options: EqOptions)
+        -> Option<String>
+    {
+        let mut stack = vec![(
+            Document::DOCUMENT_NODE_ID,
+            Document::DOCUMENT_NODE_ID
+        )];
+        while let Some((l, r)) = stack.pop() {
+            let (ln, rn) = (NodeRef::new(self, l), NodeRef::new(other, r));
+            if let Some(diff) = data_difference(&ln, &rn, options) {
+                return Some(format!("at {}: {}", path(ln), diff));
+            }
+            let lcs: Vec<_> = ln.children()
+                .filter(|c| !options.is_ignored(c))
+                .collect();
+            let rcs: Vec<_> = rn.children()
+                .filter(|c| !options.is_ignored(c))
+                .collect();
+            if lcs.len() != rcs.len() {
+                return Some(format!(
+                    "at {}: {} children != {} children\n  left:  {}\n  \
+                     right: {}",
+                    path(ln), lcs.len(), rcs.len(),
+                    summary(&lcs), summary(&rcs)));
+            }
+            for (lc, rc) in lcs.into_iter().zip(rcs).rev() {
+                stack.push((lc.id(), rc.id()));
+            }
+        }
+        None
+    }
+}
+
+fn data_difference(l: &NodeRef<'_>, r: &NodeRef<'_>, options: EqOptions)
+    -> Option<String>
+{
+    match (&l.data, &r.data) {
+        (NodeData::Elem(le), NodeData::Elem(re)) => {
+            if le.name != re.name {
+                return Some(format!(
+                    "element <{}> != <{}>", le.name.local, re.name.local));
+            }
+            let (mut la, mut ra): (Vec<&Attribute>, Vec<&Attribute>) =
+                (le.attrs.iter().collect(), re.attrs.iter().collect());
+            if options.ignore_attr_order {
+                la.sort_by(|a, b| a.name.cmp(&b.name));
+                ra.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            if la != ra {
+                return Some(format!(
+                    "attributes {} != {}", attrs(&la), attrs(&ra)));
+            }
+            None
+        }
+        (NodeData::Text(lt), NodeData::Text(rt)) if lt != rt => {
+            Some(format!("text {:?} != {:?}", &**lt, &**rt))
+        }
+        (NodeData::Comment(lt), NodeData::Comment(rt)) if lt != rt => {
+            Some(format!("comment {:?} != {:?}", &**lt, &**rt))
+        }
+        (NodeData::DocType(ld), NodeData::DocType(rd))
+            if ld.name != rd.name =>
+        {
+            Some(format!("doctype {:?} != {:?}", &*ld.name, &*rd.name))
+        }
+        (NodeData::Pi(lp), NodeData::Pi(rp)) if lp.data != rp.data => {
+            Some(format!("pi {:?} != {:?}", &*lp.data, &*rp.data))
+        }
+        (ld, rd) => {
+            let (lk, rk) = (kind(ld), kind(rd));
+            if lk != rk {
+                Some(format!("{} != {}", lk, rk))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Return a path to the node, e.g. `html > body > p[2]`, where the index is
+// of children of the same name.
+fn path(node: NodeRef<'_>) -> String {
+    let mut parts = Vec::new();
+    for n in node.node_and_ancestors() {
+        let part = match n.as_element() {
+            Some(e) => {
+                let index = n.prev_siblings()
+                    .filter(|s| s.is_elem(e.name.local.clone()))
+                    .count();
+                if index > 0 {
+                    format!("{}[{}]", e.name.local, index + 1)
+                } else {
+                    e.name.local.to_string()
+                }
+            }
+            None if n.parent().is_none() => "#document".to_string(),
+            None => kind(&n.data).to_string(),
+        };
+        parts.push(part);
+    }
+    parts.reverse();
+    parts.join(" > ")
+}
+
+// Return a short summary of nodes, e.g. `[<p>, text, <div>]`.
+fn summary(nodes: &[NodeRef<'_>]) -> String {
+    let mut out = String::from("[");
+    for (i, n) in nodes.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match n.as_element() {
+            Some(e) => write!(out, "<{}>", e.name.local).unwrap(),
+            None => out.push_str(kind(&n.data)),
+        }
+    }
+    out.push(']');
+    out
+}
+
+fn attrs(attrs: &[&Attribute]) -> String {
+    let mut out = String::from("[");
+    for (i, a) in attrs.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write!(out, "{}={:?}", a.name.local, &*a.value).unwrap();
+    }
+    out.push(']');
+    out
+}
+
+fn kind(data: &NodeData) -> &'static str {
+    match data {
+        NodeData::Hole => "hole",
+        NodeData::Document => "document",
+        NodeData::DocType(_) => "doctype",
+        NodeData::Text(_) => "text",
+        NodeData::Comment(_) => "comment",
+        NodeData::Elem(_) => "element",
+        NodeData::Pi(_) => "pi",
+    }
+}
+
+/// Assert that two `Document`s are structurally equal, optionally ignoring
+/// differences as per the given [`EqOptions`](crate::EqOptions).
+///
+/// On failure, the panic message describes the first difference, including
+/// the path to the differing node, followed by the
+/// [`debug_tree`](crate::Document::debug_tree) of each. For example:
+///
+/// ```no_run
+/// assert_doc_eq!(doc, expected);
+/// assert_doc_eq!(doc, expected, EqOptions::new().ignore_comments(true));
+/// ```
+#[macro_export]
+macro_rules! assert_doc_eq {
+    ($left:expr, $right:expr $(,)?) => (
+        $crate::assert_doc_eq!($left, $right, $crate::EqOptions::default())
+    );
+    ($left:expr, $right:expr, $options:expr $(,)?) => (
+        if let Some(diff) = $left.difference(&$right, $options) {
+            panic!(
+                "assertion failed: documents differ {}\n\
+                 left:\n{}\nright:\n{}",
+                diff, $left.debug_tree(), $right.debug_tree());
+        }
+    );
+}
@@ -0,0 +1,378 @@
+//! Basic CSS parsing, of `<style>` element stylesheets and `style`
+//! attribute declarations, and matching of rules to elements.
+//!
+//! This is a lightweight, dependency free parser, sufficient for inlining,
+//! sanitizing and simple rendering heuristics: it does not validate property
+//! names or values, nor interpret `@media` or other at-rules. Selector
+//! matching is limited to the subset of syntax supported by
+//! [`NodeRef::matches`]; rules with other selectors are parsed but never
+//! match.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::dom::{
+    html::{a, t},
+    node_ref::selector_matches,
+    Document, NodeRef,
+};
+
+/// A CSS selector specificity, as counts of (ids, classes and attributes,
+/// types).
+pub type Specificity = (u32, u32, u32);
+
+/// A single property declaration, e.g. `color: red`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Declaration {
+    /// The property name, lower-cased.
+    pub name: String,
+
+    /// The value, trimmed and without any `!important`.
+    pub value: String,
+
+    /// True if the value was marked `!important`.
+    pub important: bool,
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)?;
+        if self.important {
+            write!(f, " !important")?;
+        }
+        Ok(())
+    }
+}
+
+/// A style rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// The comma separated selector list, as found.
+    pub selectors: String,
+
+    /// The declarations of the rule block.
+    pub declarations: Vec<Declaration>,
+}
+
+impl Rule {
+    /// Return the specificity of the most specific selector of this rule
+    /// that matches the node, or `None` if no selector matches.
+    pub fn match_specificity(&self, node: NodeRef<'_>)
+        -> Option<Specificity>
+    {
+        let elm = node.as_element()?;
+        self.selectors.split(',')
+            .map(str::trim)
+            .filter(|s| is_compound(s) && selector_matches(elm, s))
+            .map(specificity)
+            .max()
+    }
+
+    /// Return true if any selector of this rule matches the node.
+    pub fn matches(&self, node: NodeRef<'_>) -> bool {
+        self.match_specificity(node).is_some()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {{ {} }}", self.selectors, to_style(&self.declarations))
+    }
+}
+
+/// A top level item of a stylesheet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    /// A style rule.
+    Rule(Rule),
+
+    /// An at-rule such as `@media` or `@import`, as its original text.
+    AtRule(String),
+}
+
+/// CSS methods.
+impl Document {
+    /// Return the style rules of all `<style>` elements, in document order.
+    ///
+    /// Elements with a `media` attribute other than `all` or `screen` are
+    /// excluded, as are at-rules (including the content of `@media` blocks).
+    pub fn style_rules(&self) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        for style in self.document_node_ref()
+            .descendants()
+            .filter(|n| n.is_elem(t::STYLE) && is_screen(*n))
+        {
+            let text = style.text().unwrap_or_default();
+            for item in parse_stylesheet(&text) {
+                if let Item::Rule(r) = item {
+                    rules.push(r);
+                }
+            }
+        }
+        rules
+    }
+}
+
+/// CSS methods.
+impl<'a> NodeRef<'a> {
+    /// Return the declarations of any `style` attribute of this element.
+    pub fn inline_style(&self) -> Vec<Declaration> {
+        self.attr(a::STYLE)
+            .map(|s| parse_declarations(s))
+            .unwrap_or_default()
+    }
+
+    /// Return the value of the property from the `style` attribute, if
+    /// declared, with the last declaration taking precedence.
+    pub fn style_property(&self, name: &str) -> Option<String> {
+        self.inline_style()
+            .into_iter()
+            .rev()
+            .find(|d| d.name.eq_ignore_ascii_case(name))
+            .map(|d| d.value)
+    }
+}
+
+/// Return the cascaded declarations for the node: those of the matching
+/// rules, in order of specificity and then rule order, followed by those of
+/// its `style` attribute, where each property is declared once, with the
+/// last taking precedence, unless a prior declaration is `!important` and
+/// the latter is not. Inheritance from ancestors is not considered.
+pub fn cascade(node: NodeRef<'_>, rules: &[Rule]) -> Vec<Declaration> {
+    let mut matched: Vec<(Specificity, &Rule)> = rules.iter()
+        .filter_map(|r| r.match_specificity(node).map(|s| (s, r)))
+        .collect();
+    // Stable sort preserves rule order for equal specificity
+    matched.sort_by_key(|&(s, _)| s);
+
+    let mut merged: Vec<Declaration> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let inline = node.inline_style();
+    let decls = matched.iter()
+        .flat_map(|(_, r)| r.declarations.iter())
+        .chain(inline.iter());
+    for d in decls {
+        match index.get(&d.name) {
+            Some(&i) => {
+                if !merged[i].important || d.important {
+                    merged[i] = d.clone();
+                }
+            }
+            None => {
+                index.insert(d.name.clone(), merged.len());
+                merged.push(d.clone());
+            }
+        }
+    }
+    merged
+}
+
+// Return true if the style element applies to screen media.
+pub(crate) fn is_screen(style: NodeRef<'_>) -> bool {
+    style.attr(a::MEDIA).map_or(true, |m| {
+        let m = m.trim();
+        m.is_empty() ||
+            m.eq_ignore_ascii_case("all") ||
+            m.eq_ignore_ascii_case("screen")
+    })
+}
+
+/// Parse a stylesheet, as found in a `<style>` element. Comments are removed,
+/// and any trailing, unterminated content is ignored.
+pub fn parse_stylesheet(css: &str) -> Vec<Item> {
+    let css = strip_comments(css);
+    let mut items = Vec::new();
+    let mut rest = css.trim_start();
+    while !rest.is_empty() {
+        if rest.starts_with('@') {
+            let end = match scan(rest, &[';', '{']) {
+                Some(i) if rest[i..].starts_with('{') => {
+                    match block_end(&rest[i..]) {
+                        Some(j) => i + j,
+                        None => break,
+                    }
+                }
+                Some(i) => i + 1,
+                None => break,
+            };
+            items.push(Item::AtRule(rest[..end].trim().to_owned()));
+            rest = rest[end..].trim_start();
+        } else {
+            let open = match scan(rest, &['{']) {
+                Some(i) => i,
+                None => break,
+            };
+            let end = match block_end(&rest[open..]) {
+                Some(j) => open + j,
+                None => break,
+            };
+            let selectors = rest[..open].trim();
+            if !selectors.is_empty() {
+                items.push(Item::Rule(Rule {
+                    selectors: selectors.to_owned(),
+                    declarations: parse_declarations(&rest[open+1..end-1]),
+                }));
+            }
+            rest = rest[end..].trim_start();
+        }
+    }
+    items
+}
+
+/// Parse a semicolon separated declaration list, as found in a `style`
+/// attribute or rule block. Invalid declarations are skipped.
+pub fn parse_declarations(decls: &str) -> Vec<Declaration> {
+    let decls = strip_comments(decls);
+    let mut out = Vec::new();
+    let mut rest = &decls[..];
+    loop {
+        let end = scan(rest, &[';']).unwrap_or_else(|| rest.len());
+        if let Some(d) = parse_declaration(&rest[..end]) {
+            out.push(d);
+        }
+        if end >= rest.len() {
+            break;
+        }
+        rest = &rest[end+1..];
+    }
+    out
+}
+
+fn parse_declaration(decl: &str) -> Option<Declaration> {
+    let colon = decl.find(':')?;
+    let name = decl[..colon].trim();
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return None;
+    }
+    let mut value = decl[colon+1..].trim();
+    let mut important = false;
+    if let Some(i) = value.rfind('!') {
+        if value[i+1..].trim().eq_ignore_ascii_case("important") {
+            important = true;
+            value = value[..i].trim_end();
+        }
+    }
+    if value.is_empty() {
+        return None;
+    }
+    Some(Declaration {
+        name: name.to_ascii_lowercase(),
+        value: value.to_owned(),
+        important,
+    })
+}
+
+/// Write declarations in `style` attribute syntax.
+pub fn to_style(decls: &[Declaration]) -> String {
+    decls.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Return the specificity of a compound selector, as per
+/// [`NodeRef::matches`].
+pub fn specificity(compound: &str) -> Specificity {
+    let mut spec = (0, 0, 0);
+    let mut in_attr = false;
+    let mut at_start = true;
+    for c in compound.trim().chars() {
+        match c {
+            ']' => in_attr = false,
+            _ if in_attr => {}
+            '[' => {
+                in_attr = true;
+                spec.1 += 1;
+            }
+            '#' => spec.0 += 1,
+            '.' => spec.1 += 1,
+            '*' => {}
+            _ if at_start => spec.2 += 1,
+            _ => {}
+        }
+        at_start = false;
+    }
+    spec
+}
+
+/// Return true if the selector is a single compound selector (no
+/// combinators or pseudo-classes), as supported by
+/// [`NodeRef::matches`].
+pub fn is_compound(selector: &str) -> bool {
+    let mut in_attr = false;
+    !selector.trim().is_empty() && selector.trim().chars().all(|c| {
+        match c {
+            '[' => in_attr = true,
+            ']' => in_attr = false,
+            _ => {}
+        }
+        in_attr || !(c.is_whitespace() || ">+~:,".contains(c))
+    })
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(i) = rest.find("/*") {
+        out.push_str(&rest[..i]);
+        match rest[i+2..].find("*/") {
+            Some(j) => rest = &rest[i+2+j+2..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Return the index of the first of the chars found outside of any quoted
+// string or parentheses.
+fn scan(s: &str, chars: &[char]) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0u32;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && chars.contains(&c) {
+            return Some(i);
+        }
+    }
+    None
+}
+
+// Given a string starting with `{`, return the index after the matching
+// `}`, accounting for nesting.
+fn block_end(s: &str) -> Option<usize> {
+    let mut depth = 0u32;
+    let mut rest = s;
+    let mut offset = 0;
+    while let Some(i) = scan(rest, &['{', '}']) {
+        if rest[i..].starts_with('{') {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return Some(offset + i + 1);
+            }
+        }
+        offset += i + 1;
+        rest = &rest[i+1..];
+    }
+    None
+}
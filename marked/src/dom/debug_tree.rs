@@ -0,0 +1,97 @@
+//! Indented tree dumps of documents and nodes, for debugging.
+
+use std::fmt::Write;
+
+use crate::dom::{Document, NodeData, NodeRef};
+
+/// Maximum chars of text, comment and other character data shown.
+const MAX_TEXT: usize = 40;
+
+/// Tree dump methods.
+impl Document {
+    /// Return a deterministic, indented text representation of the tree,
+    /// suitable for snapshot testing.
+    ///
+    /// See [`NodeRef::debug_tree`] for details.
+    pub fn debug_tree(&self) -> String {
+        self.document_node_ref().debug_tree()
+    }
+}
+
+impl<'a> NodeRef<'a> {
+    /// Return a deterministic, indented text representation of this node and
+    /// its descendants, suitable for snapshot testing.
+    ///
+    /// Each node is output on its own line, indented two spaces per level,
+    /// with elements shown as start tags with attributes sorted by name, and
+    /// text and comments quoted and escaped, e.g. `"a\n"`, truncated to 40
+    /// chars with a trailing `…`. Unlike the serialized form, whitespace is
+    /// visible and the result is independent of attribute order.
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        let mut stack = vec![(*self, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            for _ in 0..depth {
+                out.push_str("  ");
+            }
+            write_node(&mut out, &node);
+            out.push('\n');
+            for child in node.children().rev() {
+                stack.push((child, depth + 1));
+            }
+        }
+        out
+    }
+}
+
+fn write_node(out: &mut String, node: &NodeRef<'_>) {
+    match &node.data {
+        NodeData::Document => out.push_str("#document"),
+        NodeData::Hole => out.push_str("#hole"),
+        NodeData::DocType(dt) => {
+            write!(out, "<!DOCTYPE {}>", &*dt.name).unwrap();
+        }
+        NodeData::Text(t) => write_quoted(out, t),
+        NodeData::Comment(t) => {
+            out.push_str("<!-- ");
+            write_quoted(out, t);
+            out.push_str(" -->");
+        }
+        NodeData::Pi(pi) => {
+            out.push_str("<? ");
+            write_quoted(out, &pi.data);
+            out.push_str(" ?>");
+        }
+        NodeData::Elem(elm) => {
+            write!(out, "<{}", elm.name.local).unwrap();
+            let mut attrs: Vec<_> = elm.attrs.iter().collect();
+            attrs.sort_by(|a, b| {
+                let ka = (&*a.name.local, a.name.prefix.as_ref().map(|p| &**p));
+                let kb = (&*b.name.local, b.name.prefix.as_ref().map(|p| &**p));
+                ka.cmp(&kb)
+            });
+            for attr in attrs {
+                out.push(' ');
+                if let Some(ref prefix) = attr.name.prefix {
+                    write!(out, "{}:", prefix).unwrap();
+                }
+                write!(out, "{}=", attr.name.local).unwrap();
+                write_quoted(out, &attr.value);
+            }
+            out.push('>');
+        }
+    }
+}
+
+// Write text quoted and escaped, truncated to MAX_TEXT chars.
+fn write_quoted(out: &mut String, text: &str) {
+    out.push('"');
+    let mut chars = text.chars();
+    for c in chars.by_ref().take(MAX_TEXT) {
+        write!(out, "{}", c.escape_debug()).unwrap();
+    }
+    out.push('"');
+    if chars.next().is_some() {
+        out.push('…');
+    }
+}
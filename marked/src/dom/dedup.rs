@@ -0,0 +1,163 @@
+//! Content hashing and near-duplicate detection.
+//!
+//! [`NodeRef::content_hash`] gives an exact fingerprint of content which is
+//! insensitive to whitespace and attribute order. [`simhash`] (and
+//! [`NodeRef::simhash`]) give locality sensitive fingerprints of text, where
+//! the fingerprints of near-duplicate texts differ in only a few bits, as
+//! counted by [`hamming_distance`]. All hashes are stable across platforms
+//! and releases, so may be persisted.
+
+use crate::dom::{NodeData, NodeId, NodeRef};
+
+/// The number of consecutive words per shingle in [`simhash`].
+pub const SHINGLE_WORDS: usize = 3;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// 64-bit FNV-1a, used (unlike `DefaultHasher`) for its stability.
+pub(crate) struct Fnv(pub(crate) u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Fnv {
+        Fnv(FNV_OFFSET)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // Write a tagged and length delimited field, so that concatenations of
+    // distinct fields can't collide.
+    pub(crate) fn field(&mut self, tag: u8, bytes: &[u8]) {
+        self.write(&[tag]);
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+}
+
+enum Step {
+    Enter(NodeId),
+    Exit,
+}
+
+/// Content hashing methods.
+impl<'a> NodeRef<'a> {
+    /// Return a 64-bit hash of the content of this node and its
+    /// descendants: element names, attributes and text.
+    ///
+    /// Whitespace is only significant as a separator of words, in text and
+    /// attribute values, and attributes are hashed in sorted order, so for
+    /// example `<p id="a" class="x  y"> b c</p>` and
+    /// `<p class="x y" id="a">b  c</p>` hash the same. Comments, processing
+    /// instructions and doctypes are ignored.
+    pub fn content_hash(&self) -> u64 {
+        let mut h = Fnv::new();
+        let mut stack = vec![Step::Enter(self.id())];
+        while let Some(step) = stack.pop() {
+            let id = match step {
+                Step::Enter(id) => id,
+                Step::Exit => {
+                    h.write(b">");
+                    continue;
+                }
+            };
+            let node = NodeRef::new(self.doc(), id);
+            match &node.data {
+                NodeData::Elem(elm) => {
+                    h.field(b'<', elm.name.ns.as_bytes());
+                    h.field(b'<', elm.name.local.as_bytes());
+                    let mut attrs: Vec<_> = elm.attrs.iter()
+                        .map(|a| (&*a.name.ns, &*a.name.local, &*a.value))
+                        .collect();
+                    attrs.sort();
+                    for (ns, local, value) in attrs {
+                        h.field(b'=', ns.as_bytes());
+                        h.field(b'=', local.as_bytes());
+                        for w in value.split_whitespace() {
+                            h.field(b'"', w.as_bytes());
+                        }
+                    }
+                    stack.push(Step::Exit);
+                }
+                NodeData::Text(t) => {
+                    for w in t.split_whitespace() {
+                        h.field(b'w', w.as_bytes());
+                    }
+                    continue;
+                }
+                NodeData::Document => {}
+                _ => continue,
+            }
+            for child in node.children().rev() {
+                stack.push(Step::Enter(child.id()));
+            }
+        }
+        h.0
+    }
+
+    /// Return the [`simhash`] of the text content of this node and its
+    /// descendants.
+    pub fn simhash(&self) -> u64 {
+        self.text().map_or(0, |t| simhash(&t))
+    }
+}
+
+/// Return a 64-bit SimHash fingerprint of the text.
+///
+/// Text is split into words on whitespace, with leading and trailing
+/// punctuation removed, and lower-cased. Each shingle of
+/// [`SHINGLE_WORDS`] consecutive words (or all words, if fewer) is then
+/// hashed and contributes equally to the fingerprint. Texts with no words
+/// return 0.
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let hash = |shingle: &[String]| {
+        let mut h = Fnv::new();
+        for w in shingle {
+            h.field(b'w', w.as_bytes());
+        }
+        h.0
+    };
+    if words.len() < SHINGLE_WORDS {
+        fold_simhash(Some(&words[..]).filter(|w| !w.is_empty()).map(hash))
+    } else {
+        fold_simhash(words.windows(SHINGLE_WORDS).map(hash))
+    }
+}
+
+// Combine feature hashes into a SimHash fingerprint, where each feature
+// contributes equally. No features returns 0.
+pub(crate) fn fold_simhash<I>(hashes: I) -> u64
+    where I: IntoIterator<Item = u64>
+{
+    let mut counts = [0i32; 64];
+    for h in hashes {
+        for (i, c) in counts.iter_mut().enumerate() {
+            if (h >> i) & 1 == 1 {
+                *c += 1;
+            } else {
+                *c -= 1;
+            }
+        }
+    }
+    counts.iter()
+        .enumerate()
+        .filter(|&(_, &c)| c > 0)
+        .fold(0, |acc, (i, _)| acc | (1 << i))
+}
+
+/// Return the number of differing bits between two fingerprints, as
+/// returned by [`simhash`]. Near-duplicate texts typically differ by well
+/// under 16 bits, while unrelated texts differ by about half (32).
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
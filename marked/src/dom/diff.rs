@@ -0,0 +1,337 @@
+//! Tree differencing of `Document`s into edit scripts.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::dom::{
+    Attribute, Document, Element, NodeData, NodeId, QualName, StrTendril
+};
+
+/// Limit on the product of old and new child list lengths (after trimming any
+/// common prefix and suffix) for which an optimal alignment is computed.
+const MAX_ALIGN_CELLS: usize = 1024 * 1024;
+
+/// A single edit operation of an edit script, as returned by [`diff`].
+///
+/// All `NodeId`s refer to nodes of the _old_ `Document`. The edits of a
+/// script must be applied in order.
+#[derive(Debug)]
+pub enum Edit {
+    /// Insert the contents of the `tree` fragment as the child at `index` of
+    /// `parent`, with index counted _after_ any prior edits are applied.
+    Insert { parent: NodeId, index: u32, tree: Document },
+
+    /// Remove the node and its descendants.
+    Remove { id: NodeId },
+
+    /// Replace the content of a text or comment node.
+    ReplaceText { id: NodeId, text: StrTendril },
+
+    /// Set (if `Some`) or remove (if `None`) an element attribute value.
+    SetAttr { id: NodeId, name: QualName, value: Option<StrTendril> },
+}
+
+/// Compare two `Document`s, returning an edit script of operations which
+/// would transform `old` into `new`.
+///
+/// Nodes are matched by kind and (for elements) name, with an optimal
+/// (longest common subsequence) alignment of each list of children. Very long
+/// child lists, beyond an internal limit, are aligned only by common prefix
+/// and suffix, so the script may not be minimal. An empty script is returned
+/// if the documents are equivalent.
+pub fn diff(old: &Document, new: &Document) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    diff_children(
+        old, new,
+        Document::DOCUMENT_NODE_ID, Document::DOCUMENT_NODE_ID,
+        &mut edits);
+    edits
+}
+
+/// An error applying an edit script, as returned by [`Document::apply`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApplyError {
+    index: usize,
+    reason: &'static str,
+}
+
+impl ApplyError {
+    /// Return the index of the failed edit in the script.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "edit {}: {}", self.index, self.reason)
+    }
+}
+
+impl StdError for ApplyError {}
+
+/// Edit script application.
+impl Document {
+    /// Apply an edit script, as returned by [`diff`], to self.
+    ///
+    /// The script should have been computed with self (or an equivalent
+    /// `Document` with the same `NodeId`s) as the _old_ `Document`. Each
+    /// edit is validated before it is applied: if any `NodeId` is out of
+    /// range or not (or no longer) reachable from the document node, the
+    /// node is of the wrong kind for the edit, or an insert index is out of
+    /// bounds, an `ApplyError` is returned. In that case any prior edits of
+    /// the script will have already been applied.
+    ///
+    /// Removed nodes are unlinked, as with [`Document::unlink`]. Use
+    /// [`Document::compact`] to free them.
+    pub fn apply(&mut self, edits: &[Edit]) -> Result<(), ApplyError> {
+        for (index, edit) in edits.iter().enumerate() {
+            let fail = |reason| ApplyError { index, reason };
+            match edit {
+                Edit::Insert { parent, index: at, tree } => {
+                    let parent = *parent;
+                    if !self.is_live(parent) {
+                        return Err(fail("parent not found"));
+                    }
+                    match self[parent].data {
+                        NodeData::Document | NodeData::Elem(_) => {}
+                        _ => return Err(fail("parent can't have children")),
+                    }
+                    let frag = tree.deep_clone(Document::DOCUMENT_NODE_ID);
+                    let at = *at as usize;
+                    let count = self.children(parent).count();
+                    if at == count {
+                        self.attach_child(parent, frag);
+                    } else if let Some(sib) = self.children(parent).nth(at) {
+                        self.attach_before_sibling(sib, frag);
+                    } else {
+                        return Err(fail("insert index out of bounds"));
+                    }
+                }
+                Edit::Remove { id } => {
+                    if *id == Document::DOCUMENT_NODE_ID {
+                        return Err(fail("can't remove the document node"));
+                    }
+                    if !self.is_live(*id) {
+                        return Err(fail("node not found"));
+                    }
+                    self.unlink(*id);
+                }
+                Edit::ReplaceText { id, text } => {
+                    if !self.is_live(*id) {
+                        return Err(fail("node not found"));
+                    }
+                    match &mut self[*id].data {
+                        NodeData::Text(t) | NodeData::Comment(t) => {
+                            *t = text.clone();
+                        }
+                        _ => return Err(fail("not a text or comment node")),
+                    }
+                }
+                Edit::SetAttr { id, name, value } => {
+                    if !self.is_live(*id) {
+                        return Err(fail("node not found"));
+                    }
+                    match &mut self[*id].data {
+                        NodeData::Elem(elm) => {
+                            set_attr(elm, name, value.as_ref());
+                        }
+                        _ => return Err(fail("not an element")),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Return true if the id is valid and reachable from the document node.
+    fn is_live(&self, id: NodeId) -> bool {
+        self.check_id(id).is_ok() &&
+            self.node_and_ancestors(id).last() ==
+            Some(Document::DOCUMENT_NODE_ID)
+    }
+}
+
+fn set_attr(elm: &mut Element, name: &QualName, value: Option<&StrTendril>) {
+    if let Some(value) = value {
+        if let Some(attr) = elm.attrs.iter_mut().find(|a| a.name == *name) {
+            attr.value = value.clone();
+        } else {
+            elm.attrs.push(Attribute {
+                name: name.clone(),
+                value: value.clone()
+            });
+        }
+    } else {
+        elm.attrs.retain(|a| a.name != *name);
+    }
+}
+
+fn diff_node(
+    old: &Document,
+    new: &Document,
+    oid: NodeId,
+    nid: NodeId,
+    edits: &mut Vec<Edit>)
+{
+    match (&old[oid].data, &new[nid].data) {
+        (NodeData::Elem(oe), NodeData::Elem(ne)) => {
+            diff_attrs(oid, oe, ne, edits);
+        }
+        (NodeData::Text(ot), NodeData::Text(nt)) |
+        (NodeData::Comment(ot), NodeData::Comment(nt)) => {
+            if ot != nt {
+                edits.push(Edit::ReplaceText { id: oid, text: nt.clone() });
+            }
+        }
+        _ => {}
+    }
+    diff_children(old, new, oid, nid, edits);
+}
+
+fn diff_attrs(id: NodeId, oe: &Element, ne: &Element, edits: &mut Vec<Edit>) {
+    for oa in &oe.attrs {
+        if !ne.attrs.iter().any(|na| na.name == oa.name) {
+            edits.push(Edit::SetAttr {
+                id,
+                name: oa.name.clone(),
+                value: None
+            });
+        }
+    }
+    for na in &ne.attrs {
+        match oe.attrs.iter().find(|oa| oa.name == na.name) {
+            Some(oa) if oa.value == na.value => {}
+            _ => {
+                edits.push(Edit::SetAttr {
+                    id,
+                    name: na.name.clone(),
+                    value: Some(na.value.clone())
+                });
+            }
+        }
+    }
+}
+
+fn diff_children(
+    old: &Document,
+    new: &Document,
+    oid: NodeId,
+    nid: NodeId,
+    edits: &mut Vec<Edit>)
+{
+    let ocs: Vec<NodeId> = old.children(oid).collect();
+    let ncs: Vec<NodeId> = new.children(nid).collect();
+    let mut index = 0;
+    for pair in align(old, new, &ocs, &ncs) {
+        match pair {
+            (Some(o), Some(n)) => {
+                diff_node(old, new, o, n, edits);
+                index += 1;
+            }
+            (Some(o), None) => {
+                edits.push(Edit::Remove { id: o });
+            }
+            (None, Some(n)) => {
+                edits.push(Edit::Insert {
+                    parent: oid,
+                    index,
+                    tree: new.deep_clone(n)
+                });
+                index += 1;
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+type Pair = (Option<NodeId>, Option<NodeId>);
+
+// Align old and new child lists, returning matched pairs, and unmatched
+// removals and insertions, in order.
+fn align(old: &Document, new: &Document, ocs: &[NodeId], ncs: &[NodeId])
+    -> Vec<Pair>
+{
+    let same = |o: NodeId, n: NodeId| same_kind(&old[o].data, &new[n].data);
+
+    let mut pre = 0;
+    while pre < ocs.len() && pre < ncs.len() && same(ocs[pre], ncs[pre]) {
+        pre += 1;
+    }
+    let mut suf = 0;
+    while suf < ocs.len() - pre && suf < ncs.len() - pre &&
+        same(ocs[ocs.len() - 1 - suf], ncs[ncs.len() - 1 - suf])
+    {
+        suf += 1;
+    }
+    let om = &ocs[pre..ocs.len() - suf];
+    let nm = &ncs[pre..ncs.len() - suf];
+
+    let mut out = Vec::with_capacity(ocs.len() + ncs.len());
+    out.extend(
+        ocs[..pre].iter().zip(&ncs[..pre]).map(|(&o, &n)| (Some(o), Some(n)))
+    );
+    if om.len() * nm.len() <= MAX_ALIGN_CELLS {
+        align_lcs(om, nm, same, &mut out);
+    } else {
+        out.extend(om.iter().map(|&o| (Some(o), None)));
+        out.extend(nm.iter().map(|&n| (None, Some(n))));
+    }
+    out.extend(
+        ocs[ocs.len() - suf..].iter()
+            .zip(&ncs[ncs.len() - suf..])
+            .map(|(&o, &n)| (Some(o), Some(n)))
+    );
+    out
+}
+
+// Align via a longest common subsequence table.
+fn align_lcs<F>(om: &[NodeId], nm: &[NodeId], same: F, out: &mut Vec<Pair>)
+    where F: Fn(NodeId, NodeId) -> bool
+{
+    let (m, n) = (om.len(), nm.len());
+    let w = n + 1;
+    let mut lcs = vec![0u32; (m + 1) * w];
+    for i in (0..m).rev() {
+        for j in (0..n).rev() {
+            lcs[i * w + j] = if same(om[i], nm[j]) {
+                lcs[(i + 1) * w + j + 1] + 1
+            } else {
+                std::cmp::max(lcs[(i + 1) * w + j], lcs[i * w + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < m || j < n {
+        if i < m && j < n && same(om[i], nm[j]) &&
+            lcs[i * w + j] == lcs[(i + 1) * w + j + 1] + 1
+        {
+            out.push((Some(om[i]), Some(nm[j])));
+            i += 1;
+            j += 1;
+        } else if i < m &&
+            (j == n || lcs[(i + 1) * w + j] >= lcs[i * w + j + 1])
+        {
+            out.push((Some(om[i]), None));
+            i += 1;
+        } else {
+            out.push((None, Some(nm[j])));
+            j += 1;
+        }
+    }
+}
+
+// Return true if nodes are of the same kind, and can be matched such that
+// any further differences are expressible by (non-structural) edits.
+fn same_kind(a: &NodeData, b: &NodeData) -> bool {
+    match (a, b) {
+        (NodeData::Elem(a), NodeData::Elem(b)) => a.name == b.name,
+        (NodeData::Text(_), NodeData::Text(_)) => true,
+        (NodeData::Comment(_), NodeData::Comment(_)) => true,
+        (NodeData::DocType(a), NodeData::DocType(b)) => a.name == b.name,
+        (NodeData::Pi(a), NodeData::Pi(b)) => a.data == b.data,
+        (NodeData::Document, NodeData::Document) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,185 @@
+//! Node count and structure statistics of documents.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::dom::{
+    dedup::{fold_simhash, Fnv},
+    Document, NodeData, NodeId,
+};
+
+/// Node counts of a `Document` by type, as returned by
+/// [`Document::stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocStats {
+    /// Number of elements.
+    pub elements: usize,
+
+    /// Number of attributes, over all elements.
+    pub attributes: usize,
+
+    /// Number of text nodes.
+    pub texts: usize,
+
+    /// Number of comments.
+    pub comments: usize,
+
+    /// Number of processing instructions.
+    pub pis: usize,
+
+    /// Number of document types.
+    pub doctypes: usize,
+
+    /// Maximum depth of any node, where children of the document node are at
+    /// depth 1.
+    pub max_depth: usize,
+
+    /// Number of `Node`s that are not reachable from the document node,
+    /// including `NodeData::Hole`s, which could be freed via
+    /// [`Document::compact`].
+    pub garbage: usize,
+}
+
+impl DocStats {
+    /// Return the total number of nodes reachable from (and excluding) the
+    /// document node.
+    pub fn reachable(&self) -> usize {
+        self.elements + self.texts + self.comments + self.pis + self.doctypes
+    }
+}
+
+/// Statistics methods.
+impl Document {
+    /// Return counts of the nodes of this `Document` by type.
+    ///
+    /// Only nodes reachable from the document node are counted by type. This
+    /// is a single, non-allocating pass and is useful for measuring the
+    /// effect of filters, or profiling along with [`filter::timed`].
+    ///
+    /// [`filter::timed`]: crate::filter::timed
+    pub fn stats(&self) -> DocStats {
+        let mut stats = DocStats::default();
+        let mut depth = 0;
+        let mut next = self[Document::DOCUMENT_NODE_ID].first_child;
+        while let Some(id) = next {
+            depth += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            let node = &self[id];
+            match &node.data {
+                NodeData::Elem(elm) => {
+                    stats.elements += 1;
+                    stats.attributes += elm.attrs.len();
+                }
+                NodeData::Text(_) => stats.texts += 1,
+                NodeData::Comment(_) => stats.comments += 1,
+                NodeData::Pi(_) => stats.pis += 1,
+                NodeData::DocType(_) => stats.doctypes += 1,
+                NodeData::Document | NodeData::Hole => {}
+            }
+            next = if node.first_child.is_some() {
+                node.first_child
+            } else {
+                depth -= 1;
+                self.next_in_tree(id, &mut depth)
+            };
+        }
+        stats.garbage = self.len() as usize - 1 - stats.reachable();
+        stats
+    }
+
+    /// Return a 64-bit fingerprint of the element tree shape of this
+    /// `Document`, for clustering pages by template.
+    ///
+    /// This is a SimHash (as per [`dedup::simhash`]) over the distinct
+    /// paths of element local names from the root to each element, e.g.
+    /// `html/body/ul/li`. Text, comments and attributes are ignored, and
+    /// repetition (e.g. the number of list items or table rows) does not
+    /// affect the signature, so pages from the same template typically
+    /// have equal or close signatures, as compared via
+    /// [`dedup::hamming_distance`].
+    ///
+    /// [`dedup::simhash`]: crate::dedup::simhash
+    /// [`dedup::hamming_distance`]: crate::dedup::hamming_distance
+    pub fn structure_signature(&self) -> u64 {
+        let mut paths = HashSet::new();
+        let mut path_hashes: Vec<u64> = Vec::new();
+        self.walk_elements(|id, depth| {
+            let mut h = Fnv::new();
+            if depth > 1 {
+                h.field(b'/', &path_hashes[depth - 2].to_le_bytes());
+            }
+            if let Some(elm) = self[id].as_element() {
+                h.field(b'<', elm.name.local.as_bytes());
+            }
+            path_hashes.truncate(depth - 1);
+            path_hashes.push(h.0);
+            paths.insert(h.0);
+        });
+        fold_simhash(paths)
+    }
+
+    /// Return the number of elements at each depth, where index 0 is the
+    /// count of elements at depth 1 (children of the document node), and
+    /// the length is the maximum element depth.
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut hist = Vec::new();
+        self.walk_elements(|_, depth| {
+            if hist.len() < depth {
+                hist.resize(depth, 0);
+            }
+            hist[depth - 1] += 1;
+        });
+        hist
+    }
+
+    /// Return the number of elements by local name.
+    pub fn tag_histogram(&self) -> BTreeMap<String, usize> {
+        let mut hist = BTreeMap::new();
+        self.walk_elements(|id, _| {
+            if let Some(elm) = self[id].as_element() {
+                *hist.entry(elm.name.local.to_string()).or_insert(0) += 1;
+            }
+        });
+        hist
+    }
+
+    // Call f with each element id and depth, in document order, where
+    // children of the document node are at depth 1, and only elements are
+    // counted in depth.
+    fn walk_elements<F>(&self, mut f: F)
+        where F: FnMut(NodeId, usize)
+    {
+        let mut stack = vec![(Document::DOCUMENT_NODE_ID, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let depth = if id == Document::DOCUMENT_NODE_ID {
+                depth
+            } else if self[id].as_element().is_some() {
+                f(id, depth + 1);
+                depth + 1
+            } else {
+                continue;
+            };
+            let mut child = self[id].last_child;
+            while let Some(c) = child {
+                stack.push((c, depth));
+                child = self[c].prev_sibling;
+            }
+        }
+    }
+
+    // Return the next sibling of id or its nearest ancestor with one,
+    // decrementing depth for each ancestor ascended.
+    fn next_in_tree(&self, mut id: NodeId, depth: &mut usize)
+        -> Option<NodeId>
+    {
+        loop {
+            if let Some(sib) = self[id].next_sibling {
+                return Some(sib);
+            }
+            id = self[id].parent?;
+            if id == Document::DOCUMENT_NODE_ID {
+                return None;
+            }
+            *depth -= 1;
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! Extraction of structured records from documents.
+//!
+//! See the [`rules`] module.
+
+pub mod rules;
@@ -0,0 +1,413 @@
+//! Declarative, rule based extraction of named fields.
+//!
+//! A [`Rules`] set is a list of [`Field`]s, each of which selects nodes by a
+//! CSS selector (as per [`NodeRef::matches`]) or an XPath expression, takes
+//! a value from each node's text or an attribute, and applies a sequence of
+//! post-processing steps and an optional type conversion. Applying the rules
+//! to a document produces an [`ExtractedRecord`]:
+//!
+//! ```
+//! use marked::html::parse_utf8;
+//! use marked::extract::rules::{Field, Kind, Rules};
+//!
+//! let doc = parse_utf8(
+//!     "<h1> Widget </h1><span class='price'>$1,200</span>".as_bytes()
+//! );
+//! let rules = Rules::new()
+//!     .field(Field::new("title", "h1").trim().required(true))
+//!     .field(Field::new("price", "span.price").parse(Kind::Integer));
+//! let record = rules.apply(&doc);
+//! assert_eq!(Some("Widget"), record.get("title").and_then(|v| v.as_str()));
+//! assert_eq!(None, record.get("price").and_then(|v| v.as_i64()));
+//! ```
+//!
+//! (The price above fails to parse as an integer due to the `$`. With the
+//! _regex_ feature, a `Field::capture` step can extract the digits.)
+
+use crate::dom::{
+    xpath::{Value, XNode, XPath, XPathError},
+    Document, LocalName, NodeRef,
+};
+
+/// An ordered set of field rules.
+#[derive(Clone, Debug, Default)]
+pub struct Rules {
+    fields: Vec<Field>,
+}
+
+/// A named field rule.
+#[derive(Clone, Debug)]
+pub struct Field {
+    name: String,
+    query: Query,
+    source: Source,
+    steps: Vec<Step>,
+    kind: Kind,
+    all: bool,
+    required: bool,
+}
+
+/// The type to which a [`Field`] value is converted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// The string value, unconverted (the default).
+    String,
+
+    /// A signed integer. Any `,` or `_` digit group separators are
+    /// ignored.
+    Integer,
+
+    /// A floating point number. Any `,` or `_` digit group separators are
+    /// ignored.
+    Number,
+
+    /// A boolean, from (ASCII case-insensitive) `true`, `yes`, `on` or `1`,
+    /// or `false`, `no`, `off` or `0`.
+    Boolean,
+}
+
+/// An extracted field value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    /// No value was found.
+    Null,
+
+    /// A string value.
+    String(String),
+
+    /// An integer value.
+    Integer(i64),
+
+    /// A number value.
+    Number(f64),
+
+    /// A boolean value.
+    Boolean(bool),
+
+    /// The values of a field selecting all matches, in document order.
+    List(Vec<FieldValue>),
+}
+
+/// The record of named values produced by [`Rules::apply`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractedRecord {
+    values: Vec<(String, FieldValue)>,
+    missing: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+enum Query {
+    Css(String),
+    XPath(XPath),
+}
+
+#[derive(Clone, Debug)]
+enum Source {
+    Text,
+    Attr(LocalName),
+}
+
+#[derive(Clone, Debug)]
+enum Step {
+    Trim,
+    NormalizeSpace,
+    Lowercase,
+    #[cfg(feature = "regex")]
+    Capture(regex::Regex),
+}
+
+impl Rules {
+    /// Construct a new, empty rule set.
+    pub fn new() -> Rules {
+        Rules::default()
+    }
+
+    /// Add a field rule.
+    pub fn field(mut self, field: Field) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Apply the rules to the document.
+    pub fn apply(&self, doc: &Document) -> ExtractedRecord {
+        self.apply_node(doc.document_node_ref())
+    }
+
+    /// Apply the rules to the descendants of the given node, e.g. to each
+    /// item of a list page. XPath expressions are evaluated with the node as
+    /// context node.
+    pub fn apply_node(&self, node: NodeRef<'_>) -> ExtractedRecord {
+        let mut values = Vec::with_capacity(self.fields.len());
+        let mut missing = Vec::new();
+        for field in &self.fields {
+            let value = field.extract(node);
+            if field.required && value.is_empty() {
+                missing.push(field.name.clone());
+            }
+            values.push((field.name.clone(), value));
+        }
+        ExtractedRecord { values, missing }
+    }
+}
+
+impl Field {
+    /// Construct a new field rule with the given name and CSS selector. By
+    /// default the text of the first matching element is extracted.
+    pub fn new<N, S>(name: N, selector: S) -> Field
+        where N: Into<String>, S: Into<String>
+    {
+        Field::with_query(name.into(), Query::Css(selector.into()))
+    }
+
+    /// Construct a new field rule with the given name and XPath expression,
+    /// or return an error if the expression is invalid.
+    ///
+    /// Selected attribute and text nodes yield their own value. Expressions
+    /// evaluating to a string, number or boolean yield a single value.
+    pub fn xpath<N>(name: N, expr: &str) -> Result<Field, XPathError>
+        where N: Into<String>
+    {
+        Ok(Field::with_query(name.into(), Query::XPath(XPath::parse(expr)?)))
+    }
+
+    fn with_query(name: String, query: Query) -> Field {
+        Field {
+            name,
+            query,
+            source: Source::Text,
+            steps: Vec::new(),
+            kind: Kind::String,
+            all: false,
+            required: false,
+        }
+    }
+
+    /// Extract the value of the named attribute, rather than text.
+    /// Elements without the attribute are skipped.
+    pub fn attr<LN>(mut self, lname: LN) -> Self
+        where LN: Into<LocalName>
+    {
+        self.source = Source::Attr(lname.into());
+        self
+    }
+
+    /// Set whether to extract all matches as a `FieldValue::List`, or only
+    /// the first (default: false).
+    pub fn all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
+    }
+
+    /// Set whether the field is required, and reported by
+    /// [`ExtractedRecord::missing`] if no value is found (default: false).
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Add a step trimming leading and trailing whitespace.
+    pub fn trim(mut self) -> Self {
+        self.steps.push(Step::Trim);
+        self
+    }
+
+    /// Add a step trimming and replacing internal runs of whitespace with a
+    /// single space.
+    pub fn normalize_space(mut self) -> Self {
+        self.steps.push(Step::NormalizeSpace);
+        self
+    }
+
+    /// Add a step converting to lower case.
+    pub fn lowercase(mut self) -> Self {
+        self.steps.push(Step::Lowercase);
+        self
+    }
+
+    /// Add a step replacing the value with the first capture group of the
+    /// regex, or the entire match if it has no groups. Values which don't
+    /// match are dropped.
+    #[cfg(feature = "regex")]
+    pub fn capture(mut self, re: regex::Regex) -> Self {
+        self.steps.push(Step::Capture(re));
+        self
+    }
+
+    /// Set the type to which values are converted after all steps (default:
+    /// `Kind::String`). Values which fail to convert are dropped.
+    pub fn parse(mut self, kind: Kind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Return the name of this field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extract(&self, scope: NodeRef<'_>) -> FieldValue {
+        let mut values = self.raw_values(scope)
+            .into_iter()
+            .filter_map(|v| self.process(v));
+        if self.all {
+            FieldValue::List(values.collect())
+        } else {
+            values.next().unwrap_or(FieldValue::Null)
+        }
+    }
+
+    // Return the unprocessed values, in document order.
+    fn raw_values(&self, scope: NodeRef<'_>) -> Vec<String> {
+        match &self.query {
+            Query::Css(sel) => {
+                let mut found = scope.descendants()
+                    .skip(1)
+                    .filter(|n| n.matches(sel))
+                    .filter_map(|n| self.node_value(n));
+                if self.all {
+                    found.collect()
+                } else {
+                    found.next().into_iter().collect()
+                }
+            }
+            Query::XPath(xp) => match xp.evaluate(scope) {
+                Ok(Value::Nodes(nodes)) => nodes.iter()
+                    .filter_map(|x| self.xnode_value(x))
+                    .collect(),
+                Ok(v) => vec![v.string()],
+                Err(_) => Vec::new(),
+            },
+        }
+    }
+
+    fn node_value(&self, node: NodeRef<'_>) -> Option<String> {
+        match &self.source {
+            Source::Text => node.text().map(|t| t.to_string()),
+            Source::Attr(lname) => {
+                node.attr(lname.clone()).map(|v| v.to_string())
+            }
+        }
+    }
+
+    fn xnode_value(&self, xnode: &XNode<'_>) -> Option<String> {
+        match xnode {
+            XNode::Node(n) if n.as_element().is_some() => self.node_value(*n),
+            _ => Some(xnode.string_value()),
+        }
+    }
+
+    fn process(&self, mut value: String) -> Option<FieldValue> {
+        for step in &self.steps {
+            value = match step {
+                Step::Trim => value.trim().to_owned(),
+                Step::NormalizeSpace => {
+                    value.split_whitespace().collect::<Vec<_>>().join(" ")
+                }
+                Step::Lowercase => value.to_lowercase(),
+                #[cfg(feature = "regex")]
+                Step::Capture(re) => {
+                    let caps = re.captures(&value)?;
+                    caps.get(1).or_else(|| caps.get(0))?.as_str().to_owned()
+                }
+            };
+        }
+        match self.kind {
+            Kind::String => Some(FieldValue::String(value)),
+            Kind::Integer => {
+                digits(&value).parse().ok().map(FieldValue::Integer)
+            }
+            Kind::Number => {
+                digits(&value).parse().ok().map(FieldValue::Number)
+            }
+            Kind::Boolean => {
+                let b = match value.trim().to_ascii_lowercase().as_str() {
+                    "true" | "yes" | "on" | "1" => Some(true),
+                    "false" | "no" | "off" | "0" => Some(false),
+                    _ => None,
+                };
+                b.map(FieldValue::Boolean)
+            }
+        }
+    }
+}
+
+// Return the trimmed value without any digit group separators.
+fn digits(value: &str) -> String {
+    value.trim().chars().filter(|&c| c != ',' && c != '_').collect()
+}
+
+impl FieldValue {
+    /// Return true if this is `Null` or an empty `List`.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            FieldValue::Null => true,
+            FieldValue::List(l) => l.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Return the string, if this is a `String`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FieldValue::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Return the integer, if this is an `Integer`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            FieldValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Return the number, if this is a `Number` or `Integer`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::Number(n) => Some(*n),
+            FieldValue::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    /// Return the boolean, if this is a `Boolean`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            FieldValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Return the values, if this is a `List`.
+    pub fn as_list(&self) -> Option<&[FieldValue]> {
+        match self {
+            FieldValue::List(l) => Some(l.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+impl ExtractedRecord {
+    /// Return the value of the named field, or `None` if there is no such
+    /// field rule.
+    pub fn get(&self, name: &str) -> Option<&FieldValue> {
+        self.values.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// Return all field names and values, in rule order.
+    pub fn values(&self) -> &[(String, FieldValue)] {
+        &self.values
+    }
+
+    /// Return the names of any required fields for which no value was
+    /// found.
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+
+    /// Return true if all required fields have values.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
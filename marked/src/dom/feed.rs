@@ -0,0 +1,200 @@
+//! Parsing of RSS and Atom web feeds (_xml_ feature).
+//!
+//! Feeds are parsed as XML to a `Document`, via [`xml::parse_utf8`], from
+//! which a [`Feed`] is extracted. See also [`Document::feed_links`] for
+//! discovery of feeds from HTML pages.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::dom::{
+    meta::FeedKind,
+    xml::{self, child, child_text, is_named, text, XmlError},
+    Document, NodeId, NodeRef,
+};
+
+/// A parsed RSS or Atom feed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Feed {
+    /// The syntax of the feed.
+    pub kind: FeedKind,
+
+    /// The feed (channel) title.
+    pub title: Option<String>,
+
+    /// The link to the site of the feed.
+    pub link: Option<String>,
+
+    /// The feed description (RSS) or subtitle (Atom).
+    pub description: Option<String>,
+
+    /// The items (RSS) or entries (Atom) of the feed, in order.
+    pub entries: Vec<Entry>,
+}
+
+/// An item (RSS) or entry (Atom) of a [`Feed`].
+///
+/// All values are trimmed text as found, not otherwise interpreted. For
+/// example dates are in RFC 822 format for RSS, or RFC 3339 for Atom.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The entry title.
+    pub title: Option<String>,
+
+    /// The link to the entry content (Atom: `rel="alternate"` or no `rel`).
+    pub link: Option<String>,
+
+    /// The unique identifier: `guid` (RSS) or `id` (Atom).
+    pub id: Option<String>,
+
+    /// The date of publication: `pubDate` or `dc:date` (RSS), or
+    /// `published` (Atom).
+    pub published: Option<String>,
+
+    /// The date of last update (Atom `updated`).
+    pub updated: Option<String>,
+
+    /// The summary: `description` (RSS) or `summary` (Atom), typically
+    /// escaped HTML.
+    pub summary: Option<String>,
+
+    /// The full content: `content:encoded` (RSS) or `content` (Atom),
+    /// typically escaped HTML. Atom `type="xhtml"` content is serialized
+    /// as markup.
+    pub content: Option<String>,
+
+    /// The id of the item or entry element in the source `Document`.
+    pub node: NodeId,
+}
+
+/// An error returned by [`parse_utf8`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FeedError {
+    /// The input is not well-formed XML.
+    Xml(XmlError),
+
+    /// The root element is not `<rss>`, `<rdf:RDF>` or `<feed>`.
+    NotAFeed,
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::Xml(e) => e.fmt(f),
+            FeedError::NotAFeed => f.write_str("not an RSS or Atom feed"),
+        }
+    }
+}
+
+impl StdError for FeedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            FeedError::Xml(e) => Some(e),
+            FeedError::NotAFeed => None,
+        }
+    }
+}
+
+impl From<XmlError> for FeedError {
+    fn from(e: XmlError) -> FeedError {
+        FeedError::Xml(e)
+    }
+}
+
+/// Parse a feed from UTF-8 bytes in RAM.
+pub fn parse_utf8(utf8_bytes: &[u8]) -> Result<Feed, FeedError> {
+    let doc = xml::parse_utf8(utf8_bytes)?;
+    Feed::from_document(&doc).ok_or(FeedError::NotAFeed)
+}
+
+impl Feed {
+    /// Extract a feed from a `Document` parsed as XML, returning None if
+    /// its root element is not `<rss>`, `<rdf:RDF>` (RSS 1.0) or `<feed>`
+    /// (Atom). Elements are matched by local name only.
+    pub fn from_document(doc: &Document) -> Option<Feed> {
+        let root = doc.root_element_ref()?;
+        if is_named(root, "feed") {
+            Some(atom_feed(root))
+        } else if is_named(root, "rss") || is_named(root, "RDF") {
+            Some(rss_feed(root))
+        } else {
+            None
+        }
+    }
+}
+
+fn rss_feed(root: NodeRef<'_>) -> Feed {
+    let channel = child(root, "channel");
+    let items = channel.into_iter()
+        .chain(Some(root))
+        .flat_map(|p| p.children())
+        .filter(|n| is_named(*n, "item"));
+    let entries = items
+        .map(|item| Entry {
+            title: child_text(item, "title"),
+            link: child_text(item, "link"),
+            id: child_text(item, "guid")
+                .or_else(|| item.attr("about").map(|v| v.trim().to_owned())),
+            published: child_text(item, "pubDate")
+                .or_else(|| child_text(item, "date")),
+            updated: None,
+            summary: child_text(item, "description"),
+            content: child_text(item, "encoded"),
+            node: item.id(),
+        })
+        .collect();
+    Feed {
+        kind: FeedKind::Rss,
+        title: channel.and_then(|c| child_text(c, "title")),
+        link: channel.and_then(|c| child_text(c, "link")),
+        description: channel.and_then(|c| child_text(c, "description")),
+        entries,
+    }
+}
+
+fn atom_feed(root: NodeRef<'_>) -> Feed {
+    let entries = root.children()
+        .filter(|n| is_named(*n, "entry"))
+        .map(|entry| Entry {
+            title: child_text(entry, "title"),
+            link: atom_link(entry),
+            id: child_text(entry, "id"),
+            published: child_text(entry, "published"),
+            updated: child_text(entry, "updated"),
+            summary: child_text(entry, "summary"),
+            content: child(entry, "content").and_then(atom_content),
+            node: entry.id(),
+        })
+        .collect();
+    Feed {
+        kind: FeedKind::Atom,
+        title: child_text(root, "title"),
+        link: atom_link(root),
+        description: child_text(root, "subtitle"),
+        entries,
+    }
+}
+
+// Return the href of the first alternate link.
+fn atom_link(parent: NodeRef<'_>) -> Option<String> {
+    parent.children()
+        .filter(|n| is_named(*n, "link"))
+        .find(|n| n.attr("rel").map_or(true, |r| r.trim() == "alternate"))
+        .and_then(|n| n.attr("href"))
+        .map(|h| h.trim().to_owned())
+        .filter(|h| !h.is_empty())
+}
+
+fn atom_content(content: NodeRef<'_>) -> Option<String> {
+    let is_xhtml = content.attr("type").map_or(false, |t| t.trim() == "xhtml");
+    if is_xhtml {
+        let markup: String = content.children()
+            .filter(|n| n.as_element().is_some())
+            .flat_map(|div| div.children())
+            .map(|n| n.to_string())
+            .collect();
+        Some(markup.trim().to_owned()).filter(|m| !m.is_empty())
+    } else {
+        text(content)
+    }
+}
@@ -1,15 +1,30 @@
 //! Mutating visitor support for `Document`.
 
-use std::cell::RefCell;
+#[cfg(feature = "regex")]
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+use lazy_static::lazy_static;
 use log::debug;
+#[cfg(feature = "regex")]
+use regex::Regex;
 
-use crate::chars::{is_all_ctrl_ws, replace_chars};
+use crate::chars::{is_all_ctrl_ws, replace_chars, replace_chars_ext};
 use crate::dom::{
-    html::{t, TAG_META},
-    Document, Element, NodeData, NodeId, NodeRef, StrTendril
+    boilerplate::Boilerplate,
+    html::{self, a, t, TAG_META},
+    memory::TENDRIL_INLINE_MAX,
+    provenance::is_provenance_attr,
+    visibility::is_hidden_element,
+    Document, DuplicateAttrs, Element, LocalName, NodeData, NodeId, NodeRef,
+    StrTendril
 };
 
+pub mod config;
+pub mod email;
+
 /// An instruction returned by the `Fn` closure used by [`Document::filter`].
 #[derive(Debug, PartialEq, Eq)]
 pub enum Action {
@@ -28,6 +43,9 @@ pub enum Action {
     Fold,
 }
 
+// Levels of a filter pass without a depth limit.
+const UNLIMITED: u32 = u32::max_value();
+
 /// Mutating filter methods.
 impl Document {
     /// Perform a depth-first (children before parent nodes) walk of the entire
@@ -38,7 +56,8 @@ impl Document {
     pub fn filter<F>(&mut self, mut f: F)
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
-        self.filter_at_ref(Document::DOCUMENT_NODE_ID, true, &mut f);
+        self.filter_pass(Document::DOCUMENT_NODE_ID, true, UNLIMITED, &mut f);
+        self.compact_if_needed();
     }
 
     /// Perform a breadth-first (children after parent nodes) walk of the
@@ -49,7 +68,8 @@ impl Document {
     pub fn filter_breadth<F>(&mut self, mut f: F)
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
-        self.filter_at_ref(Document::DOCUMENT_NODE_ID, false, &mut f);
+        self.filter_pass(Document::DOCUMENT_NODE_ID, false, UNLIMITED, &mut f);
+        self.compact_if_needed();
     }
 
     /// Perform a depth-first (children before parent nodes) walk from the
@@ -91,11 +111,17 @@ impl Document {
     /// Note that to free up all memory associated with filtered `Node`s that
     /// have been unlinked (`Action::Detach` or `Action::Fold`), use
     /// [`Document::compact`], or [`Document::deep_clone`] and drop the
-    /// original `Document`.
+    /// original `Document`. Alternatively, whole `Document` walks compact
+    /// automatically as per [`Document::set_compact_policy`].
+    ///
+    /// ### Tracing
+    ///
+    /// With the _tracing_ feature, each pass is recorded as a `filter` span,
+    /// with counts of nodes `visited`, `detached` and `folded`.
     pub fn filter_at<F>(&mut self, id: NodeId, mut f: F)
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
-        self.filter_at_ref(id, true, &mut f);
+        self.filter_pass(id, true, UNLIMITED, &mut f);
     }
 
     /// Perform a breadth-first (children after parent nodes) walk from the
@@ -105,17 +131,131 @@ impl Document {
     pub fn filter_at_breadth<F>(&mut self, id: NodeId, mut f: F)
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
-        self.filter_at_ref(id, false, &mut f);
+        self.filter_pass(id, false, UNLIMITED, &mut f);
+    }
+
+    /// Perform a depth-first (children before parent nodes) walk of the
+    /// `Document`, including the synthetic document node, applying the
+    /// provided function only to nodes at or above `max_depth`, where
+    /// children of the document node are at depth 1.
+    ///
+    /// This is useful for filters only concerned with the shallow levels of
+    /// large documents. Note that if a node at `max_depth` is folded, its
+    /// children are not visited. See [`Document::filter_at`] for additional
+    /// details, including for filtering a specific sub-tree.
+    pub fn filter_with_depth_limit<F>(&mut self, mut f: F, max_depth: u32)
+        where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+    {
+        self.filter_pass(Document::DOCUMENT_NODE_ID, true, max_depth, &mut f);
+        self.compact_if_needed();
+    }
+
+    /// Repeat depth-first walks of the entire `Document`, applying the
+    /// provided function, until a pass makes no structural modifications or
+    /// `max_passes` have been performed. Returns the number of passes
+    /// performed.
+    ///
+    /// This is useful for functions whose results may enable further
+    /// simplification, for example, where detaching a child leaves its parent
+    /// empty. A pass is considered to have modified the `Document` if the
+    /// function returned `Action::Detach` or `Action::Fold` for any node. In
+    /// place changes to `NodeData` are not observed, so a function that
+    /// always modifies data but returns `Action::Continue` will stop after
+    /// one pass.
+    ///
+    /// See [`Document::filter_at`] for additional details.
+    pub fn filter_until_stable<F>(&mut self, f: F, max_passes: u32) -> u32
+        where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+    {
+        let changes = Cell::new(0u32);
+        let mut counting = |pos: NodeRef<'_>, data: &mut NodeData| {
+            let res = f(pos, data);
+            if res != Action::Continue {
+                changes.set(changes.get() + 1);
+            }
+            res
+        };
+        let mut passes = 0;
+        while passes < max_passes {
+            passes += 1;
+            changes.set(0);
+            self.filter_pass(
+                Document::DOCUMENT_NODE_ID, true, UNLIMITED, &mut counting);
+            debug!("filter_until_stable pass {}: {} changes",
+                   passes, changes.get());
+            if changes.get() == 0 {
+                break;
+            }
+        }
+        self.compact_if_needed();
+        passes
+    }
+
+    // Perform a complete filter pass from the specified node ID, to the
+    // given number of levels below it. With the tracing feature, this is
+    // recorded as a span, with counts of nodes visited, detached and folded.
+    #[cfg(not(feature = "tracing"))]
+    fn filter_pass<F>(
+        &mut self,
+        id: NodeId,
+        depth_first: bool,
+        levels: u32,
+        f: &mut F)
+        where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+    {
+        self.filter_at_ref(id, depth_first, levels, f);
+    }
+
+    #[cfg(feature = "tracing")]
+    fn filter_pass<F>(
+        &mut self,
+        id: NodeId,
+        depth_first: bool,
+        levels: u32,
+        f: &mut F)
+        where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+    {
+        use tracing::field::Empty;
+
+        let span = tracing::debug_span!(
+            "filter",
+            depth_first,
+            visited = Empty,
+            detached = Empty,
+            folded = Empty);
+        let _entered = span.enter();
+        let visited = Cell::new(0u64);
+        let detached = Cell::new(0u64);
+        let folded = Cell::new(0u64);
+        let mut counting = |pos: NodeRef<'_>, data: &mut NodeData| {
+            let res = f(pos, data);
+            visited.set(visited.get() + 1);
+            match res {
+                Action::Continue => {}
+                Action::Detach => detached.set(detached.get() + 1),
+                Action::Fold => folded.set(folded.get() + 1),
+            }
+            res
+        };
+        self.filter_at_ref(id, depth_first, levels, &mut counting);
+        span.record("visited", &visited.get());
+        span.record("detached", &detached.get());
+        span.record("folded", &folded.get());
     }
 
-    fn filter_at_ref<F>(&mut self, id: NodeId, depth_first: bool, f: &mut F)
+    fn filter_at_ref<F>(
+        &mut self,
+        id: NodeId,
+        depth_first: bool,
+        levels: u32,
+        f: &mut F)
         -> Action
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
         let res = if depth_first {
-            self.walk_depth(id, f)
+            self.walk_depth(id, levels, f)
         } else {
-            self.walk_breadth(id, f)
+            self.walk_breadth(id, levels, f)
         };
 
         match res {
@@ -130,25 +270,29 @@ impl Document {
         res
     }
 
-    fn walk_depth<F>(&mut self, id: NodeId, f: &mut F) -> Action
+    fn walk_depth<F>(&mut self, id: NodeId, levels: u32, f: &mut F)
+        -> Action
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
         // Children first, recursively
-        let mut next_child = self[id].first_child;
-        while let Some(child) = next_child {
-            // set before possible loss by filter action
-            next_child = self[child].next_sibling;
-            self.filter_at_ref(child, true, f);
+        if levels > 0 {
+            let mut next_child = self[id].first_child;
+            while let Some(child) = next_child {
+                // set before possible loss by filter action
+                next_child = self[child].next_sibling;
+                self.filter_at_ref(child, true, levels - 1, f);
+            }
         }
 
         self.filter_node(id, f)
     }
 
-    fn walk_breadth<F>(&mut self, id: NodeId, f: &mut F) -> Action
+    fn walk_breadth<F>(&mut self, id: NodeId, levels: u32, f: &mut F)
+        -> Action
         where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
     {
         let res = self.filter_node(id, f);
-        if res != Action::Continue {
+        if res != Action::Continue || levels == 0 {
             return res;
         }
 
@@ -160,7 +304,7 @@ impl Document {
             let prev = self[child].prev_sibling;
             let parent = self[child].parent;
 
-            let res = self.filter_at_ref(child, false, f);
+            let res = self.filter_at_ref(child, false, levels - 1, f);
 
             if res == Action::Fold {
                 if let Some(p) = prev {
@@ -175,7 +319,7 @@ impl Document {
     }
 
     fn filter_node<F>(&mut self, id: NodeId, f: &mut F) -> Action
-        where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+        where F: FnMut(NodeRef<'_>, &mut NodeData) -> Action
     {
         // We need to temporarily replace node.data with a placeholder (Hole)
         // to appease the borrow checker. Otherwise there would be an aliasing
@@ -208,6 +352,154 @@ impl Document {
     }
 }
 
+/// Names of the filter presets supported by [`Document::filter_preset`].
+pub const PRESETS: &[&str] = &[
+    "banned", "normalize", "clean", "trackers", "email"
+];
+
+/// Filter preset methods.
+impl Document {
+    /// Apply a named, commonly used filter chain, returning false if the name
+    /// is not one of the [`PRESETS`]:
+    ///
+    /// `banned`
+    /// : Breadth-first pass of [`detach_banned_elements`],
+    ///   [`detach_comments`], [`detach_pis`], [`retain_basic_attributes`]
+    ///   and [`xmp_to_pre`].
+    ///
+    /// `normalize`
+    /// : Passes of [`fold_empty_inline`], then [`text_normalize`].
+    ///
+    /// `clean`
+    /// : `banned` followed by `normalize`.
+    ///
+    /// `trackers`
+    /// : Pass of [`strip_trackers`].
+    ///
+    /// `email`
+    /// : The default [`EmailPrep`](email::EmailPrep) transforms, for
+    ///   outbound e-mail.
+    ///
+    /// This is intended for language bindings and tools where the filter is
+    /// selected by name, at runtime.
+    pub fn filter_preset(&mut self, name: &str) -> bool {
+        match name {
+            "banned" => self.filter_banned(),
+            "normalize" => self.filter_normalize(),
+            "clean" => {
+                self.filter_banned();
+                self.filter_normalize();
+            }
+            "trackers" => self.filter(strip_trackers),
+            "email" => email::EmailPrep::default().apply(self),
+            _ => return false,
+        }
+        true
+    }
+
+    fn filter_banned(&mut self) {
+        self.filter_breadth(crate::chain_filters!(
+            detach_banned_elements,
+            detach_comments,
+            detach_pis,
+            retain_basic_attributes,
+            xmp_to_pre,
+        ));
+    }
+
+    fn filter_normalize(&mut self) {
+        self.filter(fold_empty_inline);
+        self.filter(text_normalize);
+    }
+}
+
+/// A stateful, mutating visitor, with callbacks on entering and exiting each
+/// node, for use with [`Document::visit`].
+///
+/// Unlike filter functions, which see each node once, this allows algorithms
+/// which act both before and after a node's children are visited, for
+/// example, tracking depth or ancestor context on `enter` and removing
+/// elements left empty on `exit`. As with filter functions, the `NodeData`
+/// of the visited node is provided for read-write access and the remainder of
+/// the `Document` via `pos`, and the returned [`Action`] may modify the tree.
+/// Both methods default to returning `Action::Continue`.
+pub trait Visitor {
+    /// Called on entering a node, before its children.
+    ///
+    /// If `Action::Detach` is returned, the node is detached, and neither its
+    /// children nor `exit` are visited. If `Action::Fold` is returned, the
+    /// node is replaced by its children, which are then visited in its place,
+    /// without calling `exit` for the folded node.
+    fn enter(&mut self, _pos: NodeRef<'_>, _data: &mut NodeData) -> Action {
+        Action::Continue
+    }
+
+    /// Called on exiting a node, after all of its children have been visited.
+    fn exit(&mut self, _pos: NodeRef<'_>, _data: &mut NodeData) -> Action {
+        Action::Continue
+    }
+}
+
+/// Visitor methods.
+impl Document {
+    /// Walk the entire `Document`, including the synthetic document node,
+    /// calling the visitor's `enter` and `exit` methods for each node.
+    pub fn visit<V>(&mut self, visitor: &mut V)
+        where V: Visitor + ?Sized
+    {
+        self.visit_at(Document::DOCUMENT_NODE_ID, visitor);
+    }
+
+    /// Walk from the specified node ID, calling the visitor's `enter` and
+    /// `exit` methods for each node.
+    ///
+    /// See [`Visitor`] for details.
+    pub fn visit_at<V>(&mut self, id: NodeId, visitor: &mut V)
+        where V: Visitor + ?Sized
+    {
+        self.visit_node(id, visitor);
+    }
+
+    // Visit node and descendants, returning true if the node was folded on
+    // enter, such that its former children should be visited by the caller.
+    fn visit_node<V>(&mut self, id: NodeId, v: &mut V) -> bool
+        where V: Visitor + ?Sized
+    {
+        match self.filter_node(id, &mut |p, d| v.enter(p, d)) {
+            Action::Continue => {}
+            Action::Fold => {
+                self.fold_only(id);
+                return true;
+            }
+            Action::Detach => {
+                self.unlink_only(id);
+                return false;
+            }
+        }
+
+        let mut next_child = self[id].first_child;
+        while let Some(child) = next_child {
+            // set before possible loss by visitor action
+            next_child = self[child].next_sibling;
+            let prev = self[child].prev_sibling;
+
+            if self.visit_node(child, v) {
+                next_child = match prev {
+                    Some(p) => self[p].next_sibling,
+                    None => self[id].first_child,
+                };
+            }
+        }
+
+        match self.filter_node(id, &mut |p, d| v.exit(p, d)) {
+            Action::Continue => {}
+            Action::Fold => self.fold_only(id),
+            Action::Detach => self.unlink_only(id),
+        }
+        false
+    }
+}
+
 /// Compose a new filter closure, by chaining a list of 1 to many closures or
 /// function paths. Each is executed in order, while the returned action remains
 /// `Action::Continue`, or otherwise terminated early.
@@ -239,7 +531,7 @@ macro_rules! chain_filters {
 /// executed breadth-first.
 pub fn detach_banned_elements(_p: NodeRef<'_>, data: &mut NodeData) -> Action {
     if let Some(ref mut elm) = data.as_element_mut() {
-        if let Some(tmeta) = TAG_META.get(&elm.name.local) {
+        if let Some(tmeta) = html::tag_meta(&elm.name.local) {
             if tmeta.is_banned() {
                 return Action::Detach;
             }
@@ -271,6 +563,50 @@ pub fn fold_empty_inline(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
     }
 }
 
+/// Return a filter which detaches elements having no attributes and no
+/// non-whitespace text or element content, excepting elements with any of
+/// the given local names (for example, `td`) and void elements such as `br`
+/// and `img`. Use via closure, for example:
+///
+/// ```no_run
+/// let pe = prune_empty(vec![t::TD, t::TH]);
+/// doc.filter(|p, d| pe.filter(p, d));
+/// ```
+///
+/// This requires depth-first filtering (the default), such that parents left
+/// empty by pruned children are also pruned.
+pub fn prune_empty<I, LN>(keep: I) -> EmptyPruner
+    where I: IntoIterator<Item = LN>, LN: Into<LocalName>
+{
+    EmptyPruner { keep: keep.into_iter().map(Into::into).collect() }
+}
+
+/// An empty element pruning filter. See [`prune_empty`].
+#[derive(Clone, Debug)]
+pub struct EmptyPruner {
+    keep: Vec<LocalName>,
+}
+
+impl EmptyPruner {
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(elm) = data.as_element() {
+            let lname = &elm.name.local;
+            let is_void = html::tag_meta(lname).map_or(false, |m| m.is_void());
+            if  elm.attrs.is_empty() &&
+                !is_void &&
+                !self.keep.contains(lname) &&
+                pos.children().all(|c| {
+                    c.as_text().map_or(false, |t| is_all_ctrl_ws(t))
+                })
+            {
+                return Action::Detach;
+            }
+        }
+        Action::Continue
+    }
+}
+
 /// Detach any comment nodes.
 ///
 /// Compatible with depth or breadth-first filtering.
@@ -310,6 +646,20 @@ pub fn retain_basic_attributes(_p: NodeRef<'_>, data: &mut NodeData)
     Action::Continue
 }
 
+/// Remove any duplicate attributes of elements, retaining only the first
+/// instance of each, as per HTML parsing.
+///
+/// This is useful as a precaution ahead of other attribute filters, for
+/// elements not constructed by the HTML parser. See
+/// [`Element::dedup_attrs`] for other policies. Compatible with depth or
+/// breadth-first filtering.
+pub fn dedup_attributes(_p: NodeRef<'_>, data: &mut NodeData) -> Action {
+    if let Some(elm) = data.as_element_mut() {
+        elm.dedup_attrs(DuplicateAttrs::FirstWins);
+    }
+    Action::Continue
+}
+
 /// Normalize text nodes by merging, replacing control characters and
 /// minimizing whitespace.
 ///
@@ -325,49 +675,903 @@ pub fn retain_basic_attributes(_p: NodeRef<'_>, data: &mut NodeData)
 /// [`detach_banned_elements`] or [`fold_empty_inline`]. Otherwise the filter
 /// may not be able to merge text node's which become siblings too late in the
 /// process, resulting in additional unnecessary whitespace.
+///
+/// This is equivalent to the default [`TextNormalizer`], which offers
+/// additional configuration.
 pub fn text_normalize(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
-    thread_local! {
-        static MERGE_Q: RefCell<StrTendril> = RefCell::new(StrTendril::new())
-    };
+    lazy_static! {
+        static ref DEFAULT: TextNormalizer = TextNormalizer::default();
+    }
+    DEFAULT.filter(pos, data)
+}
 
-    if let Some(t) = data.as_text_mut() {
-        // If the immediately following sibling is also text, then push this
-        // tendril to the merge queue and detach.
-        let node_r = pos.next_sibling();
-        if node_r.map_or(false, |n| n.as_text().is_some()) {
+/// A configurable variant of the [`text_normalize`] filter.
+///
+/// Construct via [`TextNormalizer::builder`], then use via closure, for
+/// example:
+///
+/// ```no_run
+/// let tn = TextNormalizer::builder()
+///     .preserve_whitespace_in(t::CODE)
+///     .collapse_newlines(false)
+///     .build();
+/// doc.filter(|p, d| tn.filter(p, d));
+/// ```
+///
+/// The same traversal order and separate pass recommendations as for
+/// `text_normalize` apply.
+#[derive(Clone, Debug)]
+pub struct TextNormalizer {
+    preserve_tags: Vec<LocalName>,
+    collapse_nbsp: bool,
+    collapse_newlines: bool,
+    #[cfg(feature = "nfc")]
+    nfc: bool,
+}
+
+/// Builder for a [`TextNormalizer`].
+#[derive(Clone, Debug)]
+pub struct TextNormalizerBuilder {
+    tn: TextNormalizer,
+}
+
+impl TextNormalizer {
+    /// Return a new builder, starting with the default configuration.
+    pub fn builder() -> TextNormalizerBuilder {
+        TextNormalizerBuilder { tn: TextNormalizer::default() }
+    }
+
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        thread_local! {
+            static MERGE_Q: RefCell<StrTendril> =
+                RefCell::new(StrTendril::new())
+        };
+
+        if let Some(t) = data.as_text_mut() {
+            // If the immediately following sibling is also text, then push
+            // this tendril to the merge queue and detach.
+            let node_r = pos.next_sibling();
+            if node_r.map_or(false, |n| n.as_text().is_some()) {
+                MERGE_Q.with(|q| {
+                    q.borrow_mut().push_tendril(t)
+                });
+                return Action::Detach;
+            }
+
+            // Otherwise add this tendril to anything in the queue, consuming
+            // it.
             MERGE_Q.with(|q| {
-                q.borrow_mut().push_tendril(t)
+                let mut qt = q.borrow_mut();
+                if qt.len() > 0 {
+                    qt.push_tendril(t);
+                    drop(qt);
+                    *t = q.replace(StrTendril::new());
+                }
             });
-            return Action::Detach;
+
+            let parent = pos.parent().unwrap();
+            let parent_is_block = is_not_inline(parent);
+            let preserve = parent
+                .node_and_ancestors()
+                .any(|n| self.is_preserving(n));
+
+            let node_l = pos.prev_sibling();
+            let trim_l = node_l.map_or(parent_is_block, is_not_inline);
+            let trim_r = node_r.map_or(parent_is_block, is_not_inline);
+
+            replace_chars_ext(
+                t,
+                !preserve,
+                true,
+                !self.collapse_nbsp,
+                !self.collapse_newlines,
+                trim_l,
+                trim_r);
+
+            #[cfg(feature = "nfc")]
+            {
+                if self.nfc {
+                    nfc_normalize(t);
+                }
+            }
+
+            if t.is_empty() {
+                return Action::Detach;
+            }
+        }
+        Action::Continue
+    }
+
+    fn is_preserving(&self, n: NodeRef<'_>) -> bool {
+        if let Some(elm) = n.as_element() {
+            self.preserve_tags.contains(&elm.name.local)
+        } else {
+            false
         }
+    }
+}
 
-        // Otherwise add this tendril to anything in the queue, consuming it.
-        MERGE_Q.with(|q| {
-            let mut qt = q.borrow_mut();
-            if qt.len() > 0 {
-                qt.push_tendril(t);
-                drop(qt);
-                *t = q.replace(StrTendril::new());
+impl Default for TextNormalizer {
+    fn default() -> TextNormalizer {
+        TextNormalizer {
+            preserve_tags: vec![t::PRE, t::XMP, t::PLAINTEXT],
+            collapse_nbsp: true,
+            collapse_newlines: true,
+            #[cfg(feature = "nfc")]
+            nfc: false,
+        }
+    }
+}
+
+impl TextNormalizerBuilder {
+    /// Preserve all whitespace in text within the given element (e.g.
+    /// `<code>` or `<textarea>`). By default this includes `<pre>`, `<xmp>`
+    /// and `<plaintext>`.
+    pub fn preserve_whitespace_in<LN>(mut self, tag: LN) -> Self
+        where LN: Into<LocalName>
+    {
+        let tag = tag.into();
+        if !self.tn.preserve_tags.contains(&tag) {
+            self.tn.preserve_tags.push(tag);
+        }
+        self
+    }
+
+    /// Clear all elements, including defaults, in which whitespace is
+    /// preserved.
+    pub fn preserve_whitespace_in_none(mut self) -> Self {
+        self.tn.preserve_tags.clear();
+        self
+    }
+
+    /// If true (the default), U+00A0 NO-BREAK SPACE is treated as whitespace
+    /// and collapsed with any adjacent whitespace to a single SPACE. If false
+    /// it is retained as is.
+    pub fn collapse_nbsp(mut self, collapse: bool) -> Self {
+        self.tn.collapse_nbsp = collapse;
+        self
+    }
+
+    /// If true (the default) whitespace sequences including line feeds are
+    /// replaced with a single SPACE. If false, they are replaced with a single
+    /// LINE FEED instead, preserving line structure for display.
+    pub fn collapse_newlines(mut self, collapse: bool) -> Self {
+        self.tn.collapse_newlines = collapse;
+        self
+    }
+
+    /// If true, apply Unicode Normalization Form C (NFC) to text (_nfc_
+    /// feature). Default: false.
+    #[cfg(feature = "nfc")]
+    pub fn nfc(mut self, nfc: bool) -> Self {
+        self.tn.nfc = nfc;
+        self
+    }
+
+    /// Return the configured `TextNormalizer`.
+    pub fn build(self) -> TextNormalizer {
+        self.tn
+    }
+}
+
+#[cfg(feature = "nfc")]
+fn nfc_normalize(st: &mut StrTendril) {
+    use unicode_normalization::{
+        is_nfc_quick, IsNormalized, UnicodeNormalization
+    };
+    if is_nfc_quick(st.chars()) != IsNormalized::Yes {
+        let nfc: String = st.nfc().collect();
+        *st = nfc.into();
+    }
+}
+
+/// Normalize whitespace in element attribute values.
+///
+/// Leading and trailing whitespace is trimmed, and internal whitespace
+/// sequences are replaced with a single SPACE, in all attribute values except
+/// `value` (as of form controls, where whitespace may be significant). HTML
+/// parsing otherwise retains attribute values as found, so this allows later
+/// comparisons to avoid repeated trimming.
+///
+/// This is equivalent to the default [`AttrNormalizer`], which offers
+/// additional configuration. Compatible with depth or breadth-first
+/// filtering.
+pub fn normalize_attributes(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+    lazy_static! {
+        static ref DEFAULT: AttrNormalizer = AttrNormalizer::default();
+    }
+    DEFAULT.filter(pos, data)
+}
+
+/// A configurable variant of the [`normalize_attributes`] filter.
+///
+/// Construct via [`AttrNormalizer::builder`], then use via closure, for
+/// example:
+///
+/// ```no_run
+/// let an = AttrNormalizer::builder()
+///     .collapse(false)
+///     .preserve(a::TITLE)
+///     .build();
+/// doc.filter(|p, d| an.filter(p, d));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AttrNormalizer {
+    preserve_attrs: Vec<LocalName>,
+    trim: bool,
+    collapse: bool,
+}
+
+/// Builder for an [`AttrNormalizer`].
+#[derive(Clone, Debug)]
+pub struct AttrNormalizerBuilder {
+    an: AttrNormalizer,
+}
+
+impl AttrNormalizer {
+    /// Return a new builder, starting with the default configuration.
+    pub fn builder() -> AttrNormalizerBuilder {
+        AttrNormalizerBuilder { an: AttrNormalizer::default() }
+    }
+
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, _p: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(elm) = data.as_element_mut() {
+            for attr in &mut elm.attrs {
+                if !self.preserve_attrs.contains(&attr.name.local) {
+                    self.normalize(&mut attr.value);
+                }
             }
-        });
+        }
+        Action::Continue
+    }
 
-        let parent = pos.parent().unwrap();
-        let parent_is_block = is_block(parent);
-        let in_pre = parent.node_and_ancestors().any(is_preform_node);
+    fn normalize(&self, value: &mut StrTendril) {
+        if self.collapse {
+            replace_chars(value, true, true, self.trim, self.trim);
+        } else if self.trim {
+            let trimmed = value.trim();
+            if trimmed.len() != value.len() {
+                *value = StrTendril::from_slice(trimmed);
+            }
+        }
+    }
+}
 
-        let node_l = pos.prev_sibling();
-        let trim_l = node_l.map_or(parent_is_block, is_block);
-        let trim_r = node_r.map_or(parent_is_block, is_block);
+impl Default for AttrNormalizer {
+    fn default() -> AttrNormalizer {
+        AttrNormalizer {
+            preserve_attrs: vec![a::VALUE],
+            trim: true,
+            collapse: true,
+        }
+    }
+}
 
-        replace_chars(t, !in_pre, true, trim_l, trim_r);
+impl AttrNormalizerBuilder {
+    /// Preserve the values of attributes with the given local name as is. By
+    /// default this includes only `value`.
+    pub fn preserve<LN>(mut self, attr: LN) -> Self
+        where LN: Into<LocalName>
+    {
+        let attr = attr.into();
+        if !self.an.preserve_attrs.contains(&attr) {
+            self.an.preserve_attrs.push(attr);
+        }
+        self
+    }
+
+    /// Clear all preserved attributes, including defaults.
+    pub fn preserve_none(mut self) -> Self {
+        self.an.preserve_attrs.clear();
+        self
+    }
+
+    /// If true (the default), trim leading and trailing whitespace.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.an.trim = trim;
+        self
+    }
+
+    /// If true (the default), replace internal whitespace sequences with a
+    /// single SPACE, and remove control characters.
+    pub fn collapse(mut self, collapse: bool) -> Self {
+        self.an.collapse = collapse;
+        self
+    }
+
+    /// Return the configured `AttrNormalizer`.
+    pub fn build(self) -> AttrNormalizer {
+        self.an
+    }
+}
+
+/// Return a [`TextReplacer`] filter which replaces all matches of the regular
+/// expression in text nodes with the given replacement (_regex_ feature).
+///
+/// The replacement may reference capture groups, e.g. `$1` or `${name}`, as
+/// per `regex::Regex::replace_all`. Text within `<script>`, `<style>` and
+/// `<pre>` elements is skipped by default. Use via closure, for example:
+///
+/// ```no_run
+/// let tr = text_replace(Regex::new("[“”]").unwrap(), "\"");
+/// doc.filter(|p, d| tr.filter(p, d));
+/// ```
+///
+/// See [`TextReplacer::builder`] for additional configuration. Compatible
+/// with depth or breadth-first filtering.
+#[cfg(feature = "regex")]
+pub fn text_replace<R>(regex: Regex, replacement: R) -> TextReplacer
+    where R: Into<String>
+{
+    TextReplacer::builder(regex, replacement).build()
+}
+
+/// A configurable regular expression text replacement filter (_regex_
+/// feature). See [`text_replace`].
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct TextReplacer {
+    regex: Regex,
+    replacement: String,
+    skip_tags: Vec<LocalName>,
+}
+
+/// Builder for a [`TextReplacer`] (_regex_ feature).
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct TextReplacerBuilder {
+    tr: TextReplacer,
+}
+
+#[cfg(feature = "regex")]
+impl TextReplacer {
+    /// Return a new builder for the given regular expression and
+    /// replacement, starting with the default configuration.
+    pub fn builder<R>(regex: Regex, replacement: R) -> TextReplacerBuilder
+        where R: Into<String>
+    {
+        TextReplacerBuilder {
+            tr: TextReplacer {
+                regex,
+                replacement: replacement.into(),
+                skip_tags: vec![t::SCRIPT, t::STYLE, t::PRE],
+            }
+        }
+    }
 
-        if t.is_empty() {
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(t) = data.as_text_mut() {
+            let skip = pos.ancestors().any(|n| {
+                n.as_element()
+                    .map_or(false, |e| self.skip_tags.contains(&e.name.local))
+            });
+            if !skip {
+                let replaced = self.regex
+                    .replace_all(t, self.replacement.as_str());
+                if let Cow::Owned(s) = replaced {
+                    *t = s.into();
+                }
+            }
+        }
+        Action::Continue
+    }
+}
+
+#[cfg(feature = "regex")]
+impl TextReplacerBuilder {
+    /// Skip text within elements of the given local name. By default this
+    /// includes `script`, `style` and `pre`.
+    pub fn skip<LN>(mut self, tag: LN) -> Self
+        where LN: Into<LocalName>
+    {
+        let tag = tag.into();
+        if !self.tr.skip_tags.contains(&tag) {
+            self.tr.skip_tags.push(tag);
+        }
+        self
+    }
+
+    /// Clear all skipped elements, including defaults, such that all text is
+    /// replaced.
+    pub fn skip_none(mut self) -> Self {
+        self.tr.skip_tags.clear();
+        self
+    }
+
+    /// Return the configured `TextReplacer`.
+    pub fn build(self) -> TextReplacer {
+        self.tr
+    }
+}
+
+/// Default URL patterns of known ad and tracker hosts, for
+/// [`TrackerStripper`].
+pub const DEFAULT_TRACKER_PATTERNS: &[&str] = &[
+    "adnxs.com",
+    "adservice.google.",
+    "amazon-adsystem.com",
+    "chartbeat.com",
+    "connect.facebook.net",
+    "criteo.com",
+    "criteo.net",
+    "doubleclick.net",
+    "facebook.com/tr",
+    "google-analytics.com",
+    "googleadservices.com",
+    "googlesyndication.com",
+    "googletagmanager.com",
+    "googletagservices.com",
+    "hotjar.com",
+    "outbrain.com",
+    "quantserve.com",
+    "scorecardresearch.com",
+    "taboola.com",
+];
+
+/// Remove known ad and tracker elements and inline event handlers.
+///
+/// This detaches `<script>`, `<iframe>`, `<img>` and `<embed>` elements with
+/// a `src` matching any of the [`DEFAULT_TRACKER_PATTERNS`], tracking pixel
+/// images (with `width` and `height` of at most 1), and removes all inline
+/// `on*` event handler attributes from elements.
+///
+/// This is equivalent to the default [`TrackerStripper`], which offers
+/// additional configuration, including user-extensible blocklists.
+/// Compatible with depth or breadth-first filtering.
+pub fn strip_trackers(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+    lazy_static! {
+        static ref DEFAULT: TrackerStripper = TrackerStripper::default();
+    }
+    DEFAULT.filter(pos, data)
+}
+
+/// A configurable variant of the [`strip_trackers`] filter.
+///
+/// Construct via [`TrackerStripper::builder`], then use via closure, for
+/// example:
+///
+/// ```no_run
+/// let ts = TrackerStripper::builder()
+///     .block("ads.example.com")
+///     .event_handlers(false)
+///     .build();
+/// doc.filter(|p, d| ts.filter(p, d));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TrackerStripper {
+    patterns: Vec<String>,
+    pixels: bool,
+    event_handlers: bool,
+}
+
+/// Builder for a [`TrackerStripper`].
+#[derive(Clone, Debug)]
+pub struct TrackerStripperBuilder {
+    ts: TrackerStripper,
+}
+
+impl TrackerStripper {
+    /// Return a new builder, starting with the default configuration.
+    pub fn builder() -> TrackerStripperBuilder {
+        TrackerStripperBuilder { ts: TrackerStripper::default() }
+    }
+
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, _p: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(elm) = data.as_element_mut() {
+            let name = &elm.name.local;
+            if *name == t::SCRIPT || *name == t::IFRAME ||
+                *name == t::IMG || *name == t::EMBED
+            {
+                if let Some(src) = elm.attr(a::SRC) {
+                    if self.is_blocked(src) {
+                        return Action::Detach;
+                    }
+                }
+            }
+            if self.pixels && *name == t::IMG && is_pixel(elm) {
+                return Action::Detach;
+            }
+            if self.event_handlers {
+                elm.attrs.retain(|attr| !is_event_handler(&attr.name.local));
+            }
+        }
+        Action::Continue
+    }
+
+    /// Return true if the given URL matches any blocklist pattern, ASCII
+    /// case-insensitively.
+    pub fn is_blocked(&self, url: &str) -> bool {
+        let url = url.trim().to_ascii_lowercase();
+        self.patterns.iter().any(|p| url.contains(p.as_str()))
+    }
+}
+
+impl Default for TrackerStripper {
+    fn default() -> TrackerStripper {
+        TrackerStripper {
+            patterns: DEFAULT_TRACKER_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            pixels: true,
+            event_handlers: true,
+        }
+    }
+}
+
+impl TrackerStripperBuilder {
+    /// Add a URL pattern to the blocklist. Patterns are matched as ASCII
+    /// case-insensitive substrings of `src` values.
+    pub fn block<P>(mut self, pattern: P) -> Self
+        where P: AsRef<str>
+    {
+        let pattern = pattern.as_ref().to_ascii_lowercase();
+        if !self.ts.patterns.contains(&pattern) {
+            self.ts.patterns.push(pattern);
+        }
+        self
+    }
+
+    /// Clear all blocklist patterns, including defaults.
+    pub fn block_none(mut self) -> Self {
+        self.ts.patterns.clear();
+        self
+    }
+
+    /// If true (the default), detach tracking pixel images.
+    pub fn pixels(mut self, pixels: bool) -> Self {
+        self.ts.pixels = pixels;
+        self
+    }
+
+    /// If true (the default), remove inline `on*` event handler attributes.
+    pub fn event_handlers(mut self, event_handlers: bool) -> Self {
+        self.ts.event_handlers = event_handlers;
+        self
+    }
+
+    /// Return the configured `TrackerStripper`.
+    pub fn build(self) -> TrackerStripper {
+        self.ts
+    }
+}
+
+// Return true if the image has a width and height of at most 1 pixel.
+fn is_pixel(elm: &Element) -> bool {
+    let dim = |lname: LocalName| {
+        elm.attr(lname)
+            .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok())
+            .map_or(false, |d| d <= 1)
+    };
+    dim(a::WIDTH) && dim(a::HEIGHT)
+}
+
+fn is_event_handler(lname: &LocalName) -> bool {
+    let name: &str = lname;
+    name.len() > 2 && name.starts_with("on")
+}
+
+/// Remove `style`, inline `on*` event handler and `data-*` attributes from
+/// all elements.
+///
+/// This is equivalent to the default [`AttrScrubber`], which offers
+/// additional configuration, including per-tag allowlists. Compatible with
+/// depth or breadth-first filtering.
+pub fn scrub_attributes(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+    lazy_static! {
+        static ref DEFAULT: AttrScrubber = AttrScrubber::default();
+    }
+    DEFAULT.filter(pos, data)
+}
+
+/// A configurable variant of the [`scrub_attributes`] filter, usable for
+/// both sanitization and output minimization.
+///
+/// For elements with a per-tag allowlist, only the allowed attributes are
+/// retained (regardless of deny rules). For all other elements, attributes
+/// matching any deny rule are removed. Construct via
+/// [`AttrScrubber::builder`], then use via closure, for example:
+///
+/// ```no_run
+/// let sc = AttrScrubber::builder()
+///     .deny("class")
+///     .allow(t::A, a::HREF)
+///     .allow(t::IMG, a::SRC)
+///     .allow(t::IMG, a::ALT)
+///     .build();
+/// doc.filter(|p, d| sc.filter(p, d));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AttrScrubber {
+    deny: Vec<LocalName>,
+    event_handlers: bool,
+    data_attrs: bool,
+    allow: HashMap<LocalName, Vec<LocalName>>,
+}
+
+/// Builder for an [`AttrScrubber`].
+#[derive(Clone, Debug)]
+pub struct AttrScrubberBuilder {
+    sc: AttrScrubber,
+}
+
+impl AttrScrubber {
+    /// Return a new builder, starting with the default configuration.
+    pub fn builder() -> AttrScrubberBuilder {
+        AttrScrubberBuilder { sc: AttrScrubber::default() }
+    }
+
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, _p: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(elm) = data.as_element_mut() {
+            if let Some(allowed) = self.allow.get(&elm.name.local) {
+                elm.attrs.retain(|a| allowed.contains(&a.name.local));
+            } else {
+                elm.attrs.retain(|a| !self.is_denied(&a.name.local));
+            }
+        }
+        Action::Continue
+    }
+
+    fn is_denied(&self, lname: &LocalName) -> bool {
+        self.deny.contains(lname) ||
+            (self.event_handlers && is_event_handler(lname)) ||
+            (self.data_attrs && lname.starts_with("data-"))
+    }
+}
+
+impl Default for AttrScrubber {
+    fn default() -> AttrScrubber {
+        AttrScrubber {
+            deny: vec![a::STYLE],
+            event_handlers: true,
+            data_attrs: true,
+            allow: HashMap::new(),
+        }
+    }
+}
+
+impl AttrScrubberBuilder {
+    /// Remove attributes of the given local name. By default this includes
+    /// `style`.
+    pub fn deny<LN>(mut self, attr: LN) -> Self
+        where LN: Into<LocalName>
+    {
+        let attr = attr.into();
+        if !self.sc.deny.contains(&attr) {
+            self.sc.deny.push(attr);
+        }
+        self
+    }
+
+    /// Clear all denied attribute names, including defaults.
+    pub fn deny_none(mut self) -> Self {
+        self.sc.deny.clear();
+        self
+    }
+
+    /// If true (the default), remove inline `on*` event handler attributes.
+    pub fn event_handlers(mut self, event_handlers: bool) -> Self {
+        self.sc.event_handlers = event_handlers;
+        self
+    }
+
+    /// If true (the default), remove `data-*` attributes.
+    pub fn data_attrs(mut self, data_attrs: bool) -> Self {
+        self.sc.data_attrs = data_attrs;
+        self
+    }
+
+    /// Add an attribute to the allowlist for the given element local name.
+    /// Once an element has an allowlist, all other attributes are removed
+    /// from it.
+    pub fn allow<TN, AN>(mut self, tag: TN, attr: AN) -> Self
+        where TN: Into<LocalName>, AN: Into<LocalName>
+    {
+        let allowed = self.sc.allow.entry(tag.into()).or_insert_with(Vec::new);
+        let attr = attr.into();
+        if !allowed.contains(&attr) {
+            allowed.push(attr);
+        }
+        self
+    }
+
+    /// Return the configured `AttrScrubber`.
+    pub fn build(self) -> AttrScrubber {
+        self.sc
+    }
+}
+
+/// Convert AMP media elements to standard HTML elements.
+///
+/// The `<amp-img>` and `<amp-anim>` elements are converted to `<img>`,
+/// `<amp-video>` to `<video>`, `<amp-audio>` to `<audio>` and `<amp-iframe>`
+/// to `<iframe>`, removing the AMP `layout` attribute. Children of converted
+/// `<img>` and `<iframe>` elements, and any `fallback` or `placeholder`
+/// children of the others, are detached.
+///
+/// This requires depth-first filtering (the default), such that children are
+/// visited before the parent is converted.
+pub fn amp_to_html(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+    let parent_to = pos.parent().and_then(|p| {
+        p.as_element().and_then(|e| amp_replacement(&e.name.local))
+    });
+    if let Some(to) = parent_to {
+        let is_aux = data.as_element().map_or(false, |e| {
+            e.attr("fallback").is_some() || e.attr("placeholder").is_some()
+        });
+        if is_aux || to == t::IMG || to == t::IFRAME {
             return Action::Detach;
         }
     }
+    if let Some(elm) = data.as_element_mut() {
+        if let Some(to) = amp_replacement(&elm.name.local) {
+            elm.name.local = to;
+            elm.remove_attr("layout");
+        }
+    }
     Action::Continue
 }
 
+fn amp_replacement(name: &LocalName) -> Option<LocalName> {
+    match &**name {
+        "amp-img" | "amp-anim" => Some(t::IMG),
+        "amp-video" => Some(t::VIDEO),
+        "amp-audio" => Some(t::AUDIO),
+        "amp-iframe" => Some(t::IFRAME),
+        _ => None,
+    }
+}
+
+/// Structural cleanup methods.
+impl Document {
+    /// Replace `<noscript>` elements containing image fallbacks (as commonly
+    /// used with lazy loading images) with their parsed content, returning
+    /// the number replaced.
+    ///
+    /// When parsed with scripting enabled (as by this crate), `<noscript>`
+    /// content is retained as unparsed text. This parses that text, and if it
+    /// contains any `<img>` element, the content replaces the `<noscript>`.
+    /// Additionally, if the nearest preceding element sibling is an `<img>`
+    /// placeholder, without a `src` or with a `data:` URI `src`, it is
+    /// removed. Any `<noscript>` within `<head>` is ignored.
+    ///
+    /// Unlike a filter, this can insert new nodes. Replaced nodes are
+    /// unlinked, leaving them as unreachable nodes until
+    /// [`Document::compact`].
+    pub fn unwrap_noscript_images(&mut self) -> usize {
+        let ids: Vec<NodeId> = self.nodes()
+            .filter(|&id| {
+                self[id].is_elem(t::NOSCRIPT) &&
+                    !self.node_and_ancestors(id)
+                        .any(|n| self[n].is_elem(t::HEAD))
+            })
+            .collect();
+
+        let mut count = 0;
+        for id in ids {
+            let noscript = NodeRef::new(self, id);
+            if noscript.children().any(|c| c.as_element().is_some()) {
+                // Already parsed, e.g. with scripting disabled
+                if noscript.find(|n| n.is_elem(t::IMG)).is_some() {
+                    self.remove_placeholder_before(id);
+                    self.fold(id);
+                    count += 1;
+                }
+                continue;
+            }
+            let text = match noscript.text() {
+                Some(text) => text,
+                None => continue,
+            };
+            let frag = html::parse_fragment_in_context(
+                t::DIV,
+                text.as_bytes());
+            let has_img = frag.roots()
+                .any(|r| r.descendants().any(|n| n.is_elem(t::IMG)));
+            if has_img {
+                self.remove_placeholder_before(id);
+                self.attach_before_sibling(id, frag.into_document());
+                self.unlink(id);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    // Unlink any lazy loading img placeholder element preceding id.
+    fn remove_placeholder_before(&mut self, id: NodeId) {
+        let prev = NodeRef::new(self, id)
+            .prev_siblings()
+            .find(|n| match n.as_text() {
+                Some(t) => !is_all_ctrl_ws(t),
+                None => n.as_element().is_some(),
+            });
+        if let Some(prev) = prev {
+            let placeholder = prev.as_element().map_or(false, |e| {
+                e.is_elem(t::IMG) && e.attr(a::SRC).map_or(true, |s| {
+                    let s = s.trim();
+                    s.is_empty() || s.as_bytes().get(..5).map_or(false, |p| {
+                        p.eq_ignore_ascii_case(b"data:")
+                    })
+                })
+            });
+            if placeholder {
+                let prev = prev.id();
+                self.unlink(prev);
+            }
+        }
+    }
+}
+
+/// Propagate any inherited `lang` and `dir` attribute values to all
+/// descendant elements which lack them.
+///
+/// This is useful where sub-trees are later extracted or serialized
+/// independently, and should retain language and direction. See
+/// [`NodeRef::language`] and [`NodeRef::direction`].
+///
+/// Compatible with depth or breadth-first filtering.
+pub fn propagate_lang_dir(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+    if let Some(elm) = data.as_element_mut() {
+        if let Some(parent) = pos.parent() {
+            if elm.attr(a::LANG).is_none() {
+                if let Some(lang) = parent.language() {
+                    elm.set_attr(a::LANG, lang);
+                }
+            }
+            if elm.attr(a::DIR).is_none() {
+                let dir = parent.node_and_ancestors()
+                    .find_map(|n| n.attr(a::DIR).cloned());
+                if let Some(dir) = dir {
+                    elm.set_attr(a::DIR, dir);
+                }
+            }
+        }
+    }
+    Action::Continue
+}
+
+/// Remove any provenance (`data-marked-*`) attributes, as stamped via
+/// [`Document::annotate`], from elements.
+///
+/// Compatible with depth or breadth-first filtering.
+pub fn strip_provenance(_p: NodeRef<'_>, data: &mut NodeData) -> Action {
+    if let Some(elm) = data.as_element_mut() {
+        elm.attrs.retain(|a| !is_provenance_attr(&a.name.local));
+    }
+    Action::Continue
+}
+
+/// Detach elements which are probably hidden, as per
+/// [`NodeRef::is_probably_hidden`].
+///
+/// Compatible with depth or breadth-first filtering, but more efficiently
+/// executed breadth-first.
+pub fn detach_hidden(_p: NodeRef<'_>, data: &mut NodeData) -> Action {
+    if data.as_element().map_or(false, is_hidden_element) {
+        Action::Detach
+    } else {
+        Action::Continue
+    }
+}
+
+/// Detach any sub-trees marked as boilerplate via
+/// [`Document::mark_boilerplate`].
+///
+/// Compatible with depth or breadth-first filtering.
+pub fn detach_boilerplate(pos: NodeRef<'_>, _d: &mut NodeData) -> Action {
+    if pos.user_data::<Boilerplate>().is_some() {
+        Action::Detach
+    } else {
+        Action::Continue
+    }
+}
+
 // FIXME: Consider also offering a simpler version of the above for XML or
 // where speed trumps precision.
 
@@ -387,20 +1591,199 @@ pub fn xmp_to_pre(_p: NodeRef<'_>, data: &mut NodeData) -> Action {
     Action::Continue
 }
 
-fn is_block(node: NodeRef<'_>) -> bool {
+/// Return a filter which renames elements per the given mapping of element
+/// local names, e.g. `b` to `strong`. Use via closure, for example:
+///
+/// ```no_run
+/// let rt = retag(vec![(t::B, t::STRONG), (t::I, t::EM), (t::FONT, t::SPAN)]);
+/// doc.filter(|p, d| rt.filter(p, d));
+/// ```
+///
+/// Children and attributes are preserved. Only elements in the HTML (or
+/// empty) namespace are renamed, such that, for example, an SVG `<a>` is
+/// unaffected by a mapping for HTML `<a>`. Compatible with depth or
+/// breadth-first filtering.
+pub fn retag<I, F, T>(map: I) -> Retagger
+    where I: IntoIterator<Item = (F, T)>,
+          F: Into<LocalName>,
+          T: Into<LocalName>
+{
+    Retagger {
+        map: map.into_iter().map(|(f, t)| (f.into(), t.into())).collect()
+    }
+}
+
+/// An element renaming filter. See [`retag`].
+#[derive(Clone, Debug)]
+pub struct Retagger {
+    map: HashMap<LocalName, LocalName>,
+}
+
+impl Retagger {
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, _p: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(elm) = data.as_element_mut() {
+            if elm.name.ns == html::ns::HTML || elm.name.ns.is_empty() {
+                if let Some(to) = self.map.get(&elm.name.local) {
+                    elm.name.local = to.clone();
+                }
+            }
+        }
+        Action::Continue
+    }
+}
+
+/// Return a filter wrapping the given filter, which accumulates the time
+/// spent in, and the number of calls to, that filter. Use via closure, for
+/// example:
+///
+/// ```no_run
+/// let tm = timed(detach_banned_elements);
+/// doc.filter(chain_filters!(|p, d| tm.filter(p, d), text_normalize));
+/// println!("detach_banned_elements: {:?}", tm.elapsed());
+/// ```
+///
+/// This allows profiling the individual filters of a chain. Note that the
+/// time of the traversal itself is not included. On the
+/// `wasm32-unknown-unknown` target, where no clock is available, only calls
+/// are recorded.
+pub fn timed<F>(f: F) -> Timed<F>
+    where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+{
+    Timed { f, elapsed: Cell::new(Duration::default()), calls: Cell::new(0) }
+}
+
+/// A filter wrapper recording call counts and timings. See [`timed`].
+pub struct Timed<F> {
+    f: F,
+    elapsed: Cell<Duration>,
+    calls: Cell<u64>,
+}
+
+impl<F> Timed<F>
+    where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+{
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        let start = now();
+        let action = (self.f)(pos, data);
+        if let Some(start) = start {
+            self.elapsed.set(self.elapsed.get() + start.elapsed());
+        }
+        self.calls.set(self.calls.get() + 1);
+        action
+    }
+
+    /// Return the total time spent in the wrapped filter.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed.get()
+    }
+
+    /// Return the total number of calls to the wrapped filter.
+    pub fn calls(&self) -> u64 {
+        self.calls.get()
+    }
+
+    /// Reset the elapsed time and calls to zero.
+    pub fn reset(&self) {
+        self.elapsed.set(Duration::default());
+        self.calls.set(0);
+    }
+}
+
+// Return the current time, or None if unsupported, as on the
+// wasm32-unknown-unknown target, where `Instant::now` panics.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now() -> Option<Instant> {
+    None
+}
+
+/// Return a filter which interns attribute values up to the given maximum
+/// length in bytes, such that equal values share a single buffer. Use via
+/// closure, for example:
+///
+/// ```no_run
+/// let interner = intern_attr_values(64);
+/// doc.filter(|p, d| interner.filter(p, d));
+/// ```
+///
+/// Values such as `"stylesheet"` or `"text/javascript"` can repeat
+/// thousands of times, each separately allocated by the parser. The same
+/// interner may be reused across many documents (of the same thread), for
+/// example in a large crawl, to share buffers between them. Values of 8
+/// bytes or less are already stored inline by `StrTendril` and are not
+/// interned. Compatible with depth or breadth-first filtering.
+pub fn intern_attr_values(max_len: usize) -> AttrInterner {
+    AttrInterner { max_len, values: RefCell::new(HashMap::new()) }
+}
+
+/// An attribute value interning filter. See [`intern_attr_values`].
+#[derive(Debug)]
+pub struct AttrInterner {
+    max_len: usize,
+    values: RefCell<HashMap<Box<str>, StrTendril>>,
+}
+
+impl AttrInterner {
+    /// The filter function, for use as per [`Document::filter`].
+    pub fn filter(&self, _p: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if let Some(elm) = data.as_element_mut() {
+            let mut values = self.values.borrow_mut();
+            for attr in &mut elm.attrs {
+                let len = attr.value.len32();
+                if len <= TENDRIL_INLINE_MAX || len as usize > self.max_len {
+                    continue;
+                }
+                if let Some(v) = values.get(attr.value.as_ref()) {
+                    attr.value = v.clone();
+                } else {
+                    values.insert(
+                        attr.value.as_ref().into(),
+                        attr.value.clone()
+                    );
+                }
+            }
+        }
+        Action::Continue
+    }
+
+    /// Return the number of distinct values interned.
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    /// Return true if no values have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+
+    /// Remove all interned values.
+    pub fn clear(&self) {
+        self.values.borrow_mut().clear();
+    }
+}
+
+// Return true for known elements which are not inline. Note this differs
+// from `TagMeta::is_block`, which also excludes _meta_ elements.
+fn is_not_inline(node: NodeRef<'_>) -> bool {
     if let Some(elm) = node.as_element() {
-        if let Some(tmeta) = TAG_META.get(&elm.name.local) {
+        if let Some(tmeta) = html::tag_meta(&elm.name.local) {
             return !tmeta.is_inline();
         }
     }
     false
 }
 
-// Note this isn't an exact negation of `is_block`: it still returns false for
-// unknown elements.
+// Note this isn't an exact negation of `is_not_inline`: it still returns
+// false for unknown elements.
 fn is_inline(data: &NodeData) -> bool {
     if let Some(elm) = data.as_element() {
-        if let Some(tmeta) = TAG_META.get(&elm.name.local) {
+        if let Some(tmeta) = html::tag_meta(&elm.name.local) {
             return tmeta.is_inline();
         }
     }
@@ -411,10 +1794,6 @@ fn is_preformatted(e: &Element) -> bool {
     e.is_elem(t::PRE) || e.is_elem(t::XMP) || e.is_elem(t::PLAINTEXT)
 }
 
-fn is_preform_node(n: NodeRef<'_>) -> bool {
-    n.is_elem(t::PRE) || n.is_elem(t::XMP) || n.is_elem(t::PLAINTEXT)
-}
-
 fn is_logical_ws(n: NodeRef<'_>) -> bool {
     if let Some(t) = n.as_text() {
         is_all_ctrl_ws(t)
@@ -0,0 +1,178 @@
+//! Declarative filter configuration.
+//!
+//! A [`FilterConfig`] describes a common set of cleaning rules as plain data,
+//! so that these may be tuned without recompiling. With the _serde_ feature,
+//! it implements `Deserialize` and may be loaded from TOML, JSON or any other
+//! serde format, for example:
+//!
+//! ```toml
+//! remove = ["nav", "div.ad", "[hidden]"]
+//! fold = ["font", "span"]
+//! strip_attrs = ["style", "onclick"]
+//! detach_banned = true
+//! detach_comments = true
+//! normalize_text = true
+//! ```
+//!
+//! Missing fields take their default values.
+
+use std::collections::HashSet;
+
+use crate::dom::{
+    filter::{
+        detach_banned_elements, detach_comments, detach_pis, Action,
+        TextNormalizer,
+    },
+    node_ref::selector_matches,
+    Document, LocalName, NodeData, NodeRef,
+};
+
+/// Declarative configuration of a filter chain. See the [module
+/// documentation](self) and [`FilterConfig::build`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_dep::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(crate = "serde_dep", default, deny_unknown_fields)
+)]
+pub struct FilterConfig {
+    /// Selectors of elements to detach, with their content, in the subset of
+    /// CSS syntax supported by [`NodeRef::matches`]. Default: empty.
+    pub remove: Vec<String>,
+
+    /// Local names of elements to fold, replacing each with its children.
+    /// Default: empty.
+    pub fold: Vec<String>,
+
+    /// Local names of attributes to remove from all elements. Default:
+    /// empty.
+    pub strip_attrs: Vec<String>,
+
+    /// Detach banned and unknown elements, as per
+    /// [`detach_banned_elements`]. Default: false.
+    pub detach_banned: bool,
+
+    /// Detach comment nodes. Default: false.
+    pub detach_comments: bool,
+
+    /// Detach processing instruction nodes. Default: false.
+    pub detach_pis: bool,
+
+    /// Normalize whitespace in text, as per [`TextNormalizer`], in a
+    /// separate, subsequent pass. Default: false.
+    pub normalize_text: bool,
+
+    /// See [`TextNormalizerBuilder::collapse_nbsp`]. Default: true.
+    ///
+    /// [`TextNormalizerBuilder::collapse_nbsp`]:
+    /// crate::filter::TextNormalizerBuilder::collapse_nbsp
+    pub collapse_nbsp: bool,
+
+    /// See [`TextNormalizerBuilder::collapse_newlines`]. Default: true.
+    ///
+    /// [`TextNormalizerBuilder::collapse_newlines`]:
+    /// crate::filter::TextNormalizerBuilder::collapse_newlines
+    pub collapse_newlines: bool,
+}
+
+impl Default for FilterConfig {
+    fn default() -> FilterConfig {
+        FilterConfig {
+            remove: Vec::new(),
+            fold: Vec::new(),
+            strip_attrs: Vec::new(),
+            detach_banned: false,
+            detach_comments: false,
+            detach_pis: false,
+            normalize_text: false,
+            collapse_nbsp: true,
+            collapse_newlines: true,
+        }
+    }
+}
+
+impl FilterConfig {
+    /// Return a `ConfigFilter` for this configuration.
+    pub fn build(&self) -> ConfigFilter {
+        let text = if self.normalize_text {
+            Some(TextNormalizer::builder()
+                .collapse_nbsp(self.collapse_nbsp)
+                .collapse_newlines(self.collapse_newlines)
+                .build())
+        } else {
+            None
+        };
+        ConfigFilter {
+            remove: self.remove.join(","),
+            fold: self.fold.iter()
+                .map(|n| LocalName::from(n.to_ascii_lowercase()))
+                .collect(),
+            strip_attrs: self.strip_attrs.iter()
+                .map(|n| LocalName::from(n.to_ascii_lowercase()))
+                .collect(),
+            detach_banned: self.detach_banned,
+            detach_comments: self.detach_comments,
+            detach_pis: self.detach_pis,
+            text,
+        }
+    }
+}
+
+/// A filter chain built from a [`FilterConfig`].
+///
+/// Use [`ConfigFilter::apply`] to run all configured passes on a
+/// `Document`.
+#[derive(Clone, Debug)]
+pub struct ConfigFilter {
+    remove: String,
+    fold: HashSet<LocalName>,
+    strip_attrs: HashSet<LocalName>,
+    detach_banned: bool,
+    detach_comments: bool,
+    detach_pis: bool,
+    text: Option<TextNormalizer>,
+}
+
+impl ConfigFilter {
+    /// Apply the element filter breadth-first, followed by any text
+    /// normalization in a separate depth-first pass, then compact the
+    /// `Document`.
+    pub fn apply(&self, doc: &mut Document) {
+        doc.filter_breadth(|p, d| self.filter(p, d));
+        if let Some(tn) = &self.text {
+            doc.filter(|p, d| tn.filter(p, d));
+        }
+        doc.compact();
+    }
+
+    /// The element filter function, for use as per [`Document::filter`].
+    ///
+    /// This excludes any text normalization, which should be applied in its
+    /// own pass. Compatible with depth or breadth-first filtering, but more
+    /// efficiently executed breadth-first.
+    pub fn filter(&self, pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        let detach =
+            (self.detach_banned &&
+             detach_banned_elements(pos, data) == Action::Detach) ||
+            (self.detach_comments &&
+             detach_comments(pos, data) == Action::Detach) ||
+            (self.detach_pis &&
+             detach_pis(pos, data) == Action::Detach);
+        if detach {
+            return Action::Detach;
+        }
+        if let Some(elm) = data.as_element_mut() {
+            if !self.remove.is_empty() && selector_matches(elm, &self.remove) {
+                return Action::Detach;
+            }
+            if !self.strip_attrs.is_empty() {
+                let strip = &self.strip_attrs;
+                elm.attrs.retain(|a| !strip.contains(&a.name.local));
+            }
+            if self.fold.contains(&elm.name.local) {
+                return Action::Fold;
+            }
+        }
+        Action::Continue
+    }
+}
@@ -0,0 +1,333 @@
+//! Preparation of HTML for outbound e-mail.
+//!
+//! Many e-mail clients ignore `<style>` elements and external resources, and
+//! some (notably Outlook) size images only by their `width` and `height`
+//! attributes. [`EmailPrep`] transforms a `Document` accordingly. It is also
+//! available as the `email` filter preset, with default options, via
+//! [`Document::filter_preset`].
+
+use crate::dom::{
+    css::{self, Declaration, Item, Rule},
+    filter::Action,
+    html::{a, t},
+    Document, LocalName, NodeData, NodeId, NodeRef,
+};
+
+/// Default elements detached by [`EmailPrep`], as unsupported by common
+/// e-mail clients, or unsafe.
+pub const UNSUPPORTED_TAGS: &[&str] = &[
+    "applet", "audio", "base", "button", "canvas", "embed", "form",
+    "frame", "frameset", "iframe", "input", "link", "noscript", "object",
+    "script", "select", "svg", "textarea", "video",
+];
+
+/// Transforms for outbound e-mail HTML.
+///
+/// [`EmailPrep::apply`] performs, in order:
+///
+/// 1. Inlining of the rules of `<style>` elements into the `style`
+///    attributes of matching elements, in order of selector specificity and
+///    then source order, with existing `style` attribute declarations
+///    taking precedence, except over `!important` rule declarations. Only
+///    rules with compound selectors, as supported by
+///    [`NodeRef::matches`], are inlined. At-rules such as `@media`, and
+///    rules with other selectors, are retained in their `<style>` element;
+///    otherwise the element is removed.
+///
+/// 2. Detaching [`UNSUPPORTED_TAGS`].
+///
+/// 3. Resolving relative URLs in `href`, `src` and `background` attributes
+///    against any configured base URL.
+///
+/// 4. Adding `width` (and `height`) attributes to `<img>` elements from
+///    pixel values of any inline `width` and `height` style, and scaling
+///    images wider than any configured maximum width.
+#[derive(Clone, Debug)]
+pub struct EmailPrep {
+    base_url: Option<String>,
+    max_image_width: Option<u32>,
+    unsupported: Vec<LocalName>,
+}
+
+/// Builder for an [`EmailPrep`].
+#[derive(Clone, Debug)]
+pub struct EmailPrepBuilder {
+    ep: EmailPrep,
+}
+
+impl EmailPrep {
+    /// Return a new builder, starting with the default configuration.
+    pub fn builder() -> EmailPrepBuilder {
+        EmailPrepBuilder { ep: EmailPrep::default() }
+    }
+
+    /// Apply all transforms to the `Document`, then compact it.
+    pub fn apply(&self, doc: &mut Document) {
+        inline_styles(doc);
+        doc.filter_breadth(|p, d| self.filter(p, d));
+        doc.compact();
+    }
+
+    /// The filter function, for use as per [`Document::filter`], for all
+    /// transforms except style inlining. Compatible with depth or
+    /// breadth-first filtering, but more efficiently executed
+    /// breadth-first.
+    pub fn filter(&self, _p: NodeRef<'_>, data: &mut NodeData) -> Action {
+        let elm = match data.as_element_mut() {
+            Some(elm) => elm,
+            None => return Action::Continue,
+        };
+        if self.unsupported.contains(&elm.name.local) {
+            return Action::Detach;
+        }
+        if let Some(base) = &self.base_url {
+            for attr in &mut elm.attrs {
+                let name = &attr.name.local;
+                if *name == a::HREF || *name == a::SRC ||
+                    *name == local_name!("background")
+                {
+                    if let Some(url) = resolve_url(base, &attr.value) {
+                        attr.value = url.into();
+                    }
+                }
+            }
+        }
+        if elm.is_elem(t::IMG) {
+            let style = elm.attr(a::STYLE)
+                .map(|s| css::parse_declarations(s))
+                .unwrap_or_default();
+            for dim in &[a::WIDTH, a::HEIGHT] {
+                if elm.attr(dim.clone()).is_none() {
+                    if let Some(px) = style_px(&style, dim) {
+                        elm.set_attr(dim.clone(), px.to_string());
+                    }
+                }
+            }
+            if let Some(max) = self.max_image_width {
+                let w = elm.attr(a::WIDTH).and_then(|w| parse_px(w));
+                if let Some(w) = w.filter(|&w| w > max) {
+                    let h = elm.attr(a::HEIGHT).and_then(|h| parse_px(h));
+                    elm.set_attr(a::WIDTH, max.to_string());
+                    if let Some(h) = h {
+                        let h = (u64::from(h) * u64::from(max)
+                                 / u64::from(w)) as u32;
+                        elm.set_attr(a::HEIGHT, h.to_string());
+                    }
+                }
+            }
+        }
+        Action::Continue
+    }
+}
+
+impl Default for EmailPrep {
+    fn default() -> EmailPrep {
+        EmailPrep {
+            base_url: None,
+            max_image_width: Some(600),
+            unsupported: UNSUPPORTED_TAGS.iter()
+                .map(|&n| LocalName::from(n))
+                .collect(),
+        }
+    }
+}
+
+impl EmailPrepBuilder {
+    /// Set an absolute base URL against which relative URLs are resolved.
+    /// Default: None, relative URLs are left as is.
+    pub fn base_url<S>(mut self, url: S) -> Self
+        where S: Into<String>
+    {
+        self.ep.base_url = Some(url.into());
+        self
+    }
+
+    /// Set a maximum image width in pixels, to which wider images, with a
+    /// known width, are scaled. Default: 600.
+    pub fn max_image_width(mut self, width: Option<u32>) -> Self {
+        self.ep.max_image_width = width;
+        self
+    }
+
+    /// Also detach the given element.
+    pub fn unsupported<LN>(mut self, tag: LN) -> Self
+        where LN: Into<LocalName>
+    {
+        let tag = tag.into();
+        if !self.ep.unsupported.contains(&tag) {
+            self.ep.unsupported.push(tag);
+        }
+        self
+    }
+
+    /// Return the configured `EmailPrep`.
+    pub fn build(self) -> EmailPrep {
+        self.ep
+    }
+}
+
+fn inline_styles(doc: &mut Document) {
+    let mut rules = Vec::new();
+    let mut retained: Vec<(NodeId, String)> = Vec::new();
+
+    let styles: Vec<NodeId> = doc.document_node_ref()
+        .descendants()
+        .filter(|n| n.is_elem(t::STYLE) && css::is_screen(*n))
+        .map(|n| n.id())
+        .collect();
+    for id in styles {
+        let css_text = NodeRef::new(doc, id).text().unwrap_or_default();
+        let mut keep = Vec::new();
+        for item in css::parse_stylesheet(&css_text) {
+            match item {
+                Item::Rule(rule) => {
+                    let (inline, other): (Vec<&str>, Vec<&str>) = rule
+                        .selectors
+                        .split(',')
+                        .map(str::trim)
+                        .partition(|s| css::is_compound(s));
+                    if !other.is_empty() {
+                        keep.push(Rule {
+                            selectors: other.join(", "),
+                            declarations: rule.declarations.clone(),
+                        }.to_string());
+                    }
+                    if !inline.is_empty() {
+                        rules.push(Rule {
+                            selectors: inline.join(", "),
+                            declarations: rule.declarations,
+                        });
+                    }
+                }
+                Item::AtRule(text) => keep.push(text),
+            }
+        }
+        retained.push((id, keep.join("\n")));
+    }
+
+    let styled: Vec<(NodeId, String)> = doc.document_node_ref()
+        .descendants()
+        .filter(|n| rules.iter().any(|r| r.matches(*n)))
+        .map(|n| (n.id(), css::to_style(&css::cascade(n, &rules))))
+        .collect();
+    for (id, style) in styled {
+        doc.node_mut(id).set_attr(a::STYLE, style);
+    }
+
+    for (id, keep) in retained {
+        if keep.is_empty() {
+            doc.unlink(id);
+        } else {
+            doc.node_mut(id).set_text(keep);
+        }
+    }
+}
+
+// Return the pixel value of the named property, if specified in px (or
+// unitless).
+fn style_px(decls: &[Declaration], name: &str) -> Option<u32> {
+    decls.iter()
+        .rev()
+        .find(|d| d.name == name)
+        .and_then(|d| parse_px(&d.value))
+}
+
+fn parse_px(v: &str) -> Option<u32> {
+    let v = v.trim();
+    let px = v.len() > 2 &&
+        v.as_bytes()[v.len()-2..].eq_ignore_ascii_case(b"px");
+    let v = if px {
+        &v[..v.len()-2]
+    } else {
+        v
+    };
+    v.trim().parse().ok()
+}
+
+/// Resolve the URL against the absolute base URL, returning None if it is
+/// already absolute, or empty, or a fragment only, or if the base is not
+/// absolute.
+pub(crate) fn resolve_url(base: &str, url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with('#') || scheme(url).is_some() {
+        return None;
+    }
+    let base = base.trim();
+    let scheme = scheme(base)?;
+    let after = &base[scheme.len()+1..];
+    let (authority, path) = if after.starts_with("//") {
+        let a = &after[2..];
+        let end = a.find(|c| c == '/' || c == '?' || c == '#')
+            .unwrap_or_else(|| a.len());
+        (&a[..end], &a[end..])
+    } else {
+        ("", after)
+    };
+    let path = &path[..path.find(|c| c == '?' || c == '#')
+                      .unwrap_or_else(|| path.len())];
+    let origin = if authority.is_empty() && !after.starts_with("//") {
+        format!("{}:", scheme)
+    } else {
+        format!("{}://{}", scheme, authority)
+    };
+
+    if url.starts_with("//") {
+        return Some(format!("{}:{}", scheme, url));
+    }
+    if url.starts_with('?') {
+        return Some(format!("{}{}{}", origin, path, url));
+    }
+    let joined = if url.starts_with('/') {
+        url.to_owned()
+    } else {
+        let dir = match path.rfind('/') {
+            Some(i) => &path[..=i],
+            None => "/",
+        };
+        format!("{}{}", dir, url)
+    };
+    let split = joined.find(|c| c == '?' || c == '#')
+        .unwrap_or_else(|| joined.len());
+    let (jpath, suffix) = joined.split_at(split);
+    Some(format!("{}{}{}", origin, remove_dot_segments(jpath), suffix))
+}
+
+// Return the URL scheme, if the URL is absolute.
+fn scheme(url: &str) -> Option<&str> {
+    let end = url.find(':')?;
+    let s = &url[..end];
+    let mut chars = s.chars();
+    if chars.next().map_or(false, |c| c.is_ascii_alphabetic()) &&
+        chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    {
+        Some(s)
+    } else {
+        None
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    let segs: Vec<&str> = path.split('/').collect();
+    for (i, seg) in segs.iter().enumerate() {
+        let last = i == segs.len() - 1;
+        match *seg {
+            "." => {
+                if last {
+                    out.push("");
+                }
+            }
+            ".." => {
+                if out.len() > 1 {
+                    out.pop();
+                }
+                if last {
+                    out.push("");
+                }
+            }
+            s => out.push(s),
+        }
+    }
+    let p = out.join("/");
+    if p.starts_with('/') { p } else { format!("/{}", p) }
+}
@@ -0,0 +1,113 @@
+//! An owned fragment of markup, with any number of top level (root) nodes.
+
+use std::fmt;
+use std::io;
+use std::io::Write;
+
+use crate::dom::{Document, NodeId, NodeRef, SerializeOptions, Siblings};
+
+/// An owned sequence of sibling sub-trees (roots), without a document node.
+///
+/// Unlike a `Document`, a `Fragment` may contain any number of root nodes,
+/// including text and multiple elements, without a synthetic wrapper
+/// element. Obtain via [`html::parse_fragment_in_context`],
+/// [`NodeRef::to_fragment`] or [`Fragment::from_document`], and insert into
+/// a `Document` via [`Document::append_fragment`].
+///
+/// [`html::parse_fragment_in_context`]: crate::html::parse_fragment_in_context
+#[derive(Default)]
+pub struct Fragment {
+    // Roots are the children of the synthetic document node, which is an
+    // internal detail.
+    doc: Document,
+}
+
+impl Fragment {
+    /// Construct a new empty `Fragment`.
+    pub fn new() -> Fragment {
+        Fragment::default()
+    }
+
+    /// Construct from all top level nodes of a `Document`.
+    pub fn from_document(doc: Document) -> Fragment {
+        Fragment { doc }
+    }
+
+    /// Return a `Document` containing the roots as top level nodes.
+    pub fn into_document(self) -> Document {
+        self.doc
+    }
+
+    /// Return an iterator over the root nodes, in order.
+    pub fn roots(&self) -> Siblings<'_> {
+        self.doc.document_node_ref().children()
+    }
+
+    /// Return the number of root nodes.
+    pub fn len(&self) -> usize {
+        self.roots().count()
+    }
+
+    /// Return true if there are no root nodes.
+    pub fn is_empty(&self) -> bool {
+        self.doc[Document::DOCUMENT_NODE_ID].first_child.is_none()
+    }
+
+    /// Serialize all root nodes and their descendants in HTML syntax to the
+    /// given stream.
+    pub fn serialize<W>(&self, writer: &mut W) -> io::Result<()>
+        where W: Write
+    {
+        self.doc.serialize(writer)
+    }
+
+    /// Serialize all root nodes and their descendants in HTML syntax to the
+    /// given stream, with the given options.
+    pub fn serialize_with<W>(&self, writer: &mut W, opts: &SerializeOptions)
+        -> io::Result<()>
+        where W: Write
+    {
+        self.doc.serialize_with(writer, opts)
+    }
+}
+
+/// Implemented via deep clone of the roots.
+impl Clone for Fragment {
+    fn clone(&self) -> Fragment {
+        Fragment { doc: self.doc.deep_clone(Document::DOCUMENT_NODE_ID) }
+    }
+}
+
+impl fmt::Debug for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fragment").field("doc", &self.doc).finish()
+    }
+}
+
+/// Implemented via [`Fragment::serialize`].
+impl ToString for Fragment {
+    fn to_string(&self) -> String {
+        self.doc.to_string()
+    }
+}
+
+/// Fragment methods.
+impl<'a> NodeRef<'a> {
+    /// Return a new `Fragment` with a clone of this node (and descendants)
+    /// as its single root, or for the document node, clones of all top level
+    /// nodes as roots.
+    pub fn to_fragment(&self) -> Fragment {
+        Fragment { doc: self.deep_clone() }
+    }
+}
+
+/// Fragment methods.
+impl Document {
+    /// Append the root nodes (and descendants) of the given `Fragment` as
+    /// the last children of the given parent node, in order.
+    ///
+    /// The `Fragment` is consumed (its contents moved to self).
+    pub fn append_fragment(&mut self, parent: NodeId, fragment: Fragment) {
+        self.attach_child(parent, fragment.doc);
+    }
+}
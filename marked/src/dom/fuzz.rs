@@ -0,0 +1,246 @@
+//! `Arbitrary` generation of `Document`s (_arbitrary_ feature).
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::dom::{
+    html::{a, ns, t},
+    Attribute, Document, Element, LocalName, Node, NodeData, NodeId,
+    QualName, StrTendril,
+};
+
+/// Maximum element nesting depth, below `<body>`.
+const MAX_DEPTH: u32 = 8;
+
+/// Maximum nodes generated per `Document`.
+const MAX_NODES: u32 = 256;
+
+/// Maximum chars of each generated text node or attribute value.
+const MAX_TEXT: usize = 24;
+
+/// Phrasing (inline) elements, which only contain other phrasing content.
+const PHRASING: &[LocalName] = &[
+    t::SPAN, t::EM, t::STRONG, t::B, t::I, t::CODE, t::A,
+];
+
+/// Flow (block) container elements.
+const FLOW: &[LocalName] = &[
+    t::DIV, t::SECTION, t::BLOCKQUOTE, t::P, t::UL,
+];
+
+/// Attribute names which may be generated on any element.
+const ATTRS: &[LocalName] = &[a::ID, a::CLASS, a::TITLE, a::LANG];
+
+/// Generates a random, structurally valid HTML `Document`, with `<html>`,
+/// `<head>` and `<body>` elements and a random tree of common elements, text
+/// and attributes as body content.
+///
+/// Content is generated to conform to the HTML content model, such that the
+/// serialized output of the `Document` parses back to the same tree. This is
+/// suitable for property tests of filters and serialization (e.g. that
+/// serialize, parse, serialize is stable).
+impl<'a> Arbitrary<'a> for Document {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut gen = Generator { u, budget: MAX_NODES };
+        let mut doc = Document::new();
+        let html = doc.append_child(Document::DOCUMENT_NODE_ID, elem(t::HTML));
+        let head = doc.append_child(html, elem(t::HEAD));
+        if gen.u.arbitrary()? {
+            let title = doc.append_child(head, elem(t::TITLE));
+            if let Some(text) = gen.text()? {
+                doc.append_child(title, Node::new_text(text));
+            }
+        }
+        let body = doc.append_child(html, elem(t::BODY));
+        gen.flow(&mut doc, body, 0)?;
+        Ok(doc)
+    }
+}
+
+struct Generator<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    budget: u32,
+}
+
+impl<'u, 'a> Generator<'u, 'a> {
+    // Return true and decrement budget if another node may be generated.
+    fn take(&mut self) -> bool {
+        if self.budget > 0 {
+            self.budget -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Append flow content children to parent.
+    fn flow(&mut self, doc: &mut Document, parent: NodeId, depth: u32)
+        -> Result<()>
+    {
+        let count = self.u.int_in_range(0..=4)?;
+        let mut after_text = false;
+        for _ in 0..count {
+            if !self.take() {
+                break;
+            }
+            match self.u.int_in_range(0..=5)? {
+                0 if !after_text => {
+                    if let Some(text) = self.text()? {
+                        doc.append_child(parent, Node::new_text(text));
+                        after_text = true;
+                        continue;
+                    }
+                }
+                1 | 2 if depth < MAX_DEPTH => {
+                    let name = self.u.choose(FLOW)?.clone();
+                    let id = doc.append_child(parent, self.element(name)?);
+                    if doc[id].is_elem(t::P) {
+                        self.phrasing(doc, id, depth + 1, false)?;
+                    } else if doc[id].is_elem(t::UL) {
+                        self.list(doc, id, depth + 1)?;
+                    } else {
+                        self.flow(doc, id, depth + 1)?;
+                    }
+                }
+                3 => {
+                    doc.append_child(parent, elem(t::HR));
+                }
+                _ if depth < MAX_DEPTH => {
+                    self.phrase(doc, parent, depth, false)?;
+                }
+                _ => continue,
+            }
+            after_text = false;
+        }
+        Ok(())
+    }
+
+    // Append list items to parent.
+    fn list(&mut self, doc: &mut Document, parent: NodeId, depth: u32)
+        -> Result<()>
+    {
+        let count = self.u.int_in_range(0..=3)?;
+        for _ in 0..count {
+            if depth >= MAX_DEPTH || !self.take() {
+                break;
+            }
+            let id = doc.append_child(parent, self.element(t::LI)?);
+            self.flow(doc, id, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    // Append phrasing content children to parent.
+    fn phrasing(
+        &mut self,
+        doc: &mut Document,
+        parent: NodeId,
+        depth: u32,
+        in_link: bool)
+        -> Result<()>
+    {
+        let count = self.u.int_in_range(0..=4)?;
+        let mut after_text = false;
+        for _ in 0..count {
+            if !self.take() {
+                break;
+            }
+            if !after_text && self.u.arbitrary()? {
+                if let Some(text) = self.text()? {
+                    doc.append_child(parent, Node::new_text(text));
+                    after_text = true;
+                    continue;
+                }
+            }
+            if depth < MAX_DEPTH {
+                self.phrase(doc, parent, depth, in_link)?;
+                after_text = false;
+            }
+        }
+        Ok(())
+    }
+
+    // Append a single phrasing element (with content) to parent.
+    fn phrase(
+        &mut self,
+        doc: &mut Document,
+        parent: NodeId,
+        depth: u32,
+        in_link: bool)
+        -> Result<()>
+    {
+        match self.u.int_in_range(0..=7)? {
+            0 => {
+                doc.append_child(parent, elem(t::BR));
+            }
+            1 => {
+                let mut img = self.element(t::IMG)?;
+                if let Some(v) = self.text()? {
+                    img.as_element_mut().unwrap().set_attr(a::ALT, v);
+                }
+                if let Some(v) = self.text()? {
+                    img.as_element_mut().unwrap().set_attr(a::SRC, v);
+                }
+                doc.append_child(parent, img);
+            }
+            _ => {
+                let mut name = self.u.choose(PHRASING)?.clone();
+                if in_link && name == t::A {
+                    name = t::SPAN;
+                }
+                let is_link = name == t::A;
+                let mut node = self.element(name)?;
+                if is_link {
+                    if let Some(v) = self.text()? {
+                        node.as_element_mut().unwrap().set_attr(a::HREF, v);
+                    }
+                }
+                let id = doc.append_child(parent, node);
+                self.phrasing(doc, id, depth + 1, in_link || is_link)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Return a new element node with random attributes.
+    fn element(&mut self, lname: LocalName) -> Result<Node> {
+        let mut elm = Element {
+            name: QualName::new(None, ns::HTML, lname),
+            attrs: Vec::new(),
+            _priv: (),
+        };
+        for name in ATTRS {
+            if self.u.ratio(1, 4)? {
+                let value = self.text()?.unwrap_or_default();
+                elm.attrs.push(Attribute {
+                    name: QualName::new(None, ns!(), name.clone()),
+                    value,
+                });
+            }
+        }
+        Ok(Node::new(NodeData::Elem(elm)))
+    }
+
+    // Return some non-empty text, normalized as the parser would, or None.
+    fn text(&mut self) -> Result<Option<StrTendril>> {
+        let raw: &str = self.u.arbitrary()?;
+        let text: String = raw
+            .chars()
+            .filter(|&c| c != '\0')
+            .map(|c| if c == '\r' { '\n' } else { c })
+            .take(MAX_TEXT)
+            .collect();
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(text.into()))
+        }
+    }
+}
+
+fn elem(lname: LocalName) -> Node {
+    Node::new(NodeData::Elem(Element {
+        name: QualName::new(None, ns::HTML, lname),
+        attrs: Vec::new(),
+        _priv: (),
+    }))
+}
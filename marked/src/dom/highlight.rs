@@ -0,0 +1,164 @@
+//! Search and highlight of text matches.
+
+use crate::dom::{
+    html::t, Document, Element, LocalName, Node, NodeData, NodeId,
+    StrTendril,
+};
+
+/// A text pattern for [`Document::highlight`].
+///
+/// This is implemented for `str` (and `String`) for exact, case-sensitive
+/// matching, and, with the _regex_ feature, for `regex::Regex`.
+pub trait Matcher {
+    /// Return the byte range `(start, end)` of the first match in text, at or
+    /// after the given start byte offset.
+    fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)>;
+}
+
+impl Matcher for str {
+    fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        text[start..]
+            .find(self)
+            .map(|i| (start + i, start + i + self.len()))
+    }
+}
+
+impl Matcher for String {
+    fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        self.as_str().find_at(text, start)
+    }
+}
+
+impl<'a, M> Matcher for &'a M
+    where M: Matcher + ?Sized
+{
+    fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        (**self).find_at(text, start)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Matcher for regex::Regex {
+    fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        regex::Regex::find_at(self, text, start).map(|m| (m.start(), m.end()))
+    }
+}
+
+/// Search and highlight methods.
+impl Document {
+    /// Find all text matches of matcher and wrap each in a new element of
+    /// the given local name (e.g. `<mark>`), splitting text nodes as needed,
+    /// and return the number of matches.
+    ///
+    /// Matches may span adjacent (sibling) text nodes, but not element
+    /// boundaries. Empty matches are ignored. Text content of elements which
+    /// can't contain markup (e.g. `<script>`, `<title>`), or of existing
+    /// elements of the same local name, is not searched.
+    ///
+    /// Matched text nodes are replaced with new nodes, leaving the originals
+    /// as unreachable nodes until [`Document::compact`].
+    pub fn highlight<M, LN>(&mut self, matcher: &M, wrap_tag: LN) -> usize
+        where M: Matcher + ?Sized, LN: Into<LocalName>
+    {
+        let wrap_tag = wrap_tag.into();
+        let parents: Vec<NodeId> = self.nodes()
+            .filter(|&id| match &self[id].data {
+                NodeData::Elem(e) => !is_opaque(e) && e.name.local != wrap_tag,
+                NodeData::Document => true,
+                _ => false,
+            })
+            .collect();
+
+        let mut count = 0;
+        let mut run = Vec::new();
+        for parent in parents {
+            let children: Vec<NodeId> = self.children(parent).collect();
+            for id in children {
+                if self[id].as_text().is_some() {
+                    run.push(id);
+                } else {
+                    count += self.highlight_run(&run, matcher, &wrap_tag);
+                    run.clear();
+                }
+            }
+            count += self.highlight_run(&run, matcher, &wrap_tag);
+            run.clear();
+        }
+        count
+    }
+
+    // Highlight matches in a run of adjacent text nodes.
+    fn highlight_run<M>(
+        &mut self,
+        run: &[NodeId],
+        matcher: &M,
+        wrap: &LocalName)
+        -> usize
+        where M: Matcher + ?Sized
+    {
+        let first = match run.first() {
+            Some(&id) => id,
+            None => return 0,
+        };
+        let mut text = String::new();
+        for &id in run {
+            text.push_str(self[id].as_text().unwrap());
+        }
+
+        let mut matches = Vec::new();
+        let mut pos = 0;
+        while pos < text.len() {
+            match matcher.find_at(&text, pos) {
+                Some((start, end)) if end > start => {
+                    matches.push((start, end));
+                    pos = end;
+                }
+                Some((start, _)) => {
+                    // Skip an empty match
+                    pos = start + text[start..]
+                        .chars()
+                        .next()
+                        .map_or(1, char::len_utf8);
+                }
+                None => break,
+            }
+        }
+        if matches.is_empty() {
+            return 0;
+        }
+
+        let mut last = 0;
+        for &(start, end) in &matches {
+            if start > last {
+                self.insert_before_sibling(
+                    first,
+                    Node::new_text(StrTendril::from_slice(&text[last..start])));
+            }
+            let mark = self.insert_before_sibling(
+                first,
+                Node::new_elem(Element::new(wrap.clone())));
+            self.append_child(
+                mark,
+                Node::new_text(StrTendril::from_slice(&text[start..end])));
+            last = end;
+        }
+        if last < text.len() {
+            self.insert_before_sibling(
+                first,
+                Node::new_text(StrTendril::from_slice(&text[last..])));
+        }
+        for &id in run {
+            self.unlink(id);
+        }
+        matches.len()
+    }
+}
+
+// Return true for elements whose text content can't contain markup.
+fn is_opaque(elm: &Element) -> bool {
+    let name = &elm.name.local;
+    *name == t::SCRIPT || *name == t::STYLE || *name == t::TITLE ||
+        *name == t::TEXTAREA || *name == t::XMP || *name == t::IFRAME ||
+        *name == t::NOFRAMES || *name == t::NOSCRIPT ||
+        *name == t::PLAINTEXT || *name == local_name!("noembed")
+}
@@ -10,9 +10,11 @@
 //! Support for html5 parsing to `Document`.
 
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::default::Default;
 use std::io;
+use std::rc::Rc;
 
 use encoding_rs as enc;
 
@@ -23,38 +25,171 @@ use html5ever::{
 use html5ever::interface::tree_builder::{
     ElementFlags, NodeOrText, QuirksMode, TreeSink
 };
+use html5ever::tokenizer::TokenizerOpts;
+use html5ever::tree_builder::TreeBuilderOpts;
 use html5ever::tendril::{StrTendril, TendrilSink};
 use log::{debug, info, trace};
 use tendril::{fmt as form, Tendril};
 
 use crate::{
     Attribute, Decoder, Document, DocumentType, Element, EncodingHint,
-    Node, NodeData, NodeId, ProcessingInstruction, SharedEncodingHint,
+    Fragment, LocalName, Node, NodeData, NodeId, ProcessingInstruction,
+    SharedEncodingHint,
     BOM_CONF, HTML_META_CONF, INITIAL_BUFFER_SIZE,
 };
 
+#[cfg(feature = "tokio")]
+mod async_read;
+mod custom;
+mod error;
+mod events;
+mod filtered;
+mod limits;
 mod meta;
+mod omit;
+mod span;
 
+#[cfg(feature = "tokio")]
+pub use self::async_read::parse_async;
+pub use self::custom::{register_custom_element, tag_meta, CustomElement};
+pub use self::error::{ParseError, ParseErrorKind};
+pub use self::events::{parse_events, Event};
+pub use self::filtered::parse_utf8_filtered;
+pub use self::limits::{LimitExceeded, ParseOptions};
+pub use self::omit::NodeKinds;
+pub use self::span::SourceSpan;
 pub use self::meta::{
     a, ns, t,
     TagMeta, TAG_META
 };
 
+/// Estimated input bytes per `Node` of a typical HTML document, used to size
+/// the initial `Document` capacity when the input length is known.
+const BYTES_PER_NODE: usize = 48;
+
+/// Limit of `Node`s for initial `Document` capacity estimates.
+const MAX_CAPACITY_GUESS: usize = 1024 * 1024;
+
+/// Limit of distinct element names with separate stand-in nodes beyond the
+/// `ParseOptions::max_depth` limit.
+const MAX_DEEP_NAMES: usize = 32;
+
 /// Parse HTML document from UTF-8 bytes in RAM.
 pub fn parse_utf8(bytes: &[u8]) -> Document {
-    let sink = Sink::default();
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()));
+    parse_document(sink, Default::default())
+        .from_utf8()
+        .one(bytes)
+}
+
+/// Parse HTML document from UTF-8 bytes in RAM, recording the
+/// [`SourceSpan`] of each element and text node.
+///
+/// This is useful for reporting the source location of issues found in the
+/// `Document`, via [`Document::source_span`], at some additional cost in
+/// parse time and memory.
+pub fn parse_utf8_with_spans(bytes: &[u8]) -> Document {
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()))
+        .spans(true);
     parse_document(sink, Default::default())
         .from_utf8()
         .one(bytes)
 }
 
+/// Parse HTML document from UTF-8 bytes in RAM, also returning all parse
+/// errors found.
+///
+/// The `Document` is the same as that returned by [`parse_utf8`]. The errors
+/// are in order of line number.
+pub fn parse_utf8_checked(bytes: &[u8]) -> (Document, Vec<ParseError>) {
+    let mut errors = Vec::new();
+    error::utf8_errors(bytes, &mut errors);
+
+    let shared = Rc::new(RefCell::new(errors));
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()))
+        .collect_errors(shared.clone());
+
+    let opts = ParseOpts {
+        tokenizer: TokenizerOpts {
+            exact_errors: true,
+            ..Default::default()
+        },
+        tree_builder: TreeBuilderOpts {
+            exact_errors: true,
+            ..Default::default()
+        },
+    };
+    let doc = parse_document(sink, opts)
+        .from_utf8()
+        .one(bytes);
+
+    let mut errors = shared.replace(Vec::new());
+    errors.sort_by_key(ParseError::line);
+    (doc, errors)
+}
+
+/// Parse HTML document from UTF-8 bytes in RAM, with the given
+/// [`ParseOptions`] limits and omitted node kinds.
+///
+/// If any limit is exceeded, the returned `Document` is truncated (and
+/// compacted), and the first `LimitExceeded` is also returned. This is
+/// intended for untrusted input, such as crawler output, where otherwise a
+/// pathological document (e.g. deeply nested `<div>` elements) could consume
+/// unbounded memory or time. If any nodes were omitted, as per
+/// `ParseOptions::omit`, the returned `Document` is also compacted.
+pub fn parse_utf8_with_options(bytes: &[u8], options: &ParseOptions)
+    -> (Document, Option<LimitExceeded>)
+{
+    // Input is fed in chunks, to allow stopping early once max_nodes is
+    // reached.
+    const CHUNK_SIZE: usize = 4 * 1024;
+
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()))
+        .limits(*options);
+
+    let mut parser = parse_document(sink, Default::default());
+    let input = String::from_utf8_lossy(bytes);
+    let mut rest: &str = &input;
+    while !rest.is_empty() && !parser.tokenizer.sink.sink.is_full() {
+        let mut end = CHUNK_SIZE.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        parser.process(StrTendril::from_slice(&rest[..end]));
+        rest = &rest[end..];
+    }
+    let exceeded = parser.tokenizer.sink.sink.exceeded();
+    let omitted = parser.tokenizer.sink.sink.has_stand_ins();
+    let mut doc = parser.finish();
+    if exceeded.is_some() || omitted {
+        doc.compact();
+    }
+    (doc, exceeded)
+}
+
 /// Parse an HTML fragement from UTF-8 bytes in RAM.
 ///
 /// A single root element is guaranteed. If the provided fragment does not
 /// contain a single, block level (e.g. not [`TagMeta::is_inline`]) element, a
-/// root `<div>` element is included as parent.
+/// root `<div>` element is included as parent. See also
+/// [`parse_fragment_in_context`], which doesn't add such a wrapper.
 pub fn parse_utf8_fragment(bytes: &[u8]) -> Document {
-    let sink = Sink::default();
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()));
 
     let mut doc = parse_fragment(
         sink,
@@ -94,6 +229,40 @@ pub fn parse_utf8_fragment(bytes: &[u8]) -> Document {
     doc
 }
 
+/// Parse an HTML fragment from UTF-8 bytes in RAM, as if it were the content
+/// of the given context element (e.g. `t::TD` or `t::BODY`), returning a
+/// [`Fragment`] of any number of top level nodes.
+///
+/// Unlike [`parse_utf8_fragment`], no wrapper element is added, so an empty
+/// input results in an empty `Fragment`, and the parse is in the context of
+/// the given element (e.g. allowing `<tr>` for a `t::TABLE` context).
+pub fn parse_fragment_in_context<LN>(context: LN, bytes: &[u8]) -> Fragment
+    where LN: Into<LocalName>
+{
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()));
+
+    let mut doc = parse_fragment(
+        sink,
+        Default::default(),
+        QualName::new(None, ns::HTML, context.into()),
+        vec![])
+        .from_utf8()
+        .one(bytes);
+
+    // The parse results in an "html" root element containing the fragment
+    // nodes, which are moved to top level by folding.
+    let root_id = doc.root_element().expect("a root");
+    debug_assert!(doc[root_id].is_elem(t::HTML));
+    doc.fold(root_id);
+
+    // Free the unlinked context element and folded root
+    doc.compact();
+    Fragment::from_document(doc)
+}
+
 /// Parse and return an HTML `Document`, reading from the given stream of bytes
 /// until end, processing incrementally.
 ///
@@ -107,56 +276,14 @@ pub fn parse_buffered<R>(hint: SharedEncodingHint, r: &mut R)
     -> Result<Document, io::Error>
     where R: io::Read
 {
-    let enc = hint.borrow().top().expect("EnodingHint default encoding required");
-
-    let parser_sink: Parser<Sink> = parse_document(
-        Sink::new(hint.clone(), true),
-        ParseOpts::default()
-    );
-
-    // Decoders are "Sink adaptors" that also impl TendrilSink.
-    // The decoder is consumed to finish the parse.
-    let mut decoder = Some(Decoder::new(enc, parser_sink));
+    let mut bp = BufferedParse::new(hint.clone());
 
     // Read up to _SIZE bytes, processing as we go and finishing if end is
     // reached in that size.
-    let mut buff = Tendril::<form::Bytes>::new();
-    unsafe {
-        buff.push_uninitialized(INITIAL_BUFFER_SIZE);
-    }
-    let mut i = 0;
-    let mut finished = None;
     loop {
-        match r.read(&mut buff[i as usize..]) {
-            Ok(0) => {
-                trace!("read 0 bytes (end len {})", i);
-                finished = Some(decoder.take().unwrap().finish());
-                break;
-            }
+        match r.read(bp.unfilled()) {
             Ok(n) => {
-                let n = n as u32;
-                trace!("read {} bytes (len {})", n, i + n);
-
-                // One time, leading Byte-order-mark (BOM) detection for UTF-16
-                // little/big endian, or UTF-8, after reading initial 3 bytes.
-                // This is part of the `decode` algorithm of the Encoding
-                // Standard which is not implemented by either encoding_rs or
-                // html5ever. html5ever will ignore a BOM character so we need
-                // not remove it before processing.  If the new hint is
-                // compelling, then break early to reprocess with a new
-                // decoder.
-                if i < 3 && (i + n) >= 3 {
-                    if let Some(enc) = bom_enc(&buff) {
-                        if hint.borrow_mut().add_hint(enc, BOM_CONF) {
-                            i += n;
-                            break;
-                        }
-                    }
-                }
-
-                decoder.as_mut().unwrap().process(buff.subtendril(i, n));
-                i += n;
-                if i == INITIAL_BUFFER_SIZE || hint.borrow().changed().is_some() {
+                if bp.filled(n) {
                     break;
                 }
             }
@@ -165,43 +292,171 @@ pub fn parse_buffered<R>(hint: SharedEncodingHint, r: &mut R)
         }
     } // repeat on interrupt or short read.
 
-    // Avoid any uninitialized trailing bytes
-    buff.pop_back(INITIAL_BUFFER_SIZE - i);
-
-    let (changed, errors) = {
-        let hint = hint.borrow();
-        trace!("revised hint: {:?}", hint);
-        (hint.changed(), hint.errors())
+    // If (still) finished, return that Document, else read and process to end.
+    let res = match bp.resume() {
+        Resume::Done(d) => Ok(d),
+        Resume::Continue(decoder) => decoder.read_to_end(r),
     };
+    if res.is_ok() {
+        debug!("Final encoding errors {}", hint.borrow().errors());
+    }
+    res
+}
 
-    if let Some(enc) = changed {
-        info!(
-            "Reparsing with enc {}, buffered: {}, prior enc errors: {}",
-            enc.name(), buff.len(), errors
-        );
-        hint.borrow_mut().clear_errors();
-        finished = None;
+// The state of the initial, buffered phase of `parse_buffered` (and
+// `parse_async`), independent of how bytes are read.
+struct BufferedParse {
+    hint: SharedEncodingHint,
+    decoder: Option<Decoder<Parser<Sink>>>,
+    buff: Tendril<form::Bytes>,
+    len: u32,
+    finished: Option<Document>,
+}
+
+// Outcome of the buffered phase.
+enum Resume {
+    Done(Document),
+    Continue(Decoder<Parser<Sink>>),
+}
 
-        // Replace decoder and re-process, consuming the original tendril
-        // buffer, which was previously cloned.
-        let parser_sink = parse_document(
-            Sink::new(hint.clone(), false),
+impl BufferedParse {
+    fn new(hint: SharedEncodingHint) -> BufferedParse {
+        let enc = hint.borrow().top()
+            .expect("EnodingHint default encoding required");
+
+        let parser_sink: Parser<Sink> = parse_document(
+            Sink::with_capacity(
+                hint.clone(),
+                true,
+                capacity_guess(INITIAL_BUFFER_SIZE as usize)),
             ParseOpts::default()
         );
-        decoder = Some(Decoder::new(enc, parser_sink));
-        decoder.as_mut().unwrap().process(buff);
+
+        // Decoders are "Sink adaptors" that also impl TendrilSink.
+        // The decoder is consumed to finish the parse.
+        let decoder = Some(Decoder::new(enc, parser_sink));
+
+        let mut buff = Tendril::<form::Bytes>::new();
+        unsafe {
+            buff.push_uninitialized(INITIAL_BUFFER_SIZE);
+        }
+        BufferedParse { hint, decoder, buff, len: 0, finished: None }
     }
 
-    // If (still) finished, return that Document, else read and process to end.
-    let res = if let Some(d) = finished {
-        Ok(d)
-    } else {
-        decoder.take().unwrap().read_to_end(r)
-    };
-    if res.is_ok() {
-        debug!("Final encoding errors {}", hint.borrow().errors());
+    // Return the unfilled remainder of the buffer, to read into.
+    fn unfilled(&mut self) -> &mut [u8] {
+        &mut self.buff[self.len as usize..]
+    }
+
+    // Process n bytes read into the `unfilled` buffer, where 0 indicates the
+    // end of the stream. Return true if buffering is complete.
+    fn filled(&mut self, n: usize) -> bool {
+        let i = self.len;
+        if n == 0 {
+            trace!("read 0 bytes (end len {})", i);
+            self.finished = Some(self.decoder.take().unwrap().finish());
+            return true;
+        }
+        let n = n as u32;
+        trace!("read {} bytes (len {})", n, i + n);
+
+        // One time, leading Byte-order-mark (BOM) detection for UTF-16
+        // little/big endian, or UTF-8, after reading initial 3 bytes.
+        // This is part of the `decode` algorithm of the Encoding
+        // Standard which is not implemented by either encoding_rs or
+        // html5ever. html5ever will ignore a BOM character so we need
+        // not remove it before processing.  If the new hint is
+        // compelling, then break early to reprocess with a new
+        // decoder.
+        if i < 3 && (i + n) >= 3 {
+            if let Some(enc) = bom_enc(&self.buff) {
+                if self.hint.borrow_mut().add_hint(enc, BOM_CONF) {
+                    self.len += n;
+                    return true;
+                }
+            }
+        }
+
+        self.decoder.as_mut().unwrap().process(self.buff.subtendril(i, n));
+        self.len += n;
+        self.len == INITIAL_BUFFER_SIZE ||
+            self.hint.borrow().changed().is_some()
+    }
+
+    // Complete the buffered phase, restarting the parse with the buffered
+    // bytes if the encoding hint has changed.
+    fn resume(self) -> Resume {
+        let BufferedParse { hint, mut decoder, mut buff, len, mut finished } =
+            self;
+
+        // Avoid any uninitialized trailing bytes
+        buff.pop_back(INITIAL_BUFFER_SIZE - len);
+
+        let (changed, errors) = {
+            let hint = hint.borrow();
+            trace!("revised hint: {:?}", hint);
+            (hint.changed(), hint.errors())
+        };
+
+        if let Some(enc) = changed {
+            info!(
+                "Reparsing with enc {}, buffered: {}, prior enc errors: {}",
+                enc.name(), buff.len(), errors
+            );
+            hint.borrow_mut().clear_errors();
+            finished = None;
+
+            // Replace decoder and re-process, consuming the original tendril
+            // buffer, which was previously cloned.
+            let parser_sink = parse_document(
+                Sink::with_capacity(
+                    hint.clone(),
+                    false,
+                    capacity_guess(INITIAL_BUFFER_SIZE as usize)),
+                ParseOpts::default()
+            );
+            decoder = Some(Decoder::new(enc, parser_sink));
+            decoder.as_mut().unwrap().process(buff);
+        }
+
+        match finished {
+            Some(d) => Resume::Done(d),
+            None => Resume::Continue(decoder.unwrap()),
+        }
     }
-    res
+}
+
+/// Parse and return an HTML `Document` from the file at the given path, via a
+/// read-only memory map of the file (_mmap_ feature).
+///
+/// Encoding detection is as per [`parse_buffered`]. This avoids reading the
+/// file into a heap buffer, which reduces peak memory when batch processing
+/// large files (e.g. crawl dumps), relying instead on the OS page cache. Note
+/// however that the `StrTendril` text of the `Document` can not borrow from
+/// the mapping, and is thus still copied (decoded) from it.
+///
+/// The file must not be modified or truncated, by this or any other
+/// process, while being parsed. Doing so may produce garbled output, or on
+/// some platforms, terminate the process via a bus error.
+#[cfg(feature = "mmap")]
+pub fn parse_mmap<P>(hint: SharedEncodingHint, path: P)
+    -> Result<Document, io::Error>
+    where P: AsRef<std::path::Path>
+{
+    let file = std::fs::File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        // Zero length maps are an error on some platforms
+        return parse_buffered(hint, &mut io::empty());
+    }
+    // Safety: see above regarding concurrent modification of the file
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    parse_buffered(hint, &mut &map[..])
+}
+
+// Return an initial `Document` capacity guess for the given input length.
+fn capacity_guess(len: usize) -> u32 {
+    let guess = (len / BYTES_PER_NODE + 8).min(MAX_CAPACITY_GUESS);
+    guess as u32
 }
 
 // Return encoding for any Byte-Order-Mark found at start of buff.
@@ -217,12 +472,52 @@ fn bom_enc(buff: &Tendril::<form::Bytes>) -> Option<&'static enc::Encoding>
 
 /// A `TreeSink` implementation for parsing html to a
 /// [`Document`](crate::Document) tree.
+///
+/// This is the sink used by all parse functions of this module. It may also
+/// be used directly with html5ever, for example to parse with custom
+/// `ParseOpts`, such as with scripting disabled (such that `<noscript>`
+/// content is parsed as markup):
+///
+/// ```no_run
+/// use html5ever::{parse_document, ParseOpts};
+/// use html5ever::tendril::TendrilSink;
+/// use html5ever::tree_builder::TreeBuilderOpts;
+///
+/// let opts = ParseOpts {
+///     tree_builder: TreeBuilderOpts {
+///         scripting_enabled: false,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let doc = parse_document(Sink::default(), opts)
+///     .from_utf8()
+///     .one(bytes);
+/// ```
+///
+/// Similarly, `html5ever::parse_fragment` may be used with a custom context
+/// element. See also the `Sink` configuration methods, which enable optional
+/// features of the parse functions.
+///
+/// With the _tracing_ feature, each `Sink` creates a `parse` span on
+/// construction, recording its `nodes` count on finish, such that the span
+/// lifetime approximates the time of the parse.
 pub struct Sink {
     document: Document,
     #[allow(unused)]
     quirks_mode: QuirksMode,
     enc_hint: SharedEncodingHint,
     enc_check: bool,
+    errors: Option<Rc<RefCell<Vec<ParseError>>>>,
+    line: u64,
+    spans: bool,
+    limits: ParseOptions,
+    exceeded: Option<LimitExceeded>,
+    stand_ins: [Option<NodeId>; 4],
+    depth: u32,
+    deep: Vec<(QualName, NodeId)>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl Sink {
@@ -231,19 +526,216 @@ impl Sink {
     /// If enc_check is true, encodings mentioned in html meta elements will be
     /// added to the encoding hint as soon as possible in the parse.
     pub fn new(enc_hint: SharedEncodingHint, enc_check: bool) -> Sink {
+        Sink::with_capacity(enc_hint, enc_check, 8)
+    }
 
+    /// Construct new sink with shared `EncodingHint` and an initial
+    /// `Document` capacity in `Node`s.
+    ///
+    /// See [`Sink::new`] and [`Document::with_capacity`].
+    pub fn with_capacity(
+        enc_hint: SharedEncodingHint,
+        enc_check: bool,
+        count: u32)
+        -> Sink
+    {
         Sink {
-            document: Document::new(),
+            document: Document::with_capacity(count),
             quirks_mode: QuirksMode::NoQuirks,
             enc_hint,
             enc_check,
+            errors: None,
+            line: 1,
+            spans: false,
+            limits: ParseOptions::default(),
+            exceeded: None,
+            stand_ins: [None; 4],
+            depth: 0,
+            deep: Vec::new(),
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "parse",
+                capacity = count,
+                nodes = tracing::field::Empty),
         }
     }
 
+    /// If true, record the [`SourceSpan`] of each element and text node, as
+    /// per [`parse_utf8_with_spans`]. Default: false.
+    pub fn spans(mut self, spans: bool) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Collect parse errors into the given shared vector, as per
+    /// [`parse_utf8_checked`].
+    ///
+    /// The vector is shared because the sink is consumed by the html5ever
+    /// parser. Note that for error messages with more detail, `exact_errors`
+    /// should also be enabled via html5ever's `ParseOpts`, and that encoding
+    /// errors are not collected here (but are counted via the
+    /// `EncodingHint`).
+    pub fn collect_errors(mut self, errors: Rc<RefCell<Vec<ParseError>>>)
+        -> Self
+    {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Apply the given limits and omissions while parsing, as per
+    /// [`parse_utf8_with_options`]. Note that when driving the parser
+    /// directly, the caller is responsible for stopping input once the
+    /// `max_nodes` limit is reached, and for any `Document::compact`,
+    /// including to remove the detached node retained for each kind of node
+    /// omitted.
+    pub fn limits(mut self, limits: ParseOptions) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Return the first limit exceeded so far, if any. See [`Sink::limits`].
+    pub fn exceeded(&self) -> Option<LimitExceeded> {
+        self.exceeded
+    }
+
     fn new_node(&mut self, data: NodeData) -> NodeId {
         self.document.push_node(Node::new(data))
     }
 
+    // Return a node standing in for all nodes of an omitted kind. This is
+    // allocated once, never attached, and any content is dropped.
+    fn stand_in<F>(&mut self, kind: NodeKinds, data: F) -> NodeId
+        where F: FnOnce() -> NodeData
+    {
+        let i = kind.index();
+        if let Some(id) = self.stand_ins[i] {
+            return id;
+        }
+        let id = self.new_node(data());
+        self.stand_ins[i] = Some(id);
+        id
+    }
+
+    // Return a node standing in for all elements of the given name beyond
+    // the max_depth limit, as for omitted kinds. Past a small number of
+    // distinct names, an existing stand-in is shared.
+    fn deep_stand_in(&mut self, name: QualName) -> NodeId {
+        if let Some((_, id)) = self.deep.iter().find(|(n, _)| *n == name) {
+            return *id;
+        }
+        if self.deep.len() >= MAX_DEEP_NAMES {
+            return self.deep[0].1;
+        }
+        let id = self.new_node(NodeData::Elem(
+            Element { name: name.clone(), attrs: Vec::new(), _priv: () }
+        ));
+        self.deep.push((name, id));
+        id
+    }
+
+    fn has_stand_ins(&self) -> bool {
+        self.stand_ins.iter().any(Option::is_some) || !self.deep.is_empty()
+    }
+
+    fn is_stand_in(&self, id: NodeId) -> bool {
+        self.stand_ins.contains(&Some(id)) ||
+            self.deep.iter().any(|(_, d)| *d == id)
+    }
+
+    // Return the depth of the node, where children of the document node are
+    // at depth 1, if it is attached to the document within max + 1 levels.
+    fn depth_of(&self, id: NodeId, max: u32) -> Option<u32> {
+        let mut depth = 0;
+        for a in self.document.node_and_ancestors(id).take(max as usize + 2) {
+            if a == Document::DOCUMENT_NODE_ID {
+                return Some(depth);
+            }
+            depth += 1;
+        }
+        None
+    }
+
+    // Update the estimated depth of the current open element, with the
+    // max_depth limit, as child is appended to parent. Any element other
+    // than a void element is assumed to become the current element, as
+    // is the case for newly created elements. This estimate is corrected
+    // on each `pop`.
+    fn track_depth(
+        &mut self,
+        parent: Option<NodeId>,
+        child: &NodeOrText<NodeId>)
+    {
+        let max = match self.limits.max_depth {
+            Some(max) => max,
+            None => return,
+        };
+        let elm = match *child {
+            NodeOrText::AppendNode(id) => match self.document[id].as_element() {
+                Some(elm) => elm,
+                None => return,
+            },
+            NodeOrText::AppendText(_) => return,
+        };
+        let void = elm.name.ns == ns::HTML &&
+            TAG_META.get(&elm.name.local).map_or(false, |m| m.is_empty());
+        let parent_depth = parent
+            .filter(|&p| !self.is_stand_in(p))
+            .and_then(|p| self.depth_of(p, max))
+            .unwrap_or(self.depth);
+        self.depth = if void { parent_depth } else { parent_depth + 1 };
+    }
+
+    // Return true if child is omitted, or node is, such that any children
+    // or siblings appended to it are dropped.
+    fn is_omitted(&self, node: NodeId, child: &NodeOrText<NodeId>)
+        -> bool
+    {
+        if let NodeOrText::AppendNode(id) = *child {
+            if self.is_stand_in(id) {
+                return true;
+            }
+        }
+        self.is_stand_in(node)
+    }
+
+    fn limit_exceeded(&mut self, limit: LimitExceeded) {
+        if self.exceeded.is_none() {
+            debug!("{}", limit);
+            self.exceeded = Some(limit);
+        }
+    }
+
+    // Return true if the max_nodes limit has been reached.
+    fn is_full(&self) -> bool {
+        self.limits.max_nodes.map_or(false, |max| self.document.len() >= max)
+    }
+
+    // Return true if a new child node may be attached to parent, per limits.
+    fn admit_child(&mut self, parent: Option<NodeId>) -> bool {
+        if self.is_full() {
+            self.limit_exceeded(LimitExceeded::Nodes);
+            return false;
+        }
+        if let (Some(max), Some(parent)) = (self.limits.max_depth, parent) {
+            let depth = self.document
+                .node_and_ancestors(parent)
+                .take(max as usize + 1)
+                .count();
+            if depth > max as usize {
+                self.limit_exceeded(LimitExceeded::Depth);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn truncate_text(&mut self, text: &mut StrTendril, max: u32) {
+        if text.len32() > max {
+            *text = StrTendril::from_slice(limits::truncated(text, max));
+            self.limit_exceeded(LimitExceeded::TextLen);
+        }
+    }
+
     fn append_common<P, A>(
         &mut self,
         child: NodeOrText<NodeId>,
@@ -253,16 +745,35 @@ impl Sink {
               A: FnOnce(&mut Document, NodeId)
     {
         let new_node = match child {
-            NodeOrText::AppendText(text) => {
+            NodeOrText::AppendText(mut text) => {
                 // Append to an existing Text node if we have one.
                 if let Some(id) = previous(&mut self.document) {
                     let node = &mut self.document[id];
                     if let NodeData::Text(t) = &mut node.data {
+                        if let Some(max) = self.limits.max_text_len {
+                            let room = max.saturating_sub(t.len32());
+                            let room = limits::truncated(&text, room);
+                            if room.len() < text.len() {
+                                t.push_slice(room);
+                                self.limit_exceeded(LimitExceeded::TextLen);
+                                return;
+                            }
+                        }
                         t.push_tendril(&text);
+                        if self.spans {
+                            self.document.extend_span(id, self.line);
+                        }
                         return;
                     }
                 }
-                self.new_node(NodeData::Text(text))
+                if let Some(max) = self.limits.max_text_len {
+                    self.truncate_text(&mut text, max);
+                }
+                let id = self.new_node(NodeData::Text(text));
+                if self.spans {
+                    self.document.start_span(id, self.line);
+                }
+                id
             }
             NodeOrText::AppendNode(node) => {
                 if self.enc_check && self.document[node].is_elem(t::BODY) {
@@ -336,19 +847,29 @@ impl TreeSink for Sink {
     type Output = Document;
 
     fn finish(self) -> Document {
+        #[cfg(feature = "tracing")]
+        self.span.record("nodes", &self.document.len());
         self.document
     }
 
     fn parse_error(&mut self, err: Cow<'static, str>) {
         // Not the nicest error type to work with.
         if err == "invalid byte sequence" {
-            // From tendril crate (src/stream.rs) or our Decoder
+            // From tendril crate (src/stream.rs) or our Decoder. When
+            // checked, these are found (with offsets) before the parse.
             self.enc_hint.borrow_mut().increment_error();
         } else {
             debug!("other parser error: {}", err);
+            if let Some(ref errors) = self.errors {
+                errors.borrow_mut().push(ParseError::new(err, self.line));
+            }
         }
     }
 
+    fn set_current_line(&mut self, line_number: u64) {
+        self.line = line_number;
+    }
+
     fn get_document(&mut self) -> NodeId {
         Document::DOCUMENT_NODE_ID
     }
@@ -376,24 +897,82 @@ impl TreeSink for Sink {
     fn create_element(
         &mut self,
         name: QualName,
-        attrs: Vec<Attribute>,
+        mut attrs: Vec<Attribute>,
         _flags: ElementFlags)
         -> NodeId
     {
-        self.new_node(NodeData::Elem(Element { name, attrs, _priv: () }))
+        if let Some(max) = self.limits.max_depth {
+            // The depth of this element if appended to the current element
+            // exceeds max + 1. Elements at max + 1 are allocated, but then
+            // not admitted, which allows for error in the depth estimate.
+            if self.depth > max {
+                self.limit_exceeded(LimitExceeded::Depth);
+                return self.deep_stand_in(name);
+            }
+        }
+        if let Some(kind) = NodeKinds::of_element(&name) {
+            if self.limits.omit.contains(kind) {
+                return self.stand_in(kind, || NodeData::Elem(
+                    Element { name, attrs: Vec::new(), _priv: () }
+                ));
+            }
+        }
+        if let Some(max) = self.limits.max_attr_len {
+            for attr in &mut attrs {
+                if attr.value.len32() > max {
+                    attr.value = StrTendril::from_slice(
+                        limits::truncated(&attr.value, max));
+                    self.limit_exceeded(LimitExceeded::AttrLen);
+                }
+            }
+        }
+        let id = self.new_node(
+            NodeData::Elem(Element { name, attrs, _priv: () }));
+        if self.spans {
+            self.document.start_span(id, self.line);
+        }
+        id
+    }
+
+    fn pop(&mut self, &node: &NodeId) {
+        if self.spans {
+            self.document.extend_span(node, self.line);
+        }
+        if let Some(max) = self.limits.max_depth {
+            self.depth = match self.depth_of(node, max) {
+                Some(depth) => depth.saturating_sub(1),
+                None => self.depth.saturating_sub(1),
+            };
+        }
     }
 
     fn create_comment(&mut self, text: StrTendril) -> NodeId {
+        if self.limits.omit.contains(NodeKinds::COMMENT) {
+            return self.stand_in(NodeKinds::COMMENT, || {
+                NodeData::Comment(StrTendril::new())
+            });
+        }
         self.new_node(NodeData::Comment(text))
     }
 
     fn create_pi(&mut self, _target: StrTendril, data: StrTendril)
         -> NodeId
     {
+        if self.limits.omit.contains(NodeKinds::PI) {
+            return self.stand_in(NodeKinds::PI, || NodeData::Pi(
+                ProcessingInstruction { data: StrTendril::new(), _priv: () }
+            ));
+        }
         self.new_node(NodeData::Pi(ProcessingInstruction { data, _priv: () }))
     }
 
     fn append(&mut self, &parent: &NodeId, child: NodeOrText<NodeId>) {
+        self.track_depth(Some(parent), &child);
+        if self.is_omitted(parent, &child) ||
+            !self.admit_child(Some(parent))
+        {
+            return;
+        }
         self.append_common(
             child,
             |document| document[parent].last_child,
@@ -406,6 +985,12 @@ impl TreeSink for Sink {
         &sibling: &NodeId,
         child: NodeOrText<NodeId>)
     {
+        self.track_depth(self.document[sibling].parent, &child);
+        if self.is_omitted(sibling, &child) ||
+            !self.admit_child(self.document[sibling].parent)
+        {
+            return;
+        }
         self.append_common(
             child,
             |document| document[sibling].prev_sibling,
@@ -467,6 +1052,9 @@ impl TreeSink for Sink {
     }
 
     fn remove_from_parent(&mut self, &target: &NodeId) {
+        if self.is_stand_in(target) {
+            return;
+        }
         self.document.unlink_only(target)
     }
 
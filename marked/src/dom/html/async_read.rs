@@ -0,0 +1,69 @@
+//! Parsing from an asynchronous byte stream (_tokio_ feature).
+
+use std::io;
+
+use log::debug;
+use tendril::{fmt as form, Tendril, TendrilSink};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{Document, SharedEncodingHint, READ_BUFFER_SIZE};
+use super::{BufferedParse, Resume};
+
+/// Parse and return an HTML `Document`, reading asynchronously from the given
+/// stream of bytes until end, processing incrementally (_tokio_ feature).
+///
+/// Encoding detection is as per [`parse_buffered`](super::parse_buffered).
+/// Each chunk of bytes is decoded and fed to the parser as it is read, so
+/// there is no need to first collect the body into a `Vec`, or to run the
+/// parse as a blocking task.
+///
+/// Note that the returned future is not `Send`, as the `SharedEncodingHint`
+/// and parser state are not. In a multi-threaded runtime, run it on a
+/// `tokio::task::LocalSet` (e.g. via `spawn_local`).
+pub async fn parse_async<R>(hint: SharedEncodingHint, r: &mut R)
+    -> Result<Document, io::Error>
+    where R: AsyncRead + Unpin + ?Sized
+{
+    let mut bp = BufferedParse::new(hint.clone());
+    loop {
+        match r.read(bp.unfilled()).await {
+            Ok(n) => {
+                if bp.filled(n) {
+                    break;
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e)
+        }
+    }
+
+    let mut decoder = match bp.resume() {
+        Resume::Done(d) => return Ok(finish(&hint, d)),
+        Resume::Continue(decoder) => decoder,
+    };
+
+    // As per `Decoder::read_to_end`
+    loop {
+        let mut tendril = Tendril::<form::Bytes>::new();
+        unsafe {
+            tendril.push_uninitialized(READ_BUFFER_SIZE);
+        }
+        loop {
+            match r.read(&mut tendril).await {
+                Ok(0) => return Ok(finish(&hint, decoder.finish())),
+                Ok(n) => {
+                    tendril.pop_back(READ_BUFFER_SIZE - n as u32);
+                    decoder.process(tendril);
+                    break;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e)
+            }
+        } // repeat on interrupt
+    } // repeat until EOF (0) or Err
+}
+
+fn finish(hint: &SharedEncodingHint, doc: Document) -> Document {
+    debug!("Final encoding errors {}", hint.borrow().errors());
+    doc
+}
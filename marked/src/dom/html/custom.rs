@@ -0,0 +1,103 @@
+//! Runtime registration of custom element metadata.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use crate::dom::LocalName;
+use super::meta::{TagMeta, TAG_META};
+
+lazy_static! {
+    static ref CUSTOM_META: RwLock<HashMap<LocalName, &'static TagMeta>> =
+        RwLock::new(HashMap::new());
+
+    // The distinct metadata of custom elements, indexed by
+    // `CustomElement::index`, shared by all registrations.
+    static ref CUSTOM_KINDS: [TagMeta; 4] = [
+        TagMeta::custom(true, false),
+        TagMeta::custom(true, true),
+        TagMeta::custom(false, false),
+        TagMeta::custom(false, true),
+    ];
+}
+
+// Set once any custom element is registered, so that lookups of unknown
+// elements can otherwise avoid the lock.
+static REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Options for a custom element registered via [`register_custom_element`].
+///
+/// The default is an inline, non-banned element, consistent with the default
+/// display of unknown elements in browsers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CustomElement {
+    block: bool,
+    banned: bool,
+}
+
+impl CustomElement {
+    /// Construct new default options.
+    pub fn new() -> CustomElement {
+        CustomElement::default()
+    }
+
+    /// If true, treat the element as a block, rather than inline element, as
+    /// per [`TagMeta::is_block`]. Default: false.
+    pub fn block(mut self, block: bool) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// If true, treat the element as banned, as per [`TagMeta::is_banned`],
+    /// such that it is removed by the `detach_banned_elements` filter.
+    /// Default: false, such that the element is kept.
+    pub fn banned(mut self, banned: bool) -> Self {
+        self.banned = banned;
+        self
+    }
+
+    fn index(self) -> usize {
+        (self.block as usize) * 2 + (self.banned as usize)
+    }
+}
+
+/// Register metadata for a custom element, e.g. `my-widget`, returning the
+/// interned `LocalName` for use as a constant in comparisons.
+///
+/// Once registered, the element is no longer treated as unknown by
+/// [`tag_meta`] and the included filters. Registration is global to the
+/// process. Registering the same name again replaces its metadata, while
+/// names of standard HTML elements are never overridden.
+pub fn register_custom_element<LN>(name: LN, opts: CustomElement)
+    -> LocalName
+    where LN: Into<LocalName>
+{
+    let name = name.into();
+    if !TAG_META.contains_key(&name) {
+        let meta: &'static TagMeta = &CUSTOM_KINDS[opts.index()];
+        CUSTOM_META
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.clone(), meta);
+        REGISTERED.store(true, Ordering::Release);
+    }
+    name
+}
+
+/// Return metadata for the given local name of a standard HTML element
+/// (via [`TAG_META`]), or otherwise of any registered custom element.
+pub fn tag_meta(lname: &LocalName) -> Option<&'static TagMeta> {
+    if let Some(meta) = TAG_META.get(lname) {
+        return Some(meta);
+    }
+    if !REGISTERED.load(Ordering::Acquire) {
+        return None;
+    }
+    CUSTOM_META
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(lname)
+        .cloned()
+}
@@ -0,0 +1,124 @@
+//! Typed HTML parse errors.
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+
+/// The general kind of a [`ParseError`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// An invalid byte sequence for the encoding, replaced in the
+    /// `Document` with U+FFFD REPLACEMENT CHARACTER.
+    Encoding,
+
+    /// An invalid or unterminated character reference (entity).
+    CharacterReference,
+
+    /// A misnested, misplaced or unmatched tag (e.g. a stray end tag),
+    /// corrected by the tree builder.
+    Nesting,
+
+    /// Any other syntax error found while tokenizing.
+    Syntax,
+}
+
+/// An HTML parse error, as returned by [`parse_utf8_checked`].
+///
+/// HTML parsing always recovers from errors, and these are reported only for
+/// purposes such as validation or linting. The `kind` is inferred from the
+/// underlying html5ever parser's message, which is also available.
+///
+/// [`parse_utf8_checked`]: crate::html::parse_utf8_checked
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    message: Cow<'static, str>,
+    line: u64,
+    offset: Option<usize>,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: Cow<'static, str>, line: u64) -> ParseError {
+        ParseError { kind: classify(&message), message, line, offset: None }
+    }
+
+    pub(crate) fn encoding(line: u64, offset: usize) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::Encoding,
+            message: Cow::Borrowed("invalid byte sequence"),
+            line,
+            offset: Some(offset),
+        }
+    }
+
+    /// Return the kind of error.
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Return the parser's error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Return the 1-based line number in the input where the error was
+    /// found.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Return the byte offset in the input of the error, if known. This is
+    /// currently only available for `ParseErrorKind::Encoding` errors.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, "line {} (byte {}): {}", self.line, offset, self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl StdError for ParseError {}
+
+fn classify(message: &str) -> ParseErrorKind {
+    if message == "invalid byte sequence" {
+        ParseErrorKind::Encoding
+    } else if message.contains("character reference") {
+        ParseErrorKind::CharacterReference
+    } else if message.starts_with("Unexpected token") ||
+        message.contains("tag") ||
+        message.contains("element")
+    {
+        ParseErrorKind::Nesting
+    } else {
+        ParseErrorKind::Syntax
+    }
+}
+
+/// Push `Encoding` errors for all invalid UTF-8 sequences in bytes.
+pub(crate) fn utf8_errors(bytes: &[u8], errors: &mut Vec<ParseError>) {
+    let mut pos = 0;
+    let mut line = 1;
+    let mut line_pos = 0;
+    while pos < bytes.len() {
+        match std::str::from_utf8(&bytes[pos..]) {
+            Ok(_) => break,
+            Err(e) => {
+                let offset = pos + e.valid_up_to();
+                line += bytes[line_pos..offset]
+                    .iter()
+                    .filter(|&&b| b == b'\n')
+                    .count() as u64;
+                line_pos = offset;
+                errors.push(ParseError::encoding(line, offset));
+                pos = offset + e.error_len().unwrap_or(bytes.len() - offset);
+            }
+        }
+    }
+}
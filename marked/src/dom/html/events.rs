@@ -0,0 +1,123 @@
+//! Streaming, tokenizer-only (SAX-style) HTML event parsing.
+
+use log::trace;
+
+use html5ever::tokenizer::{
+    BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer,
+    TokenizerOpts,
+};
+use html5ever::tokenizer::states::RawKind;
+
+use crate::dom::{html::t, Attribute, LocalName, StrTendril};
+
+/// A markup event, as produced by [`parse_events`].
+#[derive(Copy, Clone, Debug)]
+pub enum Event<'a> {
+    /// A document type declaration, with any name.
+    Doctype { name: Option<&'a StrTendril> },
+
+    /// An element start tag with attributes.
+    StartTag {
+        name: &'a LocalName,
+        attrs: &'a [Attribute],
+        self_closing: bool,
+    },
+
+    /// An element end tag.
+    EndTag { name: &'a LocalName },
+
+    /// Character data. Contiguous text may be split across multiple events.
+    Text(&'a StrTendril),
+
+    /// A comment.
+    Comment(&'a StrTendril),
+}
+
+/// Parse HTML from UTF-8 bytes in RAM, calling the provided function for each
+/// markup [`Event`], without constructing a `Document` tree.
+///
+/// This is much cheaper than a full parse, for cases where only a flat view
+/// of tags is needed, e.g. finding all links. However, unlike the full
+/// (tree-building) parse, no implied elements are produced, end tags need not
+/// be balanced, and misnested markup is not corrected. The raw text content of
+/// elements such as `<script>`, `<style>`, `<title>` and `<textarea>` is
+/// properly tokenized as text. Any invalid UTF-8 sequences are replaced with
+/// U+FFFD REPLACEMENT CHARACTER.
+pub fn parse_events<F>(bytes: &[u8], f: F)
+    where F: FnMut(Event<'_>)
+{
+    let mut input = BufferQueue::new();
+    input.push_back(StrTendril::from_slice(&String::from_utf8_lossy(bytes)));
+    let mut tokenizer = Tokenizer::new(
+        EventSink { f },
+        TokenizerOpts::default());
+    let _ = tokenizer.feed(&mut input);
+    tokenizer.end();
+}
+
+struct EventSink<F> {
+    f: F,
+}
+
+impl<F> TokenSink for EventSink<F>
+    where F: FnMut(Event<'_>)
+{
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line: u64)
+        -> TokenSinkResult<()>
+    {
+        match token {
+            Token::TagToken(tag) => match tag.kind {
+                TagKind::StartTag => {
+                    (self.f)(Event::StartTag {
+                        name: &tag.name,
+                        attrs: &tag.attrs,
+                        self_closing: tag.self_closing,
+                    });
+                    return next_state(&tag.name);
+                }
+                TagKind::EndTag => {
+                    (self.f)(Event::EndTag { name: &tag.name });
+                }
+            },
+            Token::CharacterTokens(text) => {
+                (self.f)(Event::Text(&text));
+            }
+            Token::CommentToken(text) => {
+                (self.f)(Event::Comment(&text));
+            }
+            Token::DoctypeToken(dt) => {
+                (self.f)(Event::Doctype { name: dt.name.as_ref() });
+            }
+            Token::ParseError(e) => {
+                trace!("parse error: {}", e);
+            }
+            Token::NullCharacterToken | Token::EOFToken => {}
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+// Return the tokenizer state for content following the start tag of the given
+// name. This is normally done by the tree builder.
+fn next_state(name: &LocalName) -> TokenSinkResult<()> {
+    if *name == t::SCRIPT {
+        TokenSinkResult::RawData(RawKind::ScriptData)
+    } else if *name == t::TITLE || *name == t::TEXTAREA {
+        TokenSinkResult::RawData(RawKind::Rcdata)
+    } else if
+        *name == t::STYLE ||
+        *name == t::XMP ||
+        *name == t::IFRAME ||
+        *name == t::NOFRAMES ||
+        *name == t::NOSCRIPT ||
+        *name == local_name!("noembed")
+    {
+        TokenSinkResult::RawData(RawKind::Rawtext)
+    } else if *name == t::PLAINTEXT {
+        TokenSinkResult::Plaintext
+    } else {
+        TokenSinkResult::Continue
+    }
+}
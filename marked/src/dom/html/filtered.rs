@@ -0,0 +1,269 @@
+//! Parsing with filters applied during tree construction.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use encoding_rs as enc;
+use html5ever::{parse_document, ExpandedName, QualName};
+use html5ever::interface::tree_builder::{
+    ElementFlags, NodeOrText, QuirksMode, TreeSink
+};
+use html5ever::tendril::{StrTendril, TendrilSink};
+
+use crate::{Attribute, Document, EncodingHint, NodeData, NodeId, NodeRef};
+use crate::filter::Action;
+use super::{capacity_guess, Sink};
+
+/// Parse HTML document from UTF-8 bytes in RAM, applying the given filter
+/// function (or chain of functions) to each node as it is appended to the
+/// tree.
+///
+/// The result is typically equivalent to that of [`parse_utf8`] followed by
+/// [`Document::filter_breadth`], but any text or other content of detached
+/// elements (e.g. `<script>` or `<style>` elements via
+/// [`detach_banned_elements`]) is never stored.
+///
+/// Nodes are filtered in document order, when first appended and before any
+/// of their children are parsed, so only filters compatible with a
+/// breadth-first walk should be used. Text nodes are appended incrementally
+/// and are not filtered, nor is the document node. For filters of text, apply
+/// these afterward via [`Document::filter`]. If any nodes are detached or
+/// folded, the returned `Document` is compacted.
+///
+/// [`parse_utf8`]: crate::html::parse_utf8
+/// [`detach_banned_elements`]: crate::filter::detach_banned_elements
+pub fn parse_utf8_filtered<F>(bytes: &[u8], f: F) -> Document
+    where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+{
+    let sink = Sink::with_capacity(
+        EncodingHint::shared_default(enc::UTF_8),
+        false,
+        capacity_guess(bytes.len()));
+    parse_document(FilterSink::new(sink, f), Default::default())
+        .from_utf8()
+        .one(bytes)
+}
+
+// A TreeSink wrapping `Sink`, which applies a filter function to nodes as
+// appended.
+struct FilterSink<F> {
+    sink: Sink,
+    filter: F,
+    // Detached nodes, whose children are dropped.
+    detached: HashSet<NodeId>,
+    // Folded nodes, with the parent to which their children are redirected.
+    folded: HashMap<NodeId, NodeId>,
+}
+
+impl<F> FilterSink<F>
+    where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+{
+    fn new(sink: Sink, filter: F) -> Self {
+        FilterSink {
+            sink,
+            filter,
+            detached: HashSet::new(),
+            folded: HashMap::new(),
+        }
+    }
+
+    // Return the effective parent for children of the given node, or None if
+    // these should be dropped.
+    fn resolve(&self, mut id: NodeId) -> Option<NodeId> {
+        while let Some(&parent) = self.folded.get(&id) {
+            id = parent;
+        }
+        if self.detached.contains(&id) {
+            None
+        } else {
+            Some(id)
+        }
+    }
+
+    fn drop_child(&mut self, child: NodeOrText<NodeId>) {
+        if let NodeOrText::AppendNode(id) = child {
+            self.detached.insert(id);
+        }
+    }
+
+    // Apply the filter to the newly appended node, if linked.
+    fn apply(&mut self, id: NodeId) {
+        let doc = &mut self.sink.document;
+        let parent = match doc[id].parent {
+            Some(p) => p,
+            None => return,
+        };
+
+        // Unlike with Document::filter, the node data is always restored,
+        // as the parser may still reference a removed element by name.
+        let mut data = doc[id].take_data();
+        let res = (self.filter)(NodeRef::new(doc, id), &mut data);
+        doc[id].data = data;
+
+        match res {
+            Action::Continue => {}
+            Action::Detach => {
+                doc.unlink_only(id);
+                self.detached.insert(id);
+            }
+            Action::Fold => {
+                doc.unlink_only(id);
+                self.folded.insert(id, parent);
+            }
+        }
+    }
+}
+
+impl<F> TreeSink for FilterSink<F>
+    where F: Fn(NodeRef<'_>, &mut NodeData) -> Action
+{
+    type Handle = NodeId;
+    type Output = Document;
+
+    fn finish(self) -> Document {
+        let removed = !self.detached.is_empty() || !self.folded.is_empty();
+        let mut doc = self.sink.finish();
+        if removed {
+            doc.compact();
+        }
+        doc
+    }
+
+    fn parse_error(&mut self, err: Cow<'static, str>) {
+        self.sink.parse_error(err)
+    }
+
+    fn set_current_line(&mut self, line_number: u64) {
+        self.sink.set_current_line(line_number)
+    }
+
+    fn get_document(&mut self) -> NodeId {
+        self.sink.get_document()
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.sink.set_quirks_mode(mode)
+    }
+
+    fn same_node(&self, x: &NodeId, y: &NodeId) -> bool {
+        self.sink.same_node(x, y)
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a NodeId) -> ExpandedName<'a> {
+        self.sink.elem_name(target)
+    }
+
+    fn get_template_contents(&mut self, target: &NodeId) -> NodeId {
+        self.sink.get_template_contents(target)
+    }
+
+    fn create_element(
+        &mut self,
+        name: QualName,
+        attrs: Vec<Attribute>,
+        flags: ElementFlags)
+        -> NodeId
+    {
+        self.sink.create_element(name, attrs, flags)
+    }
+
+    fn pop(&mut self, node: &NodeId) {
+        self.sink.pop(node)
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> NodeId {
+        self.sink.create_comment(text)
+    }
+
+    fn create_pi(&mut self, target: StrTendril, data: StrTendril)
+        -> NodeId
+    {
+        self.sink.create_pi(target, data)
+    }
+
+    fn append(&mut self, &parent: &NodeId, child: NodeOrText<NodeId>) {
+        let parent = match self.resolve(parent) {
+            Some(p) => p,
+            None => return self.drop_child(child),
+        };
+        let node = match child {
+            NodeOrText::AppendNode(id) => Some(id),
+            NodeOrText::AppendText(_) => None,
+        };
+        self.sink.append(&parent, child);
+        if let Some(id) = node {
+            self.apply(id);
+        }
+    }
+
+    fn append_before_sibling(
+        &mut self,
+        &sibling: &NodeId,
+        child: NodeOrText<NodeId>)
+    {
+        // A removed sibling is no longer linked
+        if self.sink.document[sibling].parent.is_none() {
+            return self.drop_child(child);
+        }
+        let node = match child {
+            NodeOrText::AppendNode(id) => Some(id),
+            NodeOrText::AppendText(_) => None,
+        };
+        self.sink.append_before_sibling(&sibling, child);
+        if let Some(id) = node {
+            self.apply(id);
+        }
+    }
+
+    fn append_based_on_parent_node(
+        &mut self,
+        element: &NodeId,
+        prev_element: &NodeId,
+        child: NodeOrText<NodeId>)
+    {
+        if self.sink.document[*element].parent.is_some() {
+            self.append_before_sibling(element, child)
+        } else {
+            self.append(prev_element, child)
+        }
+    }
+
+    fn append_doctype_to_document(
+        &mut self,
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril)
+    {
+        self.sink.append_doctype_to_document(name, public_id, system_id);
+        if let Some(id) = self.sink.document[Document::DOCUMENT_NODE_ID]
+            .last_child
+        {
+            self.apply(id);
+        }
+    }
+
+    fn add_attrs_if_missing(
+        &mut self,
+        target: &NodeId,
+        attrs: Vec<Attribute>)
+    {
+        self.sink.add_attrs_if_missing(target, attrs)
+    }
+
+    fn remove_from_parent(&mut self, target: &NodeId) {
+        self.sink.remove_from_parent(target)
+    }
+
+    fn reparent_children(&mut self, node: &NodeId, new_parent: &NodeId) {
+        match self.resolve(*new_parent) {
+            Some(p) => self.sink.reparent_children(node, &p),
+            None => {
+                let mut next = self.sink.document[*node].first_child;
+                while let Some(child) = next {
+                    next = self.sink.document[child].next_sibling;
+                    self.sink.document.unlink_only(child);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Parse limits for defense against pathological input.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use super::NodeKinds;
+
+/// Limits and omissions applied while parsing, by
+/// [`parse_utf8_with_options`].
+///
+/// All limits default to `None`, for unlimited. Parsing is never aborted
+/// with an error. Instead, when a limit is exceeded, the `Document` is
+/// truncated as described for each field, and the first limit exceeded is
+/// returned.
+///
+/// [`parse_utf8_with_options`]: crate::html::parse_utf8_with_options
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Maximum element nesting depth, where children of the document node
+    /// are at depth 1. Any deeper nodes are dropped, with their content.
+    /// Elements more than one level deeper are not even allocated, so
+    /// the document built from deeply nested input stays proportional in
+    /// size to this limit.
+    pub max_depth: Option<u32>,
+
+    /// Maximum number of nodes, as per `Document::len` of the returned
+    /// `Document`. Once reached, no further nodes are attached, and parsing
+    /// stops at the end of the current (4 KiB) input chunk, with any
+    /// remaining input ignored.
+    ///
+    /// This is only an approximate bound on memory use while parsing:
+    /// elements of the remainder of the chunk are still allocated (though
+    /// not attached), until removed by the final `Document::compact`.
+    pub max_nodes: Option<u32>,
+
+    /// Maximum byte length of any attribute value. Longer values are
+    /// truncated (to a char boundary).
+    pub max_attr_len: Option<u32>,
+
+    /// Maximum byte length of any text node. Longer text is truncated (to a
+    /// char boundary).
+    pub max_text_len: Option<u32>,
+
+    /// Kinds of nodes which are omitted, such that they (and any content)
+    /// are never stored in the `Document`. Omitted nodes don't count
+    /// towards other limits. Default: empty.
+    pub omit: NodeKinds,
+}
+
+impl ParseOptions {
+    /// Set the kinds of nodes to omit, e.g. `NodeKinds::SCRIPT |
+    /// NodeKinds::STYLE | NodeKinds::COMMENT`, for crawlers that never need
+    /// them. This uses substantially less memory than removing such nodes
+    /// after the parse.
+    pub fn omit(mut self, kinds: NodeKinds) -> Self {
+        self.omit = kinds;
+        self
+    }
+}
+
+/// A [`ParseOptions`] limit which was exceeded, resulting in truncation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// `ParseOptions::max_depth`
+    Depth,
+
+    /// `ParseOptions::max_nodes`
+    Nodes,
+
+    /// `ParseOptions::max_attr_len`
+    AttrLen,
+
+    /// `ParseOptions::max_text_len`
+    TextLen,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = match self {
+            LimitExceeded::Depth => "max_depth",
+            LimitExceeded::Nodes => "max_nodes",
+            LimitExceeded::AttrLen => "max_attr_len",
+            LimitExceeded::TextLen => "max_text_len",
+        };
+        write!(f, "parse limit {} exceeded, input truncated", limit)
+    }
+}
+
+impl StdError for LimitExceeded {}
+
+/// Return the longest prefix of s of at most max bytes, ending on a char
+/// boundary.
+pub(crate) fn truncated(s: &str, max: u32) -> &str {
+    let mut end = max as usize;
+    if end >= s.len() {
+        return s;
+    }
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
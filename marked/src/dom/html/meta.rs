@@ -23,6 +23,8 @@ pub struct TagMeta {
     is_inline: bool,
     is_meta: bool,
     is_banned: bool,
+    is_raw_text: bool,
+    parents: Vec<LocalName>,
     basic_attrs: Vec<LocalName>,
 }
 
@@ -35,6 +37,12 @@ impl TagMeta {
         self.is_empty
     }
 
+    /// Return true if the element is a _void_ element, the HTML 5 term for
+    /// an empty element. This is equivalent to `is_empty`.
+    pub fn is_void(&self) -> bool {
+        self.is_empty
+    }
+
     /// Return true if the tag is deprecated as of html5.
     ///
     /// Tags include: `acronym applet basefont big blink center content dir font frame frameset isindex listing menu menuitem nobr noframes plaintext s strike tt u xmp`.
@@ -55,6 +63,13 @@ impl TagMeta {
         self.is_inline
     }
 
+    /// Return true if the tag represents a _block_ element: is neither
+    /// _inline_ nor _meta_. As with `is_inline`, this is a somewhat arbitrary
+    /// distinction, and note that `<br>` is considered a block element.
+    pub fn is_block(&self) -> bool {
+        !self.is_inline && !self.is_meta
+    }
+
     /// Return true if the tag represents metadata only, where any content is
     /// not displayed text. e.g. `<head>`.
     ///
@@ -71,6 +86,32 @@ impl TagMeta {
         self.is_banned
     }
 
+    /// Return true if the tag is a _raw text_ element, where content is not
+    /// parsed or serialized as markup.
+    ///
+    /// Tags include: `iframe noframes noscript plaintext script style xmp`.
+    pub fn is_raw_text(&self) -> bool {
+        self.is_raw_text
+    }
+
+    /// Return the only allowed parent tags, per the HTML content model, or
+    /// None if the element is not restricted to specific parents. For
+    /// example, `<li>` is allowed as a child of `<ol>` or `<ul>` (and the
+    /// deprecated `<dir>` and `<menu>`).
+    pub fn allowed_parents(&self) -> Option<&[LocalName]> {
+        if self.parents.is_empty() {
+            None
+        } else {
+            Some(&self.parents)
+        }
+    }
+
+    /// Return true if the element is allowed as a child of the given parent
+    /// tag. See [`TagMeta::allowed_parents`].
+    pub fn allows_parent(&self, parent: &LocalName) -> bool {
+        self.parents.is_empty() || self.parents.contains(parent)
+    }
+
     /// Return true if the given name is part of the _basic_ set of known
     /// attributes for this element.
     ///
@@ -81,6 +122,14 @@ impl TagMeta {
     }
 }
 
+impl TagMeta {
+    // Return metadata for a custom element, via
+    // `html::register_custom_element`.
+    pub(crate) fn custom(is_inline: bool, is_banned: bool) -> TagMeta {
+        TagMeta { is_inline, is_banned, .. TagMeta::default() }
+    }
+}
+
 impl Default for TagMeta {
     fn default() -> TagMeta {
         TagMeta {
@@ -89,6 +138,8 @@ impl Default for TagMeta {
             is_inline: false,
             is_meta: false,
             is_banned: false,
+            is_raw_text: false,
+            parents: vec![],
             basic_attrs: vec![],
         }
     }
@@ -100,6 +151,8 @@ pub mod ns {
     use crate::dom::Namespace;
 
     pub const HTML:           Namespace = ns!(html);
+    pub const MATHML:         Namespace = ns!(mathml);
+    pub const SVG:            Namespace = ns!(svg);
 }
 
 /// HTML tag constants
@@ -265,7 +318,7 @@ pub mod t {
     /// (meta: inline)
     pub const I:             LocalName = lname!("i");
     /// Tag `<iframe>`: inline frame.
-    /// (meta: inline)
+    /// (meta: inline raw_text)
     pub const IFRAME:        LocalName = lname!("iframe");
     /// Tag `<img>`: image.
     /// (meta: empty inline)
@@ -322,10 +375,10 @@ pub mod t {
     /// (meta: deprecated inline)
     pub const NOBR:          LocalName = lname!("nobr");
     /// Tag `<noframes>`: alternate content where frames not supported.
-    /// (meta: deprecated banned)
+    /// (meta: deprecated banned raw_text)
     pub const NOFRAMES:      LocalName = lname!("noframes");
     /// Tag `<noscript>`: alternate content script not supported.
-    /// (meta: inline banned)
+    /// (meta: inline banned raw_text)
     pub const NOSCRIPT:      LocalName = lname!("noscript");
     /// Tag `<object>`: embedded object.
     /// (meta: inline banned)
@@ -350,7 +403,7 @@ pub mod t {
     /// (meta: inline)
     pub const PICTURE:       LocalName = lname!("picture");
     /// Tag `<plaintext>`: like xmp; no close tag.
-    /// (meta: deprecated)
+    /// (meta: deprecated raw_text)
     pub const PLAINTEXT:     LocalName = lname!("plaintext");
     /// Tag `<pre>`: preformatted text.
     pub const PRE:           LocalName = lname!("pre");
@@ -385,7 +438,7 @@ pub mod t {
     /// (meta: inline)
     pub const SAMP:          LocalName = lname!("samp");
     /// Tag `<script>`: client-side script.
-    /// (meta: inline banned)
+    /// (meta: inline banned raw_text)
     pub const SCRIPT:        LocalName = lname!("script");
     /// Tag `<section>`: Structure: generic document/application section.
     pub const SECTION:       LocalName = lname!("section");
@@ -411,7 +464,7 @@ pub mod t {
     /// (meta: inline)
     pub const STRONG:        LocalName = lname!("strong");
     /// Tag `<style>`: style information for a document.
-    /// (meta: banned)
+    /// (meta: banned raw_text)
     pub const STYLE:         LocalName = lname!("style");
     /// Tag `<sub>`: subscripted text.
     /// (meta: inline)
@@ -467,7 +520,7 @@ pub mod t {
     /// (meta: empty inline)
     pub const WBR:           LocalName = lname!("wbr");
     /// Tag `<xmp>`: preformatted text.
-    /// (meta: deprecated)
+    /// (meta: deprecated raw_text)
     pub const XMP:           LocalName = lname!("xmp");
 }
 
@@ -626,6 +679,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     tag_meta.insert(t::BASE, TagMeta {
         is_empty: true,
         is_meta: true,
+        parents: vec![
+            t::HEAD
+        ],
         basic_attrs: vec![
             a::BASE, a::HREF
         ],
@@ -678,6 +734,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::BODY, TagMeta {
+        parents: vec![
+            t::HTML
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -706,6 +765,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::CAPTION, TagMeta {
+        parents: vec![
+            t::TABLE
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -734,12 +796,18 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::COL, TagMeta {
         is_empty: true,
+        parents: vec![
+            t::COLGROUP
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::SPAN, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::COLGROUP, TagMeta {
+        parents: vec![
+            t::TABLE
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::SPAN, a::TITLE
         ],
@@ -769,6 +837,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::DD, TagMeta {
+        parents: vec![
+            t::DIV, t::DL
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -820,6 +891,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::DT, TagMeta {
+        parents: vec![
+            t::DIV, t::DL
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -848,6 +922,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::FIGCAPTION, TagMeta {
+        parents: vec![
+            t::FIGURE
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -883,6 +960,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         is_empty: true,
         is_deprecated: true,
         is_banned: true,
+        parents: vec![
+            t::FRAMESET
+        ],
         basic_attrs: vec![
             a::BASE, a::SRC, a::TITLE
         ],
@@ -891,6 +971,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     tag_meta.insert(t::FRAMESET, TagMeta {
         is_deprecated: true,
         is_banned: true,
+        parents: vec![
+            t::FRAMESET, t::HTML
+        ],
         basic_attrs: vec![
             a::BASE, a::TITLE
         ],
@@ -934,6 +1017,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::HEAD, TagMeta {
         is_meta: true,
+        parents: vec![
+            t::HTML
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG
         ],
@@ -973,6 +1059,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::IFRAME, TagMeta {
         is_inline: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::ALIGN, a::BASE, a::TITLE
         ],
@@ -1026,12 +1113,18 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::LEGEND, TagMeta {
         is_banned: true,
+        parents: vec![
+            t::FIELDSET
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::LI, TagMeta {
+        parents: vec![
+            t::DIR, t::MENU, t::OL, t::UL
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1082,6 +1175,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     tag_meta.insert(t::MENUITEM, TagMeta {
         is_empty: true,
         is_deprecated: true,
+        parents: vec![
+            t::MENU
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1119,6 +1215,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     tag_meta.insert(t::NOFRAMES, TagMeta {
         is_deprecated: true,
         is_banned: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1127,6 +1224,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     tag_meta.insert(t::NOSCRIPT, TagMeta {
         is_inline: true,
         is_banned: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1148,6 +1246,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::OPTGROUP, TagMeta {
         is_banned: true,
+        parents: vec![
+            t::SELECT
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LABEL, a::LANG, a::TITLE
         ],
@@ -1155,6 +1256,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::OPTION, TagMeta {
         is_banned: true,
+        parents: vec![
+            t::DATALIST, t::OPTGROUP, t::SELECT
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LABEL, a::LANG, a::TITLE
         ],
@@ -1175,6 +1279,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::PARAM, TagMeta {
         is_empty: true,
+        parents: vec![
+            t::OBJECT
+        ],
         basic_attrs: vec![
             a::BASE, a::NAME, a::VALUE
         ],
@@ -1189,6 +1296,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::PLAINTEXT, TagMeta {
         is_deprecated: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1215,6 +1323,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::RB, TagMeta {
+        parents: vec![
+            t::RUBY
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1227,18 +1338,27 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::RP, TagMeta {
+        parents: vec![
+            t::RUBY
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::RT, TagMeta {
+        parents: vec![
+            t::RTC, t::RUBY
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::RTC, TagMeta {
+        parents: vec![
+            t::RUBY
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1269,6 +1389,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     tag_meta.insert(t::SCRIPT, TagMeta {
         is_inline: true,
         is_banned: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG
         ],
@@ -1305,6 +1426,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::SOURCE, TagMeta {
         is_empty: true,
+        parents: vec![
+            t::AUDIO, t::PICTURE, t::VIDEO
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::SRC, a::TITLE, a::TYPE
         ],
@@ -1334,6 +1458,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::STYLE, TagMeta {
         is_banned: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG
         ],
@@ -1347,6 +1472,9 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::SUMMARY, TagMeta {
+        parents: vec![
+            t::DETAILS
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1372,12 +1500,18 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::TBODY, TagMeta {
+        parents: vec![
+            t::TABLE
+        ],
         basic_attrs: vec![
             a::ALIGN, a::BASE, a::DIR, a::LANG, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::TD, TagMeta {
+        parents: vec![
+            t::TR
+        ],
         basic_attrs: vec![
             a::ALIGN, a::BASE, a::COLSPAN, a::DIR, a::HEADERS, a::LANG, a::ROWSPAN, a::SCOPE, a::TITLE
         ],
@@ -1399,18 +1533,27 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
         .. TagMeta::default()
     });
     tag_meta.insert(t::TFOOT, TagMeta {
+        parents: vec![
+            t::TABLE
+        ],
         basic_attrs: vec![
             a::ALIGN, a::BASE, a::DIR, a::LANG, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::TH, TagMeta {
+        parents: vec![
+            t::TR
+        ],
         basic_attrs: vec![
             a::ABBR, a::ALIGN, a::AXIS, a::BASE, a::COLSPAN, a::DIR, a::LANG, a::ROWSPAN, a::SCOPE, a::TITLE
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::THEAD, TagMeta {
+        parents: vec![
+            t::TABLE
+        ],
         basic_attrs: vec![
             a::ALIGN, a::BASE, a::DIR, a::LANG, a::TITLE
         ],
@@ -1425,12 +1568,18 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::TITLE, TagMeta {
         is_meta: true,
+        parents: vec![
+            t::HEAD
+        ],
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG
         ],
         .. TagMeta::default()
     });
     tag_meta.insert(t::TR, TagMeta {
+        parents: vec![
+            t::TABLE, t::TBODY, t::TFOOT, t::THEAD
+        ],
         basic_attrs: vec![
             a::ABBR, a::ALIGN, a::AXIS, a::BASE, a::COLSPAN, a::DIR, a::HEADERS, a::LANG, a::ROWSPAN, a::SCOPE, a::TITLE
         ],
@@ -1482,6 +1631,7 @@ fn init_tag_metadata() -> HashMap<LocalName, TagMeta> {
     });
     tag_meta.insert(t::XMP, TagMeta {
         is_deprecated: true,
+        is_raw_text: true,
         basic_attrs: vec![
             a::BASE, a::DIR, a::LANG, a::TITLE
         ],
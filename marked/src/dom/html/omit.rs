@@ -0,0 +1,109 @@
+//! Kinds of nodes which may be omitted while parsing.
+
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+use html5ever::QualName;
+
+use super::{ns, t};
+
+/// A set of node kinds, for [`ParseOptions::omit`].
+///
+/// Sets are combined with `|`, for example `NodeKinds::SCRIPT |
+/// NodeKinds::STYLE`. The default is the empty set.
+///
+/// [`ParseOptions::omit`]: crate::html::ParseOptions::omit
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct NodeKinds(u8);
+
+impl NodeKinds {
+    /// HTML `<script>` elements, with their content.
+    pub const SCRIPT: NodeKinds = NodeKinds(0x01);
+
+    /// HTML `<style>` elements, with their content.
+    pub const STYLE: NodeKinds = NodeKinds(0x02);
+
+    /// Comment nodes.
+    pub const COMMENT: NodeKinds = NodeKinds(0x04);
+
+    /// Processing instruction nodes.
+    pub const PI: NodeKinds = NodeKinds(0x08);
+
+    const NAMES: [(NodeKinds, &'static str); 4] = [
+        (NodeKinds::SCRIPT, "SCRIPT"),
+        (NodeKinds::STYLE, "STYLE"),
+        (NodeKinds::COMMENT, "COMMENT"),
+        (NodeKinds::PI, "PI"),
+    ];
+
+    /// Return the empty set.
+    pub fn empty() -> NodeKinds {
+        NodeKinds(0)
+    }
+
+    /// Return the set of all kinds.
+    pub fn all() -> NodeKinds {
+        NodeKinds(0x0f)
+    }
+
+    /// Return true if the set is empty.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Return true if all kinds of other are in this set.
+    pub fn contains(self, other: NodeKinds) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Return the kind of an element with the given name, if it is one of
+    /// the element kinds.
+    pub(crate) fn of_element(name: &QualName) -> Option<NodeKinds> {
+        if name.ns != ns::HTML {
+            None
+        } else if name.local == t::SCRIPT {
+            Some(NodeKinds::SCRIPT)
+        } else if name.local == t::STYLE {
+            Some(NodeKinds::STYLE)
+        } else {
+            None
+        }
+    }
+
+    /// Return an index for a single kind, in the range 0..4.
+    pub(crate) fn index(self) -> usize {
+        debug_assert_eq!(1, self.0.count_ones());
+        self.0.trailing_zeros() as usize
+    }
+}
+
+impl BitOr for NodeKinds {
+    type Output = NodeKinds;
+
+    fn bitor(self, other: NodeKinds) -> NodeKinds {
+        NodeKinds(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for NodeKinds {
+    fn bitor_assign(&mut self, other: NodeKinds) {
+        self.0 |= other.0;
+    }
+}
+
+impl fmt::Debug for NodeKinds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        f.write_str("NodeKinds(")?;
+        for &(kind, name) in &NodeKinds::NAMES {
+            if self.contains(kind) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        f.write_str(")")
+    }
+}
@@ -0,0 +1,47 @@
+//! Source line spans of parsed nodes.
+
+use crate::dom::{Document, NodeId};
+
+/// The span of source lines of a parsed element or text node, as recorded by
+/// [`parse_utf8_with_spans`].
+///
+/// Lines are 1-based and inclusive. The underlying html5ever parser reports
+/// only the line at which each token was completed, so the `start_line` of an
+/// element is that of the end of its start tag, and columns and byte offsets
+/// are not available.
+///
+/// [`parse_utf8_with_spans`]: crate::html::parse_utf8_with_spans
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// Line at which the node started.
+    pub start_line: u64,
+
+    /// Line at which the node ended, e.g. by its end tag.
+    pub end_line: u64,
+}
+
+/// Source span methods.
+impl Document {
+    /// Return the source line span of the specified node, if recorded by
+    /// [`parse_utf8_with_spans`].
+    ///
+    /// Spans are stored as typed user data, and are thus retained by
+    /// [`Document::compact`], but not by clones.
+    ///
+    /// [`parse_utf8_with_spans`]: crate::html::parse_utf8_with_spans
+    pub fn source_span(&self, id: NodeId) -> Option<SourceSpan> {
+        self.user_data::<SourceSpan>(id).copied()
+    }
+
+    pub(crate) fn start_span(&mut self, id: NodeId, line: u64) {
+        self.set_user_data(id, SourceSpan { start_line: line, end_line: line });
+    }
+
+    pub(crate) fn extend_span(&mut self, id: NodeId, line: u64) {
+        if let Some(span) = self.user_data_mut::<SourceSpan>(id) {
+            if line > span.end_line {
+                span.end_line = line;
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Conversion to and from the node types of other DOM crates.
+
+use html5ever::interface::tree_builder::{ElementFlags, NodeOrText, TreeSink};
+
+use crate::dom::{
+    html::{ns, t},
+    Document, DocumentType, NodeData, NodeRef, ProcessingInstruction,
+    StrTendril,
+};
+
+#[cfg(any(feature = "kuchiki", feature = "scraper"))]
+use crate::dom::{Attribute, Element, Node, NodeId, QualName};
+
+#[cfg(feature = "scraper")]
+use scraper_rs as scraper;
+
+/// Interop methods.
+impl Document {
+    /// Replay this `Document` into any html5ever `TreeSink`, returning its
+    /// output.
+    ///
+    /// This allows conversion to the tree type of any other crate built on
+    /// html5ever. The sink's document node is used in place of the document
+    /// node of self. Contents of `<template>` elements are appended via
+    /// `TreeSink::get_template_contents`. The public and system identifiers
+    /// of document types are not retained by this crate and are passed as
+    /// empty, as is the target of processing instructions.
+    pub fn to_tree_sink<S>(&self, mut sink: S) -> S::Output
+        where S: TreeSink
+    {
+        let root = sink.get_document();
+        for child in self.children(Document::DOCUMENT_NODE_ID) {
+            replay(&mut sink, &root, NodeRef::new(self, child));
+        }
+        sink.finish()
+    }
+
+    /// Return a new _kuchiki_ document tree converted from self (_kuchiki_
+    /// feature).
+    ///
+    /// As _kuchiki_ stores attributes in a map, the order of attributes is
+    /// not preserved. See also [`Document::to_tree_sink`].
+    #[cfg(feature = "kuchiki")]
+    pub fn to_kuchiki(&self) -> kuchiki::NodeRef {
+        self.to_tree_sink(kuchiki::Sink::default())
+    }
+
+    /// Return a new `Document` converted from the given _kuchiki_ node and
+    /// its descendants (_kuchiki_ feature).
+    ///
+    /// If the node is a document or document fragment, its children are
+    /// placed under the document node of the new `Document`, otherwise the
+    /// node itself is. Contents of `<template>` elements are placed as their
+    /// children.
+    #[cfg(feature = "kuchiki")]
+    pub fn from_kuchiki(node: &kuchiki::NodeRef) -> Document {
+        let mut doc = Document::new();
+        from_kuchiki_node(&mut doc, Document::DOCUMENT_NODE_ID, node);
+        doc
+    }
+
+    /// Return a new _scraper_ `Html` document converted from self (_scraper_
+    /// feature).
+    ///
+    /// As _scraper_ stores attributes in a map, the order of attributes is
+    /// not preserved. See also [`Document::to_tree_sink`].
+    #[cfg(feature = "scraper")]
+    pub fn to_scraper(&self) -> scraper::Html {
+        self.to_tree_sink(scraper::Html::new_document())
+    }
+
+    /// Return a new `Document` converted from the given _scraper_ `Html`
+    /// document or fragment (_scraper_ feature).
+    ///
+    /// Note that _scraper_ only exposes attributes by local name, so any
+    /// attribute namespaces (e.g. `xlink:href`) are not retained.
+    #[cfg(feature = "scraper")]
+    pub fn from_scraper(html: &scraper::Html) -> Document {
+        let mut doc = Document::new();
+        let root = html.tree.root();
+        from_scraper_node(&mut doc, Document::DOCUMENT_NODE_ID, root);
+        doc
+    }
+}
+
+fn replay<S>(sink: &mut S, parent: &S::Handle, node: NodeRef<'_>)
+    where S: TreeSink
+{
+    match &node.data {
+        NodeData::Elem(elm) => {
+            let is_template =
+                elm.name.ns == ns::HTML && elm.is_elem(t::TEMPLATE);
+            let mut flags = ElementFlags::default();
+            flags.template = is_template;
+            let h = sink.create_element(
+                elm.name.clone(), elm.attrs.clone(), flags
+            );
+            sink.append(parent, NodeOrText::AppendNode(h.clone()));
+            let contents = if is_template {
+                sink.get_template_contents(&h)
+            } else {
+                h
+            };
+            for child in node.children() {
+                replay(sink, &contents, child);
+            }
+        }
+        NodeData::Text(t) => {
+            sink.append(parent, NodeOrText::AppendText(t.clone()));
+        }
+        NodeData::Comment(t) => {
+            let h = sink.create_comment(t.clone());
+            sink.append(parent, NodeOrText::AppendNode(h));
+        }
+        NodeData::Pi(ProcessingInstruction { data, .. }) => {
+            let h = sink.create_pi(StrTendril::new(), data.clone());
+            sink.append(parent, NodeOrText::AppendNode(h));
+        }
+        NodeData::DocType(DocumentType { name, .. }) => {
+            sink.append_doctype_to_document(
+                name.clone(), StrTendril::new(), StrTendril::new()
+            );
+        }
+        NodeData::Document | NodeData::Hole => {}
+    }
+}
+
+#[cfg(feature = "kuchiki")]
+fn from_kuchiki_node(
+    doc: &mut Document,
+    parent: NodeId,
+    node: &kuchiki::NodeRef)
+{
+    use kuchiki::NodeData as K;
+
+    let data = match node.data() {
+        K::Document(_) | K::DocumentFragment => {
+            for child in node.children() {
+                from_kuchiki_node(doc, parent, &child);
+            }
+            return;
+        }
+        K::Element(ed) => {
+            let attrs = ed.attributes.borrow().map
+                .iter()
+                .map(|(name, a)| Attribute {
+                    name: QualName::new(
+                        a.prefix.clone(), name.ns.clone(), name.local.clone()
+                    ),
+                    value: a.value.as_str().into(),
+                })
+                .collect();
+            let elm = Element { name: ed.name.clone(), attrs, _priv: () };
+            let id = doc.append_child(parent, Node::new_elem(elm));
+            for child in node.children() {
+                from_kuchiki_node(doc, id, &child);
+            }
+            if let Some(contents) = &ed.template_contents {
+                for child in contents.children() {
+                    from_kuchiki_node(doc, id, &child);
+                }
+            }
+            return;
+        }
+        K::Text(t) => NodeData::Text(t.borrow().as_str().into()),
+        K::Comment(t) => NodeData::Comment(t.borrow().as_str().into()),
+        K::ProcessingInstruction(pi) => {
+            NodeData::Pi(ProcessingInstruction {
+                data: pi.borrow().1.as_str().into(),
+                _priv: ()
+            })
+        }
+        K::Doctype(dt) => {
+            NodeData::DocType(DocumentType {
+                name: dt.name.as_str().into(),
+                _priv: ()
+            })
+        }
+    };
+    doc.append_child(parent, Node::new(data));
+}
+
+#[cfg(feature = "scraper")]
+fn from_scraper_node(
+    doc: &mut Document,
+    parent: NodeId,
+    node: ego_tree::NodeRef<'_, scraper::Node>)
+{
+    use scraper::Node as S;
+
+    let data = match node.value() {
+        S::Document | S::Fragment => {
+            for child in node.children() {
+                from_scraper_node(doc, parent, child);
+            }
+            return;
+        }
+        S::Element(e) => {
+            let attrs = e.attrs()
+                .map(|(name, value)| Attribute {
+                    name: QualName::new(None, ns!(), name.into()),
+                    value: value.into(),
+                })
+                .collect();
+            let elm = Element { name: e.name.clone(), attrs, _priv: () };
+            let id = doc.append_child(parent, Node::new_elem(elm));
+            for child in node.children() {
+                from_scraper_node(doc, id, child);
+            }
+            return;
+        }
+        S::Text(t) => NodeData::Text(t.text.clone()),
+        S::Comment(c) => NodeData::Comment(c.comment.clone()),
+        S::ProcessingInstruction(pi) => {
+            NodeData::Pi(ProcessingInstruction {
+                data: pi.data.clone(),
+                _priv: ()
+            })
+        }
+        S::Doctype(dt) => {
+            NodeData::DocType(DocumentType {
+                name: dt.name.clone(),
+                _priv: ()
+            })
+        }
+    };
+    doc.append_child(parent, Node::new(data));
+}
@@ -0,0 +1,159 @@
+//! Extraction of JSON-LD structured data, from
+//! `<script type="application/ld+json">` elements.
+//!
+//! This module requires the _jsonld_ feature, for `serde_json`.
+
+use log::debug;
+use serde_json::Value;
+
+use crate::dom::{
+    html::{a, t},
+    Document, NodeRef,
+};
+
+/// Common schema.org types of [`JsonLd`] items.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SchemaType {
+    /// `Article` or a sub-type such as `NewsArticle` or `BlogPosting`.
+    Article,
+
+    /// `Product` or a sub-type such as `ProductModel` or `Vehicle`.
+    Product,
+
+    /// `Recipe`.
+    Recipe,
+
+    /// Any other or missing type.
+    Other,
+}
+
+/// A JSON-LD item, as returned by [`extract`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonLd {
+    /// The common type of the item, per the first recognized `@type`.
+    pub kind: SchemaType,
+
+    /// The `@type` names of the item, in order found, with any schema.org
+    /// prefix (e.g. `https://schema.org/`) removed.
+    pub types: Vec<String>,
+
+    /// The parsed item.
+    pub value: Value,
+}
+
+impl JsonLd {
+    fn new(value: Value) -> JsonLd {
+        let types: Vec<String> = match value.get("@type") {
+            Some(Value::String(s)) => vec![type_name(s)],
+            Some(Value::Array(vs)) => vs.iter()
+                .filter_map(Value::as_str)
+                .map(type_name)
+                .collect(),
+            _ => Vec::new(),
+        };
+        let kind = types.iter()
+            .map(|t| schema_type(t))
+            .find(|&k| k != SchemaType::Other)
+            .unwrap_or(SchemaType::Other);
+        JsonLd { kind, types, value }
+    }
+
+    /// Return the value of the named property of this item, if it is an
+    /// object with that property.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.value.get(name)
+    }
+}
+
+/// JSON-LD methods.
+impl Document {
+    /// Extract JSON-LD items from this `Document`.
+    ///
+    /// See [`extract`].
+    pub fn json_ld(&self) -> Vec<JsonLd> {
+        extract(self)
+    }
+}
+
+/// Extract all JSON-LD items from `<script type="application/ld+json">`
+/// elements of the `Document`, in document order.
+///
+/// Each top level JSON object is an item, or if the script contains an array,
+/// each object of the array. Objects with an `@graph` array are replaced by
+/// the objects of that array. Scripts which fail to parse as JSON are
+/// skipped. The raw text of all such scripts is available via
+/// [`PageMeta::json_ld`](crate::meta::PageMeta::json_ld).
+pub fn extract(doc: &Document) -> Vec<JsonLd> {
+    let mut items = Vec::new();
+    for script in doc.document_node_ref().select(is_json_ld) {
+        let text = script.text().unwrap_or_default();
+        match serde_json::from_str::<Value>(text.trim()) {
+            Ok(v) => push_items(&mut items, v),
+            Err(e) => {
+                debug!("skipping invalid JSON-LD script: {}", e);
+            }
+        }
+    }
+    items
+}
+
+fn is_json_ld(node: NodeRef<'_>) -> bool {
+    node.is_elem(t::SCRIPT) && node.attr(a::TYPE).map_or(false, |v| {
+        v.trim().eq_ignore_ascii_case("application/ld+json")
+    })
+}
+
+fn push_items(items: &mut Vec<JsonLd>, value: Value) {
+    match value {
+        Value::Array(vs) => {
+            for v in vs {
+                push_items(items, v);
+            }
+        }
+        Value::Object(mut obj) => {
+            match obj.remove("@graph") {
+                Some(Value::Array(graph)) => {
+                    for v in graph {
+                        push_items(items, v);
+                    }
+                }
+                Some(graph) => {
+                    obj.insert("@graph".to_owned(), graph);
+                    items.push(JsonLd::new(Value::Object(obj)));
+                }
+                None => items.push(JsonLd::new(Value::Object(obj))),
+            }
+        }
+        _ => {}
+    }
+}
+
+// Return the type name without any schema.org prefix.
+fn type_name(t: &str) -> String {
+    let t = t.trim();
+    for prefix in &["https://schema.org/", "http://schema.org/", "schema:"] {
+        if t.len() > prefix.len() &&
+            t.as_bytes()[..prefix.len()]
+                .eq_ignore_ascii_case(prefix.as_bytes())
+        {
+            return t[prefix.len()..].to_owned();
+        }
+    }
+    t.to_owned()
+}
+
+fn schema_type(name: &str) -> SchemaType {
+    match name {
+        "Article" | "AdvertiserContentArticle" | "AnalysisNewsArticle" |
+        "AskPublicNewsArticle" | "BackgroundNewsArticle" | "BlogPosting" |
+        "DiscussionForumPosting" | "LiveBlogPosting" | "NewsArticle" |
+        "OpinionNewsArticle" | "Report" | "ReportageNewsArticle" |
+        "ReviewNewsArticle" | "SatiricalArticle" | "ScholarlyArticle" |
+        "SocialMediaPosting" | "TechArticle" => SchemaType::Article,
+        "Product" | "IndividualProduct" | "ProductCollection" |
+        "ProductGroup" | "ProductModel" | "SomeProducts" | "Vehicle" |
+        "Car" | "Motorcycle" => SchemaType::Product,
+        "Recipe" => SchemaType::Recipe,
+        _ => SchemaType::Other,
+    }
+}
@@ -0,0 +1,165 @@
+//! Language (`lang`) and text direction (`dir`) support.
+
+use std::fmt;
+
+use crate::dom::{html::a, NodeRef, StrTendril};
+
+/// Text direction, as given by the HTML `dir` attribute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Left-to-right (the default).
+    Ltr,
+
+    /// Right-to-left.
+    Rtl,
+
+    /// Direction determined by content (`dir="auto"`).
+    Auto,
+}
+
+impl Direction {
+    /// Parse a `dir` attribute value, ASCII case-insensitively, returning
+    /// `None` if invalid.
+    pub fn parse(value: &str) -> Option<Direction> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("ltr") {
+            Some(Direction::Ltr)
+        } else if value.eq_ignore_ascii_case("rtl") {
+            Some(Direction::Rtl)
+        } else if value.eq_ignore_ascii_case("auto") {
+            Some(Direction::Auto)
+        } else {
+            None
+        }
+    }
+
+    /// Return the `dir` attribute value for this direction.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+            Direction::Auto => "auto",
+        }
+    }
+}
+
+impl Default for Direction {
+    fn default() -> Direction {
+        Direction::Ltr
+    }
+}
+
+/// A parsed BCP 47 language tag, of the common form used in `lang` and
+/// `hreflang` attributes: a language, with optional script, region and
+/// variant subtags, e.g. `en`, `en-US` or `zh-Hant-TW`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LangTag {
+    /// The primary language subtag, lower-case, e.g. `en`.
+    pub language: String,
+
+    /// The script subtag, title-case, e.g. `Hant`.
+    pub script: Option<String>,
+
+    /// The region subtag, upper-case, e.g. `US` or `419`.
+    pub region: Option<String>,
+
+    /// Any variant subtags, lower-case.
+    pub variants: Vec<String>,
+}
+
+impl LangTag {
+    /// Parse a language tag, ASCII case-insensitively and allowing `_` as
+    /// separator, returning `None` if invalid. Extension and private use
+    /// subtags, and grandfathered tags, are not supported.
+    pub fn parse(value: &str) -> Option<LangTag> {
+        let mut subtags = value.trim().split(|c| c == '-' || c == '_');
+        let language = subtags.next()?;
+        if !(2..=8).contains(&language.len()) ||
+            !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        let mut tag = LangTag {
+            language: language.to_ascii_lowercase(),
+            script: None,
+            region: None,
+            variants: Vec::new(),
+        };
+        for sub in subtags {
+            let len = sub.len();
+            let alpha = sub.chars().all(|c| c.is_ascii_alphabetic());
+            let digit = sub.chars().all(|c| c.is_ascii_digit());
+            if len == 4 && alpha && tag.script.is_none() &&
+                tag.region.is_none() && tag.variants.is_empty()
+            {
+                let mut s = sub.to_ascii_lowercase();
+                s[..1].make_ascii_uppercase();
+                tag.script = Some(s);
+            } else if ((len == 2 && alpha) || (len == 3 && digit)) &&
+                tag.region.is_none() && tag.variants.is_empty()
+            {
+                tag.region = Some(sub.to_ascii_uppercase());
+            } else if ((5..=8).contains(&len) ||
+                       (len == 4 && sub.starts_with(|c: char| {
+                           c.is_ascii_digit()
+                       }))) &&
+                sub.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                tag.variants.push(sub.to_ascii_lowercase());
+            } else {
+                return None;
+            }
+        }
+        Some(tag)
+    }
+}
+
+/// Writes the tag in canonical case, with `-` separators.
+impl fmt::Display for LangTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.language)?;
+        for sub in self.script.iter()
+            .chain(self.region.iter())
+            .chain(self.variants.iter())
+        {
+            write!(f, "-{}", sub)?;
+        }
+        Ok(())
+    }
+}
+
+/// Language and direction methods.
+impl<'a> NodeRef<'a> {
+    /// Return the language of this node, as given by the `lang` (or
+    /// `xml:lang`) attribute of this node or its nearest ancestor with a
+    /// non-empty value.
+    pub fn language(&self) -> Option<StrTendril> {
+        self.node_and_ancestors()
+            .filter_map(|n| n.attr(a::LANG).cloned())
+            .find(|l| !l.as_ref().trim().is_empty())
+    }
+
+    /// Return the text direction of this node, as given by the valid `dir`
+    /// attribute of this node or its nearest ancestor, or the default
+    /// `Direction::Ltr`.
+    pub fn direction(&self) -> Direction {
+        self.node_and_ancestors()
+            .find_map(|n| {
+                n.attr(a::DIR).and_then(|d| Direction::parse(d.as_ref()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Detect the language of this node's text content, returning an ISO
+    /// 639-3 code if reliably detected (_whatlang_ feature).
+    ///
+    /// This is useful for mixed-language documents where the `lang`
+    /// attribute may be missing or inaccurate.
+    #[cfg(feature = "whatlang")]
+    pub fn detect_language(&self) -> Option<&'static str> {
+        let text = self.text()?;
+        whatlang::detect(text.as_ref())
+            .filter(|info| info.is_reliable())
+            .map(|info| info.lang().code())
+    }
+}
@@ -0,0 +1,136 @@
+//! Support for associating external layout geometry with `Document` nodes.
+//!
+//! This crate does not render or compute layout. If an external renderer
+//! (e.g. a headless browser) is available, the rectangles it computes for
+//! elements may be recorded in a [`Layout`] and used to inform extraction
+//! heuristics, such as which elements are visible "above the fold".
+
+use std::collections::HashMap;
+
+use crate::dom::{Document, NodeId, NodeIdMap, NodeRef};
+
+/// A layout rectangle, in the coordinate space (typically CSS pixels, relative
+/// to the top-left of the page) of the external renderer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    /// Construct a new `Rect` by position and size.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    /// Return the right edge x coordinate.
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    /// Return the bottom edge y coordinate.
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Return the area, or 0.0 for a degenerate (negative sized) rectangle.
+    pub fn area(&self) -> f32 {
+        if self.is_empty() { 0.0 } else { self.width * self.height }
+    }
+
+    /// Return true if this has no positive width or height.
+    pub fn is_empty(&self) -> bool {
+        !(self.width > 0.0 && self.height > 0.0)
+    }
+
+    /// Return true if this and other have a non-empty intersection.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        !self.is_empty() && !other.is_empty() &&
+            self.x < other.right() && other.x < self.right() &&
+            self.y < other.bottom() && other.y < self.bottom()
+    }
+}
+
+/// A sidecar mapping of `NodeId` to layout [`Rect`], for a single `Document`.
+///
+/// As with `NodeId`s in general, a `Layout` should only be used with the
+/// `Document` it was populated for. After any [`Document::compact`], use
+/// [`Layout::remap`].
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    rects: HashMap<NodeId, Rect>,
+}
+
+impl Layout {
+    /// Construct a new empty `Layout`.
+    pub fn new() -> Layout {
+        Layout::default()
+    }
+
+    /// Set the layout `Rect` for the given node, returning any prior value.
+    pub fn set(&mut self, id: NodeId, rect: Rect) -> Option<Rect> {
+        self.rects.insert(id, rect)
+    }
+
+    /// Return the layout `Rect` for the given node, if set.
+    pub fn get(&self, id: NodeId) -> Option<Rect> {
+        self.rects.get(&id).copied()
+    }
+
+    /// Remove and return any layout `Rect` for the given node.
+    pub fn remove(&mut self, id: NodeId) -> Option<Rect> {
+        self.rects.remove(&id)
+    }
+
+    /// Translate all `NodeId`s via the given map, as returned by
+    /// [`Document::compact`], dropping those of removed nodes.
+    pub fn remap(&mut self, map: &NodeIdMap) {
+        self.rects = self.rects
+            .drain()
+            .filter_map(|(id, r)| map.get(id).map(|nid| (nid, r)))
+            .collect();
+    }
+
+    /// Return the number of nodes with a layout `Rect`.
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    /// Return true if no layout `Rect`s have been set.
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Return the layout `Rect` for the given node if set, or otherwise the
+    /// `Rect` of its nearest ancestor with one.
+    ///
+    /// This is a rough estimate for nodes (such as text) for which the
+    /// renderer doesn't report geometry.
+    pub fn nearest(&self, doc: &Document, id: NodeId) -> Option<Rect> {
+        doc.node_and_ancestors(id).find_map(|id| self.get(id))
+    }
+
+    /// Return true if the given node has a layout `Rect` which is at least
+    /// partially visible in a viewport of the given height, at the top of the
+    /// page (above the fold).
+    pub fn is_above_fold(&self, id: NodeId, fold: f32) -> bool {
+        self.get(id).map_or(false, |r| {
+            !r.is_empty() && r.y < fold && r.bottom() > 0.0
+        })
+    }
+
+    /// Return an iterator over all elements, in tree order, with a layout
+    /// `Rect` which is at least partially above the fold. See
+    /// [`Layout::is_above_fold`].
+    pub fn above_fold<'a>(&'a self, doc: &'a Document, fold: f32)
+        -> impl Iterator<Item = NodeRef<'a>> + 'a
+    {
+        doc.document_node_ref()
+            .descendants()
+            .filter(move |n| {
+                n.as_element().is_some() && self.is_above_fold(n.id(), fold)
+            })
+    }
+}
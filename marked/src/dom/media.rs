@@ -0,0 +1,131 @@
+//! Inventory of images and other embedded media.
+
+use crate::dom::{
+    html::{a, t},
+    Document, Element, LocalName, NodeId, NodeRef, StrTendril,
+};
+
+/// The kind of a [`Media`] element.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MediaKind {
+    /// An `<img>` element.
+    Image,
+
+    /// A `<video>` element.
+    Video,
+
+    /// An `<audio>` element.
+    Audio,
+
+    /// An `<iframe>` element.
+    Iframe,
+}
+
+/// A media element record, as returned by [`Document::media`].
+#[derive(Clone, Debug)]
+pub struct Media<'a> {
+    /// The media element.
+    pub node: NodeRef<'a>,
+
+    /// The kind of media.
+    pub kind: MediaKind,
+
+    /// The (unresolved) source URL, from the `src` attribute, or for
+    /// `<video>` and `<audio>` elements, the first `<source>` child with a
+    /// `src`.
+    pub src: Option<&'a str>,
+
+    /// The `width` attribute, if a valid integer.
+    pub width: Option<u32>,
+
+    /// The `height` attribute, if a valid integer.
+    pub height: Option<u32>,
+
+    /// The `alt` attribute of images, or the `title` attribute of other
+    /// media.
+    pub alt: Option<&'a str>,
+
+    /// The text of the `<figcaption>` of the nearest enclosing `<figure>`,
+    /// with whitespace trimmed, if non-empty.
+    pub caption: Option<StrTendril>,
+}
+
+/// Media inventory methods.
+impl Document {
+    /// Return records of all image, video, audio and iframe elements, in
+    /// document order.
+    pub fn media(&self) -> Vec<Media<'_>> {
+        self.nodes()
+            .filter_map(|id| self.media_at(id))
+            .collect()
+    }
+
+    fn media_at(&self, id: NodeId) -> Option<Media<'_>> {
+        let elm = self[id].as_element()?;
+        let kind = media_kind(&elm.name.local)?;
+        let mut src = attr_str(elm, a::SRC);
+        let has_sources = kind == MediaKind::Video || kind == MediaKind::Audio;
+        if src.is_none() && has_sources {
+            src = self.children(id)
+                .filter_map(|c| self[c].as_element())
+                .filter(|e| e.is_elem(t::SOURCE))
+                .find_map(|e| attr_str(e, a::SRC));
+        }
+        let alt = if kind == MediaKind::Image {
+            elm.attr(a::ALT).map(|v| &**v)
+        } else {
+            attr_str(elm, a::TITLE)
+        };
+        Some(Media {
+            node: NodeRef::new(self, id),
+            kind,
+            src,
+            width: dimension(elm, a::WIDTH),
+            height: dimension(elm, a::HEIGHT),
+            alt,
+            caption: self.caption(id),
+        })
+    }
+
+    // Return the non-empty figcaption text of any enclosing figure.
+    fn caption(&self, id: NodeId) -> Option<StrTendril> {
+        let figure = self.node_and_ancestors(id)
+            .find(|&n| self[n].is_elem(t::FIGURE))?;
+        let caption = self.children(figure)
+            .find(|&c| self[c].is_elem(t::FIGCAPTION))?;
+        let text = self.text(caption)?;
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            None
+        } else if trimmed.len() == text.len() {
+            Some(text)
+        } else {
+            Some(StrTendril::from_slice(trimmed))
+        }
+    }
+}
+
+fn media_kind(name: &LocalName) -> Option<MediaKind> {
+    if *name == t::IMG {
+        Some(MediaKind::Image)
+    } else if *name == t::VIDEO {
+        Some(MediaKind::Video)
+    } else if *name == t::AUDIO {
+        Some(MediaKind::Audio)
+    } else if *name == t::IFRAME {
+        Some(MediaKind::Iframe)
+    } else {
+        None
+    }
+}
+
+// Return the trimmed, non-empty value of an attribute.
+fn attr_str(elm: &Element, name: LocalName) -> Option<&str> {
+    elm.attr(name)
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+fn dimension(elm: &Element, name: LocalName) -> Option<u32> {
+    elm.attr(name).and_then(|v| v.trim().parse().ok())
+}
@@ -0,0 +1,210 @@
+//! Memory usage reporting, compaction policy and text storage.
+
+use std::mem::size_of;
+
+use log::debug;
+
+use crate::dom::{
+    Attribute, Document, Node, NodeData, NodeId, NodeIdMap, StrTendril,
+};
+
+/// Tendrils of this length or less are stored inline, without heap allocation.
+pub(crate) const TENDRIL_INLINE_MAX: u32 = 8;
+
+/// Approximate memory usage of a `Document`, as returned by
+/// [`Document::memory_usage`].
+///
+/// All byte counts are estimates. Tendril byte counts exclude small, inline
+/// values, do not include allocation headers, and count any shared buffers
+/// for each reference.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Total number of `Node`s, as per [`Document::len`].
+    pub nodes: usize,
+
+    /// Bytes of the `Node` vector, by capacity.
+    pub node_bytes: usize,
+
+    /// Bytes of element attribute vectors (by capacity) and values.
+    pub attr_bytes: usize,
+
+    /// Bytes of text, comment, doctype and processing instruction tendrils.
+    pub text_bytes: usize,
+
+    /// Number of `Node`s that are not reachable from the document node,
+    /// including `NodeData::Hole`s, which could be freed via
+    /// [`Document::compact`].
+    pub garbage_nodes: usize,
+
+    /// Estimated bytes of the `Node` vector occupied by garbage nodes.
+    pub garbage_bytes: usize,
+}
+
+/// A policy for automatic [`Document::compact`], when detached (garbage)
+/// nodes exceed a ratio of all nodes. See
+/// [`Document::set_compact_policy`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CompactPolicy {
+    /// Minimum ratio of detached nodes to [`Document::len`], in the range
+    /// (0.0, 1.0]. Default: 0.5.
+    pub ratio: f32,
+
+    /// Minimum number of detached nodes, to avoid frequent compaction of
+    /// small documents. Default: 1024.
+    pub min_detached: u32,
+}
+
+impl Default for CompactPolicy {
+    fn default() -> CompactPolicy {
+        CompactPolicy { ratio: 0.5, min_detached: 1024 }
+    }
+}
+
+impl CompactPolicy {
+    /// Return true if a document with the given total and detached node
+    /// counts should be compacted.
+    pub fn should_compact(&self, len: u32, detached: u32) -> bool {
+        detached > 0 &&
+            detached >= self.min_detached &&
+            detached as f32 >= self.ratio * len as f32
+    }
+}
+
+impl MemoryUsage {
+    /// Return the total estimated bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.node_bytes + self.attr_bytes + self.text_bytes
+    }
+}
+
+/// Memory usage reporting.
+impl Document {
+    /// Return an estimated breakdown of the memory used by this `Document`.
+    ///
+    /// This requires a walk of all nodes and is thus relatively expensive.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut mu = MemoryUsage {
+            nodes: self.len() as usize,
+            node_bytes: self.nodes.capacity() * size_of::<Node>(),
+            .. MemoryUsage::default()
+        };
+
+        for node in &self.nodes {
+            match &node.data {
+                NodeData::Elem(elm) => {
+                    mu.attr_bytes +=
+                        elm.attrs.capacity() * size_of::<Attribute>();
+                    for attr in &elm.attrs {
+                        mu.attr_bytes += tendril_heap_bytes(&attr.value);
+                    }
+                }
+                NodeData::Text(t) | NodeData::Comment(t) => {
+                    mu.text_bytes += tendril_heap_bytes(t);
+                }
+                NodeData::DocType(dt) => {
+                    mu.text_bytes += tendril_heap_bytes(&dt.name);
+                }
+                NodeData::Pi(pi) => {
+                    mu.text_bytes += tendril_heap_bytes(&pi.data);
+                }
+                NodeData::Document | NodeData::Hole => {}
+            }
+        }
+
+        mu.garbage_nodes = self.detached_count() as usize;
+        mu.garbage_bytes = mu.garbage_nodes * size_of::<Node>();
+        mu
+    }
+
+    /// Return the number of `Node`s that are not reachable from the document
+    /// node, including `NodeData::Hole`s, which could be freed via
+    /// [`Document::compact`].
+    ///
+    /// This requires a walk of all reachable nodes.
+    pub fn detached_count(&self) -> u32 {
+        self.len() - self.nodes().count() as u32
+    }
+
+    /// Set the policy for automatic compaction, or `None` (the default) to
+    /// disable it.
+    ///
+    /// When set, [`Document::filter`], [`Document::filter_breadth`],
+    /// [`Document::filter_with_depth_limit`] and
+    /// [`Document::filter_until_stable`] (including via
+    /// [`Document::filter_preset`]) call [`Document::compact_if_needed`]
+    /// after completing, so long-lived documents don't accumulate garbage
+    /// from repeated filtering. Note this may then change the `NodeId`s of
+    /// retained nodes, so any held should be re-obtained. The policy is
+    /// retained through compaction and [`Document::bulk_clone`].
+    pub fn set_compact_policy(&mut self, policy: Option<CompactPolicy>) {
+        self.compact_policy = policy;
+    }
+
+    /// Return the current automatic compaction policy, if any.
+    pub fn compact_policy(&self) -> Option<CompactPolicy> {
+        self.compact_policy
+    }
+
+    /// Compact, as per [`Document::compact`], if a policy is set and the
+    /// detached nodes exceed its thresholds, returning the `NodeIdMap`.
+    /// Otherwise return `None`.
+    pub fn compact_if_needed(&mut self) -> Option<NodeIdMap> {
+        let policy = self.compact_policy?;
+        let len = self.len();
+        if len < policy.min_detached {
+            return None;
+        }
+        let detached = self.detached_count();
+        if policy.should_compact(len, detached) {
+            debug!("compacting {} of {} nodes detached", detached, len);
+            Some(self.compact())
+        } else {
+            None
+        }
+    }
+}
+
+/// Text storage methods.
+impl Document {
+    /// Coalesce the text of all text nodes into a single shared buffer, in
+    /// document order, replacing each with a sub-slice of that buffer.
+    /// Returns the number of text nodes coalesced.
+    ///
+    /// The parser allocates text separately per text node, or retains
+    /// sub-slices of (larger) input buffers. This pass reduces the number of
+    /// allocations and can free input buffers for text-heavy documents. Text
+    /// of 8 bytes or less, which is stored inline, is excluded. Subsequent
+    /// mutation of any coalesced text node copies its text to a new buffer,
+    /// and the shared buffer is retained until all referencing text nodes
+    /// are dropped, so this is best applied after filtering and
+    /// [`Document::compact`].
+    pub fn coalesce_text(&mut self) -> usize {
+        let ids: Vec<NodeId> = self.nodes()
+            .filter(|&id| {
+                self[id].as_text()
+                    .map_or(false, |t| t.len32() > TENDRIL_INLINE_MAX)
+            })
+            .collect();
+        let total = ids.iter()
+            .map(|&id| self[id].as_text().unwrap().len32())
+            .sum();
+        let mut buf = StrTendril::with_capacity(total);
+        for &id in &ids {
+            buf.push_tendril(self[id].as_text().unwrap());
+        }
+        let mut offset = 0;
+        for &id in &ids {
+            if let NodeData::Text(t) = &mut self[id].data {
+                let len = t.len32();
+                *t = buf.subtendril(offset, len);
+                offset += len;
+            }
+        }
+        ids.len()
+    }
+}
+
+fn tendril_heap_bytes(t: &StrTendril) -> usize {
+    let len = t.len32();
+    if len > TENDRIL_INLINE_MAX { len as usize } else { 0 }
+}
@@ -0,0 +1,377 @@
+//! Extraction of common page metadata from HTML documents.
+
+use crate::chars::replace_chars;
+use crate::dom::{
+    html::{a, t},
+    lang::LangTag,
+    Document, NodeRef, StrTendril,
+};
+
+/// Common metadata of an HTML page, as returned by [`PageMeta::extract`].
+///
+/// Values are obtained from `<title>`, `<meta>`, `<link>` and JSON-LD
+/// `<script>` elements found anywhere in the `Document`, in document order.
+#[derive(Clone, Debug, Default)]
+pub struct PageMeta {
+    /// Text of the first non-empty `<title>` element, with whitespace
+    /// normalized.
+    pub title: Option<StrTendril>,
+
+    /// Content of the first `<meta name="description">` element.
+    pub description: Option<StrTendril>,
+
+    /// The `href` of the first `<link rel="canonical">` element.
+    pub canonical: Option<StrTendril>,
+
+    /// OpenGraph (`og:*`) property name and content pairs.
+    pub open_graph: Vec<(StrTendril, StrTendril)>,
+
+    /// Twitter card (`twitter:*`) name and content pairs.
+    pub twitter: Vec<(StrTendril, StrTendril)>,
+
+    /// Raw, trimmed bodies of `<script type="application/ld+json">` elements.
+    /// These are not parsed or validated here.
+    pub json_ld: Vec<StrTendril>,
+
+    _priv: ()
+}
+
+impl PageMeta {
+    /// Extract metadata from the given `Document`.
+    pub fn extract(doc: &Document) -> PageMeta {
+        let mut pm = PageMeta::default();
+        let found = doc.document_node_ref().select(|n| {
+            n.is_elem(t::TITLE) ||
+            n.is_elem(t::META) ||
+            n.is_elem(t::LINK) ||
+            n.is_elem(t::SCRIPT)
+        });
+        for node in found {
+            if node.is_elem(t::TITLE) {
+                pm.add_title(node);
+            } else if node.is_elem(t::META) {
+                pm.add_meta(node);
+            } else if node.is_elem(t::LINK) {
+                pm.add_link(node);
+            } else {
+                pm.add_script(node);
+            }
+        }
+        pm
+    }
+
+    /// Return the content of the first OpenGraph property with the given
+    /// name, including prefix (e.g. "og:title"), compared ASCII
+    /// case-insensitively.
+    pub fn open_graph_value(&self, prop: &str) -> Option<&StrTendril> {
+        find_pair(&self.open_graph, prop)
+    }
+
+    /// Return the content of the first Twitter card property with the given
+    /// name, including prefix (e.g. "twitter:card"), compared ASCII
+    /// case-insensitively.
+    pub fn twitter_value(&self, name: &str) -> Option<&StrTendril> {
+        find_pair(&self.twitter, name)
+    }
+
+    fn add_title(&mut self, node: NodeRef<'_>) {
+        if self.title.is_none() {
+            if let Some(mut text) = node.text() {
+                replace_chars(&mut text, true, true, true, true);
+                if !text.is_empty() {
+                    self.title = Some(text);
+                }
+            }
+        }
+    }
+
+    fn add_meta(&mut self, node: NodeRef<'_>) {
+        let content = if let Some(c) = node.attr(a::CONTENT) {
+            trimmed(c)
+        } else {
+            return;
+        };
+
+        // OpenGraph specifies `property`, but `name` is commonly found as
+        // well, and the reverse for Twitter cards.
+        let key = node.attr("property").or_else(|| node.attr(a::NAME));
+        if let Some(key) = key {
+            let key = key.as_ref().trim();
+            if starts_with_ignore_case(key, "og:") {
+                self.open_graph.push((StrTendril::from_slice(key), content));
+            } else if starts_with_ignore_case(key, "twitter:") {
+                self.twitter.push((StrTendril::from_slice(key), content));
+            } else if key.eq_ignore_ascii_case("description") &&
+                self.description.is_none()
+            {
+                self.description = Some(content);
+            }
+        }
+    }
+
+    fn add_link(&mut self, node: NodeRef<'_>) {
+        if self.canonical.is_some() {
+            return;
+        }
+        if let Some(rel) = node.attr(a::REL) {
+            let is_canonical = rel
+                .as_ref()
+                .split_ascii_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("canonical"));
+            if is_canonical {
+                if let Some(href) = node.attr(a::HREF) {
+                    self.canonical = Some(trimmed(href));
+                }
+            }
+        }
+    }
+
+    fn add_script(&mut self, node: NodeRef<'_>) {
+        let is_json_ld = node.attr(a::TYPE).map_or(false, |v| {
+            v.as_ref().trim().eq_ignore_ascii_case("application/ld+json")
+        });
+        if is_json_ld {
+            if let Some(text) = node.text() {
+                let text = trimmed(&text);
+                if !text.is_empty() {
+                    self.json_ld.push(text);
+                }
+            }
+        }
+    }
+}
+
+/// `PageMeta` convenience method.
+impl Document {
+    /// Extract common page metadata from this `Document`.
+    ///
+    /// See [`PageMeta::extract`].
+    pub fn page_meta(&self) -> PageMeta {
+        PageMeta::extract(self)
+    }
+
+    /// Return the canonical and alternate language links of the page, with
+    /// validation.
+    ///
+    /// The `href` of each `<link rel="canonical">` and of each
+    /// `<link rel="alternate" hreflang>` must be an absolute `http` or
+    /// `https` URL, as required by search engines, and `hreflang` must be a
+    /// valid language tag (per [`LangTag::parse`]) or `x-default`.
+    /// Duplicate links are ignored, but conflicting ones are reported as
+    /// issues.
+    pub fn alternates(&self) -> Alternates {
+        let mut alts = Alternates::default();
+        let links = self.document_node_ref()
+            .select(|n| n.is_elem(t::LINK));
+        for node in links {
+            let rels = node.attr(a::REL).map_or_else(Vec::new, |rel| {
+                rel.split_ascii_whitespace()
+                    .map(str::to_ascii_lowercase)
+                    .collect()
+            });
+            let is_canonical = rels.iter().any(|r| r == "canonical");
+            let is_alternate = rels.iter().any(|r| r == "alternate");
+            let hreflang = node.attr(a::HREFLANG).map(trimmed);
+            if !is_canonical && !(is_alternate && hreflang.is_some()) {
+                continue;
+            }
+            let href = node.attr(a::HREF).map(trimmed).unwrap_or_default();
+            if !is_absolute_http(&href) {
+                alts.issues.push(LinkIssue::NotAbsolute(href));
+                continue;
+            }
+
+            if is_canonical {
+                match &alts.canonical {
+                    None => alts.canonical = Some(href.clone()),
+                    Some(c) if *c != href => {
+                        alts.issues.push(
+                            LinkIssue::ConflictingCanonical(href.clone()));
+                    }
+                    Some(_) => {}
+                }
+            }
+            let hreflang = match hreflang {
+                Some(hl) if is_alternate => hl,
+                _ => continue,
+            };
+            if hreflang.eq_ignore_ascii_case("x-default") {
+                match &alts.x_default {
+                    None => alts.x_default = Some(href),
+                    Some(x) if *x != href => {
+                        alts.issues.push(
+                            LinkIssue::DuplicateLang("x-default".to_owned()));
+                    }
+                    Some(_) => {}
+                }
+            } else if let Some(lang) = LangTag::parse(&hreflang) {
+                let conflict = alts.hreflang.iter()
+                    .find(|hl| hl.lang == lang)
+                    .map(|hl| hl.href != href);
+                match conflict {
+                    None => alts.hreflang.push(HrefLang { lang, href }),
+                    Some(true) => {
+                        alts.issues.push(
+                            LinkIssue::DuplicateLang(lang.to_string()));
+                    }
+                    Some(false) => {}
+                }
+            } else {
+                alts.issues.push(LinkIssue::InvalidLang(hreflang));
+            }
+        }
+        alts
+    }
+
+    /// Return links to RSS or Atom feeds of the page, from
+    /// `<link rel="alternate">` elements with a `type` of
+    /// `application/rss+xml` or `application/atom+xml`, and a non-empty
+    /// `href`, in document order. The `href` is as found, not resolved.
+    pub fn feed_links(&self) -> Vec<FeedLink> {
+        self.document_node_ref()
+            .select(|n| n.is_elem(t::LINK))
+            .filter_map(|node| {
+                let is_alternate = node.attr(a::REL).map_or(false, |rel| {
+                    rel.split_ascii_whitespace()
+                        .any(|r| r.eq_ignore_ascii_case("alternate"))
+                });
+                if !is_alternate {
+                    return None;
+                }
+                let ctype = node.attr(a::TYPE)?.trim();
+                let kind = if ctype.eq_ignore_ascii_case(
+                    "application/rss+xml")
+                {
+                    FeedKind::Rss
+                } else if ctype.eq_ignore_ascii_case("application/atom+xml") {
+                    FeedKind::Atom
+                } else {
+                    return None;
+                };
+                let href = trimmed(node.attr(a::HREF)?);
+                if href.is_empty() {
+                    return None;
+                }
+                let title = node.attr(a::TITLE)
+                    .map(trimmed)
+                    .filter(|t| !t.is_empty());
+                Some(FeedLink { kind, href, title, _priv: () })
+            })
+            .collect()
+    }
+}
+
+/// Canonical and alternate language links of a page, as returned by
+/// [`Document::alternates`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Alternates {
+    /// The trimmed `href` of the first `<link rel="canonical">` element, if
+    /// an absolute URL.
+    pub canonical: Option<StrTendril>,
+
+    /// Valid `<link rel="alternate" hreflang>` links, other than
+    /// `x-default`, in document order.
+    pub hreflang: Vec<HrefLang>,
+
+    /// The `href` of any valid `hreflang="x-default"` link.
+    pub x_default: Option<StrTendril>,
+
+    /// Problems found, in document order. Links with issues are excluded
+    /// from the above.
+    pub issues: Vec<LinkIssue>,
+
+    _priv: ()
+}
+
+/// An alternate language link of [`Alternates`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HrefLang {
+    /// The parsed `hreflang` value.
+    pub lang: LangTag,
+
+    /// The trimmed `href`, an absolute URL.
+    pub href: StrTendril,
+}
+
+/// A problem with canonical or alternate language links, as found by
+/// [`Document::alternates`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkIssue {
+    /// An `href` which is empty or not an absolute `http` or `https` URL.
+    NotAbsolute(StrTendril),
+
+    /// A subsequent `rel="canonical"` link with a different `href`.
+    ConflictingCanonical(StrTendril),
+
+    /// An invalid `hreflang` value.
+    InvalidLang(StrTendril),
+
+    /// A subsequent link for the same language (or `x-default`) with a
+    /// different `href`.
+    DuplicateLang(String),
+}
+
+/// The syntax of a web feed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FeedKind {
+    /// RSS, including RSS 1.0 (RDF) and 2.0.
+    Rss,
+
+    /// Atom.
+    Atom,
+}
+
+/// A link to a web feed, as returned by [`Document::feed_links`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeedLink {
+    /// The syntax of the feed, per the link `type`.
+    pub kind: FeedKind,
+
+    /// The trimmed `href` of the link.
+    pub href: StrTendril,
+
+    /// The trimmed `title` of the link, if non-empty.
+    pub title: Option<StrTendril>,
+
+    _priv: ()
+}
+
+fn find_pair<'a>(pairs: &'a [(StrTendril, StrTendril)], key: &str)
+    -> Option<&'a StrTendril>
+{
+    pairs
+        .iter()
+        .find(|(k, _)| k.as_ref().eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+// Return true if the URL is absolute with an http or https scheme and a
+// host.
+fn is_absolute_http(url: &str) -> bool {
+    let rest = if starts_with_ignore_case(url, "https://") {
+        &url[8..]
+    } else if starts_with_ignore_case(url, "http://") {
+        &url[7..]
+    } else {
+        return false;
+    };
+    let host = rest.split(|c| c == '/' || c == '?' || c == '#')
+        .next()
+        .unwrap_or("");
+    !host.is_empty() && !host.contains(char::is_whitespace)
+}
+
+fn trimmed(st: &StrTendril) -> StrTendril {
+    let s = st.as_ref();
+    let t = s.trim();
+    if t.len() == s.len() {
+        st.clone()
+    } else {
+        StrTendril::from_slice(t)
+    }
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() &&
+        s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
@@ -0,0 +1,253 @@
+//! Extraction of microdata (`itemscope`, `itemprop`) and basic RDFa
+//! (`typeof`, `property`) items.
+//!
+//! See [`extract`]. For JSON-LD, see the `jsonld` module (with the _jsonld_
+//! feature).
+
+use crate::chars::replace_chars;
+use crate::dom::{
+    html::a,
+    Document, Element, LocalName, NodeId, NodeRef,
+};
+
+/// The markup syntax of an [`Item`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    /// HTML microdata: `itemscope`, `itemtype`, `itemid` and `itemprop`.
+    Microdata,
+
+    /// RDFa (Lite): `vocab`, `typeof`, `resource` (or `about`) and
+    /// `property`.
+    Rdfa,
+}
+
+/// A typed item with properties, as returned by [`extract`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    /// The syntax of the item.
+    pub syntax: Syntax,
+
+    /// The item types, e.g. `https://schema.org/Person`. RDFa types without
+    /// a prefix are prefixed with any in scope `vocab`.
+    pub types: Vec<String>,
+
+    /// The global identifier of the item (`itemid`, `resource` or `about`)
+    /// if given.
+    pub id: Option<String>,
+
+    /// The property name and value pairs of the item, in document order.
+    /// Elements with multiple (space separated) names produce a pair for
+    /// each.
+    pub properties: Vec<(String, Value)>,
+
+    /// The id of the element establishing the item.
+    pub node: NodeId,
+}
+
+/// A property value of an [`Item`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    /// A text value, from text content or a `content`, `value` or
+    /// `datetime` attribute, trimmed and with whitespace normalized.
+    Text(String),
+
+    /// A URL value, as found (not resolved), from an `href`, `src`, `data`
+    /// or (RDFa) `resource` attribute.
+    Url(String),
+
+    /// A nested item.
+    Item(Item),
+}
+
+impl Value {
+    /// Return the text or URL value, or None for a nested item.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(s) | Value::Url(s) => Some(s.as_str()),
+            Value::Item(_) => None,
+        }
+    }
+
+    /// Return the nested item, if so.
+    pub fn as_item(&self) -> Option<&Item> {
+        match self {
+            Value::Item(item) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+impl Item {
+    /// Return the first value of the named property.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.get_all(name).next()
+    }
+
+    /// Return all values of the named property, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str)
+        -> impl Iterator<Item = &'a Value> + 'a
+    {
+        self.properties.iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Return true if any of the item's types is the given type, or ends
+    /// with `/` or `:` followed by it, e.g. `Person` matches
+    /// `https://schema.org/Person`.
+    pub fn is_type(&self, name: &str) -> bool {
+        self.types.iter().any(|t| {
+            t == name || (t.ends_with(name) && {
+                let rest = &t[..t.len() - name.len()];
+                rest.ends_with('/') || rest.ends_with(':')
+            })
+        })
+    }
+}
+
+/// Microdata methods.
+impl Document {
+    /// Extract microdata and RDFa items from this `Document`.
+    ///
+    /// See [`extract`].
+    pub fn microdata_items(&self) -> Vec<Item> {
+        extract(self)
+    }
+}
+
+/// Extract all top level microdata and RDFa items of the `Document`, in
+/// document order, with nested items as property values.
+///
+/// A top level item is established by an element with `itemscope` (or for
+/// RDFa, `typeof`) which is not itself a property (`itemprop` or
+/// `property`). The properties of an item are those of its descendants,
+/// excluding descendants of nested items. Property values are obtained per
+/// the microdata standard, e.g. from `<meta content>`, `<a href>`,
+/// `<img src>`, `<time datetime>`, or otherwise the element's text. For
+/// RDFa, any `content` or `resource` attribute takes precedence. The
+/// microdata `itemref` attribute, and RDFa `prefix` and `rel` attributes, are
+/// not supported. Items of one syntax are not considered within the other.
+pub fn extract(doc: &Document) -> Vec<Item> {
+    let mut items = Vec::new();
+    for node in doc.document_node_ref().descendants() {
+        for &syntax in &[Syntax::Microdata, Syntax::Rdfa] {
+            if is_scope(node, syntax) && prop_names(node, syntax).is_empty() {
+                items.push(build_item(node, syntax));
+            }
+        }
+    }
+    items
+}
+
+fn build_item(scope: NodeRef<'_>, syntax: Syntax) -> Item {
+    let (types, id) = match syntax {
+        Syntax::Microdata => (
+            split_attr(scope, "itemtype"),
+            scope.attr("itemid").map(|v| v.trim().to_owned()),
+        ),
+        Syntax::Rdfa => {
+            let vocab = std::iter::once(scope)
+                .chain(scope.ancestors())
+                .find_map(|n| n.attr("vocab"))
+                .map(|v| v.trim());
+            let types = split_attr(scope, "typeof").into_iter()
+                .map(|t| match vocab {
+                    Some(v) if !v.is_empty() && !t.contains(':') => {
+                        if v.ends_with('/') || v.ends_with('#') {
+                            format!("{}{}", v, t)
+                        } else {
+                            format!("{}/{}", v, t)
+                        }
+                    }
+                    _ => t,
+                })
+                .collect();
+            let id = scope.attr("resource")
+                .or_else(|| scope.attr("about"))
+                .map(|v| v.trim().to_owned());
+            (types, id)
+        }
+    };
+    let mut item = Item {
+        syntax,
+        types,
+        id,
+        properties: Vec::new(),
+        node: scope.id(),
+    };
+
+    let mut stack: Vec<NodeRef<'_>> = scope.children().rev().collect();
+    while let Some(node) = stack.pop() {
+        let elm = match node.as_element() {
+            Some(elm) => elm,
+            None => continue,
+        };
+        let names = prop_names(node, syntax);
+        let nested = is_scope(node, syntax);
+        if !names.is_empty() {
+            let value = if nested {
+                Value::Item(build_item(node, syntax))
+            } else {
+                prop_value(node, elm, syntax)
+            };
+            for name in names {
+                item.properties.push((name, value.clone()));
+            }
+        }
+        if !nested {
+            stack.extend(node.children().rev());
+        }
+    }
+    item
+}
+
+fn is_scope(node: NodeRef<'_>, syntax: Syntax) -> bool {
+    match syntax {
+        Syntax::Microdata => node.attr("itemscope").is_some(),
+        Syntax::Rdfa => node.attr("typeof").is_some(),
+    }
+}
+
+fn prop_names(node: NodeRef<'_>, syntax: Syntax) -> Vec<String> {
+    match syntax {
+        Syntax::Microdata => split_attr(node, "itemprop"),
+        Syntax::Rdfa => split_attr(node, "property"),
+    }
+}
+
+fn split_attr(node: NodeRef<'_>, name: &str) -> Vec<String> {
+    node.attr(name).map_or_else(Vec::new, |v| {
+        v.split_whitespace().map(str::to_owned).collect()
+    })
+}
+
+fn prop_value(node: NodeRef<'_>, elm: &Element, syntax: Syntax) -> Value {
+    let attr = |name: LocalName| elm.attr(name).map(|v| v.trim().to_owned());
+    if syntax == Syntax::Rdfa {
+        if let Some(v) = attr(a::CONTENT) {
+            return Value::Text(v);
+        }
+        if let Some(v) = attr(LocalName::from("resource")) {
+            return Value::Url(v);
+        }
+    }
+    let found = match elm.name.local {
+        local_name!("meta") => attr(a::CONTENT).map(Value::Text),
+        local_name!("audio") | local_name!("embed") |
+        local_name!("iframe") | local_name!("img") |
+        local_name!("source") | local_name!("track") |
+        local_name!("video") => attr(a::SRC).map(Value::Url),
+        local_name!("a") | local_name!("area") |
+        local_name!("link") => attr(a::HREF).map(Value::Url),
+        local_name!("object") => attr(a::DATA).map(Value::Url),
+        local_name!("data") |
+        local_name!("meter") => attr(a::VALUE).map(Value::Text),
+        local_name!("time") => attr(a::DATETIME).map(Value::Text),
+        _ => None,
+    };
+    found.unwrap_or_else(|| {
+        let mut text = node.text().unwrap_or_default();
+        replace_chars(&mut text, true, true, true, true);
+        Value::Text(text.to_string())
+    })
+}
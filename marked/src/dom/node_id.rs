@@ -0,0 +1,190 @@
+//! `NodeId` trait implementations and checked access by `NodeId`.
+
+use std::cmp::Ordering;
+use std::error::Error as StdError;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroU32;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{self, AtomicU32};
+
+use crate::dom::{Document, Node, NodeData, NodeId, NodeRef};
+
+// Next `Document` tag, for debug builds. Zero is reserved for untagged
+// `NodeId`s, which are valid for any `Document`.
+#[cfg(debug_assertions)]
+static NEXT_TAG: AtomicU32 = AtomicU32::new(1);
+
+impl NodeId {
+    // Construct from a node index and `Document` tag, where the tag is only
+    // retained in debug builds.
+    #[cfg(debug_assertions)]
+    #[inline]
+    pub(crate) const fn from_parts(index: NonZeroU32, tag: u32) -> NodeId {
+        NodeId(index, tag)
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline]
+    pub(crate) const fn from_parts(index: NonZeroU32, _tag: u32) -> NodeId {
+        NodeId(index)
+    }
+
+    #[cfg(debug_assertions)]
+    #[inline]
+    fn tag(self) -> u32 {
+        self.1
+    }
+}
+
+impl PartialEq for NodeId {
+    #[inline]
+    fn eq(&self, other: &NodeId) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for NodeId {}
+
+impl PartialOrd for NodeId {
+    #[inline]
+    fn partial_cmp(&self, other: &NodeId) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NodeId {
+    #[inline]
+    fn cmp(&self, other: &NodeId) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Hash for NodeId {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl fmt::Debug for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("NodeId").field(&self.0).finish()
+    }
+}
+
+/// An error for a `NodeId` which is not valid for a `Document`, as returned
+/// by [`Document::check_id`] and related methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeIdError {
+    /// The `NodeId` is out of range of the `Document`, and thus must be from
+    /// another `Document`.
+    OutOfRange,
+
+    /// The `NodeId` was obtained from another `Document`, or is stale after
+    /// [`Document::compact`]. This is only detected in debug builds.
+    Foreign,
+
+    /// The node has been removed, its data replaced with `NodeData::Hole`.
+    Removed,
+}
+
+impl fmt::Display for NodeIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            NodeIdError::OutOfRange => "NodeId out of range",
+            NodeIdError::Foreign => "NodeId from another Document",
+            NodeIdError::Removed => "NodeId of removed node",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl StdError for NodeIdError {}
+
+/// Checked `NodeId` access methods.
+///
+/// Indexing a `Document` by a `NodeId` (e.g. `doc[id]`) panics if the id is
+/// out of range and, in debug builds, if the id was obtained from another
+/// `Document`, but otherwise may silently return the wrong node. These
+/// methods instead return a `NodeIdError`.
+impl Document {
+    /// Return `Ok` if the `NodeId` is valid for this `Document` and its node
+    /// has not been removed.
+    ///
+    /// Note that a node being filtered is temporarily replaced with
+    /// `NodeData::Hole`, and thus reported as `Removed` if checked via the
+    /// filter's `NodeRef`.
+    pub fn check_id(&self, id: NodeId) -> Result<(), NodeIdError> {
+        let node = self.nodes.get(id.0.get() as usize)
+            .ok_or(NodeIdError::OutOfRange)?;
+        #[cfg(debug_assertions)]
+        {
+            if id.tag() != 0 && id.tag() != self.tag {
+                return Err(NodeIdError::Foreign);
+            }
+        }
+        if let NodeData::Hole = node.data {
+            return Err(NodeIdError::Removed);
+        }
+        Ok(())
+    }
+
+    /// Return the `Node` for the given id, or an error if invalid. See
+    /// [`Document::check_id`].
+    pub fn try_node(&self, id: NodeId) -> Result<&Node, NodeIdError> {
+        self.check_id(id)?;
+        Ok(&self.nodes[id.0.get() as usize])
+    }
+
+    /// Return the mutable `Node` for the given id, or an error if invalid.
+    /// See [`Document::check_id`].
+    pub fn try_node_mut(&mut self, id: NodeId)
+        -> Result<&mut Node, NodeIdError>
+    {
+        self.check_id(id)?;
+        Ok(&mut self.nodes[id.0.get() as usize])
+    }
+
+    /// Return a `NodeRef` for the given id, or an error if invalid. See
+    /// [`Document::check_id`].
+    pub fn try_node_ref(&self, id: NodeId)
+        -> Result<NodeRef<'_>, NodeIdError>
+    {
+        self.check_id(id)?;
+        Ok(NodeRef::new(self, id))
+    }
+
+    // Return a new tag for a `Document`.
+    #[cfg(debug_assertions)]
+    pub(crate) fn next_tag() -> u32 {
+        NEXT_TAG.fetch_add(1, atomic::Ordering::Relaxed)
+    }
+
+    // Return the tag for new `NodeId`s of this `Document`.
+    #[cfg(debug_assertions)]
+    #[inline]
+    pub(crate) fn tag(&self) -> u32 {
+        self.tag
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline]
+    pub(crate) fn tag(&self) -> u32 {
+        0
+    }
+
+    // Panic if the id is tagged for another Document.
+    #[cfg(debug_assertions)]
+    #[inline]
+    pub(crate) fn assert_tag(&self, id: NodeId) {
+        assert!(
+            id.tag() == 0 || id.tag() == self.tag,
+            "{:?} used with a Document other than its own", id
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    pub(crate) fn assert_tag(&self, _id: NodeId) {}
+}
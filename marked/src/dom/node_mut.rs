@@ -0,0 +1,227 @@
+use crate::dom::{
+    html::{self, t},
+    Document, LocalName, Node, NodeData, NodeId, NodeRef, StrTendril,
+};
+
+/// A `Node` within `Document` lifetime mutable reference, or cursor.
+///
+/// This is the mutable analogue of [`NodeRef`], for transformations of
+/// specific nodes without a whole-tree [`Document::filter`] pass. As it holds
+/// the `Document` mutably, only one `NodeMut` can be used at a time.
+/// Relative navigation, e.g. [`NodeMut::parent`], returns a `NodeMut`
+/// borrowed from self, while the `into_*` variants move the cursor,
+/// returning it unchanged as `Err` if there is no such node.
+pub struct NodeMut<'a> {
+    doc: &'a mut Document,
+    id: NodeId,
+}
+
+impl<'a> NodeMut<'a> {
+    /// Constructor.
+    #[inline]
+    pub fn new(doc: &'a mut Document, id: NodeId) -> Self {
+        NodeMut { doc, id }
+    }
+
+    /// Return the associated `NodeId`.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Return a read-only `NodeRef` for this node.
+    pub fn node_ref(&self) -> NodeRef<'_> {
+        NodeRef::new(&*self.doc, self.id)
+    }
+
+    /// Return a reference to the `NodeData` of this node.
+    pub fn data(&self) -> &NodeData {
+        &self.doc[self.id].data
+    }
+
+    /// Return a mutable reference to the `NodeData` of this node.
+    ///
+    /// Note that changing the data of a node with children to anything other
+    /// than an element or document node is invalid.
+    pub fn data_mut(&mut self) -> &mut NodeData {
+        &mut self.doc[self.id].data
+    }
+
+    /// Return the parent node, if any.
+    pub fn parent(&mut self) -> Option<NodeMut<'_>> {
+        let id = self.doc[self.id].parent?;
+        Some(NodeMut::new(self.doc, id))
+    }
+
+    /// Return the first child node, if any.
+    pub fn first_child(&mut self) -> Option<NodeMut<'_>> {
+        let id = self.doc[self.id].first_child?;
+        Some(NodeMut::new(self.doc, id))
+    }
+
+    /// Return the last child node, if any.
+    pub fn last_child(&mut self) -> Option<NodeMut<'_>> {
+        let id = self.doc[self.id].last_child?;
+        Some(NodeMut::new(self.doc, id))
+    }
+
+    /// Return the next sibling node, if any.
+    pub fn next_sibling(&mut self) -> Option<NodeMut<'_>> {
+        let id = self.doc[self.id].next_sibling?;
+        Some(NodeMut::new(self.doc, id))
+    }
+
+    /// Return the previous sibling node, if any.
+    pub fn prev_sibling(&mut self) -> Option<NodeMut<'_>> {
+        let id = self.doc[self.id].prev_sibling?;
+        Some(NodeMut::new(self.doc, id))
+    }
+
+    /// Move to the parent node, or return self as `Err` if none.
+    pub fn into_parent(self) -> Result<Self, Self> {
+        let next = self.doc[self.id].parent;
+        self.move_to(next)
+    }
+
+    /// Move to the first child node, or return self as `Err` if none.
+    pub fn into_first_child(self) -> Result<Self, Self> {
+        let next = self.doc[self.id].first_child;
+        self.move_to(next)
+    }
+
+    /// Move to the last child node, or return self as `Err` if none.
+    pub fn into_last_child(self) -> Result<Self, Self> {
+        let next = self.doc[self.id].last_child;
+        self.move_to(next)
+    }
+
+    /// Move to the next sibling node, or return self as `Err` if none.
+    pub fn into_next_sibling(self) -> Result<Self, Self> {
+        let next = self.doc[self.id].next_sibling;
+        self.move_to(next)
+    }
+
+    /// Move to the previous sibling node, or return self as `Err` if none.
+    pub fn into_prev_sibling(self) -> Result<Self, Self> {
+        let next = self.doc[self.id].prev_sibling;
+        self.move_to(next)
+    }
+
+    fn move_to(self, next: Option<NodeId>) -> Result<Self, Self> {
+        match next {
+            Some(id) => Ok(NodeMut { doc: self.doc, id }),
+            None => Err(self),
+        }
+    }
+
+    /// Set the text of this node.
+    ///
+    /// For a text or comment node, its content is replaced. For an element
+    /// or the document node, all children are removed and replaced with a
+    /// single text node, if the text is non-empty, as with the DOM
+    /// `textContent` property. Other nodes are unchanged.
+    pub fn set_text<T>(&mut self, text: T)
+        where T: Into<StrTendril>
+    {
+        let text = text.into();
+        match &mut self.doc[self.id].data {
+            NodeData::Text(t) | NodeData::Comment(t) => {
+                *t = text;
+                return;
+            }
+            NodeData::Elem(_) | NodeData::Document => {}
+            _ => return,
+        }
+        while let Some(child) = self.doc[self.id].first_child {
+            self.doc.unlink(child);
+        }
+        if !text.is_empty() {
+            self.doc.append_child(self.id, Node::new_text(text));
+        }
+    }
+
+    /// Set attribute by local name, returning any prior value found, if this
+    /// is an element. See [`Element::set_attr`](crate::Element::set_attr).
+    /// Returns `None` without change for other node types.
+    pub fn set_attr<LN, V>(&mut self, lname: LN, value: V)
+        -> Option<StrTendril>
+        where LN: Into<LocalName>, V: Into<StrTendril>
+    {
+        self.doc[self.id].as_element_mut()?.set_attr(lname, value)
+    }
+
+    /// Remove attribute by local name, returning any value found, if this
+    /// is an element. See
+    /// [`Element::remove_attr`](crate::Element::remove_attr).
+    pub fn remove_attr<LN>(&mut self, lname: LN) -> Option<StrTendril>
+        where LN: Into<LocalName>
+    {
+        self.doc[self.id].as_element_mut()?.remove_attr(lname)
+    }
+
+    /// Append the given node as the last child of this node, returning a
+    /// `NodeMut` for the new child.
+    pub fn append_child(&mut self, node: Node) -> NodeMut<'_> {
+        let id = self.doc.append_child(self.id, node);
+        NodeMut::new(self.doc, id)
+    }
+
+    /// Insert the given node as the previous sibling of this node,
+    /// returning a `NodeMut` for the new sibling.
+    pub fn insert_before(&mut self, node: Node) -> NodeMut<'_> {
+        let id = self.doc.insert_before_sibling(self.id, node);
+        NodeMut::new(self.doc, id)
+    }
+
+    /// Parse the given HTML fragment in the context of the parent element of
+    /// this node, and insert the resulting nodes before this node.
+    ///
+    /// If the parent is not an element (e.g. the document node) the fragment
+    /// is parsed in the context of `<body>`. Panics if this is the document
+    /// node.
+    pub fn insert_html_before(&mut self, html: &str) {
+        let context = self.parent_context();
+        let frag = html::parse_fragment_in_context(context, html.as_bytes());
+        self.doc.attach_before_sibling(self.id, frag.into_document());
+    }
+
+    /// Parse the given HTML fragment in the context of this element (or
+    /// `<body>` if not an element), and append the resulting nodes as its
+    /// last children.
+    pub fn append_html(&mut self, html: &str) {
+        let context = self.doc[self.id].as_element()
+            .map_or(t::BODY, |e| e.name.local.clone());
+        let frag = html::parse_fragment_in_context(context, html.as_bytes());
+        self.doc.append_fragment(self.id, frag);
+    }
+
+    fn parent_context(&self) -> LocalName {
+        self.doc[self.id].parent
+            .and_then(|p| self.doc[p].as_element())
+            .map_or(t::BODY, |e| e.name.local.clone())
+    }
+
+    /// Detach this node and return it and its descendants as a new
+    /// independent `Document`. See [`Document::detach`].
+    pub fn detach(self) -> Document {
+        self.doc.detach(self.id)
+    }
+
+    /// Unlink this node and its descendants, returning its `NodeData`. See
+    /// [`Document::unlink`].
+    pub fn unlink(self) -> NodeData {
+        self.doc.unlink(self.id)
+    }
+}
+
+/// `NodeMut` accessor methods.
+impl Document {
+    /// Return a `NodeMut` for the node of the given id.
+    pub fn node_mut(&mut self, id: NodeId) -> NodeMut<'_> {
+        NodeMut::new(self, id)
+    }
+
+    /// Return the (single, always present) document node as a `NodeMut`.
+    pub fn document_node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::new(self, Document::DOCUMENT_NODE_ID)
+    }
+}
@@ -1,8 +1,12 @@
+use std::any::Any;
 use std::fmt;
 use std::iter;
 use std::ops::Deref;
 
-use crate::dom::{Document, Node, NodeId, StrTendril, NodeStack1};
+use crate::dom::{
+    html::a, Document, Element, LocalName, Namespace, Node, NodeId,
+    StrTendril, NodeStack1
+};
 
 /// A `Node` within `Document` lifetime reference.
 ///
@@ -25,6 +29,11 @@ impl<'a> NodeRef<'a> {
         self.id
     }
 
+    #[inline]
+    pub(crate) fn doc(&self) -> &'a Document {
+        self.doc
+    }
+
     /// Return an iterator over the direct children of this node that
     /// match the specified predicate.
     ///
@@ -49,6 +58,31 @@ impl<'a> NodeRef<'a> {
         Selector::new(self.doc, self.first_child, predicate)
     }
 
+    /// Return an iterator over all decendents of this node that match the
+    /// specified predicate, as per [`NodeRef::select`], but visiting (testing)
+    /// at most `max_visited` nodes.
+    ///
+    /// This bounds the time spent walking huge or pathological documents.
+    /// Use [`Selector::is_exhausted`] after iteration to determine if any
+    /// nodes were left unvisited.
+    pub fn select_limited<P>(&self, predicate: P, max_visited: usize)
+        -> Selector<'a, P>
+        where P: FnMut(&NodeRef<'a>) -> bool + 'a
+    {
+        let mut sel = Selector::new(self.doc, self.first_child, predicate);
+        sel.budget = max_visited;
+        sel
+    }
+
+    /// Find up to the first `n` decendents of this node that match the
+    /// specified predicate, as per [`NodeRef::select`], stopping the walk as
+    /// soon as `n` are found.
+    pub fn find_first_n<P>(&self, predicate: P, n: usize) -> Vec<NodeRef<'a>>
+        where P: FnMut(&NodeRef<'a>) -> bool + 'a
+    {
+        self.select(predicate).take(n).collect()
+    }
+
     /// Find the first direct child of this node that matches the
     /// specified predicate.
     ///
@@ -72,13 +106,33 @@ impl<'a> NodeRef<'a> {
 
     /// Return an iterator over node's direct children.
     ///
-    /// Will be empty if the node does not (or can not) have children.
-    pub fn children(&self) -> impl Iterator<Item = NodeRef<'a>> + 'a {
-        let this = *self;
-        iter::successors(
-            this.for_some_node(this.first_child),
-            move |nref| this.for_some_node(nref.next_sibling)
-        )
+    /// Will be empty if the node does not (or can not) have children. The
+    /// iterator is double-ended, so `children().rev()` iterates from the last
+    /// child.
+    pub fn children(&self) -> Siblings<'a> {
+        Siblings::new(self.doc, self.first_child, self.last_child)
+    }
+
+    /// Return an iterator over all subsequent (right) siblings of this node,
+    /// in document order.
+    pub fn next_siblings(&self) -> Siblings<'a> {
+        let last = self.parent().and_then(|p| p.last_child);
+        Siblings::new(self.doc, self.next_sibling, last)
+    }
+
+    /// Return an iterator over all previous (left) siblings of this node, in
+    /// reverse document order, starting with the nearest.
+    pub fn prev_siblings(&self) -> iter::Rev<Siblings<'a>> {
+        let first = self.parent().and_then(|p| p.first_child);
+        Siblings::new(self.doc, first, self.prev_sibling).rev()
+    }
+
+    /// Return an iterator over all ancestors of this node, starting with any
+    /// parent and terminating at the document node.
+    ///
+    /// Unlike `node_and_ancestors`, this does not include self.
+    pub fn ancestors(&self) -> impl Iterator<Item = NodeRef<'a>> + 'a {
+        iter::successors(self.parent(), |n| n.parent())
     }
 
     /// Return an iterator over all descendants in tree order, starting with
@@ -99,6 +153,48 @@ impl<'a> NodeRef<'a> {
         )
     }
 
+    /// Return true if this is an element with the given local name.
+    ///
+    /// This is equivalent to `is_elem`, for use in DOM-like traversal code,
+    /// e.g. `node.is(t::DIV)`.
+    pub fn is<LN>(&self, lname: LN) -> bool
+        where LN: Into<LocalName>
+    {
+        self.is_elem(lname)
+    }
+
+    /// Return the namespace if this is an element, or None.
+    ///
+    /// Embedded SVG and MathML content is parsed into the
+    /// [`html::ns::SVG`](crate::html::ns::SVG) and
+    /// [`html::ns::MATHML`](crate::html::ns::MATHML) namespaces, so this
+    /// distinguishes, for example, an SVG `<a>` or `<title>` from the HTML
+    /// elements of the same local name.
+    pub fn namespace(&self) -> Option<&'a Namespace> {
+        self.doc[self.id].as_element().map(|e| &e.name.ns)
+    }
+
+    /// Return true if this is an element matching the specified selector.
+    ///
+    /// A small subset of CSS selector syntax is supported: a comma separated
+    /// list of compound selectors, each composed of an optional type (tag
+    /// name or `*`) followed by any number of `.class`, `#id`, `[attr]`,
+    /// `[attr=value]` or `[attr~=value]` conditions, as in `p.note[lang]`.
+    /// Tag and attribute names are matched ASCII case-insensitively.
+    /// Combinators, pseudo-classes and other unsupported or invalid syntax
+    /// never match.
+    pub fn matches(&self, selector: &str) -> bool {
+        self.as_element().map_or(false, |e| selector_matches(e, selector))
+    }
+
+    /// Return the first of this node and its ancestors, walking towards the
+    /// document node, that matches the specified predicate.
+    pub fn closest<P>(&self, predicate: P) -> Option<NodeRef<'a>>
+        where P: FnMut(&NodeRef<'a>) -> bool
+    {
+        iter::successors(Some(*self), |n| n.parent()).find(predicate)
+    }
+
     /// Return any parent node or None.
     pub fn parent(&self) -> Option<NodeRef<'a>> {
         self.for_some_node(self.parent)
@@ -124,6 +220,15 @@ impl<'a> NodeRef<'a> {
         self.doc.text(self.id)
     }
 
+    /// Return a reference to any user data value of type `T` for this node.
+    ///
+    /// See [`Document::set_user_data`].
+    pub fn user_data<T>(&self) -> Option<&'a T>
+        where T: Any
+    {
+        self.doc.user_data(self.id)
+    }
+
     /// Create a new independent `Document` from the ordered sub-tree
     /// referenced by self.
     pub fn deep_clone(&self) -> Document {
@@ -163,18 +268,141 @@ impl fmt::Debug for NodeRef<'_> {
     }
 }
 
-/// A selecting iterator returned by [`NodeRef::select`].
+// Return true if the element matches the selector, as per
+// `NodeRef::matches`.
+pub(crate) fn selector_matches(elm: &Element, selector: &str) -> bool {
+    selector.split(',').any(|s| compound_matches(elm, s.trim()))
+}
+
+// Return true if the element matches the compound selector.
+fn compound_matches(elm: &Element, sel: &str) -> bool {
+    let is_delim = |c: char| c == '.' || c == '#' || c == '[';
+
+    let end = sel.find(is_delim).unwrap_or_else(|| sel.len());
+    let (tag, mut rest) = sel.split_at(end);
+    if tag.is_empty() {
+        if rest.is_empty() {
+            return false;
+        }
+    } else if tag != "*" &&
+        !(is_ident(tag) && str::eq_ignore_ascii_case(&elm.name.local, tag))
+    {
+        return false;
+    }
+
+    while !rest.is_empty() {
+        let (kind, r) = rest.split_at(1);
+        if kind == "[" {
+            let close = match r.find(']') {
+                Some(i) => i,
+                None => return false,
+            };
+            if !attr_matches(elm, &r[..close]) {
+                return false;
+            }
+            rest = &r[close+1..];
+        } else {
+            let end = r.find(is_delim).unwrap_or_else(|| r.len());
+            let (name, r) = r.split_at(end);
+            if !is_ident(name) {
+                return false;
+            }
+            let found = if kind == "." {
+                elm.attr(a::CLASS).map_or(false, |v| {
+                    v.split_ascii_whitespace().any(|c| c == name)
+                })
+            } else {
+                elm.attr(a::ID).map_or(false, |v| &**v == name)
+            };
+            if !found {
+                return false;
+            }
+            rest = r;
+        }
+    }
+    true
+}
+
+// Return true if the element matches the attribute selector (without
+// brackets).
+fn attr_matches(elm: &Element, inner: &str) -> bool {
+    let (name, value, word) = if let Some(i) = inner.find('=') {
+        let value = Some(unquote(inner[i+1..].trim()));
+        let name = &inner[..i];
+        if name.ends_with('~') {
+            (name[..i-1].trim(), value, true)
+        } else {
+            (name.trim(), value, false)
+        }
+    } else {
+        (inner.trim(), None, false)
+    };
+    if !is_ident(name) {
+        return false;
+    }
+
+    elm.attrs
+        .iter()
+        .filter(|attr| str::eq_ignore_ascii_case(&attr.name.local, name))
+        .any(|attr| match value {
+            None => true,
+            Some(v) if word => {
+                attr.value.split_ascii_whitespace().any(|w| w == v)
+            }
+            Some(v) => &*attr.value == v,
+        })
+}
+
+fn unquote(v: &str) -> &str {
+    let b = v.as_bytes();
+    if b.len() >= 2 &&
+        (b[0] == b'"' || b[0] == b'\'') &&
+        b[b.len()-1] == b[0]
+    {
+        &v[1..v.len()-1]
+    } else {
+        v
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+    })
+}
+
+/// A selecting iterator returned by [`NodeRef::select`] and
+/// [`NodeRef::select_limited`].
 pub struct Selector<'a, P> {
     doc: &'a Document,
     next: NodeStack1,
     predicate: P,
+    visited: usize,
+    budget: usize,
 }
 
 impl<'a, P> Selector<'a, P> {
     fn new(doc: &'a Document, first: Option<NodeId>, predicate: P) -> Self {
         let mut next = NodeStack1::new();
         next.push_if(first);
-        Selector { doc, next, predicate }
+        Selector {
+            doc,
+            next,
+            predicate,
+            visited: 0,
+            budget: usize::max_value(),
+        }
+    }
+
+    /// Return the number of nodes visited (tested by the predicate) so far.
+    pub fn visited(&self) -> usize {
+        self.visited
+    }
+
+    /// Return true if iteration has ended early due to the `max_visited`
+    /// budget of [`NodeRef::select_limited`], with nodes left unvisited.
+    pub fn is_exhausted(&self) -> bool {
+        self.visited >= self.budget && !self.next.is_empty()
     }
 }
 
@@ -184,7 +412,9 @@ impl<'a, P> Iterator for Selector<'a, P>
     type Item = NodeRef<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(id) = self.next.pop() {
+        while self.visited < self.budget {
+            let id = self.next.pop()?;
+            self.visited += 1;
             let node = NodeRef::new(self.doc, id);
             if (self.predicate)(&node) {
                 self.next.push_if(node.next_sibling);
@@ -198,6 +428,60 @@ impl<'a, P> Iterator for Selector<'a, P>
     }
 }
 
+/// A double-ended iterator over a contiguous range of sibling nodes, returned
+/// by [`NodeRef::children`], [`NodeRef::next_siblings`] and
+/// [`NodeRef::prev_siblings`].
+pub struct Siblings<'a> {
+    doc: &'a Document,
+    front: Option<NodeId>,
+    back: Option<NodeId>,
+}
+
+impl<'a> Siblings<'a> {
+    fn new(doc: &'a Document, front: Option<NodeId>, back: Option<NodeId>)
+        -> Self
+    {
+        if front.is_some() && back.is_some() {
+            Siblings { doc, front, back }
+        } else {
+            Siblings { doc, front: None, back: None }
+        }
+    }
+
+    // Advance past the given (front or back) node, ending iteration if this
+    // was the last remaining.
+    fn advance(&mut self, id: NodeId, next: Option<NodeId>, front: bool) {
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else if front {
+            debug_assert_eq!(self.front, Some(id));
+            self.front = next;
+        } else {
+            debug_assert_eq!(self.back, Some(id));
+            self.back = next;
+        }
+    }
+}
+
+impl<'a> Iterator for Siblings<'a> {
+    type Item = NodeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.front?;
+        self.advance(id, self.doc[id].next_sibling, true);
+        Some(NodeRef::new(self.doc, id))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Siblings<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.back?;
+        self.advance(id, self.doc[id].prev_sibling, false);
+        Some(NodeRef::new(self.doc, id))
+    }
+}
+
 /// A depth-first iterator returned by [`NodeRef::descendants`].
 pub struct Descender<'a> {
     doc: &'a Document,
@@ -0,0 +1,144 @@
+//! Heading outline (table of contents) support.
+
+use std::collections::HashSet;
+use std::iter::Peekable;
+
+use crate::chars::replace_chars;
+use crate::dom::{
+    html::{a, t},
+    Document, LocalName, NodeId, StrTendril,
+};
+
+/// A heading of the [`Document::outline`], with nested subheadings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heading {
+    /// The `h1`..`h6` element.
+    pub id: NodeId,
+
+    /// The heading level, 1 through 6.
+    pub level: u8,
+
+    /// The heading text, with whitespace normalized.
+    pub text: StrTendril,
+
+    /// The `id` attribute of the heading, if any, usable as a URL fragment.
+    /// See [`Document::add_heading_anchors`].
+    pub anchor: Option<StrTendril>,
+
+    /// Nested headings of higher level, which follow this one and precede
+    /// the next heading of the same or lower level.
+    pub children: Vec<Heading>,
+}
+
+/// Heading outline methods.
+impl Document {
+    /// Return a nested outline of all `h1` through `h6` headings, in
+    /// document order.
+    ///
+    /// Each heading contains, as children, all following headings of higher
+    /// level, until the next heading of the same or lower level. Levels may
+    /// be skipped, e.g. an `h3` directly following an `h1` is a child of it.
+    pub fn outline(&self) -> Vec<Heading> {
+        let mut flat = self.nodes()
+            .filter_map(|id| {
+                let level = heading_level(&self[id].as_element()?.name.local)?;
+                let mut text = self.text(id).unwrap_or_default();
+                replace_chars(&mut text, true, true, true, true);
+                let anchor = self[id].attr(a::ID)
+                    .filter(|v| !v.trim().is_empty())
+                    .cloned();
+                Some(Heading {
+                    id, level, text, anchor, children: Vec::new()
+                })
+            })
+            .peekable();
+        nest(&mut flat, 0)
+    }
+
+    /// Add an `id` attribute, derived from the heading text, to each `h1`
+    /// through `h6` heading lacking one, and return the number added.
+    ///
+    /// Generated ids are lowercase, with other than alphanumeric characters
+    /// replaced by `-`, and made unique in the document by a numeric suffix,
+    /// if needed. This allows linking to all headings of the
+    /// [`Document::outline`].
+    pub fn add_heading_anchors(&mut self) -> usize {
+        let mut used: HashSet<String> = self.nodes()
+            .filter_map(|id| self[id].attr(a::ID).map(|v| v.to_string()))
+            .collect();
+        let headings: Vec<NodeId> = self.nodes()
+            .filter(|&id| {
+                self[id].as_element().map_or(false, |e| {
+                    heading_level(&e.name.local).is_some() &&
+                        e.attr(a::ID).map_or(true, |v| v.trim().is_empty())
+                })
+            })
+            .collect();
+
+        for &id in &headings {
+            let base = slug(&self.text(id).unwrap_or_default());
+            let mut anchor = base.clone();
+            let mut n = 1;
+            while used.contains(&anchor) {
+                anchor = format!("{}-{}", base, n);
+                n += 1;
+            }
+            used.insert(anchor.clone());
+            self[id].as_element_mut().unwrap().set_attr(a::ID, anchor);
+        }
+        headings.len()
+    }
+}
+
+// Consume and return headings of higher level than the parent, with nested
+// children.
+fn nest<I>(flat: &mut Peekable<I>, parent_level: u8) -> Vec<Heading>
+    where I: Iterator<Item = Heading>
+{
+    let mut out = Vec::new();
+    while flat.peek().map_or(false, |h| h.level > parent_level) {
+        let mut heading = flat.next().unwrap();
+        heading.children = nest(flat, heading.level);
+        out.push(heading);
+    }
+    out
+}
+
+fn heading_level(name: &LocalName) -> Option<u8> {
+    if *name == t::H1 {
+        Some(1)
+    } else if *name == t::H2 {
+        Some(2)
+    } else if *name == t::H3 {
+        Some(3)
+    } else if *name == t::H4 {
+        Some(4)
+    } else if *name == t::H5 {
+        Some(5)
+    } else if *name == t::H6 {
+        Some(6)
+    } else {
+        None
+    }
+}
+
+// Return a URL fragment friendly identifier for the given text.
+fn slug(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut dash = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            if dash && !out.is_empty() {
+                out.push('-');
+            }
+            dash = false;
+            out.push(c);
+        } else {
+            dash = true;
+        }
+    }
+    if out.is_empty() {
+        out.push_str("section");
+    }
+    out
+}
@@ -0,0 +1,140 @@
+//! Pagination detection and multi-page article stitching.
+//!
+//! Articles are often split across multiple pages, linked via `rel="next"`
+//! or "Next page" style links. [`Document::next_page_href`] detects such
+//! links, and [`stitch`] follows them, via a caller provided [`PageSource`]
+//! for fetching and parsing, merging the main content of each subsequent
+//! page into the first.
+
+use std::collections::HashSet;
+
+use crate::chars::replace_chars;
+use crate::dom::{
+    html::{a, t},
+    Document, NodeId, NodeRef, StrTendril,
+};
+
+/// A source of subsequent pages, for [`stitch`].
+pub trait PageSource {
+    /// The error type for failed fetches.
+    type Error;
+
+    /// Fetch and parse the page for the given `href`, as found on the prior
+    /// page. The `href` may be relative, and should be resolved by the
+    /// implementation against the URL of the prior page.
+    fn fetch(&mut self, href: &str) -> Result<Document, Self::Error>;
+}
+
+/// Pagination methods.
+impl Document {
+    /// Return the `href` of a link to the next page, if found.
+    ///
+    /// In order of preference, this is the first `<link rel="next">`, the
+    /// first `<a rel="next">`, or the first `<a>` element whose text or
+    /// `aria-label`, ignoring case and any arrow characters (e.g. `»`, `›`),
+    /// is "next" or "next page". Empty and fragment-only (`#...`) hrefs are
+    /// ignored.
+    pub fn next_page_href(&self) -> Option<StrTendril> {
+        let root = self.document_node_ref();
+        let links = || {
+            root.descendants()
+                .filter(|n| n.is_elem(t::LINK) || n.is_elem(t::A))
+                .filter_map(|n| n.attr(a::HREF).map(|h| (n, h)))
+                .filter(|(_, h)| {
+                    let h = h.trim();
+                    !h.is_empty() && !h.starts_with('#')
+                })
+        };
+        links()
+            .find(|(n, _)| n.is_elem(t::LINK) && has_rel_next(*n))
+            .or_else(|| links().find(|(n, _)| {
+                n.is_elem(t::A) && has_rel_next(*n)
+            }))
+            .or_else(|| links().find(|(n, _)| {
+                n.is_elem(t::A) && is_next_label(*n)
+            }))
+            .map(|(_, h)| StrTendril::from_slice(h.trim()))
+    }
+
+    /// Return the main content element: the first `<article>`, `<main>` or
+    /// `role="main"` element, or otherwise `<body>`, or the document node.
+    pub fn content_root(&self) -> NodeId {
+        let root = self.document_node_ref();
+        root.find(|n| n.is_elem(t::ARTICLE))
+            .or_else(|| root.find(|n| n.is_elem(t::MAIN)))
+            .or_else(|| root.find(|n| n.matches("[role=main]")))
+            .or_else(|| root.find(|n| n.is_elem(t::BODY)))
+            .map_or(Document::DOCUMENT_NODE_ID, |n| n.id())
+    }
+}
+
+/// Follow next page links from the first page, up to `max_pages` in total,
+/// appending the children of the [`Document::content_root`] of each
+/// subsequent page to that of the first, and return the merged `Document`.
+///
+/// Pages are fetched via the given source, and next page links found via
+/// [`Document::next_page_href`]. An `href` already fetched (or that of the
+/// first page, if given) is not fetched again. Stitching stops, returning
+/// the pages merged so far, when no further link is found or on the first
+/// fetch error, which is returned along with the `Document`.
+pub fn stitch<S>(
+    first: Document,
+    first_href: Option<&str>,
+    source: &mut S,
+    max_pages: usize)
+    -> (Document, Option<S::Error>)
+    where S: PageSource + ?Sized
+{
+    let mut doc = first;
+    let target = doc.content_root();
+    let mut seen = HashSet::new();
+    if let Some(h) = first_href {
+        seen.insert(h.to_owned());
+    }
+    let mut next = doc.next_page_href();
+    let mut pages = 1;
+    while let Some(href) = next.take() {
+        if pages >= max_pages || !seen.insert(href.to_string()) {
+            break;
+        }
+        let mut page = match source.fetch(&href) {
+            Ok(page) => page,
+            Err(e) => return (doc, Some(e)),
+        };
+        pages += 1;
+        next = page.next_page_href();
+
+        let content = page.content_root();
+        let part = if content == Document::DOCUMENT_NODE_ID {
+            page
+        } else {
+            page.detach(content)
+        };
+        let root = part.root_element();
+        let map = doc.adopt(part, target);
+        if let Some(r) = root.and_then(|r| map.get(r)) {
+            if content != Document::DOCUMENT_NODE_ID {
+                doc.fold(r);
+            }
+        }
+    }
+    (doc, None)
+}
+
+fn has_rel_next(n: NodeRef<'_>) -> bool {
+    n.attr(a::REL).map_or(false, |rel| {
+        rel.split_ascii_whitespace().any(|r| r.eq_ignore_ascii_case("next"))
+    })
+}
+
+fn is_next_label(n: NodeRef<'_>) -> bool {
+    let label = n.attr("aria-label").cloned().or_else(|| n.text());
+    label.map_or(false, |l| {
+        let l = l.trim_matches(|c: char| {
+            c.is_whitespace() || "»›>→".contains(c)
+        });
+        let mut l = StrTendril::from_slice(l);
+        replace_chars(&mut l, true, true, true, true);
+        l.eq_ignore_ascii_case("next") || l.eq_ignore_ascii_case("next page")
+    })
+}
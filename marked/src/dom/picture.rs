@@ -0,0 +1,185 @@
+use crate::dom::{
+    html::{a, t},
+    Document, Element, NodeId, NodeRef, SrcsetCandidate, SrcsetDescriptor,
+};
+
+/// Image `type` values accepted from `<picture>` `<source>` elements.
+const IMAGE_TYPES: &[&str] = &[
+    "image/gif", "image/jpeg", "image/png", "image/svg+xml", "image/webp",
+];
+
+/// Responsive image methods.
+impl Document {
+    /// Rewrite responsive images to a single `<img src>`, selecting the best
+    /// candidate for the given target display width (in CSS pixels) and
+    /// pixel density (DPR), and return the number of images rewritten.
+    ///
+    /// For `<img>` elements with a `srcset` attribute, the `src` is replaced
+    /// with the selected candidate and the `srcset` and `sizes` attributes
+    /// are removed. Width descriptors are interpreted relative to the target
+    /// width, as `sizes` is not evaluated. The selected candidate is that
+    /// with the smallest density at least that given, or otherwise the
+    /// largest density.
+    ///
+    /// A `<picture>` element is replaced by its `<img>`, selecting from the
+    /// first `<source>` element with a matching `media` query and a common
+    /// image `type` (if given). Only simple `min-width` and `max-width` media
+    /// queries, in `px`, are evaluated against the target width. Sources with
+    /// other media queries are skipped. If no source matches, the `<img>`
+    /// `srcset` or `src` is used.
+    ///
+    /// Replaced `<picture>` and `<source>` elements are unlinked, leaving
+    /// them as unreachable nodes until [`Document::compact`].
+    pub fn resolve_images(&mut self, width: u32, density: f32) -> usize {
+        let ids: Vec<NodeId> = self.nodes()
+            .filter(|&id| self[id].is_elem(t::IMG))
+            .collect();
+        let mut count = 0;
+        for id in ids {
+            let picture = NodeRef::new(self, id)
+                .parent()
+                .filter(|p| p.is_elem(t::PICTURE))
+                .map(|p| p.id());
+            let mut url = None;
+            if let Some(picture) = picture {
+                url = self.children(picture)
+                    .filter_map(|c| self[c].as_element())
+                    .filter(|e| {
+                        e.is_elem(t::SOURCE) && source_matches(e, width)
+                    })
+                    .find_map(|e| select(&e.srcset(), width, density))
+                    .map(str::to_owned);
+            }
+            let elm = self[id].as_element().unwrap();
+            if url.is_none() {
+                url = select(&img_candidates(elm), width, density)
+                    .map(str::to_owned);
+            }
+            if picture.is_none() && elm.attr(local_name!("srcset")).is_none() {
+                continue;
+            }
+
+            let elm = self[id].as_element_mut().unwrap();
+            if let Some(url) = url {
+                elm.set_attr(a::SRC, url);
+            }
+            elm.remove_attr(local_name!("srcset"));
+            elm.remove_attr(local_name!("sizes"));
+            if let Some(picture) = picture {
+                let sources: Vec<NodeId> = self.children(picture)
+                    .filter(|&c| self[c].is_elem(t::SOURCE))
+                    .collect();
+                for source in sources {
+                    self.unlink(source);
+                }
+                self.fold(picture);
+            }
+            count += 1;
+        }
+        count
+    }
+}
+
+// Return image candidates of an img, including the src as an implied 1x
+// candidate, if applicable.
+fn img_candidates(elm: &Element) -> Vec<SrcsetCandidate<'_>> {
+    let mut cands = elm.srcset();
+    let has_width = cands.iter().any(|c| {
+        if let Some(SrcsetDescriptor::Width(_)) = c.descriptor {
+            true
+        } else {
+            false
+        }
+    });
+    let has_1x = cands.iter().any(|c| match c.descriptor {
+        None => true,
+        Some(SrcsetDescriptor::Density(d)) => (d - 1.0).abs() < 1e-6,
+        Some(SrcsetDescriptor::Width(_)) => false,
+    });
+    if let Some(src) = elm.attr(a::SRC) {
+        let src = src.trim();
+        if !src.is_empty() && !has_width && !has_1x {
+            cands.push(SrcsetCandidate { url: src, descriptor: None });
+        }
+    }
+    cands
+}
+
+// Return the effective pixel density of a candidate for a target width.
+fn density_of(cand: &SrcsetCandidate<'_>, width: u32) -> Option<f32> {
+    match cand.descriptor {
+        None => Some(1.0),
+        Some(SrcsetDescriptor::Density(d)) => Some(d),
+        Some(SrcsetDescriptor::Width(w)) if width > 0 => {
+            Some(w as f32 / width as f32)
+        }
+        Some(SrcsetDescriptor::Width(_)) => None,
+    }
+}
+
+// Select the best candidate URL for the given width and density.
+fn select<'a>(cands: &[SrcsetCandidate<'a>], width: u32, density: f32)
+    -> Option<&'a str>
+{
+    let mut best: Option<(f32, &'a str)> = None;
+    for cand in cands {
+        let d = match density_of(cand, width) {
+            Some(d) => d,
+            None => continue,
+        };
+        best = match best {
+            None => Some((d, cand.url)),
+            Some((bd, _)) if bd < density && d > bd => Some((d, cand.url)),
+            Some((bd, _)) if d >= density && d < bd => Some((d, cand.url)),
+            keep => keep,
+        };
+    }
+    best.map(|(_, url)| url)
+}
+
+// Return true if a picture source's media query and type are acceptable.
+fn source_matches(elm: &Element, width: u32) -> bool {
+    if let Some(mtype) = elm.attr(a::TYPE) {
+        let mtype = mtype.trim().to_ascii_lowercase();
+        if !mtype.is_empty() && !IMAGE_TYPES.contains(&mtype.as_str()) {
+            return false;
+        }
+    }
+    match elm.attr(a::MEDIA) {
+        Some(media) => media_matches(media, width),
+        None => true,
+    }
+}
+
+// Evaluate a simple media query of `and` separated min-width and max-width
+// features, in px. Any other query is treated as not matching.
+fn media_matches(media: &str, width: u32) -> bool {
+    let media = media.trim().to_ascii_lowercase();
+    if media.is_empty() || media == "all" || media == "screen" {
+        return true;
+    }
+    media.split(" and ").all(|feature| {
+        let feature = feature.trim();
+        if feature == "all" || feature == "screen" {
+            return true;
+        }
+        let inner = if feature.starts_with('(') && feature.ends_with(')') {
+            &feature[1..feature.len() - 1]
+        } else {
+            return false;
+        };
+        let mut parts = inner.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        let px = if value.ends_with("px") {
+            value[..value.len() - 2].trim().parse::<f32>().ok()
+        } else {
+            None
+        };
+        match (name, px) {
+            ("min-width", Some(px)) => width as f32 >= px,
+            ("max-width", Some(px)) => width as f32 <= px,
+            _ => false,
+        }
+    })
+}
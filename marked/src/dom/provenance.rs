@@ -0,0 +1,167 @@
+//! Provenance annotation of elements via `data-marked-*` attributes.
+//!
+//! When debugging an extraction pipeline, it is useful to see _why_ each
+//! element was retained. A [`Provenance`] can be stamped on elements as
+//! attributes, so that the serialized output may be inspected visually in a
+//! browser (e.g. via developer tools). Before production output, remove these
+//! with the [`strip_provenance`](crate::filter::strip_provenance) filter.
+//!
+//! Alternatively, `Provenance` may be set as user data of elements (see
+//! [`Document::set_user_data`]) and, along with any
+//! [`Boilerplate`](crate::boilerplate::Boilerplate) markers, written as
+//! attributes only on serialization, via
+//! [`SerializeOptions::annotations`](crate::SerializeOptions::annotations).
+
+use std::fmt::Write;
+
+use crate::dom::{
+    boilerplate::Boilerplate,
+    Document, Element, LocalName, NodeId, NodeRef, StrTendril,
+};
+
+/// Common prefix of all provenance attribute names.
+pub const ATTR_PREFIX: &str = "data-marked-";
+
+/// Attribute name for [`Provenance::rule`].
+pub const ATTR_RULE: &str = "data-marked-rule";
+
+/// Attribute name for [`Provenance::score`].
+pub const ATTR_SCORE: &str = "data-marked-score";
+
+/// Attribute name for [`Provenance::span`].
+pub const ATTR_SPAN: &str = "data-marked-span";
+
+/// Attribute name for the [`Boilerplate`] frequency, as written by
+/// [`annotation_attrs`].
+pub const ATTR_BOILERPLATE: &str = "data-marked-boilerplate";
+
+/// Attribute name for the language of an annotated element, as written by
+/// [`annotation_attrs`].
+pub const ATTR_LANG: &str = "data-marked-lang";
+
+/// Debugging provenance details for an element.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    /// Name of the rule or filter which retained the element.
+    pub rule: Option<StrTendril>,
+
+    /// Any score assigned by an extraction heuristic.
+    pub score: Option<f32>,
+
+    /// Source byte span (start, end), if known.
+    pub span: Option<(u32, u32)>,
+}
+
+impl Provenance {
+    /// Construct a new `Provenance` with the given rule name.
+    pub fn rule<T>(rule: T) -> Provenance
+        where T: Into<StrTendril>
+    {
+        Provenance { rule: Some(rule.into()), .. Provenance::default() }
+    }
+
+    /// Set score, builder style.
+    pub fn with_score(mut self, score: f32) -> Provenance {
+        self.score = Some(score);
+        self
+    }
+
+    /// Set source span, builder style.
+    pub fn with_span(mut self, start: u32, end: u32) -> Provenance {
+        self.span = Some((start, end));
+        self
+    }
+
+    /// Stamp the set values as attributes of the given element, replacing
+    /// any prior provenance attributes.
+    pub fn apply(&self, elm: &mut Element) {
+        for (name, value) in self.attrs() {
+            elm.set_attr(name, value);
+        }
+    }
+
+    // Return attribute names and values for the set values.
+    fn attrs(&self) -> Vec<(&'static str, StrTendril)> {
+        let mut attrs = Vec::new();
+        if let Some(rule) = &self.rule {
+            attrs.push((ATTR_RULE, rule.clone()));
+        }
+        if let Some(score) = self.score {
+            let mut v = StrTendril::new();
+            write!(v, "{}", score).unwrap();
+            attrs.push((ATTR_SCORE, v));
+        }
+        if let Some((start, end)) = self.span {
+            let mut v = StrTendril::new();
+            write!(v, "{}-{}", start, end).unwrap();
+            attrs.push((ATTR_SPAN, v));
+        }
+        attrs
+    }
+
+    /// Read any provenance attributes from the given element.
+    ///
+    /// Returns `None` if no such attributes are found. Unparsable values are
+    /// ignored.
+    pub fn read(elm: &Element) -> Option<Provenance> {
+        let rule = elm.attr(ATTR_RULE).cloned();
+        let score = elm.attr(ATTR_SCORE)
+            .and_then(|v| v.as_ref().trim().parse().ok());
+        let span = elm.attr(ATTR_SPAN).and_then(|v| {
+            let mut it = v.as_ref().trim().splitn(2, '-');
+            let start = it.next()?.parse().ok()?;
+            let end = it.next()?.parse().ok()?;
+            Some((start, end))
+        });
+        if rule.is_none() && score.is_none() && span.is_none() {
+            None
+        } else {
+            Some(Provenance { rule, score, span })
+        }
+    }
+}
+
+/// Return provenance attribute names and values for the annotation user data
+/// of the node: any [`Provenance`] and [`Boilerplate`] values, and if either
+/// is found, the language of the node (per [`NodeRef::language`]).
+///
+/// This is used by
+/// [`SerializeOptions::annotations`](crate::SerializeOptions::annotations).
+pub fn annotation_attrs(node: NodeRef<'_>) -> Vec<(&'static str, StrTendril)>
+{
+    let mut attrs = node.user_data::<Provenance>()
+        .map(Provenance::attrs)
+        .unwrap_or_default();
+    if let Some(bp) = node.user_data::<Boilerplate>() {
+        let mut v = StrTendril::new();
+        write!(v, "{}", bp.frequency).unwrap();
+        attrs.push((ATTR_BOILERPLATE, v));
+    }
+    if !attrs.is_empty() {
+        if let Some(lang) = node.language() {
+            attrs.push((ATTR_LANG, lang));
+        }
+    }
+    attrs
+}
+
+/// Return true if the given attribute name is a provenance attribute.
+pub fn is_provenance_attr(name: &LocalName) -> bool {
+    name.starts_with(ATTR_PREFIX)
+}
+
+/// Provenance annotation methods.
+impl Document {
+    /// Stamp the given provenance as attributes on the specified node, if it
+    /// is an element.
+    ///
+    /// Returns true if applied.
+    pub fn annotate(&mut self, id: NodeId, prov: &Provenance) -> bool {
+        if let Some(elm) = self[id].as_element_mut() {
+            prov.apply(elm);
+            true
+        } else {
+            false
+        }
+    }
+}
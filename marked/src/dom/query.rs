@@ -0,0 +1,101 @@
+//! Caching of selector and XPath query results.
+//!
+//! See [`QueryCache`].
+
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::dom::{
+    xpath::{Value, XPath, XPathError},
+    Document, NodeRef,
+};
+
+/// A cache of query results for a `Document`, for repeated evaluation of
+/// the same selectors or XPath expressions, as by rule engines.
+///
+/// Results are computed on first use and retained by query string. The
+/// cache borrows the document, so it must be dropped before the document
+/// can be mutated, and can never return results which are stale. After
+/// mutation, obtain a new cache.
+pub struct QueryCache<'a> {
+    doc: &'a Document,
+    elements: Option<Vec<NodeRef<'a>>>,
+    selectors: HashMap<String, Vec<NodeRef<'a>>>,
+    xpaths: HashMap<String, Value<'a>>,
+}
+
+impl<'a> QueryCache<'a> {
+    /// Construct a new, empty cache for the document.
+    pub fn new(doc: &'a Document) -> QueryCache<'a> {
+        QueryCache {
+            doc,
+            elements: None,
+            selectors: HashMap::new(),
+            xpaths: HashMap::new(),
+        }
+    }
+
+    /// Return all elements of the document matching the selector, as per
+    /// [`NodeRef::matches`], in document order.
+    pub fn select(&mut self, selector: &str) -> &[NodeRef<'a>] {
+        let QueryCache { doc, elements, selectors, .. } = self;
+        let found = selectors
+            .entry(selector.to_owned())
+            .or_insert_with(|| {
+                elements
+                    .get_or_insert_with(|| {
+                        doc.document_node_ref()
+                            .descendants()
+                            .filter(|n| n.as_element().is_some())
+                            .collect()
+                    })
+                    .iter()
+                    .filter(|n| n.matches(selector))
+                    .cloned()
+                    .collect()
+            });
+        found.as_slice()
+    }
+
+    /// Return the descendants of node matching the selector, in document
+    /// order, by filtering the cached document wide results.
+    pub fn select_within(&mut self, node: NodeRef<'a>, selector: &str)
+        -> Vec<NodeRef<'a>>
+    {
+        let id = node.id();
+        self.select(selector)
+            .iter()
+            .filter(|n| n.ancestors().any(|a| a.id() == id))
+            .cloned()
+            .collect()
+    }
+
+    /// Return the result of the XPath expression evaluated with the
+    /// document node as context node.
+    ///
+    /// Errors are not cached.
+    pub fn xpath(&mut self, expr: &str) -> Result<&Value<'a>, XPathError> {
+        let doc = self.doc;
+        let value = match self.xpaths.entry(expr.to_owned()) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let xpath = XPath::parse(expr)?;
+                e.insert(xpath.evaluate(doc.document_node_ref())?)
+            }
+        };
+        Ok(&*value)
+    }
+
+    /// Remove all cached results.
+    pub fn clear(&mut self) {
+        self.selectors.clear();
+        self.xpaths.clear();
+    }
+}
+
+/// Query cache methods.
+impl Document {
+    /// Return a new [`QueryCache`] for this document.
+    pub fn query_cache(&self) -> QueryCache<'_> {
+        QueryCache::new(self)
+    }
+}
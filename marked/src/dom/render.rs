@@ -0,0 +1,344 @@
+//! Plain text rendering, in the style of console browsers such as _lynx_.
+//!
+//! See [`plain_text`].
+
+use crate::dom::{
+    html::{a, t},
+    Element, NodeData, NodeId, NodeRef,
+};
+
+/// Render the node and its descendants as wrapped, lightly formatted plain
+/// text, for terminal readers or the text part of e-mail.
+///
+/// Text is wrapped at word boundaries to the given `width` in characters,
+/// or not at all if `width` is 0. Words longer than the width are not
+/// broken. Blocks such as `<p>` are separated by blank lines, headings are
+/// underlined (`=` for `<h1>`, otherwise `-`), list items are bulleted with
+/// `*` or numbered, `<blockquote>` lines are prefixed with `>`, `<pre>`
+/// content is preserved as is, and table cells are separated by `|`. Links
+/// are followed by a numbered reference, e.g. `[1]`, with the list of
+/// `href`s appended as footnotes. Images are rendered as their `alt` text,
+/// in brackets. Banned and metadata elements (per
+/// [`TagMeta`](crate::html::TagMeta)) such as `<script>` and `<head>` are
+/// excluded. Each line, including the last, ends with `\n`.
+pub fn plain_text(node: NodeRef<'_>, width: usize) -> String {
+    let doc = node.doc();
+    let mut r = Renderer::new(width);
+    let mut stack = vec![Step::Enter(node.id())];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Enter(id) => {
+                let node = NodeRef::new(doc, id);
+                match &node.data {
+                    NodeData::Elem(elm) => {
+                        if !r.enter(node, elm) {
+                            continue;
+                        }
+                        stack.push(Step::Exit(id));
+                    }
+                    NodeData::Text(t) => {
+                        r.text(t);
+                        continue;
+                    }
+                    NodeData::Document => {}
+                    _ => continue,
+                }
+                for child in node.children().rev() {
+                    stack.push(Step::Enter(child.id()));
+                }
+            }
+            Step::Exit(id) => {
+                if let Some(elm) = doc[id].as_element() {
+                    r.exit(elm);
+                }
+            }
+        }
+    }
+    r.finish()
+}
+
+enum Step {
+    Enter(NodeId),
+    Exit(NodeId),
+}
+
+// A line prefix of an enclosing block: for the first line, and subsequent
+// lines.
+struct Indent {
+    first: String,
+    rest: String,
+    used: bool,
+}
+
+struct Renderer {
+    width: usize,
+    lines: Vec<String>,
+    // Pending inline text, with whitespace collapsed (except in pre).
+    inline: String,
+    space: bool,
+    blank: bool,
+    indents: Vec<Indent>,
+    // Per open list, the next number for `<ol>`, or None for `<ul>`.
+    lists: Vec<Option<u32>>,
+    pre: usize,
+    links: Vec<String>,
+}
+
+impl Renderer {
+    fn new(width: usize) -> Renderer {
+        Renderer {
+            width,
+            lines: Vec::new(),
+            inline: String::new(),
+            space: false,
+            blank: false,
+            indents: Vec::new(),
+            lists: Vec::new(),
+            pre: 0,
+            links: Vec::new(),
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre > 0 {
+            self.inline.push_str(text);
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+            } else {
+                if self.space && !self.inline.is_empty() {
+                    self.inline.push(' ');
+                }
+                self.space = false;
+                self.inline.push(c);
+            }
+        }
+    }
+
+    // Handle element start, returning false if its content is excluded.
+    fn enter(&mut self, node: NodeRef<'_>, elm: &Element) -> bool {
+        if let Some(meta) = elm.html_tag_meta() {
+            if meta.is_banned() || meta.is_meta() {
+                return false;
+            }
+        }
+        let name = &elm.name.local;
+        if *name == t::BR {
+            if self.inline.is_empty() {
+                self.line("");
+            } else {
+                self.flush();
+            }
+        } else if *name == t::HR {
+            self.block(true);
+            let rule = "-".repeat(self.available().min(72).max(3));
+            self.line(&rule);
+            self.blank = true;
+        } else if *name == t::IMG {
+            if let Some(alt) = elm.attr(a::ALT) {
+                if !alt.trim().is_empty() {
+                    self.text(" [");
+                    self.text(alt);
+                    self.text("] ");
+                }
+            }
+        } else if *name == t::TD || *name == t::TH {
+            let prior = node.prev_siblings()
+                .any(|n| n.is_elem(t::TD) || n.is_elem(t::TH));
+            if prior {
+                self.space = false;
+                self.inline.push_str(" | ");
+            }
+        } else if *name == t::UL || *name == t::OL {
+            self.block(self.lists.is_empty());
+            self.lists.push(if *name == t::OL {
+                Some(elm.attr("start")
+                     .and_then(|s| s.trim().parse().ok())
+                     .unwrap_or(1))
+            } else {
+                None
+            });
+        } else if *name == t::LI {
+            self.block(false);
+            let bullet = match self.lists.last_mut() {
+                Some(Some(n)) => {
+                    *n += 1;
+                    format!("{}. ", *n - 1)
+                }
+                _ => "* ".to_owned(),
+            };
+            self.indent(bullet, None);
+        } else if *name == t::BLOCKQUOTE {
+            self.block(true);
+            self.indent("> ".to_owned(), Some("> ".to_owned()));
+        } else if *name == t::DD {
+            self.block(false);
+            self.indent("    ".to_owned(), None);
+        } else if *name == t::PRE {
+            self.block(true);
+            self.pre += 1;
+        } else if is_spaced(elm) || underline(elm).is_some() {
+            self.block(true);
+        } else if elm.html_tag_meta().map_or(false, |m| m.is_block()) {
+            self.block(false);
+        }
+        true
+    }
+
+    fn exit(&mut self, elm: &Element) {
+        let name = &elm.name.local;
+        if *name == t::A {
+            if let Some(href) = elm.attr(a::HREF) {
+                let href = href.trim();
+                if !href.is_empty() && !href.starts_with('#') {
+                    self.links.push(href.to_owned());
+                    self.space = false;
+                    self.inline.push_str(&format!("[{}]", self.links.len()));
+                }
+            }
+        } else if let Some(c) = underline(elm) {
+            let len = self.inline.chars().count().min(self.available());
+            self.flush();
+            let rule: String = std::iter::repeat(c).take(len).collect();
+            self.line(&rule);
+            self.blank = true;
+        } else if *name == t::UL || *name == t::OL {
+            self.lists.pop();
+            self.block(self.lists.is_empty());
+        } else if *name == t::LI || *name == t::DD {
+            self.flush();
+            self.indents.pop();
+        } else if *name == t::BLOCKQUOTE {
+            self.flush();
+            self.indents.pop();
+            self.blank = true;
+        } else if *name == t::PRE {
+            self.flush();
+            self.pre -= 1;
+            self.blank = true;
+        } else if is_spaced(elm) {
+            self.block(true);
+        } else if elm.html_tag_meta().map_or(false, |m| m.is_block()) {
+            self.block(false);
+        }
+    }
+
+    // Start or end a block, flushing any inline text, with a blank line
+    // to precede any subsequent text if specified.
+    fn block(&mut self, blank: bool) {
+        self.flush();
+        self.blank |= blank;
+    }
+
+    fn indent(&mut self, first: String, rest: Option<String>) {
+        let rest = rest.unwrap_or_else(|| " ".repeat(first.chars().count()));
+        self.indents.push(Indent { first, rest, used: false });
+    }
+
+    // Width available for text, after the current prefix.
+    fn available(&self) -> usize {
+        if self.width == 0 {
+            return usize::max_value();
+        }
+        let prefix: usize = self.indents.iter()
+            .map(|i| i.first.chars().count())
+            .sum();
+        self.width.saturating_sub(prefix).max(1)
+    }
+
+    // Write pending inline text as wrapped lines.
+    fn flush(&mut self) {
+        let inline = std::mem::replace(&mut self.inline, String::new());
+        self.space = false;
+        if self.pre > 0 {
+            let text = inline.trim_end_matches('\n');
+            if !text.is_empty() {
+                for l in text.split('\n') {
+                    self.line(l.trim_end());
+                }
+            }
+            return;
+        }
+        let avail = self.available();
+        let mut line = String::new();
+        let mut len = 0;
+        for word in inline.split(' ').filter(|w| !w.is_empty()) {
+            let wlen = word.chars().count();
+            if len > 0 && len + 1 + wlen > avail {
+                self.line(&line);
+                line.clear();
+                len = 0;
+            }
+            if len > 0 {
+                line.push(' ');
+                len += 1;
+            }
+            line.push_str(word);
+            len += wlen;
+        }
+        if len > 0 {
+            self.line(&line);
+        }
+    }
+
+    // Write a line with the current prefix, preceded by any pending blank
+    // line.
+    fn line(&mut self, text: &str) {
+        if self.blank && !self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.blank = false;
+        let mut l = String::new();
+        for i in &mut self.indents {
+            l.push_str(if i.used { &i.rest } else { &i.first });
+            i.used = true;
+        }
+        l.push_str(text);
+        let end = l.trim_end().len();
+        l.truncate(end);
+        self.lines.push(l);
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.indents.clear();
+        if !self.links.is_empty() {
+            self.blank = true;
+            self.line("References:");
+            self.blank = true;
+            let links = std::mem::replace(&mut self.links, Vec::new());
+            for (i, href) in links.iter().enumerate() {
+                self.line(&format!("[{}] {}", i + 1, href));
+            }
+        }
+        let mut out = String::new();
+        for l in &self.lines {
+            out.push_str(l);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// Return true for block elements to be separated by blank lines.
+fn is_spaced(elm: &Element) -> bool {
+    let name = &elm.name.local;
+    *name == t::P || *name == t::TABLE || *name == t::DL ||
+        *name == t::FIGURE
+}
+
+// Return the underline char for heading elements.
+fn underline(elm: &Element) -> Option<char> {
+    let name = &elm.name.local;
+    if *name == t::H1 {
+        Some('=')
+    } else if *name == t::H2 || *name == t::H3 || *name == t::H4 ||
+        *name == t::H5 || *name == t::H6
+    {
+        Some('-')
+    } else {
+        None
+    }
+}
@@ -0,0 +1,255 @@
+//! Robots directives, from `<meta name="robots">` elements and
+//! `X-Robots-Tag` response headers, and link `rel` classification.
+
+use crate::dom::{
+    html::{a, t},
+    Document, NodeRef,
+};
+
+/// Crawler and indexer directives, as returned by
+/// [`RobotsDirectives::extract`].
+///
+/// Where multiple sources give directives, the most restrictive applies.
+/// Unknown directives are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RobotsDirectives {
+    /// Do not index the page (`noindex` or `none`).
+    pub noindex: bool,
+
+    /// Do not follow links of the page (`nofollow` or `none`).
+    pub nofollow: bool,
+
+    /// Do not show a cached copy (`noarchive`, or `nocache`).
+    pub noarchive: bool,
+
+    /// Do not show a text snippet or video preview (`nosnippet`).
+    pub nosnippet: bool,
+
+    /// Do not index images of the page (`noimageindex`).
+    pub noimageindex: bool,
+
+    /// Do not offer translation (`notranslate`).
+    pub notranslate: bool,
+
+    /// Maximum snippet length in characters (`max-snippet`), where -1 is
+    /// no limit.
+    pub max_snippet: Option<i32>,
+
+    /// Maximum image preview size (`max-image-preview`): `none`,
+    /// `standard` or `large`.
+    pub max_image_preview: Option<ImagePreview>,
+
+    /// Maximum video preview length in seconds (`max-video-preview`), where
+    /// -1 is no limit.
+    pub max_video_preview: Option<i32>,
+
+    /// Date after which the page should not be shown
+    /// (`unavailable_after`), as found.
+    pub unavailable_after: Option<String>,
+
+    _priv: ()
+}
+
+/// Values of the `max-image-preview` directive, ordered from most to least
+/// restrictive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImagePreview {
+    /// No image preview.
+    None,
+
+    /// A default size preview.
+    Standard,
+
+    /// A larger preview, up to the viewport width.
+    Large,
+}
+
+impl RobotsDirectives {
+    /// Extract directives from `<meta>` elements of the `Document` with
+    /// name `robots`, or (case-insensitively) the given crawler `agent`
+    /// name, e.g. `googlebot`, if any.
+    pub fn extract(doc: &Document, agent: Option<&str>)
+        -> RobotsDirectives
+    {
+        let mut rd = RobotsDirectives::default();
+        let metas = doc.document_node_ref().select(|n| n.is_elem(t::META));
+        for meta in metas {
+            let applies = meta.attr(a::NAME).map_or(false, |name| {
+                let name = name.trim();
+                name.eq_ignore_ascii_case("robots") ||
+                    agent.map_or(false, |ag| name.eq_ignore_ascii_case(ag))
+            });
+            if applies {
+                if let Some(content) = meta.attr(a::CONTENT) {
+                    rd.add(content);
+                }
+            }
+        }
+        rd
+    }
+
+    /// Add directives from an `X-Robots-Tag` response header value. Per
+    /// header syntax, directives following a crawler name and colon, e.g.
+    /// `googlebot: noindex`, only apply to that crawler, and are added if
+    /// it is the given `agent`.
+    pub fn add_header(&mut self, value: &str, agent: Option<&str>) {
+        let mut applies = true;
+        for part in value.split(',') {
+            let mut part = part.trim();
+            if let Some(i) = part.find(':') {
+                let name = part[..i].trim();
+                if !is_directive(name) {
+                    applies = agent.map_or(false, |ag| {
+                        name.eq_ignore_ascii_case(ag)
+                    });
+                    part = part[i+1..].trim();
+                }
+            }
+            if applies {
+                self.add_directive(part);
+            }
+        }
+    }
+
+    /// Add comma separated directives, as found in `<meta>` content.
+    pub fn add(&mut self, directives: &str) {
+        for d in directives.split(',') {
+            self.add_directive(d);
+        }
+    }
+
+    /// Return true if links of the page may be followed.
+    pub fn is_follow(&self) -> bool {
+        !self.nofollow
+    }
+
+    /// Return true if the page may be indexed.
+    pub fn is_index(&self) -> bool {
+        !self.noindex
+    }
+
+    fn add_directive(&mut self, directive: &str) {
+        let directive = directive.trim();
+        let (name, value) = match directive.find(':') {
+            Some(i) => (directive[..i].trim(), Some(directive[i+1..].trim())),
+            None => (directive, None),
+        };
+        let name = name.to_ascii_lowercase();
+        match (name.as_str(), value) {
+            ("none", None) => {
+                self.noindex = true;
+                self.nofollow = true;
+            }
+            ("noindex", None) => self.noindex = true,
+            ("nofollow", None) => self.nofollow = true,
+            ("noarchive", None) | ("nocache", None) => self.noarchive = true,
+            ("nosnippet", None) => self.nosnippet = true,
+            ("noimageindex", None) => self.noimageindex = true,
+            ("notranslate", None) => self.notranslate = true,
+            ("max-snippet", Some(v)) => {
+                self.max_snippet = min_limit(self.max_snippet, v);
+            }
+            ("max-video-preview", Some(v)) => {
+                self.max_video_preview = min_limit(self.max_video_preview, v);
+            }
+            ("max-image-preview", Some(v)) => {
+                let p = if v.eq_ignore_ascii_case("none") {
+                    ImagePreview::None
+                } else if v.eq_ignore_ascii_case("standard") {
+                    ImagePreview::Standard
+                } else if v.eq_ignore_ascii_case("large") {
+                    ImagePreview::Large
+                } else {
+                    return;
+                };
+                self.max_image_preview = Some(
+                    self.max_image_preview.map_or(p, |q| q.min(p)));
+            }
+            ("unavailable_after", Some(v)) if !v.is_empty() => {
+                if self.unavailable_after.is_none() {
+                    self.unavailable_after = Some(v.to_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Robots methods.
+impl Document {
+    /// Extract robots directives from `<meta>` elements of this `Document`.
+    ///
+    /// See [`RobotsDirectives::extract`].
+    pub fn robots_directives(&self, agent: Option<&str>) -> RobotsDirectives {
+        RobotsDirectives::extract(self, agent)
+    }
+}
+
+/// Link `rel` classification, as returned by [`NodeRef::link_rel`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkRel {
+    /// The link is not endorsed (`nofollow`).
+    pub nofollow: bool,
+
+    /// The link is within user generated content (`ugc`).
+    pub ugc: bool,
+
+    /// The link is paid or sponsored (`sponsored`).
+    pub sponsored: bool,
+
+    _priv: ()
+}
+
+impl LinkRel {
+    /// Return true if none of `nofollow`, `ugc` or `sponsored` apply, so the
+    /// link may be followed, or credited, by crawlers.
+    pub fn is_follow(&self) -> bool {
+        !(self.nofollow || self.ugc || self.sponsored)
+    }
+}
+
+/// Robots methods.
+impl<'a> NodeRef<'a> {
+    /// Return the classification of this link per its `rel` attribute
+    /// tokens, compared ASCII case-insensitively. Any element is accepted,
+    /// but typically this is an `<a>` or `<area>`.
+    pub fn link_rel(&self) -> LinkRel {
+        let mut lr = LinkRel::default();
+        if let Some(rel) = self.attr(a::REL) {
+            for token in rel.split_ascii_whitespace() {
+                let token = token.to_ascii_lowercase();
+                match token.as_str() {
+                    "nofollow" => lr.nofollow = true,
+                    "ugc" => lr.ugc = true,
+                    "sponsored" => lr.sponsored = true,
+                    _ => {}
+                }
+            }
+        }
+        lr
+    }
+}
+
+// Return true if the name is a known directive taking a value, as opposed to
+// a crawler name prefix.
+fn is_directive(name: &str) -> bool {
+    ["max-snippet", "max-image-preview", "max-video-preview",
+     "unavailable_after"]
+        .iter()
+        .any(|d| name.eq_ignore_ascii_case(d))
+}
+
+// Return the more restrictive of the current and new limits, where -1 is
+// unlimited.
+fn min_limit(cur: Option<i32>, value: &str) -> Option<i32> {
+    let new: i32 = match value.parse() {
+        Ok(v) if v >= -1 => v,
+        _ => return cur,
+    };
+    Some(match cur {
+        Some(c) if c == -1 => new,
+        Some(c) if new == -1 => c,
+        Some(c) => c.min(new),
+        None => new,
+    })
+}
@@ -0,0 +1,76 @@
+//! Support for moving a `Document` between threads.
+
+use std::mem;
+
+use crate::dom::{Document, NodeData, StrTendril, UserData};
+
+/// A `Document` wrapper which is `Send`, for moving (but not sharing) a
+/// document to another thread.
+///
+/// `Document` is neither `Send` nor `Sync`, since its `StrTendril` text and
+/// attribute values use non-atomic reference counting, and may share buffers
+/// with other tendrils (e.g. the parser's input buffers or clones held by the
+/// user). A `SendDocument` is obtained via [`Document::into_send`] or
+/// [`Document::to_send`], which ensure that all of its tendrils own their
+/// buffers exclusively. It offers no access to the document until converted
+/// back via [`SendDocument::into_document`], typically on the receiving
+/// thread.
+///
+/// For worker threads to concurrently query the same parsed document, send
+/// each worker its own copy via [`Document::to_send`].
+pub struct SendDocument(Document);
+
+// Safety: All tendrils are made exclusively owned on construction, and are
+// inaccessible until converted back to a Document, so no reference counts
+// are shared across threads. Any (non-`Send`) user data is dropped.
+unsafe impl Send for SendDocument {}
+
+impl SendDocument {
+    /// Return the contained `Document`.
+    pub fn into_document(self) -> Document {
+        self.0
+    }
+}
+
+impl From<SendDocument> for Document {
+    fn from(sdoc: SendDocument) -> Document {
+        sdoc.into_document()
+    }
+}
+
+/// Thread transfer methods.
+impl Document {
+    /// Convert self to a `SendDocument`, copying any `StrTendril` buffers
+    /// which are shared.
+    ///
+    /// Any user data (see [`Document::set_user_data`]) is dropped.
+    pub fn into_send(mut self) -> SendDocument {
+        self.user_data = UserData::default();
+        for node in &mut self.nodes {
+            match &mut node.data {
+                NodeData::Elem(elm) => {
+                    for attr in &mut elm.attrs {
+                        unshare(&mut attr.value);
+                    }
+                }
+                NodeData::Text(t) | NodeData::Comment(t) => unshare(t),
+                NodeData::Pi(pi) => unshare(&mut pi.data),
+                NodeData::DocType(dt) => unshare(&mut dt.name),
+                NodeData::Document | NodeData::Hole => {}
+            }
+        }
+        SendDocument(self)
+    }
+
+    /// Return a new `SendDocument` copy of self, via
+    /// [`Document::bulk_clone`] and [`Document::into_send`].
+    pub fn to_send(&self) -> SendDocument {
+        self.bulk_clone().into_send()
+    }
+}
+
+// Make the tendril exclusively own its buffer, copying if shared.
+fn unshare(t: &mut StrTendril) {
+    let st = mem::replace(t, StrTendril::new()).into_send();
+    *t = StrTendril::from(st);
+}
@@ -19,11 +19,13 @@ use std::io::Write;
 use std::string::ToString;
 
 use html5ever::serialize::{
-    serialize, Serialize, SerializeOpts, Serializer,
-    TraversalScope, TraversalScope::*
+    Serialize, Serializer, TraversalScope, TraversalScope::*
 };
 
-use crate::dom::{Document, NodeData, NodeRef};
+use crate::dom::{
+    provenance::annotation_attrs,
+    Attribute, Document, Element, LocalName, NodeData, NodeRef, QualName
+};
 
 impl<'a> Serialize for NodeRef<'a> {
     fn serialize<S>(
@@ -82,6 +84,174 @@ impl<'a> Serialize for NodeRef<'a> {
     }
 }
 
+/// Character escaping style, for [`SerializeOptions::escaping`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Escaping {
+    /// Escape only the characters which require it, as named character
+    /// references: `&amp;`, `&nbsp;`, `&lt;` and `&gt;` in text, and
+    /// `&quot;` in attribute values. This is the default.
+    Minimal,
+
+    /// As per `Minimal`, and additionally escape common non-ASCII punctuation
+    /// and symbols as named character references, e.g. `&copy;` or
+    /// `&mdash;`.
+    Named,
+
+    /// Escape the same characters as `Minimal`, but as hexadecimal numeric
+    /// character references, e.g. `&#x26;`.
+    Numeric,
+}
+
+impl Default for Escaping {
+    fn default() -> Escaping {
+        Escaping::Minimal
+    }
+}
+
+/// Options for [`Document::serialize_with`] and [`NodeRef::serialize_with`].
+///
+/// The default options are equivalent to [`Document::serialize`].
+#[derive(Clone, Debug, Default)]
+pub struct SerializeOptions {
+    escaping: Escaping,
+    ascii_only: bool,
+    xhtml: bool,
+    self_closing_foreign: bool,
+    max_text_chars: Option<usize>,
+    truncation_marker: Option<String>,
+    minify: bool,
+    normalize_attrs: bool,
+    annotations: bool,
+}
+
+impl SerializeOptions {
+    /// Construct new default options.
+    pub fn new() -> SerializeOptions {
+        SerializeOptions::default()
+    }
+
+    /// Set the style of character escaping in text and attribute values.
+    /// Default: `Escaping::Minimal`.
+    pub fn escaping(mut self, escaping: Escaping) -> Self {
+        self.escaping = escaping;
+        self
+    }
+
+    /// If true, escape all non-ASCII characters in text and attribute values
+    /// (by named character reference if available via `Escaping::Named`, or
+    /// otherwise by numeric character reference), for downstream systems
+    /// which require ASCII-safe output. Note that non-ASCII characters in
+    /// comments, and the raw text content of elements such as `<script>`
+    /// and `<style>`, can not be escaped, and are output as is. Default:
+    /// false.
+    pub fn ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// If true, produce XHTML (XML-compatible) output. Default: false.
+    ///
+    /// In this mode, void elements such as `<br/>` are self-closing, an
+    /// `xmlns` attribute is added to any `<html>` element lacking one, and
+    /// only the XML predefined named character references are used (others
+    /// are output as numeric references). As XML has no raw text elements,
+    /// the text content of `<xmp>`, `<plaintext>`, etc. is escaped, and that
+    /// of `<script>` and `<style>`, if it contains `<` or `&`, is output in a
+    /// CDATA section. A `<` in attribute values is also escaped.
+    pub fn xhtml(mut self, xhtml: bool) -> Self {
+        self.xhtml = xhtml;
+        self
+    }
+
+    /// If true, write SVG and MathML (foreign) elements without children as
+    /// self-closing, e.g. `<path d="…"/>`, which is valid in both HTML and
+    /// XHTML syntax. Default: false, for which an end tag is written, as
+    /// with `Document::serialize`.
+    ///
+    /// Note that foreign element and attribute names retain their case, e.g.
+    /// `<clipPath>` or `viewBox`, in all modes.
+    pub fn self_closing_foreign(mut self, self_closing: bool) -> Self {
+        self.self_closing_foreign = self_closing;
+        self
+    }
+
+    /// Set a maximum number of characters of text content to output, for
+    /// generating previews. Default: `None`, no limit.
+    ///
+    /// Output is truncated at node boundaries: each text node is output
+    /// whole, or if it would exceed the remaining limit, is omitted along
+    /// with all subsequent nodes. Any element containing the truncation
+    /// point is still closed, so output remains well-formed. Elements without
+    /// text (e.g. `<br>` or `<img>`) following text that exactly meets the
+    /// limit are still output. Characters are counted before any escaping.
+    pub fn max_text_chars(mut self, limit: Option<usize>) -> Self {
+        self.max_text_chars = limit;
+        self
+    }
+
+    /// Set markup to be written, as is, at the point of any truncation per
+    /// [`SerializeOptions::max_text_chars`], for example
+    /// `<span class="more">…</span>`. Default: `None`.
+    pub fn truncation_marker<S>(mut self, marker: Option<S>) -> Self
+        where S: Into<String>
+    {
+        self.truncation_marker = marker.map(Into::into);
+        self
+    }
+
+    /// If true, minimize the size of output, while retaining the same
+    /// parsed HTML structure and rendering. Default: false.
+    ///
+    /// In this mode:
+    ///
+    /// * Whitespace-only text between block-level elements (and in elements
+    ///   such as `<head>` or `<ul>`) is removed, and other runs of
+    ///   whitespace in text are collapsed to a single space, except within
+    ///   `<pre>`, `<textarea>` and `<listing>`.
+    ///
+    /// * Attributes with their default value, such as `type="text"` on
+    ///   `<input>` or `type="text/javascript"` on `<script>`, are removed.
+    ///   Note this may affect matching of CSS attribute selectors.
+    ///
+    /// * End tags which are optional per the HTML standard, such as those of
+    ///   `<p>`, `<li>` or `<td>` where followed by elements which imply them,
+    ///   are omitted.
+    ///
+    /// * Attribute values are unquoted, where legal, and empty values are
+    ///   written as the attribute name only.
+    ///
+    /// With [`SerializeOptions::xhtml`], only whitespace and default
+    /// attribute removal apply, as XML requires end tags and quotes.
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// If true, write the attributes of each element sorted by (namespace
+    /// and) name, and write boolean attributes such as `checked` or
+    /// `disabled` with an empty value, regardless of their original value
+    /// (or in XHTML, with the attribute name as value). Output is then
+    /// stable across parser versions and equivalent source forms, for
+    /// caching and diffing. Default: false, attributes are written in
+    /// original order, as is.
+    pub fn normalize_attrs(mut self, normalize: bool) -> Self {
+        self.normalize_attrs = normalize;
+        self
+    }
+
+    /// If true, write the annotation user data of elements, such as
+    /// extraction scores and boilerplate markers, as `data-marked-*`
+    /// attributes, for inspection of why each element was retained.
+    /// Attributes per
+    /// [`provenance::annotation_attrs`](crate::provenance::annotation_attrs)
+    /// are written after any others, excepting those the element already
+    /// has. The document is not modified. Default: false.
+    pub fn annotations(mut self, annotations: bool) -> Self {
+        self.annotations = annotations;
+        self
+    }
+}
+
 /// Implemented via [`Document::serialize`].
 impl ToString for Document {
     fn to_string(&self) -> String {
@@ -91,39 +261,69 @@ impl ToString for Document {
     }
 }
 
-/// Serialize convenience method.
+/// Serialize convenience methods.
 impl Document {
     /// Serialize the contents of the document node and descendants in HTML
     /// syntax to the given stream.
     pub fn serialize<W>(&self, writer: &mut W) -> io::Result<()>
         where W: Write
     {
-        serialize(
-            writer,
-            &self.document_node_ref(),
-            SerializeOpts {
-                traversal_scope: ChildrenOnly(None),
-                ..Default::default()
-            },
-        )
+        self.serialize_with(writer, &SerializeOptions::default())
+    }
+
+    /// Serialize the contents of the document node and descendants in HTML
+    /// syntax to the given stream, with the given options.
+    pub fn serialize_with<W>(&self, writer: &mut W, opts: &SerializeOptions)
+        -> io::Result<()>
+        where W: Write
+    {
+        let mut ser = MarkupWriter::new(writer, opts);
+        for child in self.document_node_ref().children() {
+            ser.write_node(child, TextMode::Escaped)?;
+        }
+        Ok(())
+    }
+
+    /// Serialize to a `String`, with the given options.
+    pub fn to_string_with(&self, opts: &SerializeOptions) -> String {
+        let mut u8_vec = Vec::new();
+        self.serialize_with(&mut u8_vec, opts).unwrap();
+        unsafe { String::from_utf8_unchecked(u8_vec) }
     }
 }
 
-/// Serialize convenience method.
+/// Serialize convenience methods.
 impl<'a> NodeRef<'a> {
     /// Serialize the referenced node and its descendants in HTML syntax to the
     /// given stream.
     pub fn serialize<W>(&'a self, writer: &mut W) -> io::Result<()>
         where W: Write
     {
-        serialize(
-            writer,
-            self,
-            SerializeOpts {
-                traversal_scope: IncludeNode,
-                ..Default::default()
-            },
-        )
+        self.serialize_with(writer, &SerializeOptions::default())
+    }
+
+    /// Serialize the referenced node and its descendants in HTML syntax to the
+    /// given stream, with the given options.
+    pub fn serialize_with<W>(&self, writer: &mut W, opts: &SerializeOptions)
+        -> io::Result<()>
+        where W: Write
+    {
+        let mut ser = MarkupWriter::new(writer, opts);
+        if let NodeData::Document = self.data {
+            for child in self.children() {
+                ser.write_node(child, TextMode::Escaped)?;
+            }
+            Ok(())
+        } else {
+            ser.write_node(*self, TextMode::Escaped)
+        }
+    }
+
+    /// Serialize to a `String`, with the given options.
+    pub fn to_string_with(&self, opts: &SerializeOptions) -> String {
+        let mut u8_vec = Vec::new();
+        self.serialize_with(&mut u8_vec, opts).unwrap();
+        unsafe { String::from_utf8_unchecked(u8_vec) }
     }
 }
 
@@ -135,3 +335,548 @@ impl<'a> ToString for NodeRef<'a> {
         unsafe { String::from_utf8_unchecked(u8_vec) }
     }
 }
+
+// Serialization of nodes per `SerializeOptions`. With default options, output
+// is equivalent to the html5ever `HtmlSerializer` via the `Serialize`
+// implementation above.
+struct MarkupWriter<'o, W> {
+    writer: W,
+    opts: &'o SerializeOptions,
+    remaining: Option<usize>,
+    truncated: bool,
+    // Depth of elements preserving whitespace, when minifying.
+    pre: usize,
+}
+
+// How text content is written.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum TextMode {
+    Escaped,
+    Raw,
+    CData,
+}
+
+impl<'o, W> MarkupWriter<'o, W>
+    where W: Write
+{
+    fn new(writer: W, opts: &'o SerializeOptions) -> Self {
+        MarkupWriter {
+            writer,
+            opts,
+            remaining: opts.max_text_chars,
+            truncated: false,
+            pre: 0,
+        }
+    }
+
+    // Write node and descendants, with text children written per mode.
+    fn write_node(&mut self, node: NodeRef<'_>, mode: TextMode)
+        -> io::Result<()>
+    {
+        if self.truncated {
+            return Ok(());
+        }
+        if let Some(rem) = self.remaining {
+            match &node.data {
+                NodeData::Text(t) => {
+                    let len = t.chars().count();
+                    if len > rem {
+                        return self.truncate();
+                    }
+                    self.remaining = Some(rem - len);
+                }
+                NodeData::Elem(_) if rem == 0 && has_text(node) => {
+                    return self.truncate();
+                }
+                _ => {}
+            }
+        }
+
+        match &node.data {
+            NodeData::Elem(elm) => {
+                let xhtml = self.opts.xhtml;
+                let minify = self.opts.minify;
+                let is_html = elm.name.ns == ns!(html);
+                self.writer.write_all(b"<")?;
+                self.writer.write_all(elm.name.local.as_bytes())?;
+                let normalize = self.opts.normalize_attrs;
+                let mut attrs: Vec<&Attribute> = elm.attrs.iter().collect();
+                if normalize {
+                    attrs.sort_by(|a, b| {
+                        (&*a.name.ns, &*a.name.local)
+                            .cmp(&(&*b.name.ns, &*b.name.local))
+                    });
+                }
+                for attr in attrs {
+                    let value: &str = if normalize && is_html &&
+                        is_boolean_attr(&attr.name)
+                    {
+                        if xhtml { &*attr.name.local } else { "" }
+                    } else {
+                        &attr.value
+                    };
+                    if minify && is_html && is_default_attr(
+                        &elm.name.local, &attr.name, value)
+                    {
+                        continue;
+                    }
+                    self.writer.write_all(b" ")?;
+                    self.write_attr_name(&attr.name)?;
+                    if minify && !xhtml && value.is_empty() {
+                        continue;
+                    }
+                    if minify && !xhtml && is_unquotable(value) {
+                        self.writer.write_all(b"=")?;
+                        self.write_escaped(value, true)?;
+                        continue;
+                    }
+                    self.writer.write_all(b"=\"")?;
+                    self.write_escaped(value, true)?;
+                    self.writer.write_all(b"\"")?;
+                }
+                if self.opts.annotations {
+                    for (name, value) in annotation_attrs(node) {
+                        if elm.attrs.iter().any(|a| &*a.name.local == name) {
+                            continue;
+                        }
+                        self.writer.write_all(b" ")?;
+                        self.writer.write_all(name.as_bytes())?;
+                        self.writer.write_all(b"=\"")?;
+                        self.write_escaped(&value, true)?;
+                        self.writer.write_all(b"\"")?;
+                    }
+                }
+                if xhtml && is_html &&
+                    elm.name.local == local_name!("html") &&
+                    !elm.attrs.iter().any(|a| {
+                        a.name.local == local_name!("xmlns")
+                    })
+                {
+                    self.writer.write_all(
+                        b" xmlns=\"http://www.w3.org/1999/xhtml\"")?;
+                }
+
+                if self.opts.self_closing_foreign &&
+                    (elm.name.ns == ns!(svg) || elm.name.ns == ns!(mathml)) &&
+                    node.first_child.is_none()
+                {
+                    return self.writer.write_all(b"/>");
+                }
+                if is_html && is_void(&elm.name.local) {
+                    return if xhtml {
+                        self.writer.write_all(b"/>")
+                    } else {
+                        self.writer.write_all(b">")
+                    };
+                }
+                self.writer.write_all(b">")?;
+
+                let mode = if !is_html || !is_raw_text(&elm.name.local) {
+                    TextMode::Escaped
+                } else if !xhtml {
+                    TextMode::Raw
+                } else if elm.name.local == local_name!("script") ||
+                    elm.name.local == local_name!("style")
+                {
+                    TextMode::CData
+                } else {
+                    TextMode::Escaped
+                };
+                let preserve = is_html && is_preformatted(&elm.name.local);
+                if preserve {
+                    self.pre += 1;
+                }
+                for child in node.children() {
+                    self.write_node(child, mode)?;
+                }
+                if preserve {
+                    self.pre -= 1;
+                }
+                if minify && !xhtml && is_html && !self.truncated &&
+                    is_end_tag_optional(node, &elm.name.local, self.pre > 0)
+                {
+                    return Ok(());
+                }
+                self.writer.write_all(b"</")?;
+                self.writer.write_all(elm.name.local.as_bytes())?;
+                self.writer.write_all(b">")
+            }
+            NodeData::Text(t) => match mode {
+                TextMode::Escaped if self.opts.minify && self.pre == 0 => {
+                    if is_droppable_space(node) {
+                        Ok(())
+                    } else {
+                        self.write_escaped(&collapse_space(t), false)
+                    }
+                }
+                TextMode::Escaped => self.write_escaped(t, false),
+                TextMode::CData if t.contains(is_cdata_needed) => {
+                    self.writer.write_all(b"<![CDATA[")?;
+                    self.writer.write_all(
+                        t.replace("]]>", "]]]]><![CDATA[>").as_bytes())?;
+                    self.writer.write_all(b"]]>")
+                }
+                TextMode::Raw | TextMode::CData => {
+                    self.writer.write_all(t.as_bytes())
+                }
+            },
+            NodeData::Comment(t) => {
+                self.writer.write_all(b"<!--")?;
+                self.writer.write_all(t.as_bytes())?;
+                self.writer.write_all(b"-->")
+            }
+            NodeData::DocType(dt) => {
+                self.writer.write_all(b"<!DOCTYPE ")?;
+                self.writer.write_all(dt.name.as_bytes())?;
+                self.writer.write_all(b">")
+            }
+            NodeData::Pi(pi) => {
+                self.writer.write_all(b"<? ")?;
+                self.writer.write_all(pi.data.as_bytes())?;
+                self.writer.write_all(b">")
+            }
+            NodeData::Document => {
+                for child in node.children() {
+                    self.write_node(child, TextMode::Escaped)?;
+                }
+                Ok(())
+            }
+            NodeData::Hole => panic!("Hole in Document"),
+        }
+    }
+
+    // Mark output as truncated, writing any marker.
+    fn truncate(&mut self) -> io::Result<()> {
+        self.truncated = true;
+        if let Some(marker) = &self.opts.truncation_marker {
+            self.writer.write_all(marker.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn write_attr_name(&mut self, name: &QualName) -> io::Result<()> {
+        match name.ns {
+            ns!() => {}
+            ns!(xml) => self.writer.write_all(b"xml:")?,
+            ns!(xmlns) => {
+                if name.local != local_name!("xmlns") {
+                    self.writer.write_all(b"xmlns:")?;
+                }
+            }
+            ns!(xlink) => self.writer.write_all(b"xlink:")?,
+            _ => self.writer.write_all(b"unknown_namespace:")?,
+        }
+        self.writer.write_all(name.local.as_bytes())
+    }
+
+    fn write_escaped(&mut self, text: &str, attr: bool) -> io::Result<()> {
+        let mut last = 0;
+        for (i, c) in text.char_indices() {
+            if let Some(cref) = char_ref(c, attr, self.opts) {
+                self.writer.write_all(text[last..i].as_bytes())?;
+                match cref {
+                    CharRef::Named(name) => {
+                        write!(self.writer, "&{};", name)?;
+                    }
+                    CharRef::Numeric(c) => {
+                        write!(self.writer, "&#x{:X};", c as u32)?;
+                    }
+                }
+                last = i + c.len_utf8();
+            }
+        }
+        self.writer.write_all(text[last..].as_bytes())
+    }
+}
+
+enum CharRef {
+    Named(&'static str),
+    Numeric(char),
+}
+
+// Return any character reference to be written in place of c.
+fn char_ref(c: char, attr: bool, opts: &SerializeOptions) -> Option<CharRef> {
+    let name = match c {
+        '&' => "amp",
+        '\u{A0}' => "nbsp",
+        '<' if !attr || opts.xhtml => "lt",
+        '>' if !attr => "gt",
+        '"' if attr => "quot",
+        c if c.is_ascii() => return None,
+        c => {
+            let named = if opts.escaping == Escaping::Named {
+                named_ref(c)
+            } else {
+                None
+            };
+            return if let Some(name) = named {
+                if opts.xhtml {
+                    Some(CharRef::Numeric(c))
+                } else {
+                    Some(CharRef::Named(name))
+                }
+            } else if opts.ascii_only {
+                Some(CharRef::Numeric(c))
+            } else {
+                None
+            };
+        }
+    };
+    if opts.escaping == Escaping::Numeric || (opts.xhtml && c == '\u{A0}') {
+        Some(CharRef::Numeric(c))
+    } else {
+        Some(CharRef::Named(name))
+    }
+}
+
+// Named character references for common non-ASCII characters, sorted by
+// char.
+static NAMED_REFS: &[(char, &str)] = &[
+    ('\u{A1}', "iexcl"),
+    ('\u{A2}', "cent"),
+    ('\u{A3}', "pound"),
+    ('\u{A5}', "yen"),
+    ('\u{A7}', "sect"),
+    ('\u{A9}', "copy"),
+    ('\u{AB}', "laquo"),
+    ('\u{AE}', "reg"),
+    ('\u{B0}', "deg"),
+    ('\u{B1}', "plusmn"),
+    ('\u{B6}', "para"),
+    ('\u{B7}', "middot"),
+    ('\u{BB}', "raquo"),
+    ('\u{BF}', "iquest"),
+    ('\u{D7}', "times"),
+    ('\u{F7}', "divide"),
+    ('\u{2013}', "ndash"),
+    ('\u{2014}', "mdash"),
+    ('\u{2018}', "lsquo"),
+    ('\u{2019}', "rsquo"),
+    ('\u{201C}', "ldquo"),
+    ('\u{201D}', "rdquo"),
+    ('\u{2020}', "dagger"),
+    ('\u{2022}', "bull"),
+    ('\u{2026}', "hellip"),
+    ('\u{20AC}', "euro"),
+    ('\u{2122}', "trade"),
+];
+
+fn named_ref(c: char) -> Option<&'static str> {
+    NAMED_REFS
+        .binary_search_by_key(&c, |&(k, _)| k)
+        .ok()
+        .map(|i| NAMED_REFS[i].1)
+}
+
+// Return true if node has any non-empty text descendant.
+fn has_text(node: NodeRef<'_>) -> bool {
+    node.descendants()
+        .any(|n| n.as_text().map_or(false, |t| !t.is_empty()))
+}
+
+// Return true for elements in which whitespace is preserved.
+fn is_preformatted(name: &LocalName) -> bool {
+    match *name {
+        local_name!("pre") | local_name!("textarea") |
+        local_name!("listing") => true,
+        _ => false,
+    }
+}
+
+// Collapse each run of whitespace to a single space.
+fn collapse_space(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !space {
+                out.push(' ');
+            }
+            space = true;
+        } else {
+            out.push(c);
+            space = false;
+        }
+    }
+    out
+}
+
+// Return true if the node is whitespace-only text which may be removed
+// without affecting rendering: where neither its parent nor its nearest
+// (non-comment) siblings are inline elements or text.
+fn is_droppable_space(node: NodeRef<'_>) -> bool {
+    match node.as_text() {
+        Some(t) if t.chars().all(|c| c.is_ascii_whitespace()) => {}
+        _ => return false,
+    }
+    let parent_ok = node.parent().map_or(true, |p| match &p.data {
+        NodeData::Elem(elm) => is_block_level(elm),
+        _ => true,
+    });
+    parent_ok &&
+        is_space_boundary(node.prev_siblings().find(|n| !is_comment(*n))) &&
+        is_space_boundary(node.next_siblings().find(|n| !is_comment(*n)))
+}
+
+fn is_space_boundary(node: Option<NodeRef<'_>>) -> bool {
+    node.map_or(true, |n| match &n.data {
+        NodeData::Elem(elm) => is_block_level(elm),
+        NodeData::Text(_) => false,
+        _ => true,
+    })
+}
+
+fn is_block_level(elm: &Element) -> bool {
+    elm.html_tag_meta().map_or(false, |m| !m.is_inline())
+}
+
+fn is_comment(node: NodeRef<'_>) -> bool {
+    match node.data {
+        NodeData::Comment(_) => true,
+        _ => false,
+    }
+}
+
+// Return true if the attribute value may be written unquoted.
+fn is_unquotable(value: &str) -> bool {
+    !value.is_empty() && !value.contains(|c: char| {
+        c.is_ascii_whitespace() || "\"'=<>`".contains(c)
+    })
+}
+
+// Boolean attributes of HTML elements, where presence alone is significant.
+static BOOLEAN_ATTRS: &[&str] = &[
+    "allowfullscreen", "async", "autofocus", "autoplay", "checked",
+    "controls", "default", "defer", "disabled", "formnovalidate", "hidden",
+    "inert", "ismap", "itemscope", "loop", "multiple", "muted", "nomodule",
+    "novalidate", "open", "playsinline", "readonly", "required", "reversed",
+    "selected",
+];
+
+fn is_boolean_attr(name: &QualName) -> bool {
+    name.ns == ns!() && BOOLEAN_ATTRS.contains(&&*name.local)
+}
+
+// Default attribute values, by element, removed when minifying.
+static DEFAULT_ATTRS: &[(&str, &str, &str)] = &[
+    ("area", "shape", "rect"),
+    ("button", "type", "submit"),
+    ("col", "span", "1"),
+    ("colgroup", "span", "1"),
+    ("form", "enctype", "application/x-www-form-urlencoded"),
+    ("form", "method", "get"),
+    ("input", "type", "text"),
+    ("link", "media", "all"),
+    ("script", "type", "text/javascript"),
+    ("style", "media", "all"),
+    ("style", "type", "text/css"),
+    ("td", "colspan", "1"),
+    ("td", "rowspan", "1"),
+    ("th", "colspan", "1"),
+    ("th", "rowspan", "1"),
+];
+
+fn is_default_attr(elm: &LocalName, attr: &QualName, value: &str) -> bool {
+    attr.ns == ns!() && DEFAULT_ATTRS.iter().any(|&(e, a, v)| {
+        &**elm == e && &*attr.local == a && value.trim().eq_ignore_ascii_case(v)
+    })
+}
+
+// Return true if the end tag of the HTML element is optional, per the
+// following sibling to be written, if any.
+fn is_end_tag_optional(node: NodeRef<'_>, name: &LocalName, in_pre: bool)
+    -> bool
+{
+    let next = node.next_siblings().find(|n| {
+        !(n.as_text().map_or(false, |t| t.is_empty()) ||
+          (!in_pre && is_droppable_space(*n)))
+    });
+    let next_name = next
+        .and_then(|n| n.as_element())
+        .filter(|e| e.name.ns == ns!(html))
+        .map(|e| &e.name.local);
+    let next_is = |names: &[&str]| {
+        next_name.map_or(false, |n| names.iter().any(|m| &**n == *m))
+    };
+    let last = next.is_none();
+    match *name {
+        local_name!("li") => last || next_is(&["li"]),
+        local_name!("dt") => next_is(&["dt", "dd"]),
+        local_name!("dd") => last || next_is(&["dt", "dd"]),
+        local_name!("rt") | local_name!("rp") => {
+            last || next_is(&["rt", "rp"])
+        }
+        local_name!("optgroup") => last || next_is(&["optgroup", "hr"]),
+        local_name!("option") => {
+            last || next_is(&["option", "optgroup", "hr"])
+        }
+        local_name!("thead") => next_is(&["tbody", "tfoot"]),
+        local_name!("tbody") => last || next_is(&["tbody", "tfoot"]),
+        local_name!("tfoot") => last,
+        local_name!("tr") => last || next_is(&["tr"]),
+        local_name!("td") | local_name!("th") => {
+            last || next_is(&["td", "th"])
+        }
+        local_name!("p") => {
+            next_is(P_CLOSERS) || (last && !node.parent().map_or(false, |p| {
+                p.as_element().map_or(false, |e| {
+                    ["a", "audio", "del", "ins", "map", "noscript", "video"]
+                        .iter()
+                        .any(|n| &*e.name.local == *n)
+                })
+            }))
+        }
+        local_name!("html") | local_name!("body") => {
+            !next.map_or(false, is_comment)
+        }
+        local_name!("head") | local_name!("colgroup") |
+        local_name!("caption") => {
+            !next.map_or(false, |n| {
+                is_comment(n) || n.as_text().map_or(false, |t| {
+                    t.starts_with(|c: char| c.is_ascii_whitespace())
+                })
+            })
+        }
+        _ => false,
+    }
+}
+
+// Elements which, as the following sibling, imply the end of a `<p>`.
+static P_CLOSERS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "dialog",
+    "div", "dl", "fieldset", "figcaption", "figure", "footer", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hgroup", "hr", "main",
+    "menu", "nav", "ol", "p", "pre", "section", "table", "ul",
+];
+
+// Return true if the char requires script or style text to be written in a
+// CDATA section.
+fn is_cdata_needed(c: char) -> bool {
+    c == '<' || c == '&'
+}
+
+// Return true for void elements, which have no end tag or content.
+fn is_void(name: &LocalName) -> bool {
+    match *name {
+        local_name!("area") | local_name!("base") |
+        local_name!("basefont") | local_name!("bgsound") |
+        local_name!("br") | local_name!("col") | local_name!("embed") |
+        local_name!("frame") | local_name!("hr") | local_name!("img") |
+        local_name!("input") | local_name!("keygen") |
+        local_name!("link") | local_name!("meta") | local_name!("param") |
+        local_name!("source") | local_name!("track") |
+        local_name!("wbr") => true,
+        _ => false,
+    }
+}
+
+// Return true for elements with raw (unescaped) text content.
+fn is_raw_text(name: &LocalName) -> bool {
+    match *name {
+        local_name!("style") | local_name!("script") | local_name!("xmp") |
+        local_name!("iframe") | local_name!("noembed") |
+        local_name!("noframes") | local_name!("plaintext") |
+        local_name!("noscript") => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,169 @@
+//! Parsing of XML sitemaps (_xml_ feature).
+//!
+//! Both `<urlset>` sitemaps and `<sitemapindex>` indexes, per
+//! [sitemaps.org](https://www.sitemaps.org/protocol.html), are supported.
+//! Gzip compressed sitemaps may be parsed via [`parse_gzip`], with the
+//! additional _flate2_ feature.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use crate::dom::{
+    xml::{self, child_text, is_named, XmlError},
+    Document,
+};
+
+/// A parsed sitemap or sitemap index.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sitemap {
+    /// The `<url>` entries of a `<urlset>`.
+    UrlSet(Vec<UrlEntry>),
+
+    /// The `<sitemap>` entries of a `<sitemapindex>`.
+    Index(Vec<SitemapEntry>),
+}
+
+/// A `<url>` entry of a sitemap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrlEntry {
+    /// The URL of the page.
+    pub loc: String,
+
+    /// The date of last modification, as found (W3C datetime format).
+    pub lastmod: Option<String>,
+
+    /// The expected change frequency, e.g. `daily`, lower-cased.
+    pub changefreq: Option<String>,
+
+    /// The priority relative to other URLs of the site, in the range 0.0 to
+    /// 1.0, if valid.
+    pub priority: Option<f32>,
+}
+
+/// A `<sitemap>` entry of a sitemap index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SitemapEntry {
+    /// The URL of the sitemap.
+    pub loc: String,
+
+    /// The date of last modification, as found (W3C datetime format).
+    pub lastmod: Option<String>,
+}
+
+/// An error returned by sitemap parsing.
+#[derive(Debug)]
+pub enum SitemapError {
+    /// The input is not well-formed XML.
+    Xml(XmlError),
+
+    /// An error reading (decompressing) input.
+    Io(io::Error),
+
+    /// The root element is not `<urlset>` or `<sitemapindex>`.
+    NotASitemap,
+}
+
+impl fmt::Display for SitemapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SitemapError::Xml(e) => e.fmt(f),
+            SitemapError::Io(e) => e.fmt(f),
+            SitemapError::NotASitemap => f.write_str("not a sitemap"),
+        }
+    }
+}
+
+impl StdError for SitemapError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            SitemapError::Xml(e) => Some(e),
+            SitemapError::Io(e) => Some(e),
+            SitemapError::NotASitemap => None,
+        }
+    }
+}
+
+impl From<XmlError> for SitemapError {
+    fn from(e: XmlError) -> SitemapError {
+        SitemapError::Xml(e)
+    }
+}
+
+impl From<io::Error> for SitemapError {
+    fn from(e: io::Error) -> SitemapError {
+        SitemapError::Io(e)
+    }
+}
+
+/// Parse a sitemap from UTF-8 bytes in RAM.
+pub fn parse_utf8(utf8_bytes: &[u8]) -> Result<Sitemap, SitemapError> {
+    let doc = xml::parse_utf8(utf8_bytes)?;
+    Sitemap::from_document(&doc).ok_or(SitemapError::NotASitemap)
+}
+
+/// Parse a gzip compressed sitemap (e.g. `sitemap.xml.gz`) from bytes in
+/// RAM. Uncompressed input, without the gzip header, is also accepted.
+///
+/// This function requires the _flate2_ feature.
+#[cfg(feature = "flate2")]
+pub fn parse_gzip(bytes: &[u8]) -> Result<Sitemap, SitemapError> {
+    use std::io::Read;
+
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return parse_utf8(bytes);
+    }
+    let mut xml = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut xml)?;
+    parse_utf8(&xml)
+}
+
+impl Sitemap {
+    /// Extract a sitemap from a `Document` parsed as XML, returning None if
+    /// its root element is not `<urlset>` or `<sitemapindex>`. Entries
+    /// without a `<loc>` are skipped. Elements are matched by local name
+    /// only.
+    pub fn from_document(doc: &Document) -> Option<Sitemap> {
+        let root = doc.root_element_ref()?;
+        if is_named(root, "urlset") {
+            Some(Sitemap::UrlSet(
+                root.children()
+                    .filter(|n| is_named(*n, "url"))
+                    .filter_map(|url| Some(UrlEntry {
+                        loc: child_text(url, "loc")?,
+                        lastmod: child_text(url, "lastmod"),
+                        changefreq: child_text(url, "changefreq")
+                            .map(|c| c.to_ascii_lowercase()),
+                        priority: child_text(url, "priority")
+                            .and_then(|p| p.parse().ok())
+                            .filter(|p| (0.0..=1.0).contains(p)),
+                    }))
+                    .collect()
+            ))
+        } else if is_named(root, "sitemapindex") {
+            Some(Sitemap::Index(
+                root.children()
+                    .filter(|n| is_named(*n, "sitemap"))
+                    .filter_map(|sm| Some(SitemapEntry {
+                        loc: child_text(sm, "loc")?,
+                        lastmod: child_text(sm, "lastmod"),
+                    }))
+                    .collect()
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Return the `loc` URLs of all entries, in order.
+    pub fn locs(&self) -> Vec<&str> {
+        match self {
+            Sitemap::UrlSet(urls) => {
+                urls.iter().map(|u| u.loc.as_str()).collect()
+            }
+            Sitemap::Index(maps) => {
+                maps.iter().map(|m| m.loc.as_str()).collect()
+            }
+        }
+    }
+}
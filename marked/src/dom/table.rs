@@ -0,0 +1,198 @@
+//! Extraction of HTML `<table>` structure into a grid of cells.
+
+use crate::dom::{
+    html::{a, t},
+    LocalName, NodeRef, StrTendril,
+};
+
+/// Maximum `colspan` value, as per the HTML table processing model.
+const MAX_COLSPAN: u32 = 1000;
+
+/// Maximum `rowspan` value, as per the HTML table processing model.
+const MAX_ROWSPAN: u32 = 65534;
+
+/// A table, as returned by [`NodeRef::as_table`], with `rowspan` and
+/// `colspan` expanded into a rectangular grid of cells.
+///
+/// Each row has the same length ([`Table::width`]). Grid slots not covered
+/// by any cell, as is possible with ragged rows or spans, are `None`.
+#[derive(Clone, Debug)]
+pub struct Table<'a> {
+    /// Any `<caption>` element.
+    pub caption: Option<NodeRef<'a>>,
+
+    /// Header rows: those of any `<thead>`, or otherwise a first row
+    /// consisting entirely of (one or more) `<th>` cells.
+    pub headers: Vec<Vec<Option<CellRef<'a>>>>,
+
+    /// All remaining rows, from `<tbody>`, `<tfoot>` or direct `<tr>`
+    /// children, in document order.
+    pub rows: Vec<Vec<Option<CellRef<'a>>>>,
+}
+
+/// A reference to a `<td>` or `<th>` cell occupying a [`Table`] grid slot.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CellRef<'a> {
+    /// The cell element.
+    pub node: NodeRef<'a>,
+
+    /// Offset in rows from the slot where the cell originates, non-zero if
+    /// this slot is covered via `rowspan`.
+    pub row_offset: u32,
+
+    /// Offset in columns from the slot where the cell originates, non-zero if
+    /// this slot is covered via `colspan`.
+    pub col_offset: u32,
+}
+
+impl<'a> CellRef<'a> {
+    /// Return true if this is the originating (top-left) slot of the cell.
+    pub fn is_origin(&self) -> bool {
+        self.row_offset == 0 && self.col_offset == 0
+    }
+
+    /// Return true if the cell is a `<th>` header cell.
+    pub fn is_header(&self) -> bool {
+        self.node.is_elem(t::TH)
+    }
+
+    /// Return all descendant text of the cell.
+    pub fn text(&self) -> Option<StrTendril> {
+        self.node.text()
+    }
+}
+
+impl<'a> Table<'a> {
+    /// Return the number of columns in every row.
+    pub fn width(&self) -> usize {
+        self.headers.first()
+            .or_else(|| self.rows.first())
+            .map_or(0, |r| r.len())
+    }
+
+    fn from_node(table: NodeRef<'a>) -> Table<'a> {
+        let mut caption = None;
+        let mut headers = Vec::new();
+        let mut rows = Vec::new();
+        let mut loose = Vec::new();
+
+        for child in table.children() {
+            if child.is_elem(t::TR) {
+                loose.push(child);
+                continue;
+            }
+            // Consecutive direct `<tr>` children form an implied group
+            if !loose.is_empty() {
+                rows.extend(expand_group(&loose));
+                loose.clear();
+            }
+            if child.is_elem(t::CAPTION) {
+                if caption.is_none() {
+                    caption = Some(child);
+                }
+            } else if child.is_elem(t::THEAD) {
+                headers.extend(expand_group(&group_rows(child)));
+            } else if child.is_elem(t::TBODY) || child.is_elem(t::TFOOT) {
+                rows.extend(expand_group(&group_rows(child)));
+            }
+        }
+        if !loose.is_empty() {
+            rows.extend(expand_group(&loose));
+        }
+
+        if headers.is_empty() && rows.len() > 1 && !rows[0].is_empty() {
+            let all_th = rows[0].iter().all(|c| {
+                c.map_or(false, |c| c.is_header())
+            });
+            if all_th {
+                headers.push(rows.remove(0));
+            }
+        }
+
+        let mut table = Table { caption, headers, rows };
+        table.pad();
+        table
+    }
+
+    // Pad all rows to the maximum width
+    fn pad(&mut self) {
+        let width = self.headers.iter()
+            .chain(self.rows.iter())
+            .map(|r| r.len())
+            .max()
+            .unwrap_or(0);
+        for row in self.headers.iter_mut().chain(self.rows.iter_mut()) {
+            row.resize(width, None);
+        }
+    }
+}
+
+/// `Table` extraction method.
+impl<'a> NodeRef<'a> {
+    /// Return the grid structure of this node as a [`Table`], if it is a
+    /// `<table>` element.
+    ///
+    /// Only the structure of this table is included: cells of any nested
+    /// tables are not.
+    pub fn as_table(&self) -> Option<Table<'a>> {
+        if self.is_elem(t::TABLE) {
+            Some(Table::from_node(*self))
+        } else {
+            None
+        }
+    }
+}
+
+fn group_rows<'a>(group: NodeRef<'a>) -> Vec<NodeRef<'a>> {
+    group.children().filter(|n| n.is_elem(t::TR)).collect()
+}
+
+// Expand the rows of a single row group to a grid. As per the HTML table
+// model, a `rowspan` is limited to its row group, and zero extends to the end
+// of the group.
+fn expand_group<'a>(trs: &[NodeRef<'a>]) -> Vec<Vec<Option<CellRef<'a>>>> {
+    let mut grid: Vec<Vec<Option<CellRef<'a>>>> = vec![Vec::new(); trs.len()];
+    for (r, tr) in trs.iter().enumerate() {
+        let remaining = (trs.len() - r) as u32;
+        let mut c = 0;
+        let cells = tr.children().filter(|n| {
+            n.is_elem(t::TD) || n.is_elem(t::TH)
+        });
+        for cell in cells {
+            while grid[r].get(c).map_or(false, |s| s.is_some()) {
+                c += 1;
+            }
+            let colspan = span_attr(cell, a::COLSPAN)
+                .unwrap_or(1)
+                .max(1)
+                .min(MAX_COLSPAN);
+            let rowspan = match span_attr(cell, a::ROWSPAN) {
+                Some(0) => remaining,
+                Some(n) => n.min(MAX_ROWSPAN).min(remaining),
+                None => 1,
+            };
+            for dr in 0..rowspan {
+                let row = &mut grid[r + dr as usize];
+                for dc in 0..colspan {
+                    let i = c + dc as usize;
+                    if row.len() <= i {
+                        row.resize(i + 1, None);
+                    }
+                    if row[i].is_none() {
+                        row[i] = Some(CellRef {
+                            node: cell,
+                            row_offset: dr,
+                            col_offset: dc,
+                        });
+                    }
+                }
+            }
+            c += colspan as usize;
+        }
+    }
+    grid
+}
+
+fn span_attr(cell: NodeRef<'_>, name: LocalName) -> Option<u32> {
+    cell.attr(name).and_then(|v| v.as_ref().trim().parse().ok())
+}
@@ -0,0 +1,360 @@
+//! HTML templates, filled from [`Bindings`] to produce new documents.
+//!
+//! A template is any `Document` with the following markers, which are
+//! removed from the output:
+//!
+//! `data-bind="key"`
+//! : Replace the children of the element with the value. If there is no
+//!   value, the template children are retained as a placeholder.
+//!
+//! `data-bind-NAME="key"`
+//! : Set the attribute _NAME_ of the element to the value. A boolean `true`
+//!   sets an empty (boolean) attribute and `false` removes it.
+//!
+//! `data-if="key"` or `data-if="!key"`
+//! : Retain the element only if the value is (or is not) present and
+//!   truthy: non-empty text, `true`, or a non-empty fragment or list.
+//!
+//! `data-each="key"`
+//! : Repeat the element for each item of a list value, with keys of
+//!   descendant markers looked up first in the item. The element is removed
+//!   if there is no list.
+//!
+//! `<slot name="key">`
+//! : Replace the slot element with the value. If there is no value, the slot
+//!   is replaced with its own (fallback) children.
+//!
+//! Values are typically text, but may also be cloned [`Fragment`]s, for
+//! example a cleaned article body. See [`Values`], or the implementations for
+//! string maps and (with the _serde_json_ feature) `serde_json::Value`.
+
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use crate::dom::{
+    html::t, Document, Element, Fragment, Node, NodeId,
+};
+
+/// A source of values for filling a [`Template`], by key.
+pub trait Bindings {
+    /// Return the value for the given key, if any.
+    fn lookup(&self, key: &str) -> Option<Binding<'_>>;
+}
+
+/// A value returned by [`Bindings::lookup`].
+pub enum Binding<'a> {
+    /// Text, inserted as a text node or attribute value.
+    Text(Cow<'a, str>),
+
+    /// A flag, for `data-if` or boolean attributes.
+    Bool(bool),
+
+    /// Markup, inserted as a clone.
+    Fragment(&'a Fragment),
+
+    /// Items for `data-each`.
+    List(Vec<&'a dyn Bindings>),
+}
+
+/// A template document.
+#[derive(Debug)]
+pub struct Template {
+    doc: Document,
+}
+
+/// A `Bindings` implementation of keyed text, flag, fragment and list
+/// values.
+#[derive(Default)]
+pub struct Values {
+    map: HashMap<String, Entry>,
+}
+
+enum Entry {
+    Text(String),
+    Bool(bool),
+    Fragment(Fragment),
+    List(Vec<Values>),
+}
+
+const BIND: &str = "data-bind";
+const BIND_PREFIX: &str = "data-bind-";
+const IF: &str = "data-if";
+const EACH: &str = "data-each";
+
+impl Template {
+    /// Construct a new template from the given document.
+    pub fn new(doc: Document) -> Template {
+        Template { doc }
+    }
+
+    /// Return the template document.
+    pub fn document(&self) -> &Document {
+        &self.doc
+    }
+
+    /// Return a new `Document` by filling this template from the given
+    /// bindings.
+    pub fn fill(&self, bindings: &dyn Bindings) -> Document {
+        fill(&self.doc, bindings)
+    }
+}
+
+impl From<Document> for Template {
+    fn from(doc: Document) -> Template {
+        Template::new(doc)
+    }
+}
+
+impl Values {
+    /// Construct a new, empty set of values.
+    pub fn new() -> Values {
+        Values::default()
+    }
+
+    /// Add a text value.
+    pub fn text<K, V>(mut self, key: K, value: V) -> Self
+        where K: Into<String>, V: Into<String>
+    {
+        self.map.insert(key.into(), Entry::Text(value.into()));
+        self
+    }
+
+    /// Add a flag value.
+    pub fn flag<K>(mut self, key: K, value: bool) -> Self
+        where K: Into<String>
+    {
+        self.map.insert(key.into(), Entry::Bool(value));
+        self
+    }
+
+    /// Add a fragment value.
+    pub fn fragment<K>(mut self, key: K, value: Fragment) -> Self
+        where K: Into<String>
+    {
+        self.map.insert(key.into(), Entry::Fragment(value));
+        self
+    }
+
+    /// Add a list value, of nested values for `data-each`.
+    pub fn list<K>(mut self, key: K, items: Vec<Values>) -> Self
+        where K: Into<String>
+    {
+        self.map.insert(key.into(), Entry::List(items));
+        self
+    }
+}
+
+impl Bindings for Values {
+    fn lookup(&self, key: &str) -> Option<Binding<'_>> {
+        let b = match self.map.get(key)? {
+            Entry::Text(s) => Binding::Text(Cow::Borrowed(s.as_str())),
+            Entry::Bool(b) => Binding::Bool(*b),
+            Entry::Fragment(f) => Binding::Fragment(f),
+            Entry::List(items) => Binding::List(
+                items.iter().map(|i| i as &dyn Bindings).collect()
+            ),
+        };
+        Some(b)
+    }
+}
+
+impl<K, V, S> Bindings for HashMap<K, V, S>
+    where K: Borrow<str> + Hash + Eq, V: AsRef<str>, S: BuildHasher
+{
+    fn lookup(&self, key: &str) -> Option<Binding<'_>> {
+        self.get(key).map(|v| Binding::Text(Cow::Borrowed(v.as_ref())))
+    }
+}
+
+impl<K, V> Bindings for BTreeMap<K, V>
+    where K: Borrow<str> + Ord, V: AsRef<str>
+{
+    fn lookup(&self, key: &str) -> Option<Binding<'_>> {
+        self.get(key).map(|v| Binding::Text(Cow::Borrowed(v.as_ref())))
+    }
+}
+
+/// Keys are looked up as `.` separated paths of object members, e.g.
+/// `author.name`, or `.` for the value itself (e.g. the string items of an
+/// array). Objects are a single item list, for `data-each`.
+#[cfg(feature = "serde_json")]
+impl Bindings for serde_json::Value {
+    fn lookup(&self, key: &str) -> Option<Binding<'_>> {
+        use serde_json::Value;
+
+        let mut v = self;
+        if key != "." {
+            for member in key.split('.') {
+                v = v.get(member)?;
+            }
+        }
+        let b = match v {
+            Value::Null => return None,
+            Value::Bool(b) => Binding::Bool(*b),
+            Value::Number(n) => Binding::Text(Cow::Owned(n.to_string())),
+            Value::String(s) => Binding::Text(Cow::Borrowed(s.as_str())),
+            Value::Array(items) => Binding::List(
+                items.iter().map(|i| i as &dyn Bindings).collect()
+            ),
+            Value::Object(_) => Binding::List(vec![v as &dyn Bindings]),
+        };
+        Some(b)
+    }
+}
+
+/// Template methods.
+impl Document {
+    /// Return a new `Document` by filling self, as a template, from the
+    /// given bindings. See the `template` module.
+    pub fn fill_template(&self, bindings: &dyn Bindings) -> Document {
+        fill(self, bindings)
+    }
+}
+
+fn fill(tdoc: &Document, bindings: &dyn Bindings) -> Document {
+    let mut out = Document::new();
+    let mut scopes = vec![bindings];
+    for child in tdoc.children(Document::DOCUMENT_NODE_ID) {
+        let root = Document::DOCUMENT_NODE_ID;
+        fill_node(tdoc, child, &mut out, root, &mut scopes);
+    }
+    out
+}
+
+fn fill_node<'b>(
+    tdoc: &Document,
+    tid: NodeId,
+    out: &mut Document,
+    parent: NodeId,
+    scopes: &mut Vec<&'b dyn Bindings>)
+{
+    let elm = match tdoc[tid].as_element() {
+        Some(elm) => elm,
+        None => {
+            out.append_child(parent, Node::new(tdoc[tid].data.clone()));
+            return;
+        }
+    };
+
+    if let Some(cond) = elm.attr(IF) {
+        let cond = cond.trim();
+        let (negate, key) = if cond.starts_with('!') {
+            (true, &cond[1..])
+        } else {
+            (false, cond)
+        };
+        if is_truthy(lookup(scopes, key.trim())) == negate {
+            return;
+        }
+    }
+
+    if let Some(key) = elm.attr(EACH) {
+        if let Some(Binding::List(items)) = lookup(scopes, key.trim()) {
+            for item in items {
+                scopes.push(item);
+                fill_elem(tdoc, tid, elm, out, parent, scopes);
+                scopes.pop();
+            }
+        }
+        return;
+    }
+
+    fill_elem(tdoc, tid, elm, out, parent, scopes);
+}
+
+fn fill_elem<'b>(
+    tdoc: &Document,
+    tid: NodeId,
+    elm: &Element,
+    out: &mut Document,
+    parent: NodeId,
+    scopes: &mut Vec<&'b dyn Bindings>)
+{
+    if elm.is_elem(t::SLOT) {
+        if let Some(name) = elm.attr("name") {
+            match lookup(scopes, name.trim()) {
+                Some(value) => append_value(out, parent, value),
+                None => {
+                    for child in tdoc.children(tid) {
+                        fill_node(tdoc, child, out, parent, scopes);
+                    }
+                }
+            }
+            return;
+        }
+    }
+
+    let mut nelm = elm.clone();
+    nelm.attrs.retain(|a| !is_marker(&a.name.local));
+    for attr in &elm.attrs {
+        let lname: &str = &attr.name.local;
+        if !lname.starts_with(BIND_PREFIX) {
+            continue;
+        }
+        let target = &lname[BIND_PREFIX.len()..];
+        match lookup(scopes, attr.value.trim()) {
+            Some(Binding::Text(text)) => {
+                nelm.set_attr(target, text.as_ref());
+            }
+            Some(Binding::Bool(true)) => {
+                nelm.set_attr(target, "");
+            }
+            Some(Binding::Bool(false)) => {
+                nelm.remove_attr(target);
+            }
+            _ => {}
+        }
+    }
+
+    let id = out.append_child(parent, Node::new_elem(nelm));
+    match elm.attr(BIND).and_then(|k| lookup(scopes, k.trim())) {
+        Some(value) => append_value(out, id, value),
+        None => {
+            for child in tdoc.children(tid) {
+                fill_node(tdoc, child, out, id, scopes);
+            }
+        }
+    }
+}
+
+fn lookup<'b>(scopes: &[&'b dyn Bindings], key: &str)
+    -> Option<Binding<'b>>
+{
+    scopes.iter().rev().find_map(|&b| b.lookup(key))
+}
+
+fn is_truthy(value: Option<Binding<'_>>) -> bool {
+    match value {
+        Some(Binding::Text(s)) => !s.is_empty(),
+        Some(Binding::Bool(b)) => b,
+        Some(Binding::Fragment(f)) => !f.is_empty(),
+        Some(Binding::List(items)) => !items.is_empty(),
+        None => false,
+    }
+}
+
+fn is_marker(lname: &str) -> bool {
+    lname == BIND || lname == IF || lname == EACH ||
+        lname.starts_with(BIND_PREFIX)
+}
+
+// Append the value as children of parent.
+fn append_value(out: &mut Document, parent: NodeId, value: Binding<'_>) {
+    match value {
+        Binding::Text(s) => {
+            if !s.is_empty() {
+                out.append_child(parent, Node::new_text(s.as_ref()));
+            }
+        }
+        Binding::Bool(b) => {
+            out.append_child(parent, Node::new_text(b.to_string()));
+        }
+        Binding::Fragment(f) => {
+            for root in f.roots() {
+                out.append_deep_clone(parent, root.doc(), root.id());
+            }
+        }
+        Binding::List(_) => {}
+    }
+}
@@ -1,18 +1,30 @@
+use std::cell::RefCell;
 use std::fs::File;
 use std::{io, io::Read};
 
 use crate::{
-    Attribute, Document, Element, Node, NodeData, NodeId, NodeRef,
-    QualName, StrTendril,
-    filter, filter::Action,
-    html, html::{a, t, TAG_META},
+    Attribute, Document, DuplicateAttrs, Element, Fragment, Node, NodeData,
+    NodeId, NodeIdError, NodeRef, QualName, SendDocument, SerializeOptions,
+    EqOptions, Escaping, StrTendril,
+    archive::{Mhtml, WarcReader},
+    boilerplate::{Boilerplate, CorpusStats},
+    diff::{diff, Edit},
+    filter, filter::{Action, AttrNormalizer, TextNormalizer, Visitor},
+    html, html::{a, t, ParseErrorKind, TAG_META},
+    lang::Direction,
+    layout::{Layout, Rect},
+    media::MediaKind,
+    provenance::Provenance,
+    render,
+    table::CellRef,
+    traverse::Order,
     HTTP_CTYPE_CONF,
 };
 
 #[cfg(feature = "xml")]
 use crate::xml;
 
-use crate::chain_filters;
+use crate::{assert_doc_eq, chain_filters};
 use crate::logger::ensure_logger;
 use crate::decode::EncodingHint;
 
@@ -24,8 +36,14 @@ use rand::Rng;
 #[cfg(target_pointer_width = "64")]
 fn size_of() {
     use std::mem::size_of;
-    assert_eq!(size_of::<Node>(), 80);
-    assert_eq!(size_of::<NodeId>(), 4);
+    if cfg!(debug_assertions) {
+        // With Document tags
+        assert_eq!(size_of::<Node>(), 96);
+        assert_eq!(size_of::<NodeId>(), 8);
+    } else {
+        assert_eq!(size_of::<Node>(), 80);
+        assert_eq!(size_of::<NodeId>(), 4);
+    }
     assert_eq!(size_of::<NodeData>(), 56);
     assert_eq!(size_of::<Element>(), 48);
     assert_eq!(size_of::<Attribute>(), 40);
@@ -538,6 +556,11 @@ fn test_plaintext() {
         "<div><plaintext>bar\n\tbaz</div></plaintext></div>",
         doc.to_string()
     );
+    // But with XHTML output, its content is escaped for XML.
+    assert_eq!(
+        "<div><plaintext>bar\n\tbaz&lt;/div&gt;</plaintext></div>",
+        doc.to_string_with(&SerializeOptions::new().xhtml(true))
+    );
 
     doc.filter(chain_filters!(
         filter::xmp_to_pre,
@@ -1096,3 +1119,3711 @@ fn test_russez_windows1251_meta() {
         "txt: {}", body.text().unwrap().as_ref()
     );
 }
+
+#[test]
+fn test_page_meta() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        r##"<html><head>
+        <title>
+          The  Title
+        </title>
+        <meta name="Description" content=" A description. ">
+        <meta property="og:title" content="OG Title">
+        <meta property="og:image" content="/1.png">
+        <meta property="og:image" content="/2.png">
+        <meta name="twitter:card" content="summary">
+        <link rel="alternate canonical" href="https://example.com/a">
+        <script type="application/ld+json">
+          {"@type": "Article"}
+        </script>
+        <script>var x = 1;</script>
+        </head><body><title>Not this</title></body></html>"##
+            .as_bytes()
+    );
+    let pm = doc.page_meta();
+    assert_eq!("The Title", pm.title.as_ref().unwrap().as_ref());
+    assert_eq!("A description.", pm.description.as_ref().unwrap().as_ref());
+    assert_eq!(
+        "https://example.com/a",
+        pm.canonical.as_ref().unwrap().as_ref());
+    assert_eq!(3, pm.open_graph.len());
+    assert_eq!("OG Title", pm.open_graph_value("og:TITLE").unwrap().as_ref());
+    assert_eq!("/1.png", pm.open_graph_value("og:image").unwrap().as_ref());
+    assert_eq!("summary", pm.twitter_value("twitter:card").unwrap().as_ref());
+    assert_eq!(1, pm.json_ld.len());
+    assert_eq!(r#"{"@type": "Article"}"#, pm.json_ld[0].as_ref());
+}
+
+#[test]
+fn test_layout_above_fold() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><p>one</p><p>two</p><p>three</p></div>".as_bytes()
+    );
+    let ps = doc.root_element_ref().unwrap()
+        .select(|n| n.is_elem(t::P))
+        .map(|n| n.id())
+        .collect::<Vec<_>>();
+    assert_eq!(3, ps.len());
+
+    let mut layout = Layout::new();
+    layout.set(ps[0], Rect::new(0.0, 10.0, 800.0, 400.0));
+    layout.set(ps[1], Rect::new(0.0, 410.0, 800.0, 400.0));
+    layout.set(ps[2], Rect::new(0.0, 810.0, 800.0, 400.0));
+
+    let above = layout.above_fold(&doc, 600.0)
+        .map(|n| n.id())
+        .collect::<Vec<_>>();
+    assert_eq!(&ps[0..2], &above[..]);
+
+    let text = doc[ps[2]].first_child.unwrap();
+    assert_eq!(layout.get(ps[2]), layout.nearest(&doc, text));
+    assert!(!layout.is_above_fold(text, 600.0));
+}
+
+#[test]
+fn test_as_table() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        r##"<table>
+          <caption>Cap</caption>
+          <thead><tr><th>A</th><th colspan="2">BC</th></tr></thead>
+          <tbody>
+            <tr><td rowspan="2">1</td><td>2</td><td>3</td></tr>
+            <tr><td>5</td><td>6</td></tr>
+            <tr><td>7</td></tr>
+          </tbody>
+        </table>"##
+            .as_bytes()
+    );
+    let root = doc.root_element_ref().unwrap();
+    assert!(root.find(|n| n.is_elem(t::TD)).unwrap().as_table().is_none());
+
+    let table = root.as_table().expect("table");
+    assert_eq!("Cap", table.caption.unwrap().text().unwrap().as_ref());
+    assert_eq!(3, table.width());
+    assert_eq!(1, table.headers.len());
+    assert_eq!(3, table.rows.len());
+
+    let texts = |row: &Vec<Option<CellRef<'_>>>| {
+        row.iter()
+            .map(|c| c.map_or(String::new(), |c| c.text().unwrap().into()))
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(vec!["A", "BC", "BC"], texts(&table.headers[0]));
+    assert!(!table.headers[0][2].unwrap().is_origin());
+    assert_eq!(vec!["1", "2", "3"], texts(&table.rows[0]));
+    assert_eq!(vec!["1", "5", "6"], texts(&table.rows[1]));
+    assert_eq!(1, table.rows[1][0].unwrap().row_offset);
+    assert_eq!(vec!["7", "", ""], texts(&table.rows[2]));
+
+    // An empty first row is not promoted to headers
+    let doc = html::parse_utf8_fragment(
+        "<table><tr></tr><tr><td>1</td></tr></table>".as_bytes()
+    );
+    let table = doc.root_element_ref().unwrap().as_table().expect("table");
+    assert!(table.headers.is_empty());
+    assert_eq!(2, table.rows.len());
+    assert_eq!(vec!["1"], texts(&table.rows[1]));
+}
+
+#[test]
+fn test_provenance() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        r#"<div><p class="x">para</p></div>"#.as_bytes()
+    );
+    let pid = doc.root_element_ref().unwrap()
+        .find(|n| n.is_elem(t::P))
+        .unwrap()
+        .id();
+    let prov = Provenance::rule("keep-p").with_score(1.5).with_span(5, 25);
+    assert!(doc.annotate(pid, &prov));
+    assert_eq!(
+        r#"<div><p class="x" data-marked-rule="keep-p" data-marked-score="1.5" data-marked-span="5-25">para</p></div>"#,
+        doc.to_string()
+    );
+    assert_eq!(
+        Some(prov),
+        Provenance::read(doc[pid].as_element().unwrap())
+    );
+
+    doc.filter(filter::strip_provenance);
+    assert_eq!(r#"<div><p class="x">para</p></div>"#, doc.to_string());
+}
+
+#[test]
+fn test_text_normalizer_config() {
+    ensure_logger();
+    let src = "<div><p> a \n\n b\u{a0} c </p><p><code> x   y </code></p></div>";
+
+    let mut doc = html::parse_utf8_fragment(src.as_bytes());
+    doc.filter(filter::text_normalize);
+    assert_eq!(
+        "<div><p>a b c</p><p><code> x y </code></p></div>",
+        doc.to_string()
+    );
+
+    let tn = TextNormalizer::builder()
+        .preserve_whitespace_in(t::CODE)
+        .collapse_nbsp(false)
+        .collapse_newlines(false)
+        .build();
+    let mut doc = html::parse_utf8_fragment(src.as_bytes());
+    doc.filter(|p, d| tn.filter(p, d));
+    assert_eq!(
+        "<div><p>a\nb&nbsp; c</p><p><code> x   y </code></p></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_lang_dir() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        r#"<html lang="en"><body><p>one</p><div lang="ar" dir="rtl"><p>two</p></div></body></html>"#
+            .as_bytes()
+    );
+    let root = doc.root_element_ref().unwrap();
+    let ps = root.select(|n| n.is_elem(t::P)).collect::<Vec<_>>();
+    assert_eq!("en", ps[0].language().unwrap().as_ref());
+    assert_eq!(Direction::Ltr, ps[0].direction());
+    assert_eq!("ar", ps[1].language().unwrap().as_ref());
+    assert_eq!(Direction::Rtl, ps[1].direction());
+    assert_eq!(None, doc.document_node_ref().language());
+
+    doc.filter_breadth(filter::propagate_lang_dir);
+    assert_eq!(
+        r#"<html lang="en"><head lang="en"></head><body lang="en"><p lang="en">one</p><div lang="ar" dir="rtl"><p lang="ar" dir="rtl">two</p></div></body></html>"#,
+        doc.to_string()
+    );
+}
+
+// Pre-order via recursive `children`, for comparison with `descendants`
+fn children_pre_order(doc: &Document, id: NodeId, out: &mut Vec<NodeId>) {
+    out.push(id);
+    for child in doc.children(id) {
+        children_pre_order(doc, child, out);
+    }
+}
+
+#[test]
+fn test_document_order_consistent() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<div>foo <a href=\"link\"><i>bar</i>s</a> <strike>baz</strike></div>\
+         <div>sib<strike>ling<i>s</i></strike></div><!--c-->"
+            .as_bytes()
+    );
+    doc.assert_consistent();
+
+    doc.filter(chain_filters!(strike_fold_filter, filter::detach_comments));
+    let tid = doc.document_node_ref()
+        .find(|n| n.is_elem(t::I))
+        .unwrap()
+        .id();
+    doc.unlink(tid);
+    doc.assert_consistent();
+
+    let mut visits = Vec::new();
+    children_pre_order(&doc, Document::DOCUMENT_NODE_ID, &mut visits);
+    assert_eq!(visits, doc.nodes().collect::<Vec<_>>());
+
+    let filter_visits = RefCell::new(Vec::new());
+    doc.filter_breadth(|p, _d| {
+        filter_visits.borrow_mut().push(p.id());
+        Action::Continue
+    });
+    assert_eq!(visits, filter_visits.into_inner());
+
+    let before = doc.to_string();
+    let texts = doc.nodes()
+        .filter_map(|id| doc[id].as_text().map(|t| t.to_string()))
+        .collect::<String>();
+
+    doc.compact();
+    doc.assert_consistent();
+    assert_eq!(before, doc.to_string());
+    assert_eq!(texts, doc.text(Document::DOCUMENT_NODE_ID).unwrap().as_ref());
+
+    let mut visits = Vec::new();
+    children_pre_order(&doc, Document::DOCUMENT_NODE_ID, &mut visits);
+    assert_eq!(visits, doc.nodes().collect::<Vec<_>>());
+    assert_eq!(
+        "<html><head></head><body>\
+         <div>foo <a href=\"link\">s</a> baz</div>\
+         <div>sibling<i>s</i></div>\
+         </body></html>",
+        doc.to_string()
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn test_assert_consistent_panics() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p>a</p><p>b</p></div>".as_bytes()
+    );
+    let root = doc.root_element().unwrap();
+    let first = doc[root].first_child.unwrap();
+    doc[first].prev_sibling = Some(root);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_user_data() {
+    ensure_logger();
+    #[derive(Debug, PartialEq)]
+    struct Score(f32);
+
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p>one</p><p>two</p></div>".as_bytes()
+    );
+    let root = doc.root_element().unwrap();
+    let ps = doc.children(root).collect::<Vec<_>>();
+
+    assert_eq!(None, doc.set_user_data(ps[1], Score(2.0)));
+    assert_eq!(Some(Score(2.0)), doc.set_user_data(ps[1], Score(3.0)));
+    doc.set_user_data(ps[1], "label");
+    doc.set_user_data(root, Score(1.0));
+    doc.user_data_mut::<Score>(root).unwrap().0 += 0.5;
+
+    assert_eq!(Some(&Score(3.0)), doc.user_data::<Score>(ps[1]));
+    assert_eq!(None, doc.user_data::<Score>(ps[0]));
+    assert_eq!(Some(&"label"), doc.user_data::<&str>(ps[1]));
+
+    doc.unlink(ps[0]);
+    doc.compact();
+    let root = doc.root_element_ref().unwrap();
+    assert_eq!(Some(&Score(1.5)), root.user_data::<Score>());
+    let p = root.find_child(|n| n.is_elem(t::P)).unwrap();
+    assert_eq!(Some(&Score(3.0)), p.user_data::<Score>());
+    let pid = p.id();
+
+    doc.clear_user_data::<Score>();
+    assert_eq!(None, doc.user_data::<Score>(pid));
+    assert_eq!(Some("label"), doc.remove_user_data::<&str>(pid));
+    assert_eq!(None, doc.remove_user_data::<&str>(pid));
+}
+
+#[test]
+fn test_compact_id_map() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p>one</p><p>two</p><p>three</p></div>".as_bytes()
+    );
+    let root = doc.root_element().unwrap();
+    let ps = doc.children(root).collect::<Vec<_>>();
+    let mut layout = Layout::new();
+    layout.set(ps[0], Rect::new(0.0, 0.0, 10.0, 10.0));
+    layout.set(ps[2], Rect::new(0.0, 10.0, 10.0, 10.0));
+
+    doc.unlink(ps[0]);
+    let map = doc.compact();
+    doc.assert_consistent();
+    layout.remap(&map);
+
+    assert_eq!(Some(Document::DOCUMENT_NODE_ID),
+               map.get(Document::DOCUMENT_NODE_ID));
+    assert_eq!(None, map.get(ps[0]));
+    let nroot = map.get(root).unwrap();
+    assert_eq!(Some(nroot), doc.root_element());
+    let p3 = map.get(ps[2]).unwrap();
+    assert_eq!("three", doc.text(p3).unwrap().as_ref());
+    assert_eq!(Some(Rect::new(0.0, 10.0, 10.0, 10.0)), layout.get(p3));
+    assert_eq!(1, layout.len());
+    assert_eq!(doc.nodes().count(), map.iter().count());
+}
+
+#[test]
+fn test_memory_usage() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p class=\"a-long-class-name\">some longer text</p>\
+         <p>short</p></div>"
+            .as_bytes()
+    );
+    doc.compact();
+    let mu = doc.memory_usage();
+    assert_eq!(doc.len() as usize, mu.nodes);
+    assert_eq!(0, mu.garbage_nodes);
+    assert_eq!("some longer text".len(), mu.text_bytes);
+    assert!(mu.attr_bytes >= "a-long-class-name".len());
+    assert!(mu.total_bytes() > mu.node_bytes);
+
+    let p = doc.root_element_ref().unwrap()
+        .find_child(|n| n.is_elem(t::P))
+        .unwrap()
+        .id();
+    doc.unlink(p);
+    let mu = doc.memory_usage();
+    assert_eq!(2, mu.garbage_nodes);
+    assert_eq!(2 * std::mem::size_of::<Node>(), mu.garbage_bytes);
+    assert_eq!(2, doc.detached_count());
+}
+
+#[test]
+fn test_compact_policy() {
+    ensure_logger();
+    let input = "<p>x</p><!--c-->".repeat(500);
+    let mut doc = html::parse_utf8(input.as_bytes());
+    assert_eq!(0, doc.detached_count());
+    assert_eq!(None, doc.compact_policy());
+
+    // Without a policy, garbage accumulates
+    doc.filter(filter::detach_comments);
+    assert_eq!(500, doc.detached_count());
+
+    // Below threshold
+    let policy = crate::CompactPolicy { ratio: 0.5, min_detached: 100 };
+    doc.set_compact_policy(Some(policy));
+    assert!(doc.compact_if_needed().is_none());
+    assert_eq!(500, doc.detached_count());
+
+    // Above threshold, compacted after the pass
+    doc.filter(|p, _| {
+        if p.is_elem(t::P) { Action::Detach } else { Action::Continue }
+    });
+    doc.assert_consistent();
+    assert_eq!(0, doc.detached_count());
+    assert_eq!(doc.nodes().count() as u32, doc.len());
+    assert_eq!(Some(policy), doc.compact_policy());
+    assert_eq!(
+        "<html><head></head><body></body></html>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_parse_capacity_guess() {
+    ensure_logger();
+    let mut src = String::from("<div>");
+    for i in 0..1000 {
+        src.push_str(&format!("<p>para {}</p>", i));
+    }
+    src.push_str("</div>");
+
+    let doc = html::parse_utf8_fragment(src.as_bytes());
+    let len = doc.len() as usize;
+    assert!(len > 2000);
+    assert!(doc.nodes.capacity() >= len / 2, "cap {}", doc.nodes.capacity());
+    let doc = html::parse_utf8(src.as_bytes());
+    assert!(doc.nodes.capacity() >= len / 2);
+}
+
+#[test]
+fn test_parse_events() {
+    ensure_logger();
+    let src = r#"<!DOCTYPE html>
+<p>one <a href="x">link</a></p>
+<script>var s = '<a href="y">';</script>
+<!-- <a href="c"> -->
+<A HREF="z"/>"#;
+    let mut hrefs = Vec::new();
+    let mut texts = 0;
+    let mut doctype = None;
+    html::parse_events(src.as_bytes(), |ev| match ev {
+        html::Event::StartTag { name, attrs, .. } if *name == t::A => {
+            for attr in attrs {
+                if attr.name.local == a::HREF {
+                    hrefs.push(attr.value.to_string());
+                }
+            }
+        }
+        html::Event::Text(_) => texts += 1,
+        html::Event::Doctype { name } => {
+            doctype = name.map(|n| n.to_string());
+        }
+        _ => {}
+    });
+    assert_eq!(vec!["x", "z"], hrefs);
+    assert_eq!(Some("html".to_owned()), doctype);
+    assert!(texts > 0);
+}
+
+#[test]
+fn test_closest_matches() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        r#"<div id="main" class="x content"><p lang="en" data-k="a b">
+           <b>text</b></p></div>"#
+            .as_bytes()
+    );
+    let b = doc.document_node_ref().find(|n| n.is(t::B)).unwrap();
+    assert!(b.is(t::B));
+    assert!(!b.is(t::P));
+
+    let p = b.closest(|n| n.is(t::P)).unwrap();
+    assert!(p.matches("p"));
+    assert!(p.matches("P[lang]"));
+    assert!(p.matches("*[lang=en]"));
+    assert!(p.matches("p[data-k~=\"b\"]"));
+    assert!(!p.matches("p[data-k=b]"));
+    assert!(!p.matches("div p"));
+    assert!(!p.matches(""));
+    assert_eq!(Some(p), b.closest(|n| n.matches("[lang]")));
+    assert_eq!(Some(b), b.closest(|n| n.matches("b, i")));
+
+    let div = b.closest(|n| n.matches("div#main.content")).unwrap();
+    assert!(div.matches(".x.content"));
+    assert!(!div.matches("div.y"));
+    assert!(!div.matches("#other"));
+    assert!(div.matches("span, #main"));
+    assert!(b.closest(|n| n.matches(".y")).is_none());
+    assert!(!doc.document_node_ref().matches("*"));
+}
+
+#[test]
+fn test_sibling_iterators() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><i>1</i><b>2</b><p>3</p><s>4</s></div>".as_bytes()
+    );
+    let div = doc.document_node_ref().find(|n| n.is(t::DIV)).unwrap();
+    fn names<'a>(it: impl Iterator<Item = NodeRef<'a>>) -> Vec<String> {
+        it.map(|n| n.as_element().unwrap().name.local.to_string())
+            .collect()
+    }
+    assert_eq!(vec!["i", "b", "p", "s"], names(div.children()));
+    assert_eq!(vec!["s", "p", "b", "i"], names(div.children().rev()));
+
+    let mut both = div.children();
+    assert!(both.next().unwrap().is(t::I));
+    assert!(both.next_back().unwrap().is(t::S));
+    assert!(both.next_back().unwrap().is(t::P));
+    assert!(both.next().unwrap().is(t::B));
+    assert!(both.next().is_none());
+    assert!(both.next_back().is_none());
+
+    let p = div.find_child(|n| n.is(t::P)).unwrap();
+    assert_eq!(vec!["s"], names(p.next_siblings()));
+    assert_eq!(vec!["b", "i"], names(p.prev_siblings()));
+    assert_eq!(vec!["i", "b"], names(p.prev_siblings().rev()));
+
+    let text = p.children().next().unwrap();
+    assert_eq!(0, text.next_siblings().count());
+    assert_eq!(0, text.prev_siblings().count());
+    let ancestors: Vec<_> = text.ancestors().collect();
+    assert_eq!(p, ancestors[0]);
+    assert_eq!(div, ancestors[1]);
+    assert_eq!(
+        Some(doc.document_node_ref()),
+        ancestors.last().copied()
+    );
+    assert_eq!(0, doc.document_node_ref().ancestors().count());
+}
+
+#[test]
+fn test_traverse_orders() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><p><b>1</b>2</p><i>3</i></div>".as_bytes()
+    );
+    let div = doc.document_node_ref().find(|n| n.is(t::DIV)).unwrap();
+    let names = |order| {
+        div.traverse(order)
+            .filter_map(|n| n.as_element().map(|e| e.name.local.to_string()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["div", "p", "b", "i"], names(Order::PreOrder));
+    assert_eq!(vec!["div", "p", "i", "b"], names(Order::BreadthFirst));
+    assert_eq!(vec!["b", "p", "i", "div"], names(Order::PostOrder));
+    assert_eq!(vec!["i", "b", "p", "div"], names(Order::ReverseDocument));
+
+    let pre: Vec<_> = div.descendants().collect();
+    assert_eq!(pre, div.traverse(Order::PreOrder).collect::<Vec<_>>());
+    let mut rev: Vec<_> = div.traverse(Order::ReverseDocument).collect();
+    rev.reverse();
+    assert_eq!(pre, rev);
+    assert_eq!(7, div.traverse(Order::PostOrder).count());
+    assert_eq!(7, div.traverse(Order::BreadthFirst).count());
+
+    let b = div.find(|n| n.is(t::B)).unwrap();
+    assert_eq!(2, b.traverse(Order::PostOrder).count());
+    assert_eq!(Some(b), b.traverse(Order::PostOrder).last());
+}
+
+#[test]
+fn test_diff() {
+    ensure_logger();
+    let old = html::parse_utf8_fragment(
+        r#"<div><p class="a">one</p><i>x</i></div>"#.as_bytes()
+    );
+    let new = html::parse_utf8_fragment(
+        r#"<div><p class="b" id="z">two</p><b>y</b></div>"#.as_bytes()
+    );
+    assert!(diff(&old, &old).is_empty());
+
+    let edits = diff(&old, &new);
+    assert_eq!(5, edits.len(), "{:?}", edits);
+    let p = old.document_node_ref().find(|n| n.is(t::P)).unwrap();
+    let i = old.document_node_ref().find(|n| n.is(t::I)).unwrap();
+    match &edits[0] {
+        Edit::SetAttr { id, name, value: Some(v) } => {
+            assert_eq!(p.id(), *id);
+            assert_eq!(a::CLASS, name.local);
+            assert_eq!("b", v.as_ref());
+        }
+        e => panic!("unexpected {:?}", e),
+    }
+    match &edits[1] {
+        Edit::SetAttr { name, .. } => assert_eq!(a::ID, name.local),
+        e => panic!("unexpected {:?}", e),
+    }
+    match &edits[2] {
+        Edit::ReplaceText { text, .. } => assert_eq!("two", text.as_ref()),
+        e => panic!("unexpected {:?}", e),
+    }
+    match &edits[3] {
+        Edit::Remove { id } => assert_eq!(i.id(), *id),
+        e => panic!("unexpected {:?}", e),
+    }
+    match &edits[4] {
+        Edit::Insert { parent, index, tree } => {
+            assert_eq!(p.parent().unwrap().id(), *parent);
+            assert_eq!(1, *index);
+            assert_eq!("<b>y</b>", tree.to_string());
+        }
+        e => panic!("unexpected {:?}", e),
+    }
+
+    let edits = diff(&new, &old);
+    assert!(edits.iter().any(|e| {
+        if let Edit::SetAttr { value: None, .. } = e { true } else { false }
+    }));
+}
+
+#[test]
+fn test_diff_apply() {
+    ensure_logger();
+    let pairs = [
+        (r#"<div><p class="a">one</p><i>x</i></div>"#,
+         r#"<div><p class="b" id="z">two</p><b>y</b></div>"#),
+        ("<ul><li>1</li><li>2</li><li>3</li></ul>",
+         "<ul><li>0</li><li>1</li><li>3</li><li>4</li></ul>"),
+        ("<p>a<!--c-->b</p><hr>", "<hr><p title=t>a<!--d--><br>b</p>"),
+        ("<div></div>", "text"),
+    ];
+    for &(old_src, new_src) in &pairs {
+        let mut doc = html::parse_utf8_fragment(old_src.as_bytes());
+        let new = html::parse_utf8_fragment(new_src.as_bytes());
+        let edits = diff(&doc, &new);
+        doc.apply(&edits).unwrap();
+        doc.assert_consistent();
+        assert_eq!(new.to_string(), doc.to_string());
+        assert!(diff(&doc, &new).is_empty());
+        doc.compact();
+        assert_eq!(new.to_string(), doc.to_string());
+    }
+
+    let mut doc = html::parse_utf8_fragment("<p>one</p>".as_bytes());
+    let p = doc.document_node_ref().find(|n| n.is(t::P)).unwrap().id();
+    let text = doc.children(p).next().unwrap();
+    let edits = vec![
+        Edit::Remove { id: p },
+        Edit::ReplaceText { id: text, text: "two".into() },
+    ];
+    let err = doc.apply(&edits).unwrap_err();
+    assert_eq!(1, err.index());
+    assert_eq!("edit 1: node not found", err.to_string());
+    let err = doc.apply(&[Edit::Remove { id: Document::DOCUMENT_NODE_ID }])
+        .unwrap_err();
+    assert_eq!(0, err.index());
+}
+
+#[test]
+fn test_boilerplate() {
+    ensure_logger();
+    let page = |body: &str, year: u32| {
+        html::parse_utf8(format!(
+            "<html><head><title>Site</title></head><body>\
+             <nav><ul><li>Home</li><li>About</li></ul></nav>\
+             <article><p>{}</p></article>\
+             <footer><p>Copyright {} Site</p></footer>\
+             </body></html>",
+            body, year
+        ).as_bytes())
+    };
+    let mut stats = CorpusStats::new();
+    stats.add(&page("First article.", 2019));
+    stats.add(&page("Second article.", 2020));
+    stats.add(&page("Third article.", 2021));
+    assert_eq!(3, stats.documents());
+
+    let mut doc = page("Fourth article.", 2022);
+    assert_eq!(0, doc.mark_boilerplate(&CorpusStats::new(), 0.5));
+    assert_eq!(2, doc.mark_boilerplate(&stats, 0.5));
+    let nav = doc.document_node_ref().find(|n| n.is(t::NAV)).unwrap();
+    assert_eq!(
+        Some(&Boilerplate { frequency: 1.0 }),
+        nav.user_data::<Boilerplate>()
+    );
+    let ul = nav.find(|n| n.is(t::UL)).unwrap();
+    assert!(ul.user_data::<Boilerplate>().is_none());
+
+    doc.filter(filter::detach_boilerplate);
+    assert_eq!(
+        "<html><head><title>Site</title></head><body>\
+         <article><p>Fourth article.</p></article>\
+         </body></html>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_attr_tokens() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        r#"<img class=" hero  wide " style="width: 10px; color:red"
+                srcset="a.png 1x, b.png 2x">"#
+            .as_bytes()
+    );
+    let img = doc.document_node_ref().find(|n| n.is(t::IMG)).unwrap();
+    let img = img.as_element().unwrap();
+    assert_eq!(vec!["hero", "wide"], img.class_list().collect::<Vec<_>>());
+    assert!(img.has_class("wide"));
+    assert!(!img.has_class("hero wide"));
+    assert_eq!(vec![("width", "10px"), ("color", "red")], img.style());
+    let srcset = img.srcset();
+    assert_eq!(2, srcset.len());
+    assert_eq!("b.png", srcset[1].url);
+    assert_eq!(
+        Some(crate::SrcsetDescriptor::Density(2.0)),
+        srcset[1].descriptor
+    );
+
+    let p = Element::new(t::P);
+    assert_eq!(0, p.class_list().count());
+    assert!(p.srcset().is_empty());
+    assert!(p.style().is_empty());
+}
+
+#[test]
+fn test_normalize_attributes() {
+    ensure_logger();
+    let src = "<a href=' /x ' class=' a \t  b ' title='t\n u'>x</a>\
+               <input value=' v '>";
+    let mut doc = html::parse_utf8_fragment(src.as_bytes());
+    doc.filter(filter::normalize_attributes);
+    assert_eq!(
+        "<div><a href=\"/x\" class=\"a b\" title=\"t u\">x</a>\
+         <input value=\" v \"></div>",
+        doc.to_string()
+    );
+
+    let an = AttrNormalizer::builder()
+        .collapse(false)
+        .preserve(a::TITLE)
+        .build();
+    let mut doc = html::parse_utf8_fragment(src.as_bytes());
+    doc.filter(|p, d| an.filter(p, d));
+    assert_eq!(
+        "<div><a href=\"/x\" class=\"a \t  b\" title=\"t\n u\">x</a>\
+         <input value=\" v \"></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_serialize_escaping() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<p title='a \"&\" é'>1 &lt; 2 &amp; ©\u{A0}— é\
+         <script>a<b</script></p>"
+            .as_bytes()
+    );
+    assert_eq!(
+        "<p title=\"a &quot;&amp;&quot; é\">1 &lt; 2 &amp; ©&nbsp;— é\
+         <script>a<b</script></p>",
+        doc.to_string()
+    );
+    assert_eq!(
+        doc.to_string(),
+        doc.to_string_with(&SerializeOptions::new())
+    );
+    assert_eq!(
+        "<p title=\"a &quot;&amp;&quot; é\">1 &lt; 2 &amp; \
+         &copy;&nbsp;&mdash; é<script>a<b</script></p>",
+        doc.to_string_with(
+            &SerializeOptions::new().escaping(Escaping::Named)
+        )
+    );
+    assert_eq!(
+        "<p title=\"a &#x22;&#x26;&#x22; &#xE9;\">1 &#x3C; 2 &#x26; \
+         &#xA9;&#xA0;&#x2014; &#xE9;<script>a<b</script></p>",
+        doc.to_string_with(
+            &SerializeOptions::new()
+                .escaping(Escaping::Numeric)
+                .ascii_only(true)
+        )
+    );
+    assert_eq!(
+        "<p title=\"a &quot;&amp;&quot; &#xE9;\">1 &lt; 2 &amp; \
+         &copy;&nbsp;&mdash; &#xE9;<script>a<b</script></p>",
+        doc.root_element_ref().unwrap().to_string_with(
+            &SerializeOptions::new()
+                .escaping(Escaping::Named)
+                .ascii_only(true)
+        )
+    );
+}
+
+#[test]
+fn test_serialize_xhtml() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<!DOCTYPE html><html><head><meta charset=utf-8>\
+         <style>p > b { color: red }</style>\
+         <script>if (a < b && c) {}</script></head>\
+         <body><p>a<br>b\u{A0}©<img src=x alt=''></p>\
+         <xmp><i></xmp></body></html>"
+            .as_bytes()
+    );
+    assert_eq!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <style>p > b { color: red }</style>\
+         <script>if (a < b && c) {}</script></head>\
+         <body><p>a<br>b&nbsp;©<img src=\"x\" alt=\"\"></p>\
+         <xmp><i></xmp></body></html>",
+        doc.to_string()
+    );
+    assert_eq!(
+        "<!DOCTYPE html><html xmlns=\"http://www.w3.org/1999/xhtml\">\
+         <head><meta charset=\"utf-8\"/>\
+         <style>p > b { color: red }</style>\
+         <script><![CDATA[if (a < b && c) {}]]></script></head>\
+         <body><p>a<br/>b&#xA0;&#xA9;<img src=\"x\" alt=\"\"/></p>\
+         <xmp>&lt;i&gt;</xmp></body></html>",
+        doc.to_string_with(
+            &SerializeOptions::new()
+                .xhtml(true)
+                .escaping(Escaping::Named)
+        )
+    );
+}
+
+#[test]
+fn test_serialize_xhtml_attr_lt() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<a title=\"a<b>c\">x</a>".as_bytes()
+    );
+    assert_eq!("<div><a title=\"a<b>c\">x</a></div>", doc.to_string());
+    assert_eq!(
+        "<div><a title=\"a&lt;b>c\">x</a></div>",
+        doc.to_string_with(&SerializeOptions::new().xhtml(true))
+    );
+}
+
+#[test]
+fn test_deep_clone_mapped() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><p>one</p><p>two <b>three</b></p></div>".as_bytes()
+    );
+    let root = doc.root_element().unwrap();
+    let ps = doc.children(root).collect::<Vec<_>>();
+    let b = doc.descendants(ps[1]).find(|&n| doc[n].is_elem(t::B)).unwrap();
+    let mut layout = Layout::new();
+    layout.set(ps[0], Rect::new(0.0, 0.0, 10.0, 10.0));
+    layout.set(b, Rect::new(0.0, 10.0, 10.0, 10.0));
+
+    let (ndoc, map) = doc.deep_clone_mapped(ps[1]);
+    ndoc.assert_consistent();
+    assert_eq!("<p>two <b>three</b></p>", ndoc.to_string());
+    assert_eq!(None, map.get(ps[0]));
+    assert_eq!(None, map.get(root));
+    assert_eq!(ndoc.root_element(), map.get(ps[1]));
+    let nb = map.get(b).unwrap();
+    assert_eq!("three", ndoc.text(nb).unwrap().as_ref());
+    assert_eq!(4, map.iter().count());
+
+    let mut nlayout = layout.clone();
+    nlayout.remap(&map);
+    assert_eq!(1, nlayout.len());
+    assert_eq!(layout.get(b), nlayout.get(nb));
+
+    let (ndoc, map) = doc.deep_clone_mapped(Document::DOCUMENT_NODE_ID);
+    assert_eq!(doc.to_string(), ndoc.to_string());
+    assert_eq!(doc.nodes().count(), map.iter().count());
+}
+
+#[test]
+fn test_fragment() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><p>one</p>text<p>two</p></div>".as_bytes()
+    );
+    let p = doc.document_node_ref().find(|n| n.is(t::P)).unwrap();
+    let frag = p.to_fragment();
+    assert_eq!(1, frag.len());
+    assert_eq!("<p>one</p>", frag.to_string());
+
+    let frag = doc.root_element_ref().unwrap().to_fragment();
+    let mut inner = frag.into_document();
+    let div = inner.root_element().unwrap();
+    inner.fold(div);
+    let frag = Fragment::from_document(inner);
+    assert_eq!(3, frag.len());
+    assert!(frag.roots().nth(1).unwrap().as_text().is_some());
+    assert_eq!("<p>one</p>text<p>two</p>", frag.to_string());
+    assert_eq!(frag.to_string(), frag.clone().to_string());
+
+    let mut doc = html::parse_utf8_fragment("<ul><li>a</li></ul>".as_bytes());
+    let ul = doc.root_element().unwrap();
+    doc.append_fragment(ul, frag);
+    doc.append_fragment(ul, Fragment::new());
+    doc.assert_consistent();
+    assert_eq!(
+        "<ul><li>a</li><p>one</p>text<p>two</p></ul>",
+        doc.to_string()
+    );
+    assert!(Fragment::new().is_empty());
+}
+
+#[test]
+fn test_parse_fragment_in_context() {
+    ensure_logger();
+    let frag = html::parse_fragment_in_context(t::BODY, b"");
+    assert!(frag.is_empty());
+    assert_eq!("", frag.to_string());
+
+    let frag = html::parse_fragment_in_context(
+        t::DIV,
+        "text <i>one</i><p>two</p>".as_bytes()
+    );
+    assert_eq!(3, frag.len());
+    assert_eq!("text <i>one</i><p>two</p>", frag.to_string());
+    frag.clone().into_document().assert_consistent();
+
+    // In table context, rows are retained (in an implied tbody)
+    let frag = html::parse_fragment_in_context(
+        t::TABLE,
+        "<tr><td>1</td></tr>".as_bytes()
+    );
+    assert_eq!(
+        "<tbody><tr><td>1</td></tr></tbody>",
+        frag.to_string()
+    );
+    let frag = html::parse_fragment_in_context(
+        t::TR,
+        "<td>1</td><td>2</td>".as_bytes()
+    );
+    assert_eq!(2, frag.len());
+
+    // Whereas in div context, they are dropped as misplaced
+    let frag = html::parse_fragment_in_context(
+        t::DIV,
+        "<tr><td>1</td></tr>".as_bytes()
+    );
+    assert_eq!("1", frag.to_string());
+}
+
+#[test]
+fn test_parse_checked() {
+    ensure_logger();
+    let (doc, errors) = html::parse_utf8_checked(
+        "<!DOCTYPE html>\n<p>one</span>\n<p>two</p>".as_bytes()
+    );
+    doc.assert_consistent();
+    assert_eq!(
+        html::parse_utf8("<!DOCTYPE html>\n<p>one</span>\n<p>two</p>"
+            .as_bytes()).to_string(),
+        doc.to_string()
+    );
+    assert!(!errors.is_empty());
+    assert_eq!(ParseErrorKind::Nesting, errors[0].kind());
+    assert_eq!(2, errors[0].line());
+    assert_eq!(None, errors[0].offset());
+
+    let (doc, errors) = html::parse_utf8_checked(
+        b"<!DOCTYPE html>\n<p>a\xFFb\n<p>c\xC3</p>"
+    );
+    assert_eq!(2, errors.len(), "{:?}", errors);
+    assert_eq!(ParseErrorKind::Encoding, errors[0].kind());
+    assert_eq!((2, Some(20)), (errors[0].line(), errors[0].offset()));
+    assert_eq!((3, Some(27)), (errors[1].line(), errors[1].offset()));
+    assert_eq!("line 2 (byte 20): invalid byte sequence",
+               errors[0].to_string());
+    assert!(doc.to_string().contains("a\u{FFFD}b"));
+
+    let (_, errors) = html::parse_utf8_checked(
+        "<!DOCTYPE html><p>fine</p>".as_bytes()
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_source_spans() {
+    ensure_logger();
+    let html = "<!DOCTYPE html>\n\
+                <html><body>\n\
+                <div>\n\
+                <p>one\n\
+                two</p>\n\
+                <img src=\"a.png\">\n\
+                </div>\n\
+                </body></html>";
+    let doc = html::parse_utf8_with_spans(html.as_bytes());
+    doc.assert_consistent();
+
+    let img = doc.document_node_ref()
+        .descendants()
+        .find(|n| n.is_elem(t::IMG))
+        .unwrap();
+    let span = doc.source_span(img.id()).unwrap();
+    assert_eq!((6, 6), (span.start_line, span.end_line));
+
+    let para = doc.document_node_ref()
+        .descendants()
+        .find(|n| n.is_elem(t::P))
+        .unwrap();
+    let span = doc.source_span(para.id()).unwrap();
+    assert_eq!((4, 5), (span.start_line, span.end_line));
+    let text = para.children().next().unwrap();
+    assert_eq!(4, doc.source_span(text.id()).unwrap().start_line);
+
+    let div = img.parent().unwrap();
+    let span = doc.source_span(div.id()).unwrap();
+    assert_eq!((3, 7), (span.start_line, span.end_line));
+
+    // Not recorded by default
+    let doc = html::parse_utf8(html.as_bytes());
+    assert_eq!(None, doc.source_span(div.id()));
+}
+
+#[test]
+fn test_parse_limits() {
+    ensure_logger();
+    let bomb = "<div>".repeat(1000) + "deep";
+    let opts = html::ParseOptions {
+        max_depth: Some(10),
+        .. html::ParseOptions::default()
+    };
+    let (doc, exceeded) = html::parse_utf8_with_options(
+        bomb.as_bytes(), &opts
+    );
+    doc.assert_consistent();
+    assert_eq!(Some(html::LimitExceeded::Depth), exceeded);
+    let divs = doc.nodes().filter(|&id| doc[id].is_elem(t::DIV)).count();
+    assert_eq!(8, divs); // below html and body
+    assert!(doc.nodes().all(|id| doc.node_and_ancestors(id).count() <= 11));
+    assert!(!doc.to_string().contains("deep"));
+
+    // Elements beyond max_depth aren't allocated at all, so the document
+    // before compaction stays small. Each object bounds the tree builder's
+    // own scope checks, which are otherwise quadratic for nested divs.
+    {
+        use html5ever::parse_document;
+        use html5ever::tendril::TendrilSink;
+
+        let bomb = "<div><object>".repeat(50_000) + "deep";
+        let opts = html::ParseOptions {
+            max_depth: Some(10),
+            .. html::ParseOptions::default()
+        };
+        let sink = html::Sink::default().limits(opts);
+        let doc = parse_document(sink, Default::default())
+            .one(bomb.as_str());
+        assert!(doc.len() < 20, "uncompacted len {}", doc.len());
+        let (doc, exceeded) = html::parse_utf8_with_options(
+            bomb.as_bytes(), &opts
+        );
+        doc.assert_consistent();
+        assert_eq!(Some(html::LimitExceeded::Depth), exceeded);
+        assert!(doc.nodes().all(|id| doc.node_and_ancestors(id).count() <= 11));
+        assert!(!doc.to_string().contains("deep"));
+    }
+
+    let many = "<p>x</p>".repeat(100_000);
+    let opts = html::ParseOptions {
+        max_nodes: Some(1_000),
+        .. html::ParseOptions::default()
+    };
+    let (doc, exceeded) = html::parse_utf8_with_options(
+        many.as_bytes(), &opts
+    );
+    doc.assert_consistent();
+    assert_eq!(Some(html::LimitExceeded::Nodes), exceeded);
+    assert!(doc.len() <= 1_000);
+    assert!(doc.len() > 900);
+
+    let opts = html::ParseOptions {
+        max_attr_len: Some(5),
+        max_text_len: Some(2),
+        .. html::ParseOptions::default()
+    };
+    let (doc, exceeded) = html::parse_utf8_with_options(
+        "<a href=\"https://example.com/\">héllo</a> world".as_bytes(),
+        &opts
+    );
+    doc.assert_consistent();
+    assert_eq!(Some(html::LimitExceeded::AttrLen), exceeded);
+    assert_eq!(
+        "<html><head></head><body><a href=\"https\">h</a> w</body></html>",
+        doc.to_string()
+    );
+
+    // Within limits
+    let (doc, exceeded) = html::parse_utf8_with_options(
+        "<p>ok</p>".as_bytes(), &opts
+    );
+    assert_eq!(None, exceeded);
+    assert_eq!(
+        "<html><head></head><body><p>ok</p></body></html>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_parse_omit() {
+    ensure_logger();
+    let input = "<html><head><style>p { color: red }</style>\
+                 <script>var x = '<p>';</script></head>\
+                 <body><!-- note --><p>one<script>x()</script> two</p>\
+                 <svg><style>a {}</style></svg></body></html>";
+    let opts = html::ParseOptions::default().omit(
+        html::NodeKinds::SCRIPT |
+        html::NodeKinds::STYLE |
+        html::NodeKinds::COMMENT
+    );
+    assert_eq!(
+        "NodeKinds(SCRIPT | STYLE | COMMENT)",
+        format!("{:?}", opts.omit)
+    );
+    let (doc, exceeded) = html::parse_utf8_with_options(
+        input.as_bytes(), &opts
+    );
+    doc.assert_consistent();
+    assert_eq!(None, exceeded);
+    assert_eq!(
+        "<html><head></head><body><p>one two</p>\
+         <svg><style>a {}</style></svg></body></html>",
+        doc.to_string()
+    );
+
+    // Adjacent text is merged, and the detached node allocated per omitted
+    // kind is removed by compaction
+    let full = html::parse_utf8(input.as_bytes());
+    assert_eq!(full.len() - 8, doc.len());
+    assert_eq!(0, doc.detached_count());
+
+    let opts = html::ParseOptions::default()
+        .omit(html::NodeKinds::COMMENT);
+    let (doc, _) = html::parse_utf8_with_options(
+        input.as_bytes(), &opts
+    );
+    assert!(doc.to_string().contains("<script>x()</script>"));
+    assert!(!doc.to_string().contains("note"));
+    assert!(html::NodeKinds::all().contains(html::NodeKinds::PI));
+    assert!(html::NodeKinds::empty().is_empty());
+}
+
+#[test]
+#[cfg(feature = "arbitrary")]
+fn test_arbitrary_round_trip() {
+    use arbitrary::{Arbitrary, Unstructured};
+    ensure_logger();
+    let mut rng = rand::thread_rng();
+    for _ in 0..200 {
+        let bytes: Vec<u8> = (0..rng.gen_range(0, 2048))
+            .map(|_| rng.gen())
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        let mut doc = Document::arbitrary(&mut u).unwrap();
+        doc.assert_consistent();
+
+        let out = doc.to_string();
+        let reparsed = html::parse_utf8(out.as_bytes());
+        assert_eq!(out, reparsed.to_string());
+
+        doc.filter(chain_filters!(
+            filter::detach_comments,
+            filter::text_normalize,
+            filter::fold_empty_inline
+        ));
+        doc.assert_consistent();
+    }
+}
+
+#[test]
+fn test_highlight() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<p>The cat sat on the mat. <b>cat</b>\
+         <script>cat</script></p>".as_bytes()
+    );
+    assert_eq!(2, doc.highlight("at ", t::MARK));
+    assert_eq!(
+        "<p>The c<mark>at </mark>s<mark>at </mark>on the mat. \
+         <b>cat</b><script>cat</script></p>",
+        doc.to_string()
+    );
+
+    // Matches in existing marks are not searched again
+    assert_eq!(0, doc.highlight("at ", t::MARK));
+    assert_eq!(1, doc.highlight(&"cat".to_string(), t::MARK));
+    doc.compact();
+    doc.assert_consistent();
+
+    // Match spanning adjacent text nodes
+    let mut doc = html::parse_utf8_fragment("<p>x</p>".as_bytes());
+    let p = doc.root_element().unwrap();
+    doc.append_child(p, Node::new_text("ab"));
+    doc.append_child(p, Node::new_text("cd"));
+    assert_eq!(1, doc.highlight("xabc", "em"));
+    assert_eq!("<p><em>xabc</em>d</p>", doc.to_string());
+
+    // Empty pattern never matches
+    assert_eq!(0, doc.highlight("", t::MARK));
+}
+
+#[test]
+#[cfg(feature = "regex")]
+fn test_highlight_regex() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<p>one 22 three 4444</p>".as_bytes()
+    );
+    let re = regex::Regex::new(r"\d+").unwrap();
+    assert_eq!(2, doc.highlight(&re, t::MARK));
+    assert_eq!(
+        "<p>one <mark>22</mark> three <mark>4444</mark></p>",
+        doc.to_string()
+    );
+}
+
+#[test]
+#[cfg(feature = "regex")]
+fn test_text_replace() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p>“Quoted” and ‘single’</p>\
+         <pre>“pre”</pre><script>x = “s”</script></div>".as_bytes()
+    );
+    let tr = filter::text_replace(regex::Regex::new("[“”]").unwrap(), "\"");
+    doc.filter(|p, d| tr.filter(p, d));
+    assert_eq!(
+        "<div><p>\"Quoted\" and ‘single’</p>\
+         <pre>“pre”</pre><script>x = “s”</script></div>",
+        doc.to_string()
+    );
+
+    let tr = filter::TextReplacer::builder(
+        regex::Regex::new("‘([^’]*)’").unwrap(), "'$1'"
+    ).skip_none().build();
+    doc.filter(|p, d| tr.filter(p, d));
+    assert!(doc.to_string().contains("and 'single'</p>"));
+}
+
+#[test]
+fn test_strip_trackers() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div onclick=\"track()\">\
+         <script src=\"https://www.googletagmanager.com/gtm.js\"></script>\
+         <script src=\"/app.js\"></script>\
+         <iframe src=\"https://AD.DoubleClick.net/x\"></iframe>\
+         <img src=\"/pixel.gif\" width=\"1\" height=\"1px\">\
+         <img src=\"/photo.jpg\" width=\"1\" height=\"300\" onload=\"x()\">\
+         <p>text</p>\
+         </div>".as_bytes()
+    );
+    doc.filter(filter::strip_trackers);
+    assert_eq!(
+        "<div><script src=\"/app.js\"></script>\
+         <img src=\"/photo.jpg\" width=\"1\" height=\"300\">\
+         <p>text</p></div>",
+        doc.to_string()
+    );
+
+    let mut doc = html::parse_utf8_fragment(
+        "<div onclick=\"x()\"><script src=\"/ads/a.js\"></script>\
+         <img src=\"/pixel.gif\" width=\"0\" height=\"0\"></div>".as_bytes()
+    );
+    let ts = filter::TrackerStripper::builder()
+        .block("/ADS/")
+        .pixels(false)
+        .event_handlers(false)
+        .build();
+    assert!(ts.is_blocked("https://example.com/ads/b.js"));
+    doc.filter(|p, d| ts.filter(p, d));
+    assert_eq!(
+        "<div onclick=\"x()\">\
+         <img src=\"/pixel.gif\" width=\"0\" height=\"0\"></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_unwrap_noscript_images() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<html><head><noscript><img src=\"/t.gif\"></noscript></head>\
+         <body><p><img class=\"lazy\" data-src=\"/a.jpg\" \
+         src=\"data:image/gif;base64,R0lGOD\">\
+         <noscript><img src=\"/a.jpg\" alt=\"A\"></noscript></p>\
+         <noscript>Please enable JavaScript</noscript>\
+         </body></html>".as_bytes()
+    );
+    assert_eq!(1, doc.unwrap_noscript_images());
+    doc.compact();
+    doc.assert_consistent();
+    assert_eq!(
+        "<html><head><noscript><img src=\"/t.gif\"></noscript></head>\
+         <body><p><img src=\"/a.jpg\" alt=\"A\"></p>\
+         <noscript>Please enable JavaScript</noscript>\
+         </body></html>",
+        doc.to_string()
+    );
+
+    // A non-ASCII src is not a placeholder, and is kept
+    let mut doc = html::parse_utf8_fragment(
+        "<p><img src=\"ééé.jpg\">\
+         <noscript><img src=\"/a.jpg\"></noscript></p>".as_bytes()
+    );
+    assert_eq!(1, doc.unwrap_noscript_images());
+    assert_eq!(
+        "<p><img src=\"ééé.jpg\"><img src=\"/a.jpg\"></p>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_amp_to_html() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><amp-img src=\"/a.jpg\" width=\"800\" height=\"600\" \
+         layout=\"responsive\" alt=\"A\">\
+         <noscript><img src=\"/a.jpg\"></noscript></amp-img>\
+         <amp-video src=\"/v.mp4\" layout=\"fill\">\
+         <source src=\"/v.webm\" type=\"video/webm\">\
+         <div fallback>No video</div></amp-video></div>".as_bytes()
+    );
+    doc.filter(filter::amp_to_html);
+    assert_eq!(
+        "<div><img src=\"/a.jpg\" width=\"800\" height=\"600\" alt=\"A\">\
+         <video src=\"/v.mp4\">\
+         <source src=\"/v.webm\" type=\"video/webm\"></video></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_resolve_images() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div>\
+         <img src=\"a.jpg\" srcset=\"a2.jpg 2x, a3.jpg 3x\" alt=\"A\">\
+         <img src=\"b.jpg\" srcset=\"b400.jpg 400w, b800.jpg 800w, \
+         b1600.jpg 1600w\" sizes=\"100vw\">\
+         <img src=\"c.jpg\">\
+         <picture>\
+         <source media=\"(min-width: 1000px)\" srcset=\"wide.jpg\">\
+         <source type=\"image/avif\" srcset=\"d.avif\">\
+         <source media=\"(max-width: 700px) and (min-width: 300px)\" \
+         srcset=\"d1.webp 1x, d2.webp 2x\" type=\"image/webp\">\
+         <img src=\"d.jpg\" alt=\"D\">\
+         </picture>\
+         </div>".as_bytes()
+    );
+    assert_eq!(3, doc.resolve_images(600, 1.5));
+    doc.compact();
+    doc.assert_consistent();
+    assert_eq!(
+        "<div>\
+         <img src=\"a2.jpg\" alt=\"A\">\
+         <img src=\"b1600.jpg\">\
+         <img src=\"c.jpg\">\
+         <img src=\"d2.webp\" alt=\"D\">\
+         </div>",
+        doc.to_string()
+    );
+
+    let mut doc = html::parse_utf8_fragment(
+        "<div><img src=\"a.jpg\" srcset=\"a2.jpg 2x\">\
+         <img srcset=\"b400.jpg 400w, b800.jpg 800w\"></div>".as_bytes()
+    );
+    assert_eq!(2, doc.resolve_images(400, 1.0));
+    assert_eq!(
+        "<div><img src=\"a.jpg\"><img src=\"b400.jpg\"></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_media() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<html><body>\
+         <figure><div><img src=\" a.jpg \" width=\"640\" height=\"x\" \
+         alt=\"\"></div>\
+         <figcaption> A <b>caption</b> </figcaption></figure>\
+         <video width=\"320\"><source src=\"v.webm\"></video>\
+         <audio src=\"s.mp3\"></audio>\
+         <iframe src=\"https://example.com/embed\" title=\"Map\"></iframe>\
+         <img>\
+         </body></html>".as_bytes()
+    );
+    let media = doc.media();
+    assert_eq!(5, media.len());
+
+    let kinds: Vec<_> = media.iter().map(|m| m.kind).collect();
+    assert_eq!(
+        vec![MediaKind::Image, MediaKind::Video, MediaKind::Audio,
+             MediaKind::Iframe, MediaKind::Image],
+        kinds
+    );
+
+    assert_eq!(Some("a.jpg"), media[0].src);
+    assert_eq!((Some(640), None), (media[0].width, media[0].height));
+    assert_eq!(Some(""), media[0].alt);
+    assert_eq!(Some("A caption"), media[0].caption.as_ref().map(|c| &**c));
+    assert!(media[0].node.is_elem(t::IMG));
+
+    assert_eq!(Some("v.webm"), media[1].src);
+    assert_eq!(Some(320), media[1].width);
+    assert_eq!(None, media[1].caption);
+    assert_eq!(Some("s.mp3"), media[2].src);
+    assert_eq!(Some("Map"), media[3].alt);
+    assert_eq!((None, None), (media[4].src, media[4].alt));
+}
+
+#[test]
+fn test_outline() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<html><body>\
+         <h1>Title</h1>\
+         <h2 id=\"intro\">Intro</h2>\
+         <h3>  Some\n <i>details</i> </h3>\
+         <h2>Usage &amp; Setup</h2>\
+         <h4>Usage &amp; Setup</h4>\
+         <h1>Appendix</h1>\
+         <h2>?!</h2>\
+         </body></html>".as_bytes()
+    );
+    let outline = doc.outline();
+    assert_eq!(2, outline.len());
+    assert_eq!("Title", outline[0].text.as_ref());
+    assert_eq!(1, outline[0].level);
+    assert_eq!(None, outline[0].anchor);
+    assert_eq!(2, outline[0].children.len());
+
+    let intro = &outline[0].children[0];
+    assert_eq!(Some("intro"), intro.anchor.as_ref().map(|a| &**a));
+    assert_eq!("Some details", intro.children[0].text.as_ref());
+    assert_eq!(3, intro.children[0].level);
+
+    let usage = &outline[0].children[1];
+    assert_eq!(1, usage.children.len());
+    assert_eq!(4, usage.children[0].level);
+    assert_eq!(1, outline[1].children.len());
+
+    assert_eq!(6, doc.add_heading_anchors());
+    let anchors: Vec<String> = doc.outline()
+        .iter()
+        .flat_map(|h| {
+            let mut all = vec![h];
+            for c in &h.children {
+                all.push(c);
+                all.extend(c.children.iter());
+            }
+            all
+        })
+        .map(|h| h.anchor.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        vec!["title", "intro", "some-details", "usage-setup",
+             "usage-setup-1", "appendix", "section"],
+        anchors
+    );
+    assert_eq!(0, doc.add_heading_anchors());
+}
+
+#[test]
+fn test_text_stats() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<html><head><title>Not counted</title></head><body>\
+         <p>One two<b>three</b>. Four?</p><p>Five<br>six \
+         <a href=\"/x\">seven eight</a></p>\
+         <script>var not = 'counted';</script>\
+         <div>nine</div>\
+         </body></html>".as_bytes()
+    );
+    let stats = doc.document_node_ref().text_stats();
+    assert_eq!(8, stats.words); // "twothree." is one word
+    assert_eq!(38, stats.chars);
+    assert_eq!(4, stats.sentences);
+    assert_eq!(10, stats.link_chars);
+    assert!((stats.link_density() - 10.0 / 38.0).abs() < 1e-6);
+    assert!((stats.reading_minutes(4) - 2.0).abs() < 1e-6);
+
+    let empty = html::parse_utf8(b"");
+    let stats = empty.document_node_ref().text_stats();
+    assert_eq!(0, stats.words);
+    assert_eq!(0.0, stats.link_density());
+}
+
+#[test]
+fn test_eq_ignoring() {
+    ensure_logger();
+    let a = html::parse_utf8_fragment(
+        "<div class=\"x\" id=\"a\">\n  <p>one</p>\n  <!-- c -->\n</div>"
+            .as_bytes()
+    );
+    let b = html::parse_utf8_fragment(
+        "<div id=\"a\" class=\"x\"><p>one</p></div>".as_bytes()
+    );
+    assert!(a.eq_ignoring(&a.deep_clone(Document::DOCUMENT_NODE_ID),
+                          EqOptions::default()));
+    assert!(!a.eq_ignoring(&b, EqOptions::default()));
+    assert!(!a.eq_ignoring(&b, EqOptions::new().ignore_whitespace(true)));
+    let opts = EqOptions::new()
+        .ignore_whitespace(true)
+        .ignore_comments(true);
+    assert_eq!(
+        Some("at #document > div: attributes \
+              [class=\"x\", id=\"a\"] != [id=\"a\", class=\"x\"]"
+             .to_string()),
+        a.difference(&b, opts)
+    );
+    assert_doc_eq!(a, b, opts.ignore_attr_order(true));
+
+    let c = html::parse_utf8_fragment(
+        "<div class=\"x\" id=\"a\"><p>one</p><p>two</p></div>".as_bytes()
+    );
+    let d = html::parse_utf8_fragment(
+        "<div class=\"x\" id=\"a\"><p>one</p><p>2</p></div>".as_bytes()
+    );
+    assert_eq!(
+        Some("at #document > div > p[2] > text: \"two\" != \"2\"".to_string()),
+        c.difference(&d, EqOptions::default())
+    );
+    let diff = b.difference(&c, opts.ignore_attr_order(true)).unwrap();
+    assert!(diff.starts_with("at #document > div: 1 children != 2 children"),
+            "{}", diff);
+}
+
+#[test]
+fn test_debug_tree() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<!DOCTYPE html>\n<html><body id=\"b\" class=\"c\">\n\
+         <p>A \"quoted\"\ttext which is longer than forty chars</p>\
+         <!-- note --></body></html>".as_bytes()
+    );
+    assert_eq!(
+        "#document\n\
+        \x20 <!DOCTYPE html>\n\
+        \x20 <html>\n\
+        \x20   <head>\n\
+        \x20   <body class=\"c\" id=\"b\">\n\
+        \x20     \"\\n\"\n\
+        \x20     <p>\n\
+        \x20       \"A \\\"quoted\\\"\\ttext which is longer than for\"…\n\
+        \x20     <!-- \" note \" -->\n",
+        doc.debug_tree()
+    );
+    let p = doc.document_node_ref().find(|n| n.is_elem(t::P)).unwrap();
+    assert!(p.debug_tree().starts_with("<p>\n  \"A "));
+}
+
+struct EmptyFolder {
+    depth: usize,
+    max_depth: usize,
+    text_lens: Vec<usize>,
+}
+
+impl Visitor for EmptyFolder {
+    fn enter(&mut self, _pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        if data.is_elem(t::SCRIPT) {
+            return Action::Detach;
+        }
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        self.text_lens.push(0);
+        Action::Continue
+    }
+
+    fn exit(&mut self, pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+        self.depth -= 1;
+        let mut len = self.text_lens.pop().unwrap();
+        if let Some(t) = data.as_text() {
+            len += t.trim().len();
+        }
+        if let Some(l) = self.text_lens.last_mut() {
+            *l += len;
+        }
+        if len == 0 && data.is_elem(t::SPAN) && pos.first_child.is_none() {
+            Action::Detach
+        } else if len == 0 && data.is_elem(t::SPAN) {
+            Action::Fold
+        } else {
+            Action::Continue
+        }
+    }
+}
+
+#[test]
+fn test_visitor() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><span><span> </span></span><script>x()</script>\
+         <p>one <span>two</span></p></div>".as_bytes()
+    );
+    let mut v = EmptyFolder { depth: 0, max_depth: 0, text_lens: vec![] };
+    doc.visit(&mut v);
+    assert_eq!(0, v.depth);
+    assert_eq!(5, v.max_depth);
+    assert_eq!(
+        "<div> <p>one <span>two</span></p></div>",
+        doc.to_string()
+    );
+}
+
+fn trailing_hr_filter(pos: NodeRef<'_>, data: &mut NodeData) -> Action {
+    if data.is_elem(t::HR) && pos.next_sibling.is_none() {
+        Action::Detach
+    } else if data.is_elem(t::P) && pos.first_child.is_none() {
+        Action::Detach
+    } else {
+        Action::Continue
+    }
+}
+
+#[test]
+fn test_filter_until_stable() {
+    ensure_logger();
+    let input = "<div>one<hr><p></p></div>";
+    let mut doc = html::parse_utf8_fragment(input.as_bytes());
+    assert_eq!(1, doc.filter_until_stable(trailing_hr_filter, 1));
+    assert_eq!("<div>one<hr></div>", doc.to_string());
+
+    let mut doc = html::parse_utf8_fragment(input.as_bytes());
+    assert_eq!(3, doc.filter_until_stable(trailing_hr_filter, 10));
+    assert_eq!("<div>one</div>", doc.to_string());
+}
+
+#[test]
+fn test_prune_empty() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p> </p><p><span><i></i></span>x</p>\
+         <table><tr><td></td><th></th></tr></table>\
+         <br><div class=\"c\"></div></div>".as_bytes()
+    );
+    let pe = filter::prune_empty(vec![t::TD]);
+    doc.filter(|p, d| pe.filter(p, d));
+    assert_eq!(
+        "<div><p>x</p><table><tbody><tr><td></td></tr></tbody></table>\
+         <br><div class=\"c\"></div></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_scrub_attributes() {
+    ensure_logger();
+    let input = "<div style=\"color:red\" class=\"c\" data-x=\"1\" \
+                 onclick=\"f()\"><a href=\"/\" class=\"l\" title=\"t\">l</a>\
+                 </div>";
+    let mut doc = html::parse_utf8_fragment(input.as_bytes());
+    doc.filter(filter::scrub_attributes);
+    assert_eq!(
+        "<div class=\"c\"><a href=\"/\" class=\"l\" title=\"t\">l</a></div>",
+        doc.to_string()
+    );
+
+    let mut doc = html::parse_utf8_fragment(input.as_bytes());
+    let sc = filter::AttrScrubber::builder()
+        .deny("class")
+        .data_attrs(false)
+        .allow(t::A, a::HREF)
+        .build();
+    doc.filter(|p, d| sc.filter(p, d));
+    assert_eq!(
+        "<div data-x=\"1\"><a href=\"/\">l</a></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_retag() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<p><b>x</b><i class=\"c\">y</i><font color=\"red\">z</font>\
+         <a href=\"/\">l</a><svg><a></a></svg></p>".as_bytes()
+    );
+    let rt = filter::retag(vec![
+        (t::B, t::STRONG), (t::I, t::EM), (t::FONT, t::SPAN), (t::A, t::SPAN)
+    ]);
+    doc.filter(|p, d| rt.filter(p, d));
+    assert_eq!(
+        "<p><strong>x</strong><em class=\"c\">y</em>\
+         <span color=\"red\">z</span><span href=\"/\">l</span>\
+         <svg><a></a></svg></p>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_foreign_namespaces() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><svg viewBox=\"0 0 1 1\"><clipPath id=\"c\"><rect/></clipPath>\
+         <a></a></svg><math><mi>x</mi></math><a href=\"/\">l</a></div>"
+            .as_bytes()
+    );
+    let root = doc.document_node_ref();
+    let links: Vec<_> = root.select(|n| n.is(t::A)).collect();
+    assert_eq!(2, links.len());
+    assert_eq!(Some(&html::ns::SVG), links[0].namespace());
+    assert_eq!(Some(&html::ns::HTML), links[1].namespace());
+    let mi = root.find(|n| n.is("mi")).unwrap();
+    assert_eq!(Some(&html::ns::MATHML), mi.namespace());
+    assert_eq!(None, mi.children().next().and_then(|c| c.namespace()));
+
+    assert_eq!(
+        "<div><svg viewBox=\"0 0 1 1\"><clipPath id=\"c\"><rect></rect>\
+         </clipPath><a></a></svg><math><mi>x</mi></math>\
+         <a href=\"/\">l</a></div>",
+        doc.to_string()
+    );
+    let opts = SerializeOptions::new().self_closing_foreign(true);
+    assert_eq!(
+        "<div><svg viewBox=\"0 0 1 1\"><clipPath id=\"c\"><rect/></clipPath>\
+         <a/></svg><math><mi>x</mi></math><a href=\"/\">l</a></div>",
+        doc.to_string_with(&opts)
+    );
+}
+
+#[test]
+fn test_tag_meta_flags() {
+    let br = TAG_META.get(&t::BR).unwrap();
+    assert!(br.is_void());
+    assert!(br.is_block());
+    assert!(br.allowed_parents().is_none());
+    assert!(br.allows_parent(&t::P));
+
+    let span = TAG_META.get(&t::SPAN).unwrap();
+    assert!(span.is_inline());
+    assert!(!span.is_block());
+    assert!(!TAG_META.get(&t::TITLE).unwrap().is_block());
+
+    assert!(TAG_META.get(&t::SCRIPT).unwrap().is_raw_text());
+    assert!(TAG_META.get(&t::XMP).unwrap().is_raw_text());
+    assert!(!TAG_META.get(&t::PRE).unwrap().is_raw_text());
+
+    let li = TAG_META.get(&t::LI).unwrap();
+    assert_eq!(
+        Some(&[t::DIR, t::MENU, t::OL, t::UL][..]),
+        li.allowed_parents()
+    );
+    assert!(li.allows_parent(&t::UL));
+    assert!(!li.allows_parent(&t::DIV));
+    assert!(TAG_META.get(&t::TD).unwrap().allows_parent(&t::TR));
+}
+
+#[test]
+fn test_custom_element() {
+    ensure_logger();
+    let input = "<div><my-widget>w</my-widget><my-ad>ad</my-ad>\
+                 <x-unknown>u</x-unknown></div>";
+    let widget = html::register_custom_element(
+        "my-widget",
+        html::CustomElement::new().block(true)
+    );
+    html::register_custom_element(
+        "my-ad",
+        html::CustomElement::new().banned(true)
+    );
+    assert!(html::tag_meta(&widget).unwrap().is_block());
+    assert!(html::tag_meta(&"my-ad".into()).unwrap().is_inline());
+    assert!(html::tag_meta(&"x-unknown".into()).is_none());
+    assert!(html::tag_meta(&t::P).is_some());
+
+    let mut doc = html::parse_utf8_fragment(input.as_bytes());
+    let w = doc.document_node_ref().find(|n| n.is(widget.clone())).unwrap();
+    assert!(w.as_element().unwrap().html_tag_meta().unwrap().is_block());
+
+    doc.filter(filter::detach_banned_elements);
+    assert_eq!("<div><my-widget>w</my-widget></div>", doc.to_string());
+}
+
+#[test]
+fn test_is_elem_local() {
+    ensure_logger();
+    let mut elm = Element::new("DIV");
+    elm.set_attr("Data-X", "1");
+    assert!(!elm.is_elem(t::DIV));
+    assert!(elm.is_elem_local("div"));
+    assert!(elm.is_elem_local("Div"));
+    assert!(!elm.is_elem_local("dive"));
+    assert!(elm.is_any_elem_local(&["p", "div"]));
+    assert_eq!("1", elm.attr_local("data-x").unwrap().as_ref());
+    assert!(elm.attr("data-x").is_none());
+
+    let doc = html::parse_utf8_fragment(
+        "<svg><clipPath></clipPath></svg>".as_bytes()
+    );
+    let cp = doc.document_node_ref()
+        .find(|n| n.is_elem_local("CLIPPATH"))
+        .unwrap();
+    assert_eq!(Some(&html::ns::SVG), cp.namespace());
+}
+
+#[test]
+fn test_to_tree_sink() {
+    use markup5ever_rcdom::{RcDom, SerializableHandle};
+
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<!DOCTYPE html>\n<html><body><!--c--><p class=\"a\" id=\"b\">one\
+         <svg><path d=\"M0\"></path></svg></p>\
+         <template><i>t</i></template></body></html>".as_bytes()
+    );
+    let rcdom = doc.to_tree_sink(RcDom::default());
+    let mut out = Vec::new();
+    let handle: SerializableHandle = rcdom.document.into();
+    html5ever::serialize(&mut out, &handle, Default::default()).unwrap();
+    assert_eq!(doc.to_string(), String::from_utf8(out).unwrap());
+}
+
+#[cfg(feature = "kuchiki")]
+#[test]
+fn test_kuchiki_round_trip() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<!DOCTYPE html>\n<html><body><!--c--><p class=\"a\">one\
+         <svg><path d=\"M0\"></path></svg></p>\
+         <template><i>t</i></template></body></html>".as_bytes()
+    );
+    let knode = doc.to_kuchiki();
+    assert_eq!(doc.to_string(), knode.to_string());
+    let back = Document::from_kuchiki(&knode);
+    assert_doc_eq!(doc, back);
+}
+
+#[cfg(feature = "scraper")]
+#[test]
+fn test_scraper_round_trip() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<!DOCTYPE html>\n<html><body><!--c--><p class=\"a\">one\
+         <svg><path d=\"M0\"></path></svg></p></body></html>".as_bytes()
+    );
+    let html = doc.to_scraper();
+    assert_eq!(
+        doc.root_element_ref().unwrap().to_string(),
+        html.root_element().html()
+    );
+    let back = Document::from_scraper(&html);
+    assert_doc_eq!(doc, back);
+}
+
+#[test]
+fn test_sink_custom_parse_opts() {
+    use std::rc::Rc;
+    use html5ever::{parse_document, ParseOpts};
+    use html5ever::tendril::TendrilSink;
+    use html5ever::tree_builder::TreeBuilderOpts;
+
+    ensure_logger();
+    let input = "<html><body>\n<noscript><img src=\"a.png\"></noscript>\n\
+                 <p>x</i></p></body></html>";
+    let opts = ParseOpts {
+        tree_builder: TreeBuilderOpts {
+            scripting_enabled: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let sink = html::Sink::default()
+        .spans(true)
+        .collect_errors(errors.clone());
+    let doc = parse_document(sink, opts).from_utf8().one(input.as_bytes());
+
+    let img = doc.document_node_ref().find(|n| n.is(t::IMG)).unwrap();
+    assert!(img.parent().unwrap().is(t::NOSCRIPT));
+    assert_eq!(2, doc.source_span(img.id()).unwrap().start_line);
+    assert!(!errors.borrow().is_empty());
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_parse_mmap() {
+    ensure_logger();
+    let root = env!("CARGO_MANIFEST_DIR");
+    let fname = "documento_utf8.html";
+    let eh = EncodingHint::shared_default(enc::UTF_8);
+    let doc = html::parse_mmap(eh, format!("{}/samples/{}", root, fname))
+        .unwrap();
+    let eh = EncodingHint::shared_default(enc::UTF_8);
+    let mut reader = sample_file(fname);
+    let exp = html::parse_buffered(eh, &mut reader).unwrap();
+    assert_doc_eq!(exp, doc);
+
+    let eh = EncodingHint::shared_default(enc::UTF_8);
+    assert!(html::parse_mmap(eh, format!("{}/samples/none.html", root))
+        .is_err());
+}
+
+fn warc_record(headers: &str, block: &[u8]) -> Vec<u8> {
+    let mut rec = format!(
+        "WARC/1.0\r\n{}Content-Length: {}\r\n\r\n",
+        headers, block.len()
+    ).into_bytes();
+    rec.extend_from_slice(block);
+    rec.extend_from_slice(b"\r\n\r\n");
+    rec
+}
+
+#[test]
+fn test_warc_reader() {
+    ensure_logger();
+    let http = "WARC-Type: response\r\n\
+                Content-Type: application/http; msgtype=response\r\n";
+    let mut warc = warc_record(
+        "WARC-Type: warcinfo\r\nContent-Type: application/warc-fields\r\n",
+        b"software: test\r\n"
+    );
+    let mut latin = b"HTTP/1.1 200 OK\r\n\
+                      Content-Type: text/html; charset=iso-8859-1\r\n\r\n\
+                      <p>Se\xf1or</p>".to_vec();
+    warc.extend(warc_record(
+        &format!("{}WARC-Target-URI: http://a.example/\r\n", http),
+        &latin
+    ));
+    warc.extend(warc_record(
+        http,
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: text/html\r\n\
+          Transfer-Encoding: chunked\r\n\r\n\
+          5\r\n<p>ch\r\n8\r\nunked</p>\r\n0\r\n\r\n"
+    ));
+    warc.extend(warc_record(
+        http,
+        b"HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\n\r\n<p>404"
+    ));
+    warc.extend(warc_record(
+        http,
+        b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n\x89PNG"
+    ));
+
+    let all: Vec<_> = WarcReader::new(&warc[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(5, all.len());
+    assert_eq!(Some("warcinfo"), all[0].record_type());
+    assert_eq!(404, all[3].http_response().unwrap().status);
+
+    let html: Vec<_> = WarcReader::new(&warc[..])
+        .html_records()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(2, html.len());
+    assert_eq!(Some("http://a.example/"), html[0].target_uri());
+
+    let doc = html[0].parse_html().unwrap();
+    assert_eq!(
+        "<html><head></head><body><p>Señor</p></body></html>",
+        doc.to_string()
+    );
+    let doc = html[1].parse_html().unwrap();
+    assert_eq!(
+        "<html><head></head><body><p>chunked</p></body></html>",
+        doc.to_string()
+    );
+
+    latin.truncate(10);
+    let bad = warc_record(http, &latin);
+    let trunc = &bad[..bad.len() - 6];
+    assert!(WarcReader::new(trunc).next().unwrap().is_err());
+    assert!(WarcReader::new(&b"<html>\n"[..]).next().unwrap().is_err());
+}
+
+#[test]
+fn test_mhtml() {
+    ensure_logger();
+    let mht = "From: <Saved by a browser>\n\
+        MIME-Version: 1.0\n\
+        Content-Type: multipart/related;\n\
+        \ttype=\"text/html\";\n\
+        \tboundary=\"----=_Part_0\"\n\
+        \n\
+        ------=_Part_0\n\
+        Content-Type: text/html; charset=\"utf-8\"\n\
+        Content-Transfer-Encoding: quoted-printable\n\
+        Content-Location: http://a.example/\n\
+        \n\
+        <p class=3D\"x\">Se=C3=B1or <img src=3D\"http://a.example/a.png\">=\n\
+        </p>\n\
+        ------=_Part_0\n\
+        Content-Type: image/png\n\
+        Content-Transfer-Encoding: base64\n\
+        Content-Location: http://a.example/a.png\n\
+        Content-ID: <img0@mhtml>\n\
+        \n\
+        iVBO\n\
+        Rw==\n\
+        ------=_Part_0--\n";
+    let mhtml = Mhtml::parse(mht.as_bytes()).unwrap();
+    assert_eq!(2, mhtml.parts().len());
+
+    let root = mhtml.root().unwrap();
+    assert_eq!(Some("http://a.example/"), root.content_location());
+    assert_eq!(
+        "<p class=\"x\">Señor <img src=\"http://a.example/a.png\"></p>"
+            .as_bytes(),
+        root.body()
+    );
+
+    let img = mhtml.resource("http://a.example/a.png").unwrap();
+    assert_eq!(b"\x89PNG", img.body());
+    assert!(!img.is_html());
+    assert_eq!(
+        img.body(),
+        mhtml.resource("cid:img0@mhtml").unwrap().body()
+    );
+    assert!(mhtml.resource("http://a.example/b.png").is_none());
+    assert!(mhtml.resource("ñño.png").is_none());
+
+    let doc = mhtml.parse_html().unwrap();
+    assert_eq!(
+        "<html><head></head><body>\
+         <p class=\"x\">Señor <img src=\"http://a.example/a.png\"></p>\
+         </body></html>",
+        doc.to_string()
+    );
+
+    assert!(Mhtml::parse(b"Content-Type: text/html\n\n<p>").is_err());
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_parse_async() {
+    ensure_logger();
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    for fname in &[
+        "documento_utf8.html",
+        "documento_utf16le_bom.html",
+        "documento_windows1252_meta.html",
+        "iro0094_shiftjis_meta.html",
+        "github-dekellum.html",
+    ] {
+        let mut bytes = Vec::new();
+        sample_file(fname).read_to_end(&mut bytes).unwrap();
+
+        let eh = EncodingHint::shared_default(enc::UTF_8);
+        let doc = rt.block_on(html::parse_async(eh, &mut &bytes[..]))
+            .unwrap();
+        let eh = EncodingHint::shared_default(enc::UTF_8);
+        let exp = html::parse_buffered(eh, &mut &bytes[..]).unwrap();
+        assert_doc_eq!(exp, doc);
+    }
+}
+
+// Adapted from static_asserts 1.1.0 `assert_not_impl_any` macro
+// MIT/Apache licensed
+trait AmbiguousIfImpl<A> {
+    fn some_f() -> bool { true }
+}
+impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+
+#[allow(unused)] struct NotSync;
+impl<T: ?Sized + Sync> AmbiguousIfImpl<NotSync> for T {}
+
+#[allow(unused)] struct NotSend;
+impl<T: ?Sized + Send> AmbiguousIfImpl<NotSend> for T {}
+
+fn is_send<T: Send>() -> bool { true }
+
+#[test]
+fn test_send_document() {
+    ensure_logger();
+    assert!(<Document as AmbiguousIfImpl<_>>::some_f());
+    assert!(<StrTendril as AmbiguousIfImpl<_>>::some_f());
+    assert!(is_send::<SendDocument>());
+
+    let mut doc = html::parse_utf8(
+        "<div id=\"a\"><p>one</p><!--two--><p title=\"three\">four</p></div>"
+            .as_bytes()
+    );
+    let p = doc.document_node_ref().find(|n| n.is_elem(t::P)).unwrap().id();
+    doc.set_user_data(p, 42u32);
+    let exp = doc.to_string();
+    let text_clone = doc[doc[p].first_child.unwrap()].as_text().cloned();
+
+    let sdocs: Vec<SendDocument> = (0..3).map(|_| doc.to_send()).collect();
+    let handles: Vec<_> = sdocs.into_iter().map(|sdoc| {
+        std::thread::spawn(move || {
+            let doc = sdoc.into_document();
+            let p = doc.document_node_ref()
+                .find(|n| n.is_elem(t::P))
+                .unwrap();
+            assert!(doc.user_data::<u32>(p.id()).is_none());
+            doc.to_string()
+        })
+    }).collect();
+    for h in handles {
+        assert_eq!(exp, h.join().unwrap());
+    }
+
+    let sdoc = doc.into_send();
+    let doc: Document = std::thread::spawn(move || sdoc)
+        .join()
+        .unwrap()
+        .into();
+    assert_eq!(exp, doc.to_string());
+    assert_eq!("one", text_clone.unwrap().as_ref());
+}
+
+#[test]
+fn test_doc_stats() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<!DOCTYPE html><div id=\"a\" class=\"b\"><p>one<!--two--></p>\
+         <p><i>three</i></p></div>"
+            .as_bytes()
+    );
+    let stats = doc.stats();
+    assert_eq!(7, stats.elements); // html, head, body, div, p, p, i
+    assert_eq!(2, stats.attributes);
+    assert_eq!(2, stats.texts);
+    assert_eq!(1, stats.comments);
+    assert_eq!(1, stats.doctypes);
+    assert_eq!(6, stats.max_depth); // html body div p i text
+    assert_eq!(0, stats.garbage);
+    assert_eq!(doc.nodes().count() - 1, stats.reachable());
+
+    let tm = filter::timed(filter::detach_comments);
+    doc.filter(|p, d| tm.filter(p, d));
+    doc.filter(|p, d| tm.filter(p, d));
+    assert_eq!(2 * stats.reachable() as u64 + 1, tm.calls());
+    tm.reset();
+    assert_eq!(0, tm.calls());
+
+    let stats = doc.stats();
+    assert_eq!(0, stats.comments);
+    assert_eq!(1, stats.garbage);
+}
+
+#[test]
+fn test_intern_attr_values() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<link rel=\"stylesheet\" href=\"a.css\">\
+         <link rel=\"stylesheet\" href=\"b.css\">\
+         <script type=\"text/javascript\"></script>\
+         <p class=\"x\">1</p><p class=\"x\">2</p>"
+            .as_bytes()
+    );
+    let exp = doc.to_string();
+    let interner = filter::intern_attr_values(32);
+    doc.filter(|p, d| interner.filter(p, d));
+    assert_eq!(exp, doc.to_string());
+    assert_eq!(2, interner.len()); // stylesheet, text/javascript
+
+    let rels: Vec<_> = doc.nodes()
+        .filter_map(|id| doc[id].as_element())
+        .filter_map(|e| e.attr(a::REL))
+        .collect();
+    assert_eq!(2, rels.len());
+    assert_eq!(rels[0].as_ptr(), rels[1].as_ptr());
+
+    let mut doc2 = html::parse_utf8(
+        "<link rel=\"stylesheet\" href=\"c.css\">".as_bytes()
+    );
+    doc2.filter(|p, d| interner.filter(p, d));
+    let rel = doc2.nodes()
+        .find_map(|id| doc2[id].as_element().and_then(|e| e.attr(a::REL)))
+        .unwrap();
+    assert_eq!(rels[0].as_ptr(), rel.as_ptr());
+    assert_eq!(2, interner.len());
+
+    interner.clear();
+    assert!(interner.is_empty());
+}
+
+#[test]
+fn test_coalesce_text() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<div><p>First paragraph text</p><p>short</p>\
+         <p>Second <i>paragraph</i> text here</p></div>"
+            .as_bytes()
+    );
+    let exp = doc.to_string();
+    assert_eq!(3, doc.coalesce_text());
+    assert_eq!(exp, doc.to_string());
+    doc.assert_consistent();
+
+    let texts: Vec<_> = doc.nodes()
+        .filter_map(|id| doc[id].as_text())
+        .filter(|t| t.len32() > 8)
+        .collect();
+    assert_eq!(3, texts.len());
+    for pair in texts.windows(2) {
+        assert_eq!(
+            pair[0].as_ptr() as usize + pair[0].len(),
+            pair[1].as_ptr() as usize
+        );
+    }
+
+    let p = doc.nodes().find(|&id| doc[id].is_elem(t::P)).unwrap();
+    let tid = doc[p].first_child.unwrap();
+    doc[tid].as_text_mut().unwrap().push_slice(" appended");
+    assert!(doc.to_string().contains("First paragraph text appended"));
+    assert!(doc.to_string().contains("Second <i>paragraph</i> text here"));
+}
+
+#[test]
+fn test_checked_node_id() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<p>one</p><p>two</p>".as_bytes()
+    );
+    let other = html::parse_utf8_fragment(
+        "<div><p>one</p><p>two</p><p>three</p></div>".as_bytes()
+    );
+    let p = doc.document_node_ref().find(|n| n.is(t::P)).unwrap().id();
+    assert!(doc.check_id(p).is_ok());
+    assert!(doc.try_node_ref(p).unwrap().is(t::P));
+    assert!(doc.check_id(Document::DOCUMENT_NODE_ID).is_ok());
+    assert!(other.check_id(Document::DOCUMENT_NODE_ID).is_ok());
+
+    if cfg!(debug_assertions) {
+        assert_eq!(Err(NodeIdError::Foreign), other.check_id(p));
+    } else {
+        assert!(other.check_id(p).is_ok());
+    }
+
+    let last = doc.nodes().last().unwrap();
+    let far = other.nodes().last().unwrap();
+    assert!(far > last);
+    assert_eq!(Err(NodeIdError::OutOfRange), doc.check_id(far));
+    assert_eq!(
+        "NodeId out of range",
+        doc.try_node(far).unwrap_err().to_string()
+    );
+
+    doc.unlink(p);
+    assert_eq!(Err(NodeIdError::Removed), doc.check_id(p));
+    assert!(doc.try_node_mut(p).is_err());
+
+    let clone = doc.bulk_clone();
+    assert!(clone.check_id(last).is_ok());
+
+    doc.compact();
+    let text = doc.document_node_ref()
+        .find(|n| n.as_text().is_some())
+        .unwrap()
+        .id();
+    assert!(doc.check_id(text).is_ok());
+    if cfg!(debug_assertions) {
+        assert_eq!(Err(NodeIdError::Foreign), doc.check_id(last));
+    }
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "used with a Document other than its own")]
+fn test_foreign_node_id_panics() {
+    let doc = html::parse_utf8_fragment("<p>one</p>".as_bytes());
+    let other = html::parse_utf8_fragment("<p>one</p>".as_bytes());
+    let p = doc.document_node_ref().find(|n| n.is(t::P)).unwrap().id();
+    let _ = &other[p];
+}
+
+#[test]
+fn test_node_mut() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<ul><li>one</li><li class=\"x\">two</li></ul><p>three</p>".as_bytes()
+    );
+    let ul = doc.document_node_ref().find(|n| n.is(t::UL)).unwrap().id();
+
+    let mut cur = doc.node_mut(ul);
+    {
+        let mut li = cur.first_child().unwrap();
+        li.set_attr(a::CLASS, "first");
+        li.insert_html_before("<li>zero</li>");
+        let mut text = li.first_child().unwrap();
+        text.set_text("uno");
+    }
+    let mut cur = cur.into_last_child().ok().unwrap();
+    assert_eq!(Some("x".into()), cur.remove_attr(a::CLASS));
+    cur.set_text("dos");
+    cur.append_html("<b>!</b>");
+    let mut cur = cur.into_next_sibling().err().unwrap();
+    let cur = cur.parent().unwrap().into_next_sibling().ok().unwrap();
+    assert!(cur.node_ref().is(t::P));
+    let p = cur.detach();
+    assert_eq!("<p>three</p>", p.to_string());
+
+    doc.node_mut(ul).append_child(Node::new_elem(Element::new(t::LI)));
+    doc.assert_consistent();
+    assert_eq!(
+        "<div><ul><li>zero</li><li class=\"first\">uno</li>\
+         <li>dos<b>!</b></li><li></li></ul></div>",
+        doc.to_string()
+    );
+
+    let mut dn = doc.document_node_mut();
+    assert!(dn.parent().is_none());
+    dn.set_text("plain");
+    assert_eq!("plain", doc.to_string());
+}
+
+#[test]
+fn test_parse_utf8_filtered() {
+    ensure_logger();
+    let input = "<!DOCTYPE html>\n\
+                 <html><head><style>p { color: red }</style></head>\
+                 <body><!-- note --><script>var x = '<p>not</p>';</script>\
+                 <p class=\"a\" onclick=\"f()\">one <span>two</span></p>\
+                 <div><script>var y = 1;</script>three</div></body></html>";
+    let chain = chain_filters!(
+        filter::detach_banned_elements,
+        filter::detach_comments,
+        filter::retain_basic_attributes,
+        |_p: NodeRef<'_>, d: &mut NodeData| {
+            if d.is_elem(t::SPAN) { Action::Fold } else { Action::Continue }
+        }
+    );
+
+    let doc = html::parse_utf8_filtered(input.as_bytes(), &chain);
+    doc.assert_consistent();
+
+    let mut post = html::parse_utf8(input.as_bytes());
+    post.filter_breadth(&chain);
+    post.compact();
+
+    assert_eq!(post.to_string(), doc.to_string());
+    assert!(!doc.to_string().contains("var"));
+    let body = doc.document_node_ref().find(|n| n.is(t::BODY)).unwrap();
+    assert_eq!(
+        "<body><p class=\"a\">one two</p><div>three</div></body>",
+        body.to_string()
+    );
+
+    // Folded text is merged, rather than left as adjacent text nodes
+    assert!(doc.len() < post.len());
+}
+
+#[test]
+fn test_filter_config() {
+    ensure_logger();
+    let config = filter::config::FilterConfig {
+        remove: vec!["div.ad".to_owned(), "[hidden]".to_owned()],
+        fold: vec!["FONT".to_owned()],
+        strip_attrs: vec!["style".to_owned()],
+        detach_banned: true,
+        detach_comments: true,
+        normalize_text: true,
+        ..Default::default()
+    };
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p style=\"x\" class=\"c\"><font>one</font>  two</p>\
+         <!-- c --><div class=\"ad big\">buy</div><p hidden>h</p>\
+         <script>s()</script></div>".as_bytes()
+    );
+    config.build().apply(&mut doc);
+    doc.assert_consistent();
+    assert_eq!("<div><p class=\"c\">one two</p></div>", doc.to_string());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_filter_config_json() {
+    let config: filter::config::FilterConfig = serde_json::from_str(
+        r#"{ "remove": ["nav"], "detach_comments": true }"#
+    ).unwrap();
+    assert_eq!(vec!["nav".to_owned()], config.remove);
+    assert!(config.detach_comments);
+    assert!(config.collapse_nbsp);
+
+    let res: Result<filter::config::FilterConfig, _> =
+        serde_json::from_str(r#"{ "removes": [] }"#);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_filter_preset() {
+    ensure_logger();
+    let input = "<div><!-- c --><script>s()</script>\
+                 <p onclick=\"f()\">one  <i> </i> two</p></div>";
+    for &name in filter::PRESETS {
+        let mut doc = html::parse_utf8_fragment(input.as_bytes());
+        assert!(doc.filter_preset(name), "preset {}", name);
+        doc.assert_consistent();
+    }
+
+    let mut doc = html::parse_utf8_fragment(input.as_bytes());
+    assert!(doc.filter_preset("clean"));
+    assert_eq!("<div><p>one two</p></div>", doc.to_string());
+
+    assert!(!doc.filter_preset("unknown"));
+}
+
+#[test]
+#[cfg(feature = "capi")]
+fn test_capi() {
+    use std::ffi::{CStr, CString};
+    use crate::capi::*;
+
+    ensure_logger();
+    let input = "<div><p class=\"a\">one</p><!--c--><p>two <b>2</b></p></div>";
+    unsafe {
+        let doc = marked_parse_fragment(input.as_ptr(), input.len());
+        assert!(!doc.is_null());
+
+        let unknown = CString::new("unknown").unwrap();
+        assert_eq!(-1, marked_filter_preset(doc, unknown.as_ptr()));
+        let banned = CString::new("banned").unwrap();
+        assert_eq!(0, marked_filter_preset(doc, banned.as_ptr()));
+
+        let s = marked_serialize(doc);
+        assert_eq!(
+            "<div><p>one</p><p>two <b>2</b></p></div>",
+            CStr::from_ptr(s).to_str().unwrap()
+        );
+        marked_string_free(s);
+
+        let selector = CString::new("p").unwrap();
+        let sel = marked_select(doc, selector.as_ptr());
+        assert_eq!(2, marked_selection_len(sel));
+        let s = marked_selection_text(doc, sel, 1);
+        assert_eq!("two 2", CStr::from_ptr(s).to_str().unwrap());
+        marked_string_free(s);
+        let s = marked_selection_html(doc, sel, 0);
+        assert_eq!("<p>one</p>", CStr::from_ptr(s).to_str().unwrap());
+        marked_string_free(s);
+        assert!(marked_selection_html(doc, sel, 2).is_null());
+        marked_selection_free(sel);
+
+        marked_document_free(doc);
+        assert!(marked_parse(std::ptr::null(), 1).is_null());
+        assert!(marked_select(std::ptr::null(), selector.as_ptr()).is_null());
+    }
+}
+
+#[test]
+fn test_duplicate_attrs() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<p title=\"a\" title=\"b\" lang=\"en\">p</p>".as_bytes()
+    );
+    let p = doc.document_node_ref().find(|n| n.is(t::P)).unwrap();
+    let values: Vec<_> = p.as_element().unwrap()
+        .attrs_all(a::TITLE)
+        .map(|v| v.as_ref().to_owned())
+        .collect();
+    assert_eq!(vec!["a"], values, "parser retains first");
+
+    let dup = || {
+        let mut e = Element::new(t::P);
+        for (n, v) in &[("title", "a"), ("lang", "en"), ("title", "b")] {
+            e.attrs.push(Attribute {
+                name: QualName::new(None, ns!(), (*n).into()),
+                value: (*v).into()
+            });
+        }
+        e
+    };
+    assert_eq!(2, dup().attrs_all(a::TITLE).count());
+
+    let mut e = dup();
+    assert_eq!(0, e.dedup_attrs(DuplicateAttrs::KeepAll));
+    assert_eq!(3, e.attrs.len());
+
+    let mut e = dup();
+    assert_eq!(1, e.dedup_attrs(DuplicateAttrs::FirstWins));
+    assert_eq!("a", e.attr(a::TITLE).unwrap().as_ref());
+    assert_eq!(Some("en"), e.attrs.get(1).map(|a| a.value.as_ref()));
+
+    let mut e = dup();
+    assert_eq!(1, e.dedup_attrs(DuplicateAttrs::LastWins));
+    assert_eq!("b", e.attr(a::TITLE).unwrap().as_ref());
+    assert_eq!(Some("en"), e.attrs.get(0).map(|a| a.value.as_ref()));
+
+    let mut doc = Document::new();
+    let id = doc.append_child(
+        Document::DOCUMENT_NODE_ID,
+        Node::new_elem(dup()));
+    doc.filter(filter::dedup_attributes);
+    assert_eq!(2, doc[id].as_element().unwrap().attrs.len());
+    assert_eq!("<p title=\"a\" lang=\"en\"></p>", doc.to_string());
+}
+
+#[test]
+fn test_adopt() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div><p>one</p></div>".as_bytes()
+    );
+    let div = doc.root_element().unwrap();
+
+    let mut part = html::parse_utf8_fragment(
+        "<section><!--x--><p>two</p><p>three</p></section>".as_bytes()
+    );
+    part.filter(filter::detach_comments);
+    let p2 = part.document_node_ref()
+        .find(|n| n.is(t::P))
+        .unwrap()
+        .id();
+    part.set_user_data(p2, 2u8);
+
+    let map = doc.adopt(part, div);
+    doc.assert_consistent();
+    assert_eq!(
+        "<div><p>one</p><section><p>two</p><p>three</p></section></div>",
+        doc.to_string()
+    );
+    let np2 = map.get(p2).unwrap();
+    assert_eq!("two", doc.text(np2).unwrap().as_ref());
+    assert_eq!(Some(&2u8), doc.user_data::<u8>(np2));
+    assert!(map.get(Document::DOCUMENT_NODE_ID).is_none());
+
+    // Adopting an empty document is a no-op
+    let len = doc.len();
+    doc.adopt(Document::new(), div);
+    assert_eq!(len, doc.len());
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_paginate_stitch() {
+    use std::collections::HashMap;
+    use crate::paginate::{stitch, PageSource};
+
+    struct Pages(HashMap<&'static str, &'static str>, Vec<String>);
+
+    impl PageSource for Pages {
+        type Error = String;
+
+        fn fetch(&mut self, href: &str) -> Result<Document, String> {
+            self.1.push(href.to_owned());
+            match self.0.get(href) {
+                Some(html) => Ok(html::parse_utf8(html.as_bytes())),
+                None => Err(format!("not found: {}", href)),
+            }
+        }
+    }
+
+    ensure_logger();
+    let first = html::parse_utf8(
+        "<html><head><link rel=\"next\" href=\"/p2\"></head>\
+         <body><nav>menu</nav><article><p>one</p></article></body>\
+         </html>".as_bytes()
+    );
+    assert_eq!("/p2", first.next_page_href().unwrap().as_ref());
+
+    let mut pages = Pages(HashMap::new(), Vec::new());
+    pages.0.insert(
+        "/p2",
+        "<body><article><p>two</p></article>\
+         <a href=\"#top\">Next</a><a href=\"/p3\">Next &raquo;</a></body>");
+    pages.0.insert(
+        "/p3",
+        "<body><main><p>three</p></main>\
+         <a rel=\"next\" href=\"/p1\">more</a></body>");
+
+    let (doc, err) = stitch(first, Some("/p1"), &mut pages, 10);
+    assert!(err.is_none());
+    assert_eq!(vec!["/p2", "/p3"], pages.1);
+    doc.assert_consistent();
+    let article = doc.document_node_ref()
+        .find(|n| n.is(t::ARTICLE))
+        .unwrap();
+    assert_eq!(
+        "<article><p>one</p><p>two</p><p>three</p></article>",
+        article.to_string()
+    );
+
+    let (doc, err) = stitch(
+        html::parse_utf8("<a href=\"/none\">next page</a>".as_bytes()),
+        None, &mut pages, 10);
+    assert_eq!(Some("not found: /none".to_owned()), err);
+    assert!(doc.to_string().contains("next page"));
+}
+
+#[test]
+fn test_content_hash_simhash() {
+    use crate::dedup::{hamming_distance, simhash};
+    ensure_logger();
+
+    let d1 = html::parse_utf8_fragment(
+        "<p id=\"a\" class=\"x  y\"> b c<!--1--></p>".as_bytes());
+    let d2 = html::parse_utf8_fragment(
+        "<p class=\"x y\" id=\"a\">b\n c</p>".as_bytes());
+    let d3 = html::parse_utf8_fragment(
+        "<p class=\"x y\" id=\"a\">b <i>c</i></p>".as_bytes());
+    let h1 = d1.document_node_ref().content_hash();
+    assert_eq!(h1, d2.document_node_ref().content_hash());
+    assert_ne!(h1, d3.document_node_ref().content_hash());
+
+    let a = "The quick brown fox jumps over the lazy dog while the farmer \
+             sleeps in the warm afternoon sun, dreaming of a bountiful \
+             harvest and a quiet winter by the fire with his family and \
+             friends.";
+    let b = "The quick brown fox jumps over the lazy dog, while the farmer \
+             sleeps in the warm afternoon sun, dreaming of a bountiful \
+             harvest and a quiet winter by the fire with her family and \
+             friends!";
+    let c = "Stock markets fell sharply on Tuesday as investors reacted to \
+             unexpected inflation figures, with technology shares leading \
+             the decline and bond yields rising to their highest level in \
+             a decade.";
+    assert_eq!(0, simhash(""));
+    assert_eq!(simhash(a), simhash(&a.to_uppercase()));
+    assert!(hamming_distance(simhash(a), simhash(b)) < 16);
+    assert!(hamming_distance(simhash(a), simhash(c)) > 24);
+
+    let doc = html::parse_utf8_fragment(
+        format!("<div><p>{}</p></div>", a).as_bytes());
+    assert_eq!(simhash(a), doc.document_node_ref().simhash());
+}
+
+#[test]
+fn test_serialize_max_text_chars() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div><p>Hello <b>world</b>!</p><br><p>More &amp; more</p></div>"
+            .as_bytes()
+    );
+    let ser = |limit, marker: Option<&str>| doc.to_string_with(
+        &SerializeOptions::new()
+            .max_text_chars(limit)
+            .truncation_marker(marker)
+    );
+    let full = "<div><p>Hello <b>world</b>!</p><br><p>More &amp; more</p>\
+                </div>";
+    assert_eq!(full, ser(None, None));
+    assert_eq!(full, ser(Some(100), Some("…")));
+    assert_eq!(
+        "<div><p>Hello <b>world</b>!</p><br></div>",
+        ser(Some(12), None)
+    );
+    assert_eq!(
+        "<div><p>Hello <b>…</b></p></div>",
+        ser(Some(9), Some("…"))
+    );
+    assert_eq!(
+        "<div><p>Hello <b><span class=\"more\">…</span></b></p></div>",
+        ser(Some(9), Some("<span class=\"more\">…</span>"))
+    );
+    assert_eq!("…", ser(Some(0), Some("…")));
+}
+
+#[test]
+fn test_render_plain_text() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<h1>Title</h1>\
+         <p>Some <b>bold</b> text with a <a href=\"http://x.com/\">link</a>\n\
+         and more words here.</p>\
+         <ul><li>one</li><li>two<ol><li>a</li><li>b</li></ol></li></ul>\
+         <blockquote>quoted</blockquote>\
+         <pre>  code\n  more</pre><script>x</script>"
+            .as_bytes()
+    );
+    assert_eq!(
+        "Title\n\
+         =====\n\
+         \n\
+         Some bold text with\n\
+         a link[1] and more\n\
+         words here.\n\
+         \n\
+         * one\n\
+         * two\n\
+         \x20 1. a\n\
+         \x20 2. b\n\
+         \n\
+         > quoted\n\
+         \n\
+         \x20 code\n\
+         \x20 more\n\
+         \n\
+         References:\n\
+         \n\
+         [1] http://x.com/\n",
+        render::plain_text(doc.document_node_ref(), 20)
+    );
+}
+
+#[test]
+fn test_email_prep() {
+    use crate::filter::email::EmailPrep;
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<html><head><style>\
+         /* base */ p { color: red; margin: 0 }\n\
+         p.note { color: blue !important }\n\
+         #x { font-weight: bold }\n\
+         div p { padding: 1px }\n\
+         @media (max-width: 600px) { p { margin: 4px } }\
+         </style><style>a { color: green }</style></head>\
+         <body><p class=\"note\" style=\"color: black; margin: 2px\">n</p>\
+         <p id=\"x\">x <a href=\"../b/c.html?q#f\">b</a> \
+         <a href=\"#top\">t</a> <a href=\"//cdn.test/s\">s</a></p>\
+         <img src=\"/i.png\" style=\"width: 1200px; height: 300px\">\
+         <img src=\"i2.png\" width=\"100\">\
+         <img src=\"i3.png\" width=\"1ñ\">\
+         <script>x()</script><form><input></form></body></html>"
+            .as_bytes()
+    );
+    let ep = EmailPrep::builder()
+        .base_url("https://ex.test/a/page.html")
+        .build();
+    ep.apply(&mut doc);
+    doc.assert_consistent();
+    assert_eq!(
+        "<html><head><style>div p { padding: 1px }\n\
+         @media (max-width: 600px) { p { margin: 4px } }</style></head>\
+         <body>\
+         <p class=\"note\" style=\"color: blue !important; margin: 2px\">n</p>\
+         <p id=\"x\" style=\"color: red; margin: 0; font-weight: bold\">x \
+         <a href=\"https://ex.test/b/c.html?q#f\" style=\"color: green\">b</a> \
+         <a href=\"#top\" style=\"color: green\">t</a> \
+         <a href=\"https://cdn.test/s\" style=\"color: green\">s</a></p>\
+         <img src=\"https://ex.test/i.png\" \
+         style=\"width: 1200px; height: 300px\" width=\"600\" height=\"150\">\
+         <img src=\"https://ex.test/a/i2.png\" width=\"100\">\
+         <img src=\"https://ex.test/a/i3.png\" width=\"1ñ\">\
+         </body></html>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_css_parse_cascade() {
+    use crate::css::{self, Declaration, Item};
+    ensure_logger();
+
+    let items = css::parse_stylesheet(
+        "/* c */ h1, p.x { color: red; font: 12px \"a;b\" } \
+         @import url(\"x;y.css\");\
+         @media print { p { display: none } }\
+         div > p { margin: 0 !IMPORTANT; bad; : x }\
+         p { unterminated");
+    assert_eq!(4, items.len());
+    match &items[0] {
+        Item::Rule(r) => {
+            assert_eq!("h1, p.x", r.selectors);
+            assert_eq!("font", r.declarations[1].name);
+            assert_eq!("12px \"a;b\"", r.declarations[1].value);
+        }
+        _ => panic!("not a rule"),
+    }
+    assert_eq!(Item::AtRule("@import url(\"x;y.css\");".to_owned()), items[1]);
+    assert_eq!(
+        Item::AtRule("@media print { p { display: none } }".to_owned()),
+        items[2]
+    );
+    match &items[3] {
+        Item::Rule(r) => assert_eq!(
+            vec![Declaration {
+                name: "margin".to_owned(),
+                value: "0".to_owned(),
+                important: true,
+            }],
+            r.declarations
+        ),
+        _ => panic!("not a rule"),
+    }
+    assert_eq!((1, 2, 1), css::specificity("p#a.b[c]"));
+    assert!(css::is_compound("p.x[title='a b']"));
+    assert!(!css::is_compound("div p"));
+    assert!(!css::is_compound("a:hover"));
+
+    let doc = html::parse_utf8(
+        "<style>p { color: red; margin: 0 }\
+         .x { color: blue } div p { color: pink }</style>\
+         <style media=\"print\">p { color: black }</style>\
+         <p class=\"x\" style=\"margin: 1px; Color: green\">a</p>"
+            .as_bytes()
+    );
+    let rules = doc.style_rules();
+    assert_eq!(3, rules.len());
+    let p = doc.document_node_ref().find(|n| n.is_elem(t::P)).unwrap();
+    assert!(rules[0].matches(p));
+    assert!(!rules[2].matches(p));
+    assert_eq!(Some("green".to_owned()), p.style_property("color"));
+    assert_eq!(
+        "color: green; margin: 1px",
+        css::to_style(&css::cascade(p, &rules))
+    );
+}
+
+#[test]
+fn test_is_probably_hidden() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div>\
+         <p id=\"a\">shown <b id=\"b\">bold</b></p>\
+         <p id=\"c\" hidden><i id=\"d\">x</i></p>\
+         <p id=\"e\" aria-hidden=\"TRUE\">x</p>\
+         <p id=\"f\" style=\"color: red; DISPLAY : none\">x</p>\
+         <p id=\"g\" style=\"visibility: hidden\">x</p>\
+         <p id=\"h\" style=\"height: 0px; overflow: hidden\">x</p>\
+         <p id=\"i\" style=\"height: 0\">x</p>\
+         <p id=\"j\" style=\"opacity: 0.0\">x</p>\
+         <p id=\"k\" style=\"opacity: 0.5\">x</p>\
+         <img id=\"l\" src=\"t.gif\" width=\"0\" height=\"0\">\
+         <input id=\"m\" type=\"hidden\">\
+         </div>"
+            .as_bytes()
+    );
+    let hidden = |doc: &Document, id: &str| {
+        doc.document_node_ref()
+            .find(|n| n.attr(a::ID).map_or(false, |v| v.as_ref() == id))
+            .unwrap()
+            .is_probably_hidden()
+    };
+    for &id in &["c", "d", "e", "f", "g", "h", "j", "l", "m"] {
+        assert!(hidden(&doc, id), "{} hidden", id);
+    }
+    for &id in &["a", "b", "i", "k"] {
+        assert!(!hidden(&doc, id), "{} visible", id);
+    }
+
+    doc.filter_breadth(filter::detach_hidden);
+    doc.compact();
+    assert_eq!(
+        "<div><p id=\"a\">shown <b id=\"b\">bold</b></p>\
+         <p id=\"i\" style=\"height: 0\">x</p>\
+         <p id=\"k\" style=\"opacity: 0.5\">x</p></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_audit_a11y() {
+    use crate::audit::{a11y, Severity};
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<h1>T</h1>\
+         <img src=\"a.png\"><img src=\"b.png\" alt=\"\">\
+         <h3>skip</h3><h2>ok</h2><h3>ok</h3>\
+         <label>Name <input name=\"n\"></label>\
+         <label for=\"e\">E-mail</label><input id=\"e\">\
+         <input id=\"q\"><input type=\"hidden\"><input type=\"submit\">\
+         <textarea aria-label=\"Comment\"></textarea><select></select>\
+         <a href=\"/\"> </a><a href=\"/\"><img src=\"c\" alt=\"Home\"></a>\
+         <a href=\"/\" title=\"Home\"></a><a name=\"x\"></a>\
+         <div hidden><img src=\"d.png\"></div>"
+            .as_bytes()
+    );
+    let diags = a11y::check(&doc);
+    let codes: Vec<_> = diags.iter().map(|d| d.code()).collect();
+    assert_eq!(
+        vec![a11y::IMG_ALT, a11y::HEADING_SKIP, a11y::INPUT_LABEL,
+             a11y::INPUT_LABEL, a11y::LINK_EMPTY],
+        codes
+    );
+    assert_eq!(Severity::Warning, diags[1].severity());
+    assert_eq!(
+        "warning [heading-skip]: <h3> following <h1>",
+        diags[1].to_string()
+    );
+    let node = doc.try_node_ref(diags[2].node()).unwrap();
+    assert_eq!(Some("q"), node.attr(a::ID).map(|v| v.as_ref()));
+    assert!(doc[diags[3].node()].is_elem(t::SELECT));
+}
+
+#[test]
+fn test_audit_lint() {
+    use crate::audit::{
+        lint::{LintRule, Linter},
+        Diagnostic, Severity,
+    };
+    ensure_logger();
+
+    let mut doc = html::parse_utf8_with_spans(
+        "<div id=\"a\">\n\
+         <center>c</center>\n\
+         <span id=\"s\">x</span><li>i</li>\n\
+         <p id=\"a\">bad \u{FFFD}</p>\n\
+         </div>"
+            .as_bytes()
+    );
+    let span = doc.document_node_ref()
+        .find(|n| n.is_elem(t::SPAN))
+        .unwrap()
+        .id();
+    doc.append_child(span, Node::new_elem(Element::new(t::P)));
+
+    let mut linter = Linter::default();
+    assert_eq!(
+        vec!["obsolete-tag", "duplicate-id", "invalid-nesting",
+             "invalid-char"],
+        linter.codes()
+    );
+    let diags = linter.check(&doc);
+    let found: Vec<_> = diags.iter()
+        .map(|d| (d.code(), d.span().map(|s| s.start_line)))
+        .collect();
+    assert_eq!(
+        vec![
+            ("obsolete-tag", Some(2)),
+            ("invalid-nesting", None),
+            ("invalid-nesting", Some(3)),
+            ("duplicate-id", Some(4)),
+            ("invalid-char", Some(4)),
+        ],
+        found
+    );
+    assert_eq!("block <p> in <span>", diags[1].message());
+    assert_eq!("<li> not allowed in <div>", diags[2].message());
+    assert_eq!(
+        "line 4: error [duplicate-id]: duplicate id \"a\"",
+        diags[3].to_string()
+    );
+
+    // Re-check yields the same, with rule state reset
+    assert_eq!(diags, linter.check(&doc));
+
+    struct NoDivs;
+    impl LintRule for NoDivs {
+        fn code(&self) -> &'static str {
+            "no-div"
+        }
+
+        fn check(&mut self, node: NodeRef<'_>, out: &mut Vec<Diagnostic>) {
+            if node.is_elem(t::DIV) {
+                out.push(Diagnostic::new(
+                    node.id(), self.code(), Severity::Warning, "div"));
+            }
+        }
+    }
+    let diags = Linter::new().rule(NoDivs).check(&doc);
+    assert_eq!(1, diags.len());
+    assert_eq!(Some(1), diags[0].span().map(|s| s.start_line));
+}
+
+#[test]
+fn test_serialize_minify() {
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        "<div class=\"a b\">\n  \
+           <p>Some   <b>bold</b>\n  text</p>\n  \
+           <p>Two</p>\n  \
+           <ul>\n    \
+             <li>one</li>\n    \
+             <li><a href=\"/x\" title=\"\" data-x=\"a&amp;b\">two</a></li>\n  \
+           </ul>\n  \
+           <pre>  keep\n   this  </pre>\n  \
+           <table><tr><td colspan=\"1\">a</td><td>b</td></tr></table>\n  \
+           <input type=\"text\" name=q>\n\
+         </div>"
+            .as_bytes()
+    );
+    let opts = SerializeOptions::new().minify(true);
+    assert_eq!(
+        "<div class=\"a b\"><p>Some <b>bold</b> text<p>Two<ul><li>one\
+         <li><a href=/x title data-x=a&amp;b>two</a></ul>\
+         <pre>  keep\n   this  </pre>\
+         <table><tbody><tr><td>a<td>b</table> <input name=q> </div>",
+        doc.to_string_with(&opts)
+    );
+
+    let doc = html::parse_utf8(
+        "<!DOCTYPE html>\n<html><head><title>T</title></head>\n\
+         <body><p>x</p></body></html>"
+            .as_bytes()
+    );
+    assert_eq!(
+        "<!DOCTYPE html><html><head><title>T</title><body><p>x",
+        doc.to_string_with(&opts)
+    );
+
+    let doc = html::parse_utf8_fragment(
+        "<p title=\"\">a</p>\n<p>b  c</p>".as_bytes()
+    );
+    assert_eq!(
+        "<p title=\"\">a</p><p>b c</p>",
+        doc.to_string_with(&opts.xhtml(true))
+    );
+}
+
+#[test]
+fn test_serialize_normalize_attrs() {
+    ensure_logger();
+    let doc1 = html::parse_utf8_fragment(
+        "<input disabled=\"disabled\" type=\"checkbox\" checked=\"CHECKED\" \
+                name=\"a\">"
+            .as_bytes()
+    );
+    let doc2 = html::parse_utf8_fragment(
+        "<input name=\"a\" checked type=\"checkbox\" disabled>".as_bytes()
+    );
+    let opts = SerializeOptions::new().normalize_attrs(true);
+    let out = "<input checked=\"\" disabled=\"\" name=\"a\" type=\"checkbox\">";
+    assert_eq!(out, doc1.to_string_with(&opts));
+    assert_eq!(out, doc2.to_string_with(&opts));
+    assert_eq!(
+        "<input checked disabled name=a type=checkbox>",
+        doc1.to_string_with(&opts.clone().minify(true))
+    );
+    assert_eq!(
+        "<input checked=\"checked\" disabled=\"disabled\" name=\"a\" \
+         type=\"checkbox\"/>",
+        doc2.to_string_with(&opts.xhtml(true))
+    );
+    assert_eq!(
+        "<input disabled=\"disabled\" type=\"checkbox\" checked=\"CHECKED\" \
+         name=\"a\">",
+        doc1.to_string()
+    );
+}
+
+#[test]
+#[cfg(feature = "jsonld")]
+fn test_json_ld() {
+    use crate::jsonld::SchemaType;
+    ensure_logger();
+    let doc = html::parse_utf8(
+        r#"<html><head>
+        <script type="application/ld+json">
+          {"@context": "https://schema.org", "@type": "NewsArticle",
+           "headline": "Title"}
+        </script>
+        <script type="application/ld+json">{ invalid </script>
+        <script type="Application/LD+JSON">
+          {"@context": "https://schema.org", "@graph": [
+            {"@type": ["Thing", "schema:Recipe"], "name": "Soup"},
+            {"@type": "https://schema.org/Car", "name": "Roadster"},
+            {"@type": ["Person", "Personñe"], "name": "Ann"}
+          ]}
+        </script>
+        <script>{"@type": "Article"}</script>
+        </head><body></body></html>"#
+            .as_bytes()
+    );
+    let items = doc.json_ld();
+    let kinds: Vec<_> = items.iter().map(|i| i.kind).collect();
+    assert_eq!(
+        vec![SchemaType::Article, SchemaType::Recipe, SchemaType::Product,
+             SchemaType::Other],
+        kinds
+    );
+    assert_eq!("Title", items[0].get("headline").unwrap());
+    assert_eq!(vec!["Thing", "Recipe"], items[1].types);
+    assert_eq!(vec!["Car"], items[2].types);
+    assert_eq!(vec!["Person", "Personñe"], items[3].types);
+    assert_eq!(Some("Ann"), items[3].get("name").and_then(|v| v.as_str()));
+}
+
+#[test]
+fn test_microdata_items() {
+    use crate::microdata::{Syntax, Value};
+    ensure_logger();
+    let doc = html::parse_utf8_fragment(
+        r#"<div itemscope itemtype="https://schema.org/Person" itemid="p1">
+          <span itemprop="name">Jane
+            Doe</span>
+          <a itemprop="url sameAs" href="/jane">home</a>
+          <div itemprop="address" itemscope
+               itemtype="https://schema.org/PostalAddress">
+            <span itemprop="addressLocality">Seattle</span>
+          </div>
+          <meta itemprop="birthDate" content="1980-01-01">
+          <p>Updated <time itemprop="updated" datetime="2020-01-01">Jan</time>
+        </div>
+        <div vocab="https://schema.org/" typeof="Product">
+          <span property="name">Widget</span>
+          <div property="offers" typeof="Offer">
+            <span property="price" content="9.99">$9.99</span>
+          </div>
+          <img property="image" src="w.png">
+        </div>"#
+            .as_bytes()
+    );
+    let items = doc.microdata_items();
+    assert_eq!(2, items.len());
+
+    let person = &items[0];
+    assert_eq!(Syntax::Microdata, person.syntax);
+    assert!(person.is_type("Person"));
+    assert_eq!(Some("p1"), person.id.as_ref().map(String::as_str));
+    let names: Vec<&str> = person.properties.iter()
+        .map(|(n, _)| n.as_str())
+        .collect();
+    assert_eq!(
+        vec!["name", "url", "sameAs", "address", "birthDate", "updated"],
+        names
+    );
+    assert_eq!(Some(&Value::Text("Jane Doe".to_owned())), person.get("name"));
+    assert_eq!(Some(&Value::Url("/jane".to_owned())), person.get("sameAs"));
+    let address = person.get("address").and_then(Value::as_item).unwrap();
+    assert!(address.is_type("PostalAddress"));
+    assert_eq!(
+        Some("Seattle"),
+        address.get("addressLocality").and_then(Value::as_str)
+    );
+    assert_eq!(
+        Some("2020-01-01"),
+        person.get("updated").and_then(Value::as_str)
+    );
+
+    let product = &items[1];
+    assert_eq!(Syntax::Rdfa, product.syntax);
+    assert_eq!(vec!["https://schema.org/Product"], product.types);
+    let offer = product.get("offers").and_then(Value::as_item).unwrap();
+    assert_eq!(vec!["https://schema.org/Offer"], offer.types);
+    assert_eq!(Some("9.99"), offer.get("price").and_then(Value::as_str));
+    assert_eq!(Some("w.png"), product.get("image").and_then(Value::as_str));
+    assert_eq!(Some("Widget"), product.get("name").and_then(Value::as_str));
+}
+
+#[test]
+fn test_feed_links() {
+    use crate::meta::FeedKind;
+    ensure_logger();
+    let doc = html::parse_utf8(
+        r#"<html><head>
+        <link rel="alternate" type="application/rss+xml" href="/rss.xml"
+              title="News">
+        <link rel="Alternate" type="Application/Atom+XML" href=" /atom ">
+        <link rel="alternate" type="text/html" href="/fr" hreflang="fr">
+        <link rel="stylesheet" type="application/rss+xml" href="/x">
+        <link rel="alternate" type="application/rss+xml" href="">
+        </head><body></body></html>"#
+            .as_bytes()
+    );
+    let links = doc.feed_links();
+    assert_eq!(2, links.len());
+    assert_eq!(FeedKind::Rss, links[0].kind);
+    assert_eq!("/rss.xml", links[0].href.as_ref());
+    assert_eq!(Some("News"), links[0].title.as_ref().map(|t| t.as_ref()));
+    assert_eq!(FeedKind::Atom, links[1].kind);
+    assert_eq!("/atom", links[1].href.as_ref());
+    assert_eq!(None, links[1].title);
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_feed_parse() {
+    use crate::feed::{self, FeedError};
+    use crate::meta::FeedKind;
+    ensure_logger();
+    let rss = feed::parse_utf8(
+        r#"<?xml version="1.0"?>
+        <rss version="2.0"
+             xmlns:content="http://purl.org/rss/1.0/modules/content/">
+          <channel>
+            <title>News</title>
+            <link>http://example.com/</link>
+            <description>All the news</description>
+            <item>
+              <title>First</title>
+              <link>http://example.com/1</link>
+              <guid>1</guid>
+              <pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate>
+              <description>&lt;p&gt;Summary&lt;/p&gt;</description>
+              <content:encoded><![CDATA[<p>Full</p>]]></content:encoded>
+            </item>
+            <item><title>Second</title></item>
+          </channel>
+        </rss>"#
+            .as_bytes()
+    ).expect("rss");
+    assert_eq!(FeedKind::Rss, rss.kind);
+    assert_eq!(Some("News"), rss.title.as_ref().map(String::as_str));
+    assert_eq!(2, rss.entries.len());
+    let first = &rss.entries[0];
+    assert_eq!(Some("1"), first.id.as_ref().map(String::as_str));
+    assert_eq!(
+        Some("<p>Summary</p>"),
+        first.summary.as_ref().map(String::as_str)
+    );
+    assert_eq!(Some("<p>Full</p>"), first.content.as_ref().map(String::as_str));
+    assert_eq!(None, rss.entries[1].link);
+
+    let atom = feed::parse_utf8(
+        r#"<feed xmlns="http://www.w3.org/2005/Atom">
+          <title>Blog</title>
+          <link rel="self" href="http://example.com/atom"/>
+          <link href="http://example.com/"/>
+          <entry>
+            <title>Post</title>
+            <link rel="alternate" href="http://example.com/post"/>
+            <id>urn:1</id>
+            <updated>2003-12-13T18:30:02Z</updated>
+            <content type="xhtml">
+              <div xmlns="http://www.w3.org/1999/xhtml"><p>Hi</p></div>
+            </content>
+          </entry>
+        </feed>"#
+            .as_bytes()
+    ).expect("atom");
+    assert_eq!(FeedKind::Atom, atom.kind);
+    assert_eq!(
+        Some("http://example.com/"),
+        atom.link.as_ref().map(String::as_str)
+    );
+    let post = &atom.entries[0];
+    assert_eq!(
+        Some("http://example.com/post"),
+        post.link.as_ref().map(String::as_str)
+    );
+    assert_eq!(Some("<p>Hi</p>"), post.content.as_ref().map(String::as_str));
+
+    assert_eq!(
+        FeedError::NotAFeed,
+        feed::parse_utf8(b"<html><body/></html>").unwrap_err()
+    );
+}
+
+#[cfg(feature = "xml")]
+static SITEMAP_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>http://example.com/</loc>
+    <lastmod>2005-01-01</lastmod>
+    <changefreq>Monthly</changefreq>
+    <priority>0.8</priority>
+  </url>
+  <url><loc>http://example.com/a</loc><priority>2.0</priority></url>
+  <url><lastmod>2005-01-01</lastmod></url>
+</urlset>"#;
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_sitemap_parse() {
+    use crate::sitemap::{self, Sitemap, SitemapError};
+    ensure_logger();
+    let sm = sitemap::parse_utf8(SITEMAP_XML.as_bytes()).expect("urlset");
+    assert_eq!(vec!["http://example.com/", "http://example.com/a"], sm.locs());
+    if let Sitemap::UrlSet(urls) = &sm {
+        assert_eq!(
+            Some("2005-01-01"),
+            urls[0].lastmod.as_ref().map(String::as_str)
+        );
+        assert_eq!(
+            Some("monthly"),
+            urls[0].changefreq.as_ref().map(String::as_str)
+        );
+        assert!(urls[0].priority.map_or(false, |p| (p - 0.8).abs() < 1e-6));
+        assert_eq!(None, urls[1].priority);
+    } else {
+        panic!("not a urlset: {:?}", sm);
+    }
+
+    let sm = sitemap::parse_utf8(
+        r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <sitemap><loc>http://example.com/s1.xml.gz</loc></sitemap>
+        </sitemapindex>"#
+            .as_bytes()
+    ).expect("index");
+    match &sm {
+        Sitemap::Index(maps) => assert_eq!(None, maps[0].lastmod),
+        _ => panic!("not an index: {:?}", sm),
+    }
+    assert_eq!(vec!["http://example.com/s1.xml.gz"], sm.locs());
+
+    match sitemap::parse_utf8(b"<rss/>") {
+        Err(SitemapError::NotASitemap) => {}
+        r => panic!("unexpected: {:?}", r),
+    }
+}
+
+#[test]
+#[cfg(all(feature = "xml", feature = "flate2"))]
+fn test_sitemap_parse_gzip() {
+    use std::io::Write;
+    use flate2::{write::GzEncoder, Compression};
+    use crate::sitemap;
+    ensure_logger();
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(SITEMAP_XML.as_bytes()).unwrap();
+    let gz = enc.finish().unwrap();
+    let sm = sitemap::parse_gzip(&gz).expect("gzip");
+    assert_eq!(2, sm.locs().len());
+    assert_eq!(sm, sitemap::parse_gzip(SITEMAP_XML.as_bytes()).unwrap());
+    assert!(sitemap::parse_gzip(&gz[..gz.len() / 2]).is_err());
+}
+
+#[test]
+fn test_robots_directives() {
+    use crate::robots::ImagePreview;
+    ensure_logger();
+    let doc = html::parse_utf8(
+        r#"<html><head>
+        <meta name="robots" content="noarchive, max-snippet:50">
+        <meta name="Googlebot" content="nofollow, max-image-preview:large">
+        <meta name="otherbot" content="none">
+        </head><body>
+        <a href="/a" rel="NoFollow">a</a>
+        <a href="/b" rel="ugc noopener">b</a>
+        <a href="/c" rel="sponsored">c</a>
+        <a href="/d">d</a>
+        </body></html>"#
+            .as_bytes()
+    );
+    let mut rd = doc.robots_directives(Some("googlebot"));
+    assert!(rd.is_index());
+    assert!(!rd.is_follow());
+    assert!(rd.noarchive);
+    assert_eq!(Some(50), rd.max_snippet);
+    assert_eq!(Some(ImagePreview::Large), rd.max_image_preview);
+
+    rd.add_header("max-snippet: -1, max-image-preview: standard", None);
+    rd.add_header(
+        "otherbot: noindex, googlebot: notranslate",
+        Some("googlebot")
+    );
+    rd.add_header(
+        "unavailable_after: 2030-01-01T00:00:00Z, max-video-preview: 5",
+        None
+    );
+    assert!(rd.is_index());
+    assert!(rd.notranslate);
+    assert_eq!(Some(50), rd.max_snippet);
+    assert_eq!(Some(ImagePreview::Standard), rd.max_image_preview);
+    assert_eq!(Some(5), rd.max_video_preview);
+    assert_eq!(
+        Some("2030-01-01T00:00:00Z"),
+        rd.unavailable_after.as_ref().map(String::as_str)
+    );
+
+    let rd = doc.robots_directives(None);
+    assert!(rd.is_index() && rd.is_follow());
+    assert!(!doc.robots_directives(Some("otherbot")).is_index());
+
+    let rels: Vec<_> = doc.document_node_ref()
+        .select(|n| n.is_elem(t::A))
+        .map(|n| n.link_rel())
+        .collect();
+    assert!(rels[0].nofollow && !rels[0].is_follow());
+    assert!(rels[1].ugc && !rels[1].nofollow);
+    assert!(rels[2].sponsored);
+    assert!(rels[3].is_follow());
+}
+
+#[test]
+fn test_alternates() {
+    use crate::lang::LangTag;
+    use crate::meta::LinkIssue;
+    ensure_logger();
+    let doc = html::parse_utf8(
+        r#"<html><head>
+        <link rel="canonical" href=" https://example.com/page ">
+        <link rel="canonical" href="https://example.com/other">
+        <link rel="alternate" hreflang="en-us" href="https://example.com/">
+        <link rel="alternate" hreflang="zh_hant_TW" href="https://e.tw/">
+        <link rel="alternate" hreflang="x-default" href="https://e.com/">
+        <link rel="alternate" hreflang="EN-US" href="https://example.com/">
+        <link rel="alternate" hreflang="en-US" href="https://e.co.uk/">
+        <link rel="alternate" hreflang="fr" href="/fr/">
+        <link rel="alternate" hreflang="en-USA" href="https://e.com/en">
+        <link rel="alternate" hreflang="de-1-x" href="https://e.de/">
+        <link rel="alternate" type="application/rss+xml" href="/rss">
+        </head><body></body></html>"#
+            .as_bytes()
+    );
+    let alts = doc.alternates();
+    assert_eq!(
+        Some("https://example.com/page"),
+        alts.canonical.as_ref().map(|c| c.as_ref())
+    );
+    let langs: Vec<String> = alts.hreflang.iter()
+        .map(|hl| hl.lang.to_string())
+        .collect();
+    assert_eq!(vec!["en-US", "zh-Hant-TW"], langs);
+    assert_eq!(
+        Some("https://e.com/"),
+        alts.x_default.as_ref().map(|x| x.as_ref())
+    );
+    assert_eq!(
+        vec![
+            LinkIssue::ConflictingCanonical(
+                "https://example.com/other".into()),
+            LinkIssue::DuplicateLang("en-US".to_owned()),
+            LinkIssue::NotAbsolute("/fr/".into()),
+            LinkIssue::InvalidLang("en-USA".into()),
+            LinkIssue::InvalidLang("de-1-x".into()),
+        ],
+        alts.issues
+    );
+
+    let tag = LangTag::parse("sr-latn-rs-1996").unwrap();
+    assert_eq!(Some("Latn"), tag.script.as_ref().map(String::as_str));
+    assert_eq!("sr-Latn-RS-1996", tag.to_string());
+    assert_eq!(Some("419"), LangTag::parse("es-419").unwrap()
+               .region.as_ref().map(String::as_str));
+    assert_eq!(None, LangTag::parse("en--US"));
+}
+
+#[test]
+fn test_structure_signature() {
+    ensure_logger();
+    let doc_a = html::parse_utf8(
+        "<html><head><title>A</title></head><body>\
+         <div class=\"nav\"><ul><li>x</li><li>y</li></ul></div>\
+         <h1>Title A</h1><p>text</p></body></html>"
+            .as_bytes()
+    );
+    let doc_b = html::parse_utf8(
+        "<html><head><title>Page B</title></head><body>\
+         <div id=\"menu\"><ul><li>1</li><li>2</li><li>3</li></ul></div>\
+         <!-- ad --><h1>Other</h1><p>more text</p><p>more</p></body></html>"
+            .as_bytes()
+    );
+    let doc_c = html::parse_utf8(
+        "<html><body><table><tr><td>1</td></tr></table></body></html>"
+            .as_bytes()
+    );
+    let sig_a = doc_a.structure_signature();
+    assert_eq!(sig_a, doc_b.structure_signature());
+    assert_ne!(sig_a, doc_c.structure_signature());
+    assert_eq!(0, Document::new().structure_signature());
+
+    assert_eq!(vec![1, 2, 4, 1, 2], doc_a.depth_histogram());
+    let tags = doc_a.tag_histogram();
+    assert_eq!(Some(&2), tags.get("li"));
+    assert_eq!(Some(&1), tags.get("html"));
+    assert_eq!(10, tags.values().sum::<usize>());
+    assert_eq!(doc_a.stats().elements, 10);
+}
+
+#[test]
+fn test_xpath() {
+    use crate::xpath::{Value, XPath};
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<div id=\"main\"><p class=\"a\">one</p><div><p>two</p></div>\
+         <a href=\"/x\">X</a><a href=\"/y\">Y</a></div>\
+         <p>three</p><ul><li>1</li><li>2</li><li>3</li></ul>"
+            .as_bytes()
+    );
+    let v = doc.xpath("//div[@id='main']//p/text()").unwrap();
+    assert_eq!(vec!["one", "two"], v.strings());
+    assert_eq!(3, doc.xpath("//P").unwrap().nodes().len());
+    assert_eq!(
+        vec!["one", "two", "three"],
+        doc.xpath("//div/p | //p").unwrap().strings()
+    );
+
+    let v = doc.xpath("//a/@href").unwrap();
+    assert_eq!(vec!["/x", "/y"], v.strings());
+    let attr = v.nodes()[0].as_attr().unwrap();
+    assert_eq!("href", &*attr.name.local);
+
+    let strings = |expr: &str| doc.xpath(expr).unwrap().strings();
+    assert_eq!(vec!["1"], strings("//li[1]"));
+    assert_eq!(vec!["3"], strings("//li[last()]"));
+    assert_eq!(vec!["2", "3"], strings("//li[position() > 1]"));
+    assert_eq!(vec!["2"], strings("//li[3]/preceding-sibling::li[1]"));
+    assert_eq!(vec!["X"], strings("//a[contains(@href, 'x')]"));
+
+    let string = |expr: &str| doc.xpath(expr).unwrap().string();
+    assert_eq!("3", string("count(//li)"));
+    assert_eq!("6", string("sum(//li)"));
+    assert_eq!("one", string("string(//p[@class='a'])"));
+    assert_eq!("a b", string("normalize-space('  a \n b ')"));
+    assert_eq!("true", string("//li = '2'"));
+    assert_eq!("2.5", string("5 div 2"));
+
+    let main = match doc.xpath("//div[@id='main']").unwrap() {
+        Value::Nodes(ns) => ns[0].node_ref(),
+        v => panic!("unexpected {:?}", v),
+    };
+    assert_eq!(vec!["two"], main.xpath("div/p").unwrap().strings());
+    assert_eq!("body", main.xpath("name(..)").unwrap().string());
+    let xp = XPath::parse("count(p)").unwrap();
+    assert_eq!("1", xp.evaluate(main).unwrap().string());
+
+    assert!(XPath::parse("//li[").is_err());
+    assert!(XPath::parse("//li[. = $x]").is_err());
+    assert!(XPath::parse("foo()").is_err());
+    assert!(XPath::parse("count()").is_err());
+    assert!(doc.xpath("count('a')").is_err());
+}
+
+#[test]
+fn test_query_cache() {
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<div id=\"a\"><p class=\"x\">1</p><p>2</p></div>\
+         <div id=\"b\"><p class=\"x\">3</p></div>"
+            .as_bytes()
+    );
+    let mut cache = doc.query_cache();
+    let ids = |c: &[NodeRef<'_>]| c.iter().map(|n| n.id()).collect::<Vec<_>>();
+    let first = ids(cache.select("p.x"));
+    assert_eq!(2, first.len());
+    assert_eq!(first, ids(cache.select("p.x")));
+    assert!(cache.select("table").is_empty());
+
+    let b = cache.select("#b")[0];
+    let within = cache.select_within(b, "p");
+    assert_eq!(1, within.len());
+    assert_eq!("3", within[0].text().unwrap().as_ref());
+
+    assert_eq!("3", cache.xpath("count(//p)").unwrap().string());
+    assert_eq!(3, cache.xpath("//p").unwrap().nodes().len());
+    assert!(cache.xpath("//p[").is_err());
+    cache.clear();
+    assert_eq!(2, cache.select("p.x").len());
+}
+
+#[test]
+fn test_extract_rules() {
+    use crate::extract::rules::{Field, FieldValue, Kind, Rules};
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<h1>  A   Widget </h1>\
+         <ul><li><a href=\"/a\">x</a></li><li><a href=\"/b\">y</a></li>\
+         <li><a>z</a></li></ul>\
+         <span class=\"qty\">1,200</span><span class=\"stock\">Yes</span>\
+         <meta name=\"rating\" content=\"4.5\">"
+            .as_bytes()
+    );
+    let rules = Rules::new()
+        .field(Field::new("title", "h1").normalize_space().required(true))
+        .field(Field::new("links", "a").attr("href").all(true))
+        .field(Field::new("qty", "span.qty").parse(Kind::Integer))
+        .field(Field::new("stock", ".stock").parse(Kind::Boolean))
+        .field(
+            Field::xpath("rating", "//meta[@name='rating']/@content")
+                .unwrap()
+                .parse(Kind::Number)
+        )
+        .field(Field::xpath("count", "count(//li)").unwrap())
+        .field(Field::new("sku", "#sku").required(true));
+    let rec = rules.apply(&doc);
+    assert_eq!(Some("A Widget"), rec.get("title").and_then(|v| v.as_str()));
+    assert_eq!(
+        Some(&FieldValue::List(vec![
+            FieldValue::String("/a".to_owned()),
+            FieldValue::String("/b".to_owned()),
+        ])),
+        rec.get("links")
+    );
+    assert_eq!(Some(1200), rec.get("qty").and_then(|v| v.as_i64()));
+    assert_eq!(Some(true), rec.get("stock").and_then(|v| v.as_bool()));
+    let rating = rec.get("rating").and_then(|v| v.as_f64()).unwrap();
+    assert!((rating - 4.5).abs() < 1e-9);
+    assert_eq!(Some("3"), rec.get("count").and_then(|v| v.as_str()));
+    assert_eq!(Some(&FieldValue::Null), rec.get("sku"));
+    assert_eq!(&["sku".to_owned()], rec.missing());
+    assert!(!rec.is_complete());
+    assert!(rec.get("other").is_none());
+
+    let item = Rules::new().field(Field::new("link", "a").attr("href"));
+    let lis: Vec<_> = doc.document_node_ref()
+        .select(|n| n.is_elem(t::LI))
+        .collect();
+    let hrefs: Vec<_> = lis.iter()
+        .map(|li| item.apply_node(*li).get("link").unwrap().clone())
+        .collect();
+    assert_eq!(FieldValue::Null, hrefs[2]);
+    assert_eq!(Some("/b"), hrefs[1].as_str());
+    assert!(Field::xpath("bad", "//[").is_err());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_extract_rules_capture() {
+    use crate::extract::rules::{Field, Kind, Rules};
+    ensure_logger();
+    let doc = html::parse_utf8(
+        "<span class=\"price\">Now $1,299.50!</span>".as_bytes()
+    );
+    let re = regex::Regex::new(r"\$([\d,.]+)").unwrap();
+    let rules = Rules::new()
+        .field(Field::new("price", ".price").capture(re).parse(Kind::Number))
+        .field(
+            Field::new("none", ".price")
+                .capture(regex::Regex::new("€").unwrap())
+        );
+    let rec = rules.apply(&doc);
+    let price = rec.get("price").and_then(|v| v.as_f64()).unwrap();
+    assert!((price - 1299.5).abs() < 1e-9);
+    assert!(rec.get("none").unwrap().is_empty());
+}
+
+#[test]
+fn test_template_fill() {
+    use crate::template::{Template, Values};
+    ensure_logger();
+    let tmpl = Template::new(html::parse_utf8_fragment(
+        "<article><h1 data-bind=\"title\">Title</h1>\
+         <a data-bind-href=\"url\" data-bind=\"title\">link</a>\
+         <p data-if=\"!body\">No content</p>\
+         <slot name=\"body\"><p>fallback</p></slot>\
+         <ul><li data-each=\"tags\" data-bind=\"name\"></li></ul>\
+         <input type=\"checkbox\" data-bind-checked=\"done\"></article>"
+            .as_bytes()
+    ));
+    let body = Fragment::from_document(html::parse_utf8_fragment(
+        "<p>Body <b>text</b></p>".as_bytes()
+    ));
+    let vals = Values::new()
+        .text("title", "Hello & bye")
+        .text("url", "/hello")
+        .fragment("body", body)
+        .list("tags", vec![
+            Values::new().text("name", "a"),
+            Values::new().text("name", "b"),
+        ])
+        .flag("done", true);
+    assert_eq!(
+        "<article><h1>Hello &amp; bye</h1>\
+         <a href=\"/hello\">Hello &amp; bye</a>\
+         <p>Body <b>text</b></p><ul><li>a</li><li>b</li></ul>\
+         <input type=\"checkbox\" checked=\"\"></article>",
+        tmpl.fill(&vals).to_string()
+    );
+    assert_eq!(
+        "<article><h1>Title</h1><a>link</a><p>No content</p>\
+         <p>fallback</p><ul></ul><input type=\"checkbox\"></article>",
+        tmpl.fill(&Values::new()).to_string()
+    );
+
+    let mut map = std::collections::HashMap::new();
+    map.insert("title", "Mapped");
+    let doc = tmpl.document().fill_template(&map);
+    assert_eq!("Mapped", doc.root_element_ref().unwrap()
+        .find(|n| n.is_elem(t::H1)).unwrap()
+        .text().unwrap().as_ref());
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn test_template_fill_json() {
+    ensure_logger();
+    let tmpl = html::parse_utf8_fragment(
+        "<h1 data-bind=\"title\"></h1>\
+         <i data-each=\"tags\" data-bind=\".\"></i>\
+         <span data-bind=\"author.name\" data-if=\"draft\"></span>\
+         <span data-bind=\"author.name\"></span>"
+            .as_bytes()
+    );
+    let value = serde_json::json!({
+        "title": "J",
+        "tags": ["x", "y"],
+        "draft": false,
+        "author": { "name": "N" }
+    });
+    assert_eq!(
+        "<h1>J</h1><i>x</i><i>y</i><span>N</span>",
+        tmpl.fill_template(&value).to_string()
+    );
+}
+
+#[test]
+fn test_serialize_annotations() {
+    ensure_logger();
+    let mut doc = html::parse_utf8_fragment(
+        "<div lang=\"fr\"><p>un</p><p data-marked-rule=\"kept\">deux</p>\
+         <nav>x</nav></div>"
+            .as_bytes()
+    );
+    let ps: Vec<NodeId> = doc.document_node_ref()
+        .select(|n| n.is_elem(t::P))
+        .map(|n| n.id())
+        .collect();
+    let nav = doc.document_node_ref()
+        .find(|n| n.is_elem(t::NAV))
+        .unwrap()
+        .id();
+    doc.set_user_data(ps[0], Provenance::rule("dense").with_score(2.5));
+    doc.set_user_data(ps[1], Provenance::rule("other"));
+    doc.set_user_data(nav, Boilerplate { frequency: 0.5 });
+
+    let opts = SerializeOptions::new().annotations(true);
+    assert_eq!(
+        "<div lang=\"fr\">\
+         <p data-marked-rule=\"dense\" data-marked-score=\"2.5\" \
+         data-marked-lang=\"fr\">un</p>\
+         <p data-marked-rule=\"kept\" data-marked-lang=\"fr\">deux</p>\
+         <nav data-marked-boilerplate=\"0.5\" data-marked-lang=\"fr\">x</nav>\
+         </div>",
+        doc.to_string_with(&opts)
+    );
+    assert_eq!(
+        "<div lang=\"fr\"><p>un</p><p data-marked-rule=\"kept\">deux</p>\
+         <nav>x</nav></div>",
+        doc.to_string()
+    );
+}
+
+#[test]
+fn test_select_limited() {
+    ensure_logger();
+    let input = "<div><p>a</p><p>b</p></div>".repeat(1000);
+    let doc = html::parse_utf8_fragment(input.as_bytes());
+    let root = doc.document_node_ref();
+
+    let ps = root.find_first_n(|n| n.is_elem(t::P), 3);
+    assert_eq!(3, ps.len());
+    assert_eq!("a", ps[0].text().unwrap().as_ref());
+    assert_eq!("b", ps[1].text().unwrap().as_ref());
+
+    // Visits the wrapping div, div, p, p, div, p (not the p children)
+    let mut sel = root.select_limited(|n| n.is_elem(t::P), 6);
+    assert_eq!(3, sel.by_ref().count());
+    assert_eq!(6, sel.visited());
+    assert!(sel.is_exhausted());
+
+    // Sufficient budget
+    let mut sel = root.select_limited(|n| n.is_elem(t::P), 100_000);
+    assert_eq!(2000, sel.by_ref().count());
+    assert!(!sel.is_exhausted());
+    assert!(sel.visited() < 100_000);
+}
+
+#[test]
+fn test_filter_with_depth_limit() {
+    ensure_logger();
+    let mut doc = html::parse_utf8(
+        "<div><p><i>x</i></p></div><i>y</i>".as_bytes()
+    );
+    let detach_pi = |p: NodeRef<'_>, _: &mut NodeData| {
+        if p.is_elem(t::P) || p.is_elem(t::I) {
+            Action::Detach
+        } else {
+            Action::Continue
+        }
+    };
+
+    // html (1) body (2) div (3) p (4) i (5)
+    let max = std::cell::Cell::new(0);
+    doc.filter_with_depth_limit(
+        |p, _| {
+            max.set(max.get().max(p.ancestors().count()));
+            Action::Continue
+        },
+        4
+    );
+    assert_eq!(4, max.get());
+
+    doc.filter_with_depth_limit(detach_pi, 3);
+    doc.assert_consistent();
+    assert_eq!(
+        "<html><head></head><body><div><p><i>x</i></p></div></body></html>",
+        doc.to_string()
+    );
+
+    let div = doc.root_element_ref().unwrap()
+        .find(|n| n.is_elem(t::DIV))
+        .unwrap()
+        .id();
+    doc.filter_at(div, detach_pi);
+    assert_eq!(
+        "<html><head></head><body><div></div></body></html>",
+        doc.to_string()
+    );
+}
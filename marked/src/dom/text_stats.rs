@@ -0,0 +1,144 @@
+use crate::dom::{html::{a, t}, NodeId, NodeRef};
+
+/// Text statistics of a node, as returned by [`NodeRef::text_stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextStats {
+    /// Number of words: sequences of non-whitespace characters.
+    pub words: usize,
+
+    /// Number of non-whitespace characters.
+    pub chars: usize,
+
+    /// Estimated number of sentences, by terminal punctuation.
+    pub sentences: usize,
+
+    /// Number of non-whitespace characters within `<a href>` links.
+    pub link_chars: usize,
+}
+
+impl TextStats {
+    /// Return the ratio of link characters to all characters, or 0.0 if
+    /// there is no text.
+    pub fn link_density(&self) -> f32 {
+        if self.chars > 0 {
+            self.link_chars as f32 / self.chars as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Return the estimated reading time in minutes, at the given reading
+    /// speed in words per minute, e.g. 200-250 for typical adult readers.
+    pub fn reading_minutes(&self, words_per_minute: u32) -> f32 {
+        self.words as f32 / words_per_minute.max(1) as f32
+    }
+}
+
+enum Step {
+    Enter(NodeId, bool),
+    Break,
+}
+
+/// Text statistics methods.
+impl<'a> NodeRef<'a> {
+    /// Return word, character, sentence and link text statistics of the text
+    /// content of this node and its descendants.
+    ///
+    /// Words are separated by whitespace and by the boundaries of block
+    /// elements and `<br>`, but not by inline elements, e.g. `a<b>b</b>` is
+    /// a single word, while `<p>a</p><p>b</p>` is two. Sentences end with
+    /// terminal punctuation or at block element boundaries. Text of banned and
+    /// metadata elements (per [`TagMeta`](crate::html::TagMeta)), such as
+    /// `<script>`, `<style>` and `<title>`, is excluded. This is computed in a
+    /// single pass, without allocating the text.
+    pub fn text_stats(&self) -> TextStats {
+        let mut acc = Accumulator::default();
+        let mut stack = vec![Step::Enter(self.id(), false)];
+        while let Some(step) = stack.pop() {
+            let (id, in_link) = match step {
+                Step::Enter(id, in_link) => (id, in_link),
+                Step::Break => {
+                    acc.end_block();
+                    continue;
+                }
+            };
+            let node = NodeRef::new(self.doc(), id);
+            if let Some(text) = node.as_text() {
+                acc.text(text, in_link);
+                continue;
+            }
+            let mut in_link = in_link;
+            if let Some(elm) = node.as_element() {
+                if let Some(meta) = elm.html_tag_meta() {
+                    if meta.is_banned() || meta.is_meta() {
+                        continue;
+                    }
+                    if meta.is_empty() {
+                        // e.g. <br>, <img>
+                        acc.in_word = false;
+                    } else if !meta.is_inline() {
+                        acc.end_block();
+                        stack.push(Step::Break);
+                    }
+                }
+                in_link |= elm.is_elem(t::A) && elm.attr(a::HREF).is_some();
+            }
+            for child in node.children().rev() {
+                stack.push(Step::Enter(child.id(), in_link));
+            }
+        }
+        acc.finish()
+    }
+}
+
+#[derive(Default)]
+struct Accumulator {
+    stats: TextStats,
+    in_word: bool,
+    in_sentence: bool,
+}
+
+impl Accumulator {
+    fn text(&mut self, text: &str, in_link: bool) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.in_word = false;
+                continue;
+            }
+            if !self.in_word {
+                self.stats.words += 1;
+                self.in_word = true;
+            }
+            self.stats.chars += 1;
+            if in_link {
+                self.stats.link_chars += 1;
+            }
+            if is_terminal(c) {
+                if self.in_sentence {
+                    self.stats.sentences += 1;
+                    self.in_sentence = false;
+                }
+            } else if c.is_alphanumeric() {
+                self.in_sentence = true;
+            }
+        }
+    }
+
+    // End any word and sentence, at a block boundary.
+    fn end_block(&mut self) {
+        self.in_word = false;
+        if self.in_sentence {
+            self.stats.sentences += 1;
+            self.in_sentence = false;
+        }
+    }
+
+    fn finish(mut self) -> TextStats {
+        self.end_block();
+        self.stats
+    }
+}
+
+fn is_terminal(c: char) -> bool {
+    c == '.' || c == '!' || c == '?' || c == '。' || c == '！' || c == '？'
+}
@@ -0,0 +1,133 @@
+//! Alternative traversal orders over `NodeRef` sub-trees.
+
+use std::collections::VecDeque;
+
+use crate::dom::{Document, NodeId, NodeRef};
+
+/// A sub-tree traversal order, for [`NodeRef::traverse`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Depth-first, parents before children, in document order. This is
+    /// equivalent to [`NodeRef::descendants`].
+    PreOrder,
+
+    /// Level by level, starting with the root, with each level in document
+    /// order.
+    BreadthFirst,
+
+    /// Depth-first, children before parents, such that the root is last.
+    /// This is suitable for bottom-up algorithms like folding or scoring.
+    PostOrder,
+
+    /// The exact reverse of `PreOrder`, starting with the last descendant
+    /// and ending with the root.
+    ReverseDocument,
+}
+
+/// An iterator over a sub-tree in a given [`Order`], returned by
+/// [`NodeRef::traverse`].
+pub struct Traverse<'a> {
+    doc: &'a Document,
+    root: NodeId,
+    order: Order,
+    next: Option<NodeId>,
+    queue: VecDeque<NodeId>,
+}
+
+impl<'a> Traverse<'a> {
+    fn new(root: NodeRef<'a>, order: Order) -> Self {
+        let doc = root.doc();
+        let root = root.id();
+        let mut queue = VecDeque::new();
+        let next = match order {
+            Order::PreOrder => Some(root),
+            Order::BreadthFirst => {
+                queue.push_back(root);
+                None
+            }
+            Order::PostOrder => Some(first_leaf(doc, root)),
+            Order::ReverseDocument => Some(last_leaf(doc, root)),
+        };
+        Traverse { doc, root, order, next, queue }
+    }
+
+    fn successor(&self, id: NodeId) -> Option<NodeId> {
+        let doc = self.doc;
+        match self.order {
+            Order::PreOrder => {
+                if let Some(c) = doc[id].first_child {
+                    return Some(c);
+                }
+                let mut cur = id;
+                while cur != self.root {
+                    if let Some(s) = doc[cur].next_sibling {
+                        return Some(s);
+                    }
+                    cur = doc[cur].parent?;
+                }
+                None
+            }
+            Order::PostOrder => {
+                if id == self.root {
+                    None
+                } else if let Some(s) = doc[id].next_sibling {
+                    Some(first_leaf(doc, s))
+                } else {
+                    doc[id].parent
+                }
+            }
+            Order::ReverseDocument => {
+                if id == self.root {
+                    None
+                } else if let Some(s) = doc[id].prev_sibling {
+                    Some(last_leaf(doc, s))
+                } else {
+                    doc[id].parent
+                }
+            }
+            Order::BreadthFirst => None,
+        }
+    }
+}
+
+impl<'a> Iterator for Traverse<'a> {
+    type Item = NodeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = if self.order == Order::BreadthFirst {
+            let id = self.queue.pop_front()?;
+            self.queue.extend(self.doc.children(id));
+            id
+        } else {
+            let id = self.next?;
+            self.next = self.successor(id);
+            id
+        };
+        Some(NodeRef::new(self.doc, id))
+    }
+}
+
+// Return the first (left-most) leaf node of the given sub-tree.
+fn first_leaf(doc: &Document, mut id: NodeId) -> NodeId {
+    while let Some(c) = doc[id].first_child {
+        id = c;
+    }
+    id
+}
+
+// Return the last (right-most) leaf node of the given sub-tree.
+fn last_leaf(doc: &Document, mut id: NodeId) -> NodeId {
+    while let Some(c) = doc[id].last_child {
+        id = c;
+    }
+    id
+}
+
+/// Traversal order methods.
+impl<'a> NodeRef<'a> {
+    /// Return an iterator over this node and all its descendants, in the
+    /// specified [`Order`].
+    pub fn traverse(&self, order: Order) -> Traverse<'a> {
+        Traverse::new(*self, order)
+    }
+}
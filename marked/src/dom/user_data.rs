@@ -0,0 +1,109 @@
+//! Typed, per-node user data storage.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::dom::{Document, NodeId};
+
+type AnyMap = HashMap<TypeId, Box<dyn Any>>;
+
+/// Sidecar storage of user data, by `NodeId` and type.
+#[derive(Default)]
+pub(crate) struct UserData {
+    nodes: HashMap<NodeId, AnyMap>,
+}
+
+impl UserData {
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Remove and return all data for the given node.
+    pub(crate) fn take_node(&mut self, id: NodeId) -> Option<AnyMap> {
+        self.nodes.remove(&id)
+    }
+
+    /// Remove and return all data, for all nodes.
+    pub(crate) fn drain(&mut self)
+        -> impl Iterator<Item = (NodeId, AnyMap)> + '_
+    {
+        self.nodes.drain()
+    }
+
+    /// Replace all data for the given node.
+    pub(crate) fn insert_node(&mut self, id: NodeId, data: AnyMap) {
+        self.nodes.insert(id, data);
+    }
+}
+
+/// Typed user data methods.
+///
+/// Any number of values, one per distinct type, may be associated with each
+/// node. This is intended for multi-pass processing, for example where
+/// scores are computed for nodes in one pass and used in a subsequent pass,
+/// without the need to maintain separate maps keyed by `NodeId`. Use a
+/// private newtype for values to avoid conflicts with other users.
+///
+/// User data is retained (by remapped `NodeId`) for nodes retained by
+/// [`Document::compact`], but is otherwise dropped when nodes are removed via
+/// `compact`. It is also moved by [`Document::adopt`], but is not moved or
+/// cloned by any of [`Document::detach`], [`Document::deep_clone`] or
+/// [`Document::bulk_clone`].
+impl Document {
+    /// Set a value of type `T` for the specified node, returning any prior
+    /// value of the same type.
+    pub fn set_user_data<T>(&mut self, id: NodeId, value: T) -> Option<T>
+        where T: Any
+    {
+        self.user_data.nodes
+            .entry(id)
+            .or_default()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|prior| *prior.downcast::<T>().unwrap())
+    }
+
+    /// Return a reference to any value of type `T` for the specified node.
+    pub fn user_data<T>(&self, id: NodeId) -> Option<&T>
+        where T: Any
+    {
+        self.user_data.nodes
+            .get(&id)
+            .and_then(|m| m.get(&TypeId::of::<T>()))
+            .and_then(|v| v.downcast_ref::<T>())
+    }
+
+    /// Return a mutable reference to any value of type `T` for the specified
+    /// node.
+    pub fn user_data_mut<T>(&mut self, id: NodeId) -> Option<&mut T>
+        where T: Any
+    {
+        self.user_data.nodes
+            .get_mut(&id)
+            .and_then(|m| m.get_mut(&TypeId::of::<T>()))
+            .and_then(|v| v.downcast_mut::<T>())
+    }
+
+    /// Remove and return any value of type `T` for the specified node.
+    pub fn remove_user_data<T>(&mut self, id: NodeId) -> Option<T>
+        where T: Any
+    {
+        let map = self.user_data.nodes.get_mut(&id)?;
+        let value = map.remove(&TypeId::of::<T>());
+        if map.is_empty() {
+            self.user_data.nodes.remove(&id);
+        }
+        value.map(|v| *v.downcast::<T>().unwrap())
+    }
+
+    /// Remove all values of type `T`, for all nodes.
+    pub fn clear_user_data<T>(&mut self)
+        where T: Any
+    {
+        let tid = TypeId::of::<T>();
+        self.user_data.nodes.retain(|_, m| {
+            m.remove(&tid);
+            !m.is_empty()
+        });
+    }
+}
@@ -0,0 +1,79 @@
+use crate::dom::{
+    css::{self, Declaration},
+    html::{a, t},
+    Element, NodeRef,
+};
+
+/// Visibility methods.
+impl<'a> NodeRef<'a> {
+    /// Return true if this node or any ancestor element is probably not
+    /// visible to users, by heuristics on markup alone.
+    ///
+    /// An element is considered hidden if it has a `hidden` attribute,
+    /// `aria-hidden="true"`, is an `<input type="hidden">`, or has a `style`
+    /// attribute declaring `display: none`, `visibility: hidden` (or
+    /// `collapse`), `opacity: 0`, `font-size: 0`, or a zero `width` or
+    /// `height` with `overflow: hidden`. Images and frames with a zero
+    /// `width` or `height` attribute are also considered hidden. Stylesheet
+    /// rules and layout are not considered.
+    pub fn is_probably_hidden(&self) -> bool {
+        self.closest(|n| n.as_element().map_or(false, is_hidden_element))
+            .is_some()
+    }
+}
+
+// Return true if the element itself is probably hidden, as per
+// `NodeRef::is_probably_hidden`.
+pub(crate) fn is_hidden_element(elm: &Element) -> bool {
+    if elm.attr(a::HIDDEN).is_some() {
+        return true;
+    }
+    if elm.attr("aria-hidden")
+        .map_or(false, |v| v.trim().eq_ignore_ascii_case("true"))
+    {
+        return true;
+    }
+    if elm.is_elem(t::INPUT) &&
+        elm.attr(a::TYPE)
+        .map_or(false, |v| v.trim().eq_ignore_ascii_case("hidden"))
+    {
+        return true;
+    }
+    if (elm.is_elem(t::IMG) || elm.is_elem(t::IFRAME)) &&
+        (elm.attr(a::WIDTH).map_or(false, |v| is_zero(v)) ||
+         elm.attr(a::HEIGHT).map_or(false, |v| is_zero(v)))
+    {
+        return true;
+    }
+
+    let style = match elm.attr(a::STYLE) {
+        Some(s) => css::parse_declarations(s),
+        None => return false,
+    };
+    let prop = |name: &str| -> Option<&str> {
+        style.iter()
+            .rev()
+            .find(|d: &&Declaration| d.name == name)
+            .map(|d| d.value.as_str())
+    };
+    let is = |name: &str, vals: &[&str]| {
+        prop(name).map_or(false, |v| {
+            vals.iter().any(|x| v.eq_ignore_ascii_case(x))
+        })
+    };
+    let zero = |name: &str| prop(name).map_or(false, is_zero);
+
+    is("display", &["none"]) ||
+        is("visibility", &["hidden", "collapse"]) ||
+        zero("opacity") ||
+        zero("font-size") ||
+        ((zero("width") || zero("height")) && is("overflow", &["hidden"]))
+}
+
+// Return true for a zero number or length, e.g. `0`, `0.0` or `0px`.
+fn is_zero(v: &str) -> bool {
+    let num = v.trim()
+        .trim_start_matches(|c| c == '-' || c == '+')
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%');
+    num.contains('0') && num.chars().all(|c| c == '0' || c == '.')
+}
@@ -18,7 +18,7 @@ use xml_rs::reader::XmlEvent;
 use xml_rs::attribute::OwnedAttribute;
 
 use crate::dom::{
-    Attribute, Document, Element, Node, NodeData,
+    Attribute, Document, Element, Node, NodeData, NodeRef,
     ProcessingInstruction, QualName, StrTendril
 };
 use crate::chars::is_all_ctrl_ws;
@@ -100,6 +100,35 @@ fn convert_name(name: xml_rs::name::OwnedName) -> QualName {
     }
 }
 
+// Return true if node is an element with the local name, regardless of
+// namespace.
+pub(crate) fn is_named(node: NodeRef<'_>, name: &str) -> bool {
+    match &node.data {
+        NodeData::Elem(elm) => &*elm.name.local == name,
+        _ => false,
+    }
+}
+
+// Return the first child element with the local name.
+pub(crate) fn child<'a>(parent: NodeRef<'a>, name: &str)
+    -> Option<NodeRef<'a>>
+{
+    parent.children().find(|n| is_named(*n, name))
+}
+
+// Return the trimmed, non-empty text of the first child element with the
+// local name.
+pub(crate) fn child_text(parent: NodeRef<'_>, name: &str) -> Option<String> {
+    child(parent, name).and_then(text)
+}
+
+// Return the trimmed text of the node, if non-empty.
+pub(crate) fn text(node: NodeRef<'_>) -> Option<String> {
+    node.text()
+        .map(|t| t.trim().to_owned())
+        .filter(|t| !t.is_empty())
+}
+
 /// An XML parsing error.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct XmlError(xml_rs::reader::Error);
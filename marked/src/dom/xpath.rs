@@ -0,0 +1,1413 @@
+//! XPath 1.0 queries.
+//!
+//! An expression is compiled with [`XPath::parse`] and may then be evaluated
+//! repeatedly against any node via [`XPath::evaluate`]. For one-off queries
+//! [`Document::xpath`] and [`NodeRef::xpath`] parse and evaluate in a single
+//! step:
+//!
+//! ```
+//! use marked::html::parse_utf8;
+//!
+//! let doc = parse_utf8(
+//!     "<div id='main'><p>one</p><div><p>two</p></div></div><p>three</p>"
+//!         .as_bytes()
+//! );
+//! let v = doc.xpath("//div[@id='main']//p/text()").unwrap();
+//! assert_eq!(v.strings(), vec!["one", "two"]);
+//! ```
+//!
+//! All axes other than `namespace` are supported, along with the XPath 1.0
+//! core function library except `id`. Variables are not supported. Element
+//! and attribute names are matched by local name, ignoring any prefix, and
+//! ASCII case-insensitively, as is appropriate for HTML. Document type
+//! nodes are not part of the XPath data model and are never selected.
+
+use std::cmp::Ordering;
+use std::error::Error as StdError;
+use std::fmt;
+use std::iter;
+
+use crate::dom::{Attribute, Document, Node, NodeData, NodeRef};
+
+/// A compiled XPath expression.
+#[derive(Clone, Debug)]
+pub struct XPath {
+    source: String,
+    expr: Expr,
+}
+
+/// A node selected by an XPath expression.
+#[derive(Copy, Clone, Debug)]
+pub enum XNode<'a> {
+    /// A document, element, text, comment or processing instruction node.
+    Node(NodeRef<'a>),
+
+    /// An attribute, as the owning element node and index into its
+    /// attributes.
+    Attr(NodeRef<'a>, usize),
+}
+
+/// The result of evaluating an XPath expression.
+#[derive(Clone, Debug)]
+pub enum Value<'a> {
+    /// A node-set, in document order and without duplicates.
+    Nodes(Vec<XNode<'a>>),
+
+    /// A string.
+    String(String),
+
+    /// A number.
+    Number(f64),
+
+    /// A boolean.
+    Boolean(bool),
+}
+
+/// An error parsing or evaluating an XPath expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XPathError(String);
+
+impl XPathError {
+    fn new<S: Into<String>>(msg: S) -> XPathError {
+        XPathError(msg.into())
+    }
+}
+
+impl fmt::Display for XPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "XPath: {}", self.0)
+    }
+}
+
+impl StdError for XPathError {}
+
+impl XPath {
+    /// Parse and compile the given XPath 1.0 expression.
+    pub fn parse(expr: &str) -> Result<XPath, XPathError> {
+        let toks = tokenize(expr)?;
+        let mut parser = Parser { toks, pos: 0 };
+        let e = parser.or_expr()?;
+        if let Some(t) = parser.peek() {
+            return Err(XPathError::new(
+                format!("unexpected {:?} in {:?}", t, expr)
+            ));
+        }
+        Ok(XPath { source: expr.to_owned(), expr: e })
+    }
+
+    /// Evaluate this expression with the given node as context node.
+    ///
+    /// Errors are returned for expressions that are well formed but
+    /// misapply node-set operations, e.g. `count('a')` or `'a' | //b`.
+    pub fn evaluate<'a>(&self, node: NodeRef<'a>)
+        -> Result<Value<'a>, XPathError>
+    {
+        let eval = Eval::new(node.doc());
+        let ctx = Ctx { node: XNode::Node(node), pos: 1, size: 1 };
+        eval.eval(&self.expr, ctx)
+    }
+
+    /// Return the source text of this expression.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl fmt::Display for XPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// XPath methods.
+impl Document {
+    /// Parse and evaluate the given XPath 1.0 expression, with the document
+    /// node as context node.
+    pub fn xpath(&self, expr: &str) -> Result<Value<'_>, XPathError> {
+        XPath::parse(expr)?.evaluate(self.document_node_ref())
+    }
+}
+
+/// XPath methods.
+impl<'a> NodeRef<'a> {
+    /// Parse and evaluate the given XPath 1.0 expression, with this node as
+    /// context node.
+    pub fn xpath(&self, expr: &str) -> Result<Value<'a>, XPathError> {
+        XPath::parse(expr)?.evaluate(*self)
+    }
+}
+
+impl<'a> XNode<'a> {
+    /// Return the node, or for an attribute, its owning element.
+    pub fn node_ref(&self) -> NodeRef<'a> {
+        match *self {
+            XNode::Node(n) | XNode::Attr(n, _) => n,
+        }
+    }
+
+    /// Return the attribute, if this is an attribute.
+    pub fn as_attr(&self) -> Option<&'a Attribute> {
+        match *self {
+            XNode::Attr(n, i) => {
+                node_of(n).as_element().and_then(|e| e.attrs.get(i))
+            }
+            XNode::Node(_) => None,
+        }
+    }
+
+    /// Return the XPath string-value of this node: the attribute value,
+    /// character data, or concatenated descendant text.
+    pub fn string_value(&self) -> String {
+        let n = match *self {
+            XNode::Attr(..) => {
+                return self.as_attr()
+                    .map_or_else(String::new, |a| a.value.to_string());
+            }
+            XNode::Node(n) => n,
+        };
+        match &node_of(n).data {
+            NodeData::Text(t) | NodeData::Comment(t) => t.to_string(),
+            NodeData::Pi(pi) => pi.data.to_string(),
+            NodeData::Elem(_) | NodeData::Document => {
+                n.text().map_or_else(String::new, |t| t.to_string())
+            }
+            NodeData::DocType(_) | NodeData::Hole => String::new(),
+        }
+    }
+
+    /// Return the element or attribute name, with any prefix if
+    /// `qualified`, or the empty string for other nodes.
+    fn name(&self, qualified: bool) -> String {
+        let qname = match *self {
+            XNode::Attr(..) => self.as_attr().map(|a| &a.name),
+            XNode::Node(n) => node_of(n).as_element().map(|e| &e.name),
+        };
+        match qname {
+            Some(q) => match &q.prefix {
+                Some(p) if qualified => format!("{}:{}", p, q.local),
+                _ => q.local.to_string(),
+            },
+            None => String::new(),
+        }
+    }
+
+    fn is_attr(&self) -> bool {
+        match self {
+            XNode::Attr(..) => true,
+            XNode::Node(_) => false,
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Return the node-set, or an empty slice for other types.
+    pub fn nodes(&self) -> &[XNode<'a>] {
+        match self {
+            Value::Nodes(ns) => ns,
+            _ => &[],
+        }
+    }
+
+    /// Return the string-value of each node of a node-set, or for other
+    /// types, a single string conversion.
+    pub fn strings(&self) -> Vec<String> {
+        match self {
+            Value::Nodes(ns) => ns.iter().map(XNode::string_value).collect(),
+            _ => vec![self.string()],
+        }
+    }
+
+    /// Convert to a string, as per the XPath `string()` function. A node-set
+    /// converts to the string-value of its first node.
+    pub fn string(&self) -> String {
+        match self {
+            Value::Nodes(ns) => {
+                ns.first().map_or_else(String::new, XNode::string_value)
+            }
+            Value::String(s) => s.clone(),
+            Value::Number(n) => number_to_string(*n),
+            Value::Boolean(b) => b.to_string(),
+        }
+    }
+
+    /// Convert to a number, as per the XPath `number()` function.
+    pub fn number(&self) -> f64 {
+        match self {
+            Value::Nodes(_) => str_to_number(&self.string()),
+            Value::String(s) => str_to_number(s),
+            Value::Number(n) => *n,
+            Value::Boolean(b) => if *b { 1.0 } else { 0.0 },
+        }
+    }
+
+    /// Convert to a boolean, as per the XPath `boolean()` function.
+    pub fn boolean(&self) -> bool {
+        match self {
+            Value::Nodes(ns) => !ns.is_empty(),
+            Value::String(s) => !s.is_empty(),
+            Value::Number(n) => n.abs() > 0.0,
+            Value::Boolean(b) => *b,
+        }
+    }
+}
+
+fn node_of(n: NodeRef<'_>) -> &Node {
+    &n.doc()[n.id()]
+}
+
+fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_owned()
+    } else if n.is_infinite() {
+        let s = if n > 0.0 { "Infinity" } else { "-Infinity" };
+        s.to_owned()
+    } else if is_zero(n.fract()) && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+fn str_to_number(s: &str) -> f64 {
+    let s = s.trim();
+    let digits = if s.starts_with('-') { &s[1..] } else { s };
+    let valid = !digits.is_empty()
+        && digits.chars().any(|c| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.matches('.').count() <= 1;
+    if valid {
+        s.parse().unwrap_or(std::f64::NAN)
+    } else {
+        std::f64::NAN
+    }
+}
+
+fn is_zero(n: f64) -> bool {
+    n.partial_cmp(&0.0) == Some(Ordering::Equal)
+}
+
+/// Round as per XPath: to the nearest integer, with halves rounded towards
+/// positive infinity.
+fn round(n: f64) -> f64 {
+    if n.is_nan() || n.is_infinite() {
+        n
+    } else {
+        (n + 0.5).floor()
+    }
+}
+
+// Lexical tokens
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Slash,
+    DSlash,
+    LParen,
+    RParen,
+    LBrack,
+    RBrack,
+    Dot,
+    DDot,
+    At,
+    Comma,
+    DColon,
+    Pipe,
+    Plus,
+    Minus,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Star,
+    Mul,
+    And,
+    Or,
+    Div,
+    Mod,
+    Lit(String),
+    Num(f64),
+    Name(String),
+}
+
+impl Tok {
+    /// Return true if a following `*` or name is an operator, as per the
+    /// lexical disambiguation rules of XPath 1.0 (3.7).
+    fn precedes_operator(&self) -> bool {
+        match self {
+            Tok::RParen | Tok::RBrack | Tok::Dot | Tok::DDot | Tok::Star |
+            Tok::Lit(_) | Tok::Num(_) | Tok::Name(_) => true,
+            _ => false,
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Tok>, XPathError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut toks: Vec<Tok> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let next = chars.get(i + 1).cloned();
+        let op_ctx = toks.last().map_or(false, Tok::precedes_operator);
+        let (tok, len) = match (c, next) {
+            ('/', Some('/')) => (Tok::DSlash, 2),
+            ('/', _) => (Tok::Slash, 1),
+            ('(', _) => (Tok::LParen, 1),
+            (')', _) => (Tok::RParen, 1),
+            ('[', _) => (Tok::LBrack, 1),
+            (']', _) => (Tok::RBrack, 1),
+            ('.', Some('.')) => (Tok::DDot, 2),
+            ('.', Some(d)) if d.is_ascii_digit() => number(&chars[i..])?,
+            ('.', _) => (Tok::Dot, 1),
+            ('@', _) => (Tok::At, 1),
+            (',', _) => (Tok::Comma, 1),
+            (':', Some(':')) => (Tok::DColon, 2),
+            ('|', _) => (Tok::Pipe, 1),
+            ('+', _) => (Tok::Plus, 1),
+            ('-', _) => (Tok::Minus, 1),
+            ('=', _) => (Tok::Eq, 1),
+            ('!', Some('=')) => (Tok::Ne, 2),
+            ('<', Some('=')) => (Tok::Le, 2),
+            ('<', _) => (Tok::Lt, 1),
+            ('>', Some('=')) => (Tok::Ge, 2),
+            ('>', _) => (Tok::Gt, 1),
+            ('*', _) => (if op_ctx { Tok::Mul } else { Tok::Star }, 1),
+            ('"', _) | ('\'', _) => {
+                let end = chars[i + 1..].iter().position(|&q| q == c)
+                    .ok_or_else(|| XPathError::new(
+                        format!("unterminated literal in {:?}", s)
+                    ))?;
+                let lit = chars[i + 1..i + 1 + end].iter().collect();
+                (Tok::Lit(lit), end + 2)
+            }
+            (d, _) if d.is_ascii_digit() => number(&chars[i..])?,
+            (n, _) if is_name_start(n) => {
+                let (name, len) = name(&chars[i..]);
+                let tok = match name.as_str() {
+                    "and" if op_ctx => Tok::And,
+                    "or" if op_ctx => Tok::Or,
+                    "div" if op_ctx => Tok::Div,
+                    "mod" if op_ctx => Tok::Mod,
+                    _ => Tok::Name(name),
+                };
+                (tok, len)
+            }
+            _ => {
+                return Err(XPathError::new(
+                    format!("unexpected {:?} in {:?}", c, s)
+                ));
+            }
+        };
+        toks.push(tok);
+        i += len;
+    }
+    Ok(toks)
+}
+
+fn number(chars: &[char]) -> Result<(Tok, usize), XPathError> {
+    let mut len = 0;
+    let mut dot = false;
+    for &c in chars {
+        if c == '.' && !dot {
+            dot = true;
+        } else if !c.is_ascii_digit() {
+            break;
+        }
+        len += 1;
+    }
+    let s: String = chars[..len].iter().collect();
+    s.parse()
+        .map(|n| (Tok::Num(n), len))
+        .map_err(|_| XPathError::new(format!("invalid number {:?}", s)))
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Lex a name, including any `prefix:` and a `prefix:*` wildcard.
+fn name(chars: &[char]) -> (String, usize) {
+    let mut len = chars.iter().take_while(|&&c| is_name_char(c)).count();
+    if chars.get(len) == Some(&':') {
+        match chars.get(len + 1) {
+            Some('*') => len += 2,
+            Some(&c) if is_name_start(c) => {
+                len += 1;
+                len += chars[len..].iter()
+                    .take_while(|&&c| is_name_char(c))
+                    .count();
+            }
+            _ => {}
+        }
+    }
+    (chars[..len].iter().collect(), len)
+}
+
+// Syntax tree
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Cmp(CmpOp, Box<Expr>, Box<Expr>),
+    Arith(ArithOp, Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Call(Func, Vec<Expr>),
+    Path(bool, Vec<Step>),
+    Filter(Box<Expr>, Vec<Expr>, Vec<Step>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CmpOp { Eq, Ne, Lt, Le, Gt, Ge }
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ArithOp { Add, Sub, Mul, Div, Mod }
+
+#[derive(Clone, Debug)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    preds: Vec<Expr>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    SelfAxis,
+}
+
+#[derive(Clone, Debug)]
+enum NodeTest {
+    Name(String),
+    Any,
+    Text,
+    Comment,
+    Pi,
+    Node,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Func {
+    Last,
+    Position,
+    Count,
+    LocalName,
+    Name,
+    String,
+    Concat,
+    StartsWith,
+    Contains,
+    SubstringBefore,
+    SubstringAfter,
+    Substring,
+    StringLength,
+    NormalizeSpace,
+    Translate,
+    Not,
+    True,
+    False,
+    Boolean,
+    Lang,
+    Number,
+    Sum,
+    Floor,
+    Ceiling,
+    Round,
+}
+
+/// Return the function and its minimum and maximum argument count.
+fn function(name: &str) -> Option<(Func, usize, usize)> {
+    let f = match name {
+        "last" => (Func::Last, 0, 0),
+        "position" => (Func::Position, 0, 0),
+        "count" => (Func::Count, 1, 1),
+        "local-name" => (Func::LocalName, 0, 1),
+        "name" => (Func::Name, 0, 1),
+        "string" => (Func::String, 0, 1),
+        "concat" => (Func::Concat, 2, usize::max_value()),
+        "starts-with" => (Func::StartsWith, 2, 2),
+        "contains" => (Func::Contains, 2, 2),
+        "substring-before" => (Func::SubstringBefore, 2, 2),
+        "substring-after" => (Func::SubstringAfter, 2, 2),
+        "substring" => (Func::Substring, 2, 3),
+        "string-length" => (Func::StringLength, 0, 1),
+        "normalize-space" => (Func::NormalizeSpace, 0, 1),
+        "translate" => (Func::Translate, 3, 3),
+        "not" => (Func::Not, 1, 1),
+        "true" => (Func::True, 0, 0),
+        "false" => (Func::False, 0, 0),
+        "boolean" => (Func::Boolean, 1, 1),
+        "lang" => (Func::Lang, 1, 1),
+        "number" => (Func::Number, 0, 1),
+        "sum" => (Func::Sum, 1, 1),
+        "floor" => (Func::Floor, 1, 1),
+        "ceiling" => (Func::Ceiling, 1, 1),
+        "round" => (Func::Round, 1, 1),
+        _ => return None,
+    };
+    Some(f)
+}
+
+fn axis(name: &str) -> Option<Axis> {
+    let a = match name {
+        "ancestor" => Axis::Ancestor,
+        "ancestor-or-self" => Axis::AncestorOrSelf,
+        "attribute" => Axis::Attribute,
+        "child" => Axis::Child,
+        "descendant" => Axis::Descendant,
+        "descendant-or-self" => Axis::DescendantOrSelf,
+        "following" => Axis::Following,
+        "following-sibling" => Axis::FollowingSibling,
+        "parent" => Axis::Parent,
+        "preceding" => Axis::Preceding,
+        "preceding-sibling" => Axis::PrecedingSibling,
+        "self" => Axis::SelfAxis,
+        _ => return None,
+    };
+    Some(a)
+}
+
+fn node_type(name: &str) -> Option<NodeTest> {
+    let t = match name {
+        "text" => NodeTest::Text,
+        "comment" => NodeTest::Comment,
+        "processing-instruction" => NodeTest::Pi,
+        "node" => NodeTest::Node,
+        _ => return None,
+    };
+    Some(t)
+}
+
+// Parser
+
+struct Parser {
+    toks: Vec<Tok>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos)
+    }
+
+    fn peek_at(&self, n: usize) -> Option<&Tok> {
+        self.toks.get(self.pos + n)
+    }
+
+    fn bump(&mut self) -> Option<Tok> {
+        let t = self.toks.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat(&mut self, t: &Tok) -> bool {
+        if self.peek() == Some(t) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, t: &Tok) -> Result<(), XPathError> {
+        if self.eat(t) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("{:?}", t)))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> XPathError {
+        match self.peek() {
+            Some(t) => XPathError::new(
+                format!("expected {}, found {:?}", expected, t)
+            ),
+            None => XPathError::new(
+                format!("expected {}, found end of expression", expected)
+            ),
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.and_expr()?;
+        while self.eat(&Tok::Or) {
+            e = Expr::Or(Box::new(e), Box::new(self.and_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.eq_expr()?;
+        while self.eat(&Tok::And) {
+            e = Expr::And(Box::new(e), Box::new(self.eq_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn eq_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.rel_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Eq) => CmpOp::Eq,
+                Some(Tok::Ne) => CmpOp::Ne,
+                _ => break,
+            };
+            self.pos += 1;
+            e = Expr::Cmp(op, Box::new(e), Box::new(self.rel_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn rel_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.add_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Lt) => CmpOp::Lt,
+                Some(Tok::Le) => CmpOp::Le,
+                Some(Tok::Gt) => CmpOp::Gt,
+                Some(Tok::Ge) => CmpOp::Ge,
+                _ => break,
+            };
+            self.pos += 1;
+            e = Expr::Cmp(op, Box::new(e), Box::new(self.add_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn add_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.mul_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Plus) => ArithOp::Add,
+                Some(Tok::Minus) => ArithOp::Sub,
+                _ => break,
+            };
+            self.pos += 1;
+            e = Expr::Arith(op, Box::new(e), Box::new(self.mul_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn mul_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.unary_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Mul) => ArithOp::Mul,
+                Some(Tok::Div) => ArithOp::Div,
+                Some(Tok::Mod) => ArithOp::Mod,
+                _ => break,
+            };
+            self.pos += 1;
+            e = Expr::Arith(op, Box::new(e), Box::new(self.unary_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr, XPathError> {
+        if self.eat(&Tok::Minus) {
+            Ok(Expr::Neg(Box::new(self.unary_expr()?)))
+        } else {
+            self.union_expr()
+        }
+    }
+
+    fn union_expr(&mut self) -> Result<Expr, XPathError> {
+        let mut e = self.path_expr()?;
+        while self.eat(&Tok::Pipe) {
+            e = Expr::Union(Box::new(e), Box::new(self.path_expr()?));
+        }
+        Ok(e)
+    }
+
+    fn path_expr(&mut self) -> Result<Expr, XPathError> {
+        let is_filter = match self.peek() {
+            Some(Tok::Lit(_)) | Some(Tok::Num(_)) | Some(Tok::LParen) => true,
+            Some(Tok::Name(n)) => {
+                self.peek_at(1) == Some(&Tok::LParen)
+                    && node_type(n).is_none()
+            }
+            _ => false,
+        };
+        if !is_filter {
+            return self.location_path();
+        }
+        let primary = self.primary_expr()?;
+        let mut preds = Vec::new();
+        while self.peek() == Some(&Tok::LBrack) {
+            preds.push(self.predicate()?);
+        }
+        let mut steps = Vec::new();
+        self.more_steps(&mut steps)?;
+        if preds.is_empty() && steps.is_empty() {
+            Ok(primary)
+        } else {
+            Ok(Expr::Filter(Box::new(primary), preds, steps))
+        }
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr, XPathError> {
+        match self.bump() {
+            Some(Tok::Lit(s)) => Ok(Expr::Literal(s)),
+            Some(Tok::Num(n)) => Ok(Expr::Number(n)),
+            Some(Tok::LParen) => {
+                let e = self.or_expr()?;
+                self.expect(&Tok::RParen)?;
+                Ok(e)
+            }
+            Some(Tok::Name(name)) => {
+                let (func, min, max) = function(&name).ok_or_else(|| {
+                    XPathError::new(format!("unknown function {}()", name))
+                })?;
+                self.expect(&Tok::LParen)?;
+                let mut args = Vec::new();
+                if !self.eat(&Tok::RParen) {
+                    loop {
+                        args.push(self.or_expr()?);
+                        if self.eat(&Tok::RParen) {
+                            break;
+                        }
+                        self.expect(&Tok::Comma)?;
+                    }
+                }
+                if args.len() < min || args.len() > max {
+                    return Err(XPathError::new(format!(
+                        "wrong number of arguments ({}) to {}()",
+                        args.len(), name
+                    )));
+                }
+                Ok(Expr::Call(func, args))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("expression"))
+            }
+        }
+    }
+
+    fn location_path(&mut self) -> Result<Expr, XPathError> {
+        let mut steps = Vec::new();
+        let absolute = if self.eat(&Tok::Slash) {
+            // A lone `/` selects the document node
+            if self.starts_step() {
+                steps.push(self.step()?);
+            }
+            true
+        } else if self.eat(&Tok::DSlash) {
+            steps.push(descendant_or_self());
+            steps.push(self.step()?);
+            true
+        } else {
+            steps.push(self.step()?);
+            false
+        };
+        if !steps.is_empty() {
+            self.more_steps(&mut steps)?;
+        }
+        Ok(Expr::Path(absolute, steps))
+    }
+
+    /// Parse any further `/step` or `//step` onto steps.
+    fn more_steps(&mut self, steps: &mut Vec<Step>)
+        -> Result<(), XPathError>
+    {
+        loop {
+            if self.eat(&Tok::DSlash) {
+                steps.push(descendant_or_self());
+            } else if !self.eat(&Tok::Slash) {
+                return Ok(());
+            }
+            steps.push(self.step()?);
+        }
+    }
+
+    fn starts_step(&self) -> bool {
+        match self.peek() {
+            Some(Tok::Dot) | Some(Tok::DDot) | Some(Tok::At) |
+            Some(Tok::Star) | Some(Tok::Name(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, XPathError> {
+        if self.eat(&Tok::Dot) {
+            return Ok(Step {
+                axis: Axis::SelfAxis, test: NodeTest::Node, preds: vec![]
+            });
+        }
+        if self.eat(&Tok::DDot) {
+            return Ok(Step {
+                axis: Axis::Parent, test: NodeTest::Node, preds: vec![]
+            });
+        }
+        let axis = if self.eat(&Tok::At) {
+            Axis::Attribute
+        } else if self.peek_at(1) == Some(&Tok::DColon) {
+            let a = match self.peek() {
+                Some(Tok::Name(n)) => axis(n),
+                _ => None,
+            };
+            let a = a.ok_or_else(|| self.unexpected("axis name"))?;
+            self.pos += 2;
+            a
+        } else {
+            Axis::Child
+        };
+        let test = match self.bump() {
+            Some(Tok::Star) => NodeTest::Any,
+            Some(Tok::Name(n)) => {
+                match node_type(&n) {
+                    Some(t) if self.peek() == Some(&Tok::LParen) => {
+                        self.pos += 1;
+                        // processing-instruction('target') is accepted, but
+                        // the target is not retained
+                        if let Some(Tok::Lit(_)) = self.peek() {
+                            self.pos += 1;
+                        }
+                        self.expect(&Tok::RParen)?;
+                        t
+                    }
+                    _ => name_test(n),
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected("node test"));
+            }
+        };
+        let mut preds = Vec::new();
+        while self.peek() == Some(&Tok::LBrack) {
+            preds.push(self.predicate()?);
+        }
+        Ok(Step { axis, test, preds })
+    }
+
+    fn predicate(&mut self) -> Result<Expr, XPathError> {
+        self.expect(&Tok::LBrack)?;
+        let e = self.or_expr()?;
+        self.expect(&Tok::RBrack)?;
+        Ok(e)
+    }
+}
+
+fn descendant_or_self() -> Step {
+    Step {
+        axis: Axis::DescendantOrSelf,
+        test: NodeTest::Node,
+        preds: vec![],
+    }
+}
+
+/// Return a name test for the given name, stripping any prefix.
+fn name_test(name: String) -> NodeTest {
+    match name.find(':') {
+        Some(i) if &name[i + 1..] == "*" => NodeTest::Any,
+        Some(i) => NodeTest::Name(name[i + 1..].to_owned()),
+        None => NodeTest::Name(name),
+    }
+}
+
+impl NodeTest {
+    /// Test a node found on any axis other than `attribute`, for which the
+    /// principal node type is element.
+    fn matches(&self, n: XNode<'_>) -> bool {
+        let n = match n {
+            XNode::Node(n) => n,
+            XNode::Attr(..) => {
+                return match self {
+                    NodeTest::Node => true,
+                    _ => false,
+                };
+            }
+        };
+        match (self, &node_of(n).data) {
+            (_, NodeData::DocType(_)) | (_, NodeData::Hole) => false,
+            (NodeTest::Node, _) => true,
+            (NodeTest::Any, NodeData::Elem(_)) => true,
+            (NodeTest::Name(name), NodeData::Elem(e)) => {
+                (*e.name.local).eq_ignore_ascii_case(name)
+            }
+            (NodeTest::Text, NodeData::Text(_)) => true,
+            (NodeTest::Comment, NodeData::Comment(_)) => true,
+            (NodeTest::Pi, NodeData::Pi(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Test an attribute found on the `attribute` axis.
+    fn matches_attr(&self, attr: &Attribute) -> bool {
+        match self {
+            NodeTest::Node | NodeTest::Any => true,
+            NodeTest::Name(name) => {
+                (*attr.name.local).eq_ignore_ascii_case(name)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Evaluation
+
+#[derive(Copy, Clone)]
+struct Ctx<'a> {
+    node: XNode<'a>,
+    pos: usize,
+    size: usize,
+}
+
+struct Eval<'a> {
+    doc: &'a Document,
+    order: Vec<u32>,
+}
+
+impl<'a> Eval<'a> {
+    fn new(doc: &'a Document) -> Eval<'a> {
+        let root = doc.document_node_ref();
+        let mut order = vec![0; doc.len() as usize + 1];
+        for (i, n) in root.descendants().enumerate() {
+            order[n.id().0.get() as usize] = i as u32;
+        }
+        Eval { doc, order }
+    }
+
+    /// Return a document order sort key for the node.
+    fn key(&self, n: &XNode<'a>) -> (u32, usize) {
+        match *n {
+            XNode::Node(r) => (self.order[r.id().0.get() as usize], 0),
+            XNode::Attr(r, i) => (self.order[r.id().0.get() as usize], i + 1),
+        }
+    }
+
+    fn sort_dedup(&self, nodes: &mut Vec<XNode<'a>>) {
+        nodes.sort_by_key(|n| self.key(n));
+        nodes.dedup_by_key(|n| self.key(n));
+    }
+
+    fn eval(&self, e: &Expr, ctx: Ctx<'a>)
+        -> Result<Value<'a>, XPathError>
+    {
+        let v = match e {
+            Expr::Or(l, r) => Value::Boolean(
+                self.eval(l, ctx)?.boolean() || self.eval(r, ctx)?.boolean()
+            ),
+            Expr::And(l, r) => Value::Boolean(
+                self.eval(l, ctx)?.boolean() && self.eval(r, ctx)?.boolean()
+            ),
+            Expr::Cmp(op, l, r) => Value::Boolean(
+                compare(*op, &self.eval(l, ctx)?, &self.eval(r, ctx)?)
+            ),
+            Expr::Arith(op, l, r) => {
+                let a = self.eval(l, ctx)?.number();
+                let b = self.eval(r, ctx)?.number();
+                Value::Number(match op {
+                    ArithOp::Add => a + b,
+                    ArithOp::Sub => a - b,
+                    ArithOp::Mul => a * b,
+                    ArithOp::Div => a / b,
+                    ArithOp::Mod => a % b,
+                })
+            }
+            Expr::Neg(x) => Value::Number(-self.eval(x, ctx)?.number()),
+            Expr::Union(l, r) => {
+                let mut nodes = self.node_set(self.eval(l, ctx)?)?;
+                nodes.extend(self.node_set(self.eval(r, ctx)?)?);
+                self.sort_dedup(&mut nodes);
+                Value::Nodes(nodes)
+            }
+            Expr::Literal(s) => Value::String(s.clone()),
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Call(f, args) => self.call(*f, args, ctx)?,
+            Expr::Path(absolute, steps) => {
+                let mut nodes = if *absolute {
+                    vec![XNode::Node(self.doc.document_node_ref())]
+                } else {
+                    vec![ctx.node]
+                };
+                for step in steps {
+                    nodes = self.step(&nodes, step)?;
+                }
+                Value::Nodes(nodes)
+            }
+            Expr::Filter(primary, preds, steps) => {
+                let mut nodes = self.node_set(self.eval(primary, ctx)?)?;
+                for pred in preds {
+                    nodes = self.filter(nodes, pred)?;
+                }
+                for step in steps {
+                    nodes = self.step(&nodes, step)?;
+                }
+                Value::Nodes(nodes)
+            }
+        };
+        Ok(v)
+    }
+
+    fn node_set(&self, v: Value<'a>) -> Result<Vec<XNode<'a>>, XPathError> {
+        match v {
+            Value::Nodes(ns) => Ok(ns),
+            _ => Err(XPathError::new("expected a node-set")),
+        }
+    }
+
+    fn step(&self, input: &[XNode<'a>], step: &Step)
+        -> Result<Vec<XNode<'a>>, XPathError>
+    {
+        let mut out = Vec::new();
+        for &n in input {
+            let mut found = self.axis(n, step.axis, &step.test);
+            for pred in &step.preds {
+                found = self.filter(found, pred)?;
+            }
+            out.extend(found);
+        }
+        self.sort_dedup(&mut out);
+        Ok(out)
+    }
+
+    /// Filter nodes, in axis order, by a predicate.
+    fn filter(&self, nodes: Vec<XNode<'a>>, pred: &Expr)
+        -> Result<Vec<XNode<'a>>, XPathError>
+    {
+        let size = nodes.len();
+        let mut out = Vec::new();
+        for (i, n) in nodes.into_iter().enumerate() {
+            let ctx = Ctx { node: n, pos: i + 1, size };
+            let keep = match self.eval(pred, ctx)? {
+                Value::Number(x) => {
+                    cmp_num(CmpOp::Eq, x, (i + 1) as f64)
+                }
+                v => v.boolean(),
+            };
+            if keep {
+                out.push(n);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Return nodes on the axis from n which match test, in axis order:
+    /// reverse document order for the reverse axes.
+    fn axis(&self, n: XNode<'a>, axis: Axis, test: &NodeTest)
+        -> Vec<XNode<'a>>
+    {
+        let node = n.node_ref();
+        let is_attr = n.is_attr();
+        let mut found: Vec<XNode<'a>> = Vec::new();
+        match axis {
+            Axis::Attribute => {
+                if let (XNode::Node(r), Some(e)) =
+                    (n, node_of(node).as_element())
+                {
+                    for (i, attr) in e.attrs.iter().enumerate() {
+                        if test.matches_attr(attr) {
+                            found.push(XNode::Attr(r, i));
+                        }
+                    }
+                }
+                return found;
+            }
+            Axis::SelfAxis | Axis::AncestorOrSelf |
+            Axis::DescendantOrSelf if is_attr => {
+                found.push(n);
+            }
+            _ => {}
+        }
+        let nodes: Vec<NodeRef<'a>> = match axis {
+            Axis::Child if !is_attr => node.children().collect(),
+            Axis::Descendant if !is_attr => {
+                node.descendants().skip(1).collect()
+            }
+            Axis::DescendantOrSelf if !is_attr => {
+                node.descendants().collect()
+            }
+            Axis::SelfAxis if !is_attr => vec![node],
+            Axis::Parent if is_attr => vec![node],
+            Axis::Parent => node.parent().into_iter().collect(),
+            Axis::Ancestor | Axis::AncestorOrSelf => {
+                if is_attr || axis == Axis::AncestorOrSelf {
+                    iter::once(node).chain(node.ancestors()).collect()
+                } else {
+                    node.ancestors().collect()
+                }
+            }
+            Axis::FollowingSibling if !is_attr => {
+                node.next_siblings().collect()
+            }
+            Axis::PrecedingSibling if !is_attr => {
+                node.prev_siblings().collect()
+            }
+            Axis::Following => following(node, is_attr),
+            Axis::Preceding => preceding(node),
+            _ => Vec::new(),
+        };
+        found.extend(nodes.into_iter().map(XNode::Node));
+        found.retain(|&x| test.matches(x));
+        found
+    }
+
+    fn call(&self, f: Func, args: &[Expr], ctx: Ctx<'a>)
+        -> Result<Value<'a>, XPathError>
+    {
+        let arg = |i: usize| self.eval(&args[i], ctx);
+
+        // The string argument i, or the context node string-value.
+        let str_arg = |i: usize| -> Result<String, XPathError> {
+            if i < args.len() {
+                Ok(arg(i)?.string())
+            } else {
+                Ok(ctx.node.string_value())
+            }
+        };
+
+        let v = match f {
+            Func::Last => Value::Number(ctx.size as f64),
+            Func::Position => Value::Number(ctx.pos as f64),
+            Func::Count => {
+                Value::Number(self.node_set(arg(0)?)?.len() as f64)
+            }
+            Func::LocalName | Func::Name => {
+                let n = if args.is_empty() {
+                    Some(ctx.node)
+                } else {
+                    self.node_set(arg(0)?)?.first().cloned()
+                };
+                let qualified = f == Func::Name;
+                Value::String(
+                    n.map_or_else(String::new, |n| n.name(qualified))
+                )
+            }
+            Func::String => Value::String(str_arg(0)?),
+            Func::Concat => {
+                let mut s = String::new();
+                for a in args {
+                    s.push_str(&self.eval(a, ctx)?.string());
+                }
+                Value::String(s)
+            }
+            Func::StartsWith => {
+                Value::Boolean(str_arg(0)?.starts_with(str_arg(1)?.as_str()))
+            }
+            Func::Contains => {
+                Value::Boolean(str_arg(0)?.contains(str_arg(1)?.as_str()))
+            }
+            Func::SubstringBefore => {
+                let s = str_arg(0)?;
+                let t = str_arg(1)?;
+                Value::String(s.find(t.as_str())
+                    .map_or_else(String::new, |i| s[..i].to_owned()))
+            }
+            Func::SubstringAfter => {
+                let s = str_arg(0)?;
+                let t = str_arg(1)?;
+                Value::String(s.find(t.as_str()).map_or_else(
+                    String::new,
+                    |i| s[i + t.len()..].to_owned()
+                ))
+            }
+            Func::Substring => {
+                let s = str_arg(0)?;
+                let start = round(arg(1)?.number());
+                let end = if args.len() > 2 {
+                    start + round(arg(2)?.number())
+                } else {
+                    std::f64::INFINITY
+                };
+                Value::String(
+                    s.chars()
+                        .enumerate()
+                        .filter(|&(i, _)| {
+                            let p = (i + 1) as f64;
+                            p >= start && p < end
+                        })
+                        .map(|(_, c)| c)
+                        .collect()
+                )
+            }
+            Func::StringLength => {
+                Value::Number(str_arg(0)?.chars().count() as f64)
+            }
+            Func::NormalizeSpace => Value::String(
+                str_arg(0)?.split_ascii_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            Func::Translate => {
+                let s = str_arg(0)?;
+                let from: Vec<char> = str_arg(1)?.chars().collect();
+                let to: Vec<char> = str_arg(2)?.chars().collect();
+                Value::String(
+                    s.chars()
+                        .filter_map(|c| {
+                            match from.iter().position(|&f| f == c) {
+                                Some(i) => to.get(i).cloned(),
+                                None => Some(c),
+                            }
+                        })
+                        .collect()
+                )
+            }
+            Func::Not => Value::Boolean(!arg(0)?.boolean()),
+            Func::True => Value::Boolean(true),
+            Func::False => Value::Boolean(false),
+            Func::Boolean => Value::Boolean(arg(0)?.boolean()),
+            Func::Lang => {
+                let l = arg(0)?.string();
+                let lang = ctx.node.node_ref().language();
+                Value::Boolean(lang.map_or(false, |v| lang_matches(&v, &l)))
+            }
+            Func::Number if args.is_empty() => {
+                Value::Number(str_to_number(&ctx.node.string_value()))
+            }
+            Func::Number => Value::Number(arg(0)?.number()),
+            Func::Sum => Value::Number(
+                self.node_set(arg(0)?)?
+                    .iter()
+                    .map(|n| str_to_number(&n.string_value()))
+                    .sum()
+            ),
+            Func::Floor => Value::Number(arg(0)?.number().floor()),
+            Func::Ceiling => Value::Number(arg(0)?.number().ceil()),
+            Func::Round => Value::Number(round(arg(0)?.number())),
+        };
+        Ok(v)
+    }
+}
+
+/// Return nodes following node in document order, excluding descendants,
+/// or for an attribute (of element node), including descendants.
+fn following(node: NodeRef<'_>, is_attr: bool) -> Vec<NodeRef<'_>> {
+    let mut out: Vec<NodeRef<'_>> = if is_attr {
+        node.descendants().skip(1).collect()
+    } else {
+        Vec::new()
+    };
+    let mut cur = Some(node);
+    while let Some(n) = cur {
+        for sib in n.next_siblings() {
+            out.extend(sib.descendants());
+        }
+        cur = n.parent();
+    }
+    out
+}
+
+/// Return nodes preceding node in reverse document order, excluding
+/// ancestors.
+fn preceding(node: NodeRef<'_>) -> Vec<NodeRef<'_>> {
+    let mut out = Vec::new();
+    let mut cur = Some(node);
+    while let Some(n) = cur {
+        for sib in n.prev_siblings() {
+            let desc: Vec<_> = sib.descendants().collect();
+            out.extend(desc.into_iter().rev());
+        }
+        cur = n.parent();
+    }
+    out
+}
+
+/// Return true if the language value equals or is a sub-language of lang,
+/// ignoring case.
+fn lang_matches(value: &str, lang: &str) -> bool {
+    let v = value.trim();
+    let n = lang.len();
+    v.eq_ignore_ascii_case(lang) || (
+        v.len() > n
+            && v.as_bytes()[n] == b'-'
+            && v[..n].eq_ignore_ascii_case(lang)
+    )
+}
+
+fn compare(op: CmpOp, a: &Value<'_>, b: &Value<'_>) -> bool {
+    match (a, b) {
+        (Value::Nodes(x), Value::Nodes(y)) => {
+            let ys: Vec<String> = y.iter().map(XNode::string_value).collect();
+            x.iter().any(|n| {
+                let s = n.string_value();
+                ys.iter().any(|t| cmp_str(op, &s, t))
+            })
+        }
+        (Value::Nodes(x), Value::Boolean(v)) => {
+            cmp_atomic(op, &Value::Boolean(!x.is_empty()), &Value::Boolean(*v))
+        }
+        (Value::Boolean(v), Value::Nodes(y)) => {
+            cmp_atomic(op, &Value::Boolean(*v), &Value::Boolean(!y.is_empty()))
+        }
+        (Value::Nodes(x), other) => x.iter().any(|n| {
+            cmp_atomic(op, &Value::String(n.string_value()), other)
+        }),
+        (other, Value::Nodes(y)) => y.iter().any(|n| {
+            cmp_atomic(op, other, &Value::String(n.string_value()))
+        }),
+        _ => cmp_atomic(op, a, b),
+    }
+}
+
+fn cmp_str(op: CmpOp, a: &str, b: &str) -> bool {
+    match op {
+        CmpOp::Eq => a == b,
+        CmpOp::Ne => a != b,
+        _ => cmp_num(op, str_to_number(a), str_to_number(b)),
+    }
+}
+
+/// Compare values which are not node-sets.
+fn cmp_atomic(op: CmpOp, a: &Value<'_>, b: &Value<'_>) -> bool {
+    let is_bool = |v: &Value<'_>| match v {
+        Value::Boolean(_) => true,
+        _ => false,
+    };
+    let is_num = |v: &Value<'_>| match v {
+        Value::Number(_) => true,
+        _ => false,
+    };
+    match op {
+        CmpOp::Eq | CmpOp::Ne => {
+            let eq = if is_bool(a) || is_bool(b) {
+                a.boolean() == b.boolean()
+            } else if is_num(a) || is_num(b) {
+                cmp_num(CmpOp::Eq, a.number(), b.number())
+            } else {
+                a.string() == b.string()
+            };
+            eq == (op == CmpOp::Eq)
+        }
+        _ => cmp_num(op, a.number(), b.number()),
+    }
+}
+
+fn cmp_num(op: CmpOp, a: f64, b: f64) -> bool {
+    let ord = a.partial_cmp(&b);
+    match op {
+        CmpOp::Eq => ord == Some(Ordering::Equal),
+        CmpOp::Ne => ord != Some(Ordering::Equal),
+        CmpOp::Lt => ord == Some(Ordering::Less),
+        CmpOp::Le => ord == Some(Ordering::Less)
+            || ord == Some(Ordering::Equal),
+        CmpOp::Gt => ord == Some(Ordering::Greater),
+        CmpOp::Ge => ord == Some(Ordering::Greater)
+            || ord == Some(Ordering::Equal),
+    }
+}
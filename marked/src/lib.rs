@@ -34,6 +34,8 @@ pub const HTML_META_CONF: f32     = 0.20;
 /// at the start of a document stream.
 pub const BOM_CONF: f32           = 0.31;
 
+pub mod archive;
+
 mod chars;
 
 mod decode;
@@ -44,16 +46,57 @@ pub use decode::{
 mod dom;
 pub use dom::{
     html,
-    Document, DocumentType, Element,
-    Node, NodeData, NodeId, NodeRef, ProcessingInstruction,
-    Descender, Selector,
+    CompactPolicy, DocStats, Document, DocumentType, DuplicateAttrs,
+    Element, EqOptions,
+    Escaping, Fragment,
+    MemoryUsage, Node, NodeData, NodeId, NodeIdError, NodeIdMap, NodeMut,
+    NodeRef, ProcessingInstruction, SendDocument,
+    Descender, Selector, Siblings,
+    SerializeOptions, SrcsetCandidate, SrcsetDescriptor, TextStats,
     Attribute, LocalName, Namespace, QualName, StrTendril,
 };
 
+pub use dom::audit;
+pub use dom::boilerplate;
+pub use dom::css;
+pub use dom::dedup;
+pub use dom::diff;
+pub use dom::extract;
 pub use dom::filter;
+pub use dom::highlight;
+pub use dom::lang;
+pub use dom::layout;
+pub use dom::media;
+pub use dom::meta;
+pub use dom::microdata;
+pub use dom::outline;
+pub use dom::paginate;
+pub use dom::provenance;
+pub use dom::query;
+pub use dom::render;
+pub use dom::robots;
+pub use dom::table;
+pub use dom::template;
+pub use dom::traverse;
+pub use dom::xpath;
 
 #[cfg(feature = "xml")]
 pub use dom::xml;
 
+#[cfg(feature = "xml")]
+pub use dom::feed;
+
+#[cfg(feature = "xml")]
+pub use dom::sitemap;
+
+#[cfg(feature = "jsonld")]
+pub use dom::jsonld;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
 #[doc(hidden)]
 pub mod logger;
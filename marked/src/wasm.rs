@@ -0,0 +1,81 @@
+//! JavaScript bindings via _wasm-bindgen_ (_wasm_ feature).
+//!
+//! These expose a small parse, filter and serialize surface, for reuse of
+//! the same cleaning pipeline in browsers or other WebAssembly hosts. The
+//! bindings are exported by any `cdylib` crate, built for the
+//! `wasm32-unknown-unknown` target, that links this crate with the _wasm_
+//! feature enabled. From JavaScript, for example:
+//!
+//! ```js
+//! const doc = new HtmlDocument(html);
+//! doc.filter("clean");
+//! const out = doc.toHtml();
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::{filter::config::FilterConfig, html, Document};
+
+/// A parsed HTML document.
+#[wasm_bindgen]
+pub struct HtmlDocument {
+    doc: Document,
+}
+
+#[wasm_bindgen]
+impl HtmlDocument {
+    /// Parse a complete HTML document.
+    #[wasm_bindgen(constructor)]
+    pub fn new(html: &str) -> HtmlDocument {
+        HtmlDocument { doc: html::parse_utf8(html.as_bytes()) }
+    }
+
+    /// Parse an HTML fragment, as per [`html::parse_utf8_fragment`].
+    #[wasm_bindgen(js_name = parseFragment)]
+    pub fn parse_fragment(html: &str) -> HtmlDocument {
+        HtmlDocument { doc: html::parse_utf8_fragment(html.as_bytes()) }
+    }
+
+    /// Apply a named filter preset, as per [`Document::filter_preset`], or
+    /// throw an error if the name is unknown.
+    pub fn filter(&mut self, preset: &str) -> Result<(), JsValue> {
+        if self.doc.filter_preset(preset) {
+            Ok(())
+        } else {
+            Err(JsValue::from_str(
+                &format!("unknown filter preset: {}", preset)))
+        }
+    }
+
+    /// Detach all elements matching the selector, in the subset of CSS
+    /// syntax supported by [`NodeRef::matches`](crate::NodeRef::matches).
+    pub fn remove(&mut self, selector: &str) {
+        let config = FilterConfig {
+            remove: vec![selector.to_owned()],
+            ..FilterConfig::default()
+        };
+        config.build().apply(&mut self.doc);
+    }
+
+    /// Return all text content.
+    pub fn text(&self) -> String {
+        self.doc.document_node_ref()
+            .text()
+            .map_or_else(String::new, |t| t.to_string())
+    }
+
+    /// Serialize to an HTML string.
+    #[wasm_bindgen(js_name = toHtml)]
+    pub fn to_html(&self) -> String {
+        self.doc.to_string()
+    }
+}
+
+/// Parse the HTML document, apply the `clean` filter preset, and return the
+/// serialized result.
+#[wasm_bindgen]
+pub fn clean(html: &str) -> String {
+    let mut doc = html::parse_utf8(html.as_bytes());
+    doc.filter_preset("clean");
+    doc.to_string()
+}